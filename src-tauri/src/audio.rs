@@ -1,5 +1,6 @@
 use crate::common::fatal_error;
 use crate::common::shutdown::quitter;
+use crate::dry_run::{EffectRecorder, RecordedOutput};
 use awedio::backends::CpalBackend;
 use awedio::manager::Manager;
 use awedio::Sound as _;
@@ -14,8 +15,9 @@ const AUDIO_MIXER_CHANNEL_SIZE: usize = 10;
 
 #[derive(Debug)]
 pub struct AudioMixer {
+  /// This is `None` for a recording AudioMixer, which does not need a thread
   #[allow(unused)]
-  join_handle: thread::JoinHandle<()>,
+  join_handle: Option<thread::JoinHandle<()>>,
   sender: mpsc::Sender<AudioMixerEvent>,
 }
 
@@ -46,7 +48,30 @@ impl AudioMixer {
     });
 
     Self {
-      join_handle,
+      join_handle: Some(join_handle),
+      sender: tx,
+    }
+  }
+
+  /// Creates an AudioMixer that records every file it is asked to play instead of playing it
+  pub fn recording(recorder: EffectRecorder) -> Self {
+    let (tx, mut rx) = mpsc::channel::<AudioMixerEvent>(AUDIO_MIXER_CHANNEL_SIZE);
+    spawn(async move {
+      while let Some(event) = rx.recv().await {
+        match event {
+          AudioMixerEvent::PlayFile(file_path, tx_complete) => {
+            recorder.record(RecordedOutput::PlayAudioFile(
+              file_path.to_string_lossy().into_owned(),
+            ));
+            _ = tx_complete.send(());
+          }
+          AudioMixerEvent::Reset => {}
+          AudioMixerEvent::Terminate => break,
+        }
+      }
+    });
+    Self {
+      join_handle: None,
       sender: tx,
     }
   }
@@ -130,6 +155,6 @@ pub fn print_audio_devices() -> ! {
   for device_name in devices.iter() {
     println!(" - {device_name}");
   }
-  println!("");
+  println!();
   std::process::exit(0)
 }
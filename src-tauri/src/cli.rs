@@ -1,6 +1,8 @@
 use crate::common::fatal_error;
+use crate::logs::chat::ChatChannel;
+use crate::logs::log_event_broadcaster::LogWatcherMode;
 use crate::state::overlay::OverlayMode;
-use clap::{command, Parser, Subcommand};
+use clap::{Parser, Subcommand};
 use std::env;
 use std::path::PathBuf;
 
@@ -17,12 +19,7 @@ pub fn cmd_with_optional_env_override() -> CLICommand {
   if let Ok(params_override) = env::var(LQ_CMD_OVERRIDE_ENV_VAR) {
     let params: Vec<String> = env::args()
       .take(1)
-      .chain(
-        params_override
-          .split_whitespace()
-          .into_iter()
-          .map(String::from),
-      )
+      .chain(params_override.split_whitespace().map(String::from))
       .collect();
     match CLI::try_parse_from(params.clone()) {
       Ok(CLI {
@@ -79,6 +76,180 @@ pub enum CLICommand {
   /// Prints out all detected audio devices
   PrintAudioDevices,
 
+  /// Check all triggers for problems, such as missing audio files or unapproved commands
+  Lint {
+    /// Override the path to the LogQuest configuration directory
+    #[arg(long = "config-dir", short = 'C')]
+    config_dir_override: Option<PathBuf>,
+  },
+
+  /// Run a saved trigger against sample log lines without firing any of its effects
+  TestTrigger {
+    /// The ID of the trigger to test
+    trigger_id: String,
+
+    /// Sample log lines to test. If none are given, lines are read from STDIN.
+    lines: Vec<String>,
+
+    /// The character name used for {C} and ${C}
+    #[arg(long = "character", short = 'c')]
+    character_name: String,
+
+    /// Override the path to the LogQuest configuration directory
+    #[arg(long = "config-dir", short = 'C')]
+    config_dir_override: Option<PathBuf>,
+  },
+
+  /// Run a log file through all triggers and print what their effects would have done,
+  /// without playing audio, speaking, or running any commands
+  DryRun {
+    /// The EverQuest log file to process from beginning to end
+    log_file: PathBuf,
+
+    /// The character name used for {C} and ${C}. Defaults to the name in the log file's name.
+    #[arg(long = "character", short = 'c')]
+    character_name: Option<String>,
+
+    /// The name or ID of a TriggerTag to turn on. Can be given more than once. TriggerTags with
+    /// zones are also turned on while the log is in one of their zones.
+    #[arg(long = "tag", short = 't')]
+    trigger_tags: Vec<String>,
+
+    /// Override the path to the LogQuest configuration directory
+    #[arg(long = "config-dir", short = 'C')]
+    config_dir_override: Option<PathBuf>,
+  },
+
+  /// Measure how fast all triggers match the lines of a log file, and which are the slowest
+  Benchmark {
+    /// The EverQuest log file whose lines are matched against every trigger
+    log_file: PathBuf,
+
+    /// The character name used for {C}. Defaults to the name in the log file's name.
+    #[arg(long = "character", short = 'c')]
+    character_name: Option<String>,
+
+    /// How many of the most expensive triggers and patterns to show
+    #[arg(long, short = 'n', default_value_t = 20)]
+    top: usize,
+
+    /// Override the path to the LogQuest configuration directory
+    #[arg(long = "config-dir", short = 'C')]
+    config_dir_override: Option<PathBuf>,
+  },
+
+  /// Parse the damage and healing in a log file into fights, and print the DPS and HPS of each
+  Parse {
+    /// The EverQuest log file to parse from beginning to end
+    log_file: PathBuf,
+
+    /// The name that "You" refers to. Defaults to the name in the log file's name.
+    #[arg(long = "character", short = 'c')]
+    character_name: Option<String>,
+
+    /// How many seconds without any damage end a fight
+    #[arg(long, default_value_t = 10)]
+    inactivity: u64,
+
+    /// How many of the sources that did the most damage to show for each fight
+    #[arg(long, short = 'n', default_value_t = 10)]
+    top: usize,
+  },
+
+  /// Print every /who listing in a log file as attendance CSV
+  Attendance {
+    /// The EverQuest log file to read from beginning to end
+    log_file: PathBuf,
+
+    /// The character whose log it is. Defaults to the name in the log file's name.
+    #[arg(long = "character", short = 'c')]
+    character_name: Option<String>,
+
+    /// The label given to each attendance snapshot
+    #[arg(long, default_value = "/who")]
+    label: String,
+
+    /// Also save the listings to LogQuest's attendance snapshots
+    #[arg(long)]
+    save: bool,
+
+    /// Override the path to the LogQuest configuration directory
+    #[arg(long = "config-dir", short = 'C')]
+    config_dir_override: Option<PathBuf>,
+  },
+
+  /// Print the chat in a log file, optionally only that of some channels or of one speaker
+  Chat {
+    /// The EverQuest log file to read from beginning to end
+    log_file: PathBuf,
+
+    /// The name that "You" refers to. Defaults to the name in the log file's name.
+    #[arg(long = "character", short = 'c')]
+    character_name: Option<String>,
+
+    /// Only show lines of this channel. Can be given more than once.
+    #[arg(long = "channel", value_enum)]
+    channels: Vec<ChatChannel>,
+
+    /// Only show lines said by this speaker (case-insensitive)
+    #[arg(long, short = 's')]
+    speaker: Option<String>,
+  },
+
+  /// Search every EverQuest log file in the logs directory for lines that match a pattern
+  Grep(GrepCommand),
+
+  /// Move the old lines of the log files of characters that are not being played into
+  /// compressed archives in the Logs/Archive directory. Log files that were written to in the
+  /// last hour are skipped.
+  ArchiveLogs {
+    /// Leave this many days of lines in each log file. Defaults to the configured number of days.
+    #[arg(long = "keep-days", short = 'k')]
+    keep_days: Option<u32>,
+
+    /// Only archive the log files of this character
+    #[arg(long = "character", short = 'c')]
+    character_name: Option<String>,
+
+    /// Override the path to EverQuest's logs
+    #[arg(long = "logs-dir", short = 'L')]
+    logs_dir_override: Option<PathBuf>,
+
+    /// Override the path to the LogQuest configuration directory
+    #[arg(long = "config-dir", short = 'C')]
+    config_dir_override: Option<PathBuf>,
+  },
+
+  /// List the characters that have a log file in the logs directory
+  Characters {
+    /// Override the path to EverQuest's logs
+    #[arg(long = "logs-dir", short = 'L')]
+    logs_dir_override: Option<PathBuf>,
+
+    /// Override the path to the LogQuest configuration directory
+    #[arg(long = "config-dir", short = 'C')]
+    config_dir_override: Option<PathBuf>,
+  },
+
+  /// Print the trigger firings recorded in the trigger history journal
+  History {
+    /// Only show firings of the trigger with this ID
+    #[arg(long = "trigger", short = 't')]
+    trigger_id: Option<String>,
+
+    /// The maximum number of firings to show, most recent last
+    #[arg(long, short = 'n', default_value_t = 50)]
+    limit: usize,
+
+    /// Instead of listing firings, print how often each trigger fired and failed
+    #[arg(long)]
+    stats: bool,
+
+    /// Override the path to the LogQuest configuration directory
+    #[arg(long = "config-dir", short = 'C')]
+    config_dir_override: Option<PathBuf>,
+  },
+
   /// Utilities for tinkering with the LogQuest text-to-speech engine
   #[command(subcommand)]
   TTS(TTSCommand),
@@ -90,7 +261,13 @@ pub enum CLICommand {
 
   /// (DEBUG BUILDS ONLY) Specify a file path to watch filesystem create/modify/delete events
   #[cfg(debug_assertions)]
-  Tail { file: PathBuf },
+  Tail {
+    file: PathBuf,
+
+    /// How the file is watched for changes
+    #[arg(long = "watcher", value_enum, default_value_t = LogWatcherMode::Native)]
+    mode: LogWatcherMode,
+  },
 }
 
 #[derive(Parser, Debug, Clone)]
@@ -107,12 +284,59 @@ pub struct StartCommand {
   #[arg(long="overlay", value_enum, default_value_t=OverlayMode::Default)]
   pub overlay_mode: OverlayMode,
 
+  /// Override how the logs directory is watched for changes, for filesystems that do not
+  /// reliably send change notifications
+  #[arg(long = "watcher", value_enum)]
+  pub log_watcher_mode_override: Option<LogWatcherMode>,
+
   /// (DEBUG BUILDS ONLY) If given, this will automatically open the dev tools for the overlay window
   #[cfg(debug_assertions)]
   #[arg(long)]
   pub overlay_dev_tools: bool,
 }
 
+#[derive(Parser, Debug, Clone)]
+pub struct GrepCommand {
+  /// A GINA pattern, or a regular expression if --regex is given
+  pub pattern: String,
+
+  /// Treat the pattern as a regular expression instead of a GINA pattern
+  #[arg(long)]
+  pub regex: bool,
+
+  /// Match regardless of case
+  #[arg(long = "ignore-case", short = 'i')]
+  pub case_insensitive: bool,
+
+  /// Only search the log files of this character
+  #[arg(long = "character", short = 'c')]
+  pub character_name: Option<String>,
+
+  /// Only lines logged on or after this date (YYYY-MM-DD or "YYYY-MM-DD HH:MM:SS")
+  #[arg(long)]
+  pub from: Option<String>,
+
+  /// Only lines logged up to this date (YYYY-MM-DD is the end of that day)
+  #[arg(long)]
+  pub until: Option<String>,
+
+  /// Stop after this many matching lines
+  #[arg(long, short = 'n')]
+  pub limit: Option<u32>,
+
+  /// Use and update LogQuest's log search index, which speeds up repeated date range searches
+  #[arg(long)]
+  pub index: bool,
+
+  /// Override the path to EverQuest's logs
+  #[arg(long = "logs-dir", short = 'L')]
+  pub logs_dir_override: Option<PathBuf>,
+
+  /// Override the path to the LogQuest configuration directory
+  #[arg(long = "config-dir", short = 'C')]
+  pub config_dir_override: Option<PathBuf>,
+}
+
 #[derive(Subcommand, Debug, Clone)]
 pub enum TTSCommand {
   /// Speak a message with text-to-speech. You can specify a specific voice.
@@ -1,35 +1,53 @@
 use crate::{
   common::{
-    file_path_is_executable, format_integer, progress_reporter::ProgressUpdate,
-    security::is_crypto_available, UUID,
+    file_path_is_executable, format_integer,
+    progress_reporter::{ProgressReporter, ProgressUpdate},
+    security::is_crypto_available,
+    UUID,
   },
+  dry_run,
   gina::{importer::import_from_gina_export_file, regex::RegexGINA},
-  logs::active_character_detection::Character,
+  logs::{
+    active_character_detection::{Character, PinnedCharacter},
+    chat::{self, ChatMessage, ChatQuery},
+    log_event_broadcaster::LogWatcherMode,
+    log_file_cursor::LogFileCursorCache,
+    search::{self, index::LogSearchIndex, LogSearchHit, LogSearchQuery},
+  },
   matchers::MatchContext,
   reactor::ReactorEvent,
   state::{
+    attendance::{self, AttendanceSnapshot},
     config::LogQuestConfig,
+    loot_history::{self, LootExport, LootHistory},
     state_handle::StateHandle,
-    state_tree::{OverlayState, ReactorState},
+    state_tree::{OverlayState, ReactorState, TriggerTagActivation},
     timer_manager::TimerLifetime,
+    trigger_history::{TriggerFiring, TriggerStats},
   },
   triggers::{
     command_template::{CommandTemplate, CommandTemplateSecurityCheck},
+    lint::{self, TriggerLintIssue},
+    template_string::TemplateString,
+    test_bench::{self, TriggerTestResult, TriggerTestSubject},
     trigger_index::{DataDelta, Mutation, TriggerIndex},
   },
   ui::{
-    OverlayManagerState, OVERLAY_WINDOW_LABEL, PROGRESS_UPDATE_EVENT_NAME,
-    PROGRESS_UPDATE_FINISHED_EVENT_NAME,
+    OverlayManagerState, LOG_SEARCH_HITS_EVENT_NAME, OVERLAY_WINDOW_LABEL,
+    PROGRESS_UPDATE_EVENT_NAME, PROGRESS_UPDATE_FINISHED_EVENT_NAME,
   },
 };
 use serde::Serialize;
 use std::{collections::HashSet, path::PathBuf};
 use tauri::{AppHandle, Manager, State, Window};
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, watch};
 use tracing::{debug, error, event, info};
 
 pub const CROSS_DISPATCH_EVENT_NAME: &str = "cross-dispatch";
 
+/// How many log search hits are sent to the UI in each event
+const LOG_SEARCH_HITS_BATCH_SIZE: usize = 100;
+
 #[derive(Serialize, ts_rs::TS)]
 #[serde(tag = "variant", content = "value")]
 #[ts(tag = "variant", content = "value")]
@@ -65,25 +83,48 @@ impl Bootstrap {
 
 pub fn handler() -> impl Fn(tauri::Invoke) {
   tauri::generate_handler![
+    activate_created_trigger_tag,
     bootstrap,
     bootstrap_overlay,
     dispatch_to_overlay,
+    export_attendance_csv,
+    export_loot_history_csv,
     get_active_trigger_tags,
+    get_attendance_snapshots,
     get_config,
     get_current_character,
+    get_loot_history,
+    get_trigger_history,
+    get_trigger_stats,
     import_gina_triggers_file,
+    lint_triggers,
+    list_characters,
     mutate,
     play_audio_file,
     print_to_stderr,
     print_to_stdout,
+    search_chat,
+    search_logs,
+    set_boxed_set,
+    set_character_switch_delay,
+    set_combat_parsing,
     set_everquest_dir,
+    set_log_archiving,
+    set_log_watcher_mode,
+    set_loot_tracking,
     set_overlay_opacity,
+    set_parallel_trigger_evaluation,
+    set_pinned_character,
+    set_trigger_history_journal,
     set_trigger_tag_activated,
     sign_command_template,
     start_timers_sync,
     sys_command_info,
+    take_attendance_snapshot,
+    test_trigger,
     validate_gina_regex,
-    validate_gina_regex_with_context
+    validate_gina_regex_with_context,
+    validate_template
   ]
 }
 
@@ -119,20 +160,205 @@ fn get_active_trigger_tags(state: State<StateHandle>) -> Result<HashSet<UUID>, S
   Ok(active)
 }
 
+#[tauri::command]
+fn lint_triggers(state: State<StateHandle>) -> Vec<TriggerLintIssue> {
+  let active_trigger_tags = state.select_reactor(|r| r.active_trigger_tags.clone());
+  state.select_triggers(|index| lint::lint(index, Some(&active_trigger_tags)))
+}
+
+#[tauri::command]
+fn get_trigger_history(
+  trigger_id: Option<UUID>,
+  limit: usize,
+  state: State<StateHandle>,
+) -> Vec<TriggerFiring> {
+  state.select_trigger_history(|history| history.recent(trigger_id.as_ref(), limit))
+}
+
+#[tauri::command]
+fn get_trigger_stats(state: State<StateHandle>) -> Vec<TriggerStats> {
+  state.select_triggers(|index| state.select_trigger_history(|history| history.stats(index)))
+}
+
+#[tauri::command]
+fn set_trigger_history_journal(enabled: bool, state: State<StateHandle>) -> LogQuestConfig {
+  let config = state.update_config_and_select(|config| {
+    config.trigger_history_journal = enabled;
+    config.clone()
+  });
+  let journal_path = config.trigger_history_journal_path();
+  state.update_trigger_history(|history| history.set_journal(journal_path.as_deref()));
+  config
+}
+
+#[tauri::command]
+fn set_character_switch_delay(secs: Option<u32>, state: State<StateHandle>) -> LogQuestConfig {
+  state.update_config_and_select(|config| {
+    config.character_switch_delay_secs = secs;
+    config.clone()
+  })
+}
+
+#[tauri::command]
+fn set_combat_parsing(enabled: bool, state: State<StateHandle>) -> LogQuestConfig {
+  state.update_config_and_select(|config| {
+    config.combat_parsing = enabled;
+    config.clone()
+  })
+}
+
+#[tauri::command]
+fn set_log_archiving(
+  enabled: bool,
+  after_days: Option<u32>,
+  state: State<StateHandle>,
+) -> LogQuestConfig {
+  state.update_config_and_select(|config| {
+    config.log_archiving = enabled;
+    config.log_archive_after_days = after_days;
+    config.clone()
+  })
+}
+
+#[tauri::command]
+fn set_log_watcher_mode(
+  mode: LogWatcherMode,
+  poll_interval_ms: Option<u32>,
+  state: State<StateHandle>,
+) -> LogQuestConfig {
+  state.update_config_and_select(|config| {
+    config.log_watcher_mode = mode;
+    config.log_watcher_poll_interval_ms = poll_interval_ms;
+    config.clone()
+  })
+}
+
+/// The change takes effect right away if the reactor is running
+#[tauri::command]
+fn set_pinned_character(
+  character: Option<PinnedCharacter>,
+  state: State<StateHandle>,
+) -> Result<LogQuestConfig, String> {
+  // Only a character returned by `list_characters` can be pinned, and it is saved as it is
+  // written in its log file name
+  let character = match character {
+    Some(pinned) => {
+      let known = known_characters(&state)?;
+      let Some(found) = pinned.find_in(known) else {
+        return Err(format!(
+          "{} ({}) has no log file in the logs directory",
+          pinned.name, pinned.server
+        ));
+      };
+      Some(PinnedCharacter {
+        name: found.name,
+        server: found.server,
+      })
+    }
+    None => None,
+  };
+  Ok(state.update_config_and_select(|config| {
+    config.pinned_character = character;
+    config.clone()
+  }))
+}
+
+#[tauri::command]
+fn set_loot_tracking(enabled: bool, state: State<StateHandle>) -> LogQuestConfig {
+  state.update_config_and_select(|config| {
+    config.loot_tracking = enabled;
+    config.clone()
+  })
+}
+
+#[tauri::command]
+fn get_loot_history(
+  character_name: String,
+  state: State<StateHandle>,
+) -> Result<LootHistory, String> {
+  let path = state
+    .select_config(|config| config.loot_history_file_path(&character_name))
+    .map_err(|e| e.to_string())?;
+  loot_history::read_loot_history(&path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn export_loot_history_csv(
+  character_name: String,
+  export: LootExport,
+  csv_path: PathBuf,
+  state: State<StateHandle>,
+) -> Result<(), String> {
+  let path = state
+    .select_config(|config| config.loot_history_file_path(&character_name))
+    .map_err(|e| e.to_string())?;
+  loot_history::export_csv(&path, export, &csv_path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn take_attendance_snapshot(
+  label: String,
+  state: State<StateHandle>,
+) -> Result<AttendanceSnapshot, String> {
+  state
+    .take_attendance_snapshot(&label)
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_attendance_snapshots(state: State<StateHandle>) -> Result<Vec<AttendanceSnapshot>, String> {
+  let path = state.select_config(|config| config.attendance_snapshots_file_path());
+  attendance::read_snapshots(&path).map_err(|e| e.to_string())
+}
+
+/// Exports every snapshot if no IDs are given
+#[tauri::command]
+fn export_attendance_csv(
+  snapshot_ids: Vec<UUID>,
+  csv_path: PathBuf,
+  state: State<StateHandle>,
+) -> Result<(), String> {
+  let path = state.select_config(|config| config.attendance_snapshots_file_path());
+  attendance::export_csv(&path, &snapshot_ids, &csv_path).map_err(|e| e.to_string())
+}
+
+/// Reads the whole log file, so it runs on a blocking thread rather than the main thread
+#[tauri::command]
+async fn search_chat(log_file_path: PathBuf, query: ChatQuery) -> Result<Vec<ChatMessage>, String> {
+  tauri::async_runtime::spawn_blocking(move || {
+    let character_name = dry_run::character_name_from_path(&log_file_path.to_string_lossy());
+    chat::search_log_file(&log_file_path, &character_name, &query)
+  })
+  .await
+  .map_err(|e| e.to_string())?
+  .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_boxed_set(character_names: Vec<String>, state: State<StateHandle>) -> LogQuestConfig {
+  state.set_boxed_set(character_names)
+}
+
+#[tauri::command]
+fn set_parallel_trigger_evaluation(enabled: bool, state: State<StateHandle>) -> LogQuestConfig {
+  state.update_config_and_select(|config| {
+    config.parallel_trigger_evaluation = enabled;
+    config.clone()
+  })
+}
+
 #[tauri::command]
 fn set_trigger_tag_activated(
   id: UUID,
   activated: bool,
   state: State<StateHandle>,
-) -> HashSet<UUID> {
-  state.update_reactor_and_select(|c| {
-    if activated {
-      c.active_trigger_tags.insert(id);
-    } else {
-      c.active_trigger_tags.remove(&id);
-    }
-    c.active_trigger_tags.clone()
-  })
+) -> TriggerTagActivation {
+  state.set_trigger_tag_activated(id, activated)
+}
+
+#[tauri::command]
+fn activate_created_trigger_tag(id: UUID, state: State<StateHandle>) -> TriggerTagActivation {
+  state.activate_created_trigger_tag(id)
 }
 
 #[tauri::command]
@@ -182,25 +408,7 @@ async fn import_gina_triggers_file(
 
   let count_before = state.select_triggers(|root| root.trigger_count());
 
-  let mut watch_progress_updates = Box::pin(watch_progress_updates);
-  let window_label = window.label().to_owned();
-  let app_handle = window.app_handle();
-  tauri::async_runtime::spawn(async move {
-    if let Some(window) = app_handle.get_window(&window_label) {
-      while let Ok(()) = watch_progress_updates.changed().await {
-        let current: &ProgressUpdate = &*watch_progress_updates.borrow();
-        let event_name = if let ProgressUpdate::Finished { .. } = current {
-          PROGRESS_UPDATE_FINISHED_EVENT_NAME
-        } else {
-          PROGRESS_UPDATE_EVENT_NAME
-        };
-        _ = window.emit(event_name, current);
-      }
-    } else {
-      error!("Could not send updates to unknown window: {window_label}");
-    }
-    debug!("Progress update sender task complete");
-  });
+  forward_progress_updates(&window, watch_progress_updates);
 
   progress_reporter.update("Starting import...");
 
@@ -222,6 +430,81 @@ async fn import_gina_triggers_file(
   Ok(index_copy)
 }
 
+/// Streams the hits to the window in batches as LOG_SEARCH_HITS_EVENT_NAME events while the
+/// progress is reported as usual, and returns how many hits there were
+#[tauri::command]
+async fn search_logs(
+  query: LogSearchQuery,
+  use_index: bool,
+  window: Window,
+  state: State<'_, StateHandle>,
+) -> Result<u32, String> {
+  let (logs_dir, index_path) = state.select_config(|config| {
+    (
+      config.logs_dir_path.clone(),
+      config.log_search_index_file_path(),
+    )
+  });
+  let logs_dir = logs_dir.ok_or("The EverQuest directory has not been set")?;
+
+  let (progress_reporter, watch_progress_updates) = ProgressReporter::new();
+  forward_progress_updates(&window, watch_progress_updates);
+
+  tauri::async_runtime::spawn_blocking(move || {
+    let mut index = use_index.then(|| LogSearchIndex::load(&index_path));
+    let mut batch: Vec<LogSearchHit> = Vec::with_capacity(LOG_SEARCH_HITS_BATCH_SIZE);
+    let hit_count = search::search_logs(
+      &logs_dir,
+      &query,
+      index.as_mut(),
+      &progress_reporter,
+      |hit| {
+        batch.push(hit);
+        if batch.len() == LOG_SEARCH_HITS_BATCH_SIZE {
+          _ = window.emit(LOG_SEARCH_HITS_EVENT_NAME, std::mem::take(&mut batch));
+        }
+      },
+    )?;
+    if !batch.is_empty() {
+      _ = window.emit(LOG_SEARCH_HITS_EVENT_NAME, batch);
+    }
+    if let Some(index) = index {
+      if let Err(e) = index.save(&index_path) {
+        error!("Could not save the log search index! Error: {e:?}");
+      }
+    }
+    Ok::<u32, std::io::Error>(hit_count)
+  })
+  .await
+  .map_err(|e| e.to_string())?
+  .map_err(|e| e.to_string())
+}
+
+fn forward_progress_updates(
+  window: &Window,
+  watch_progress_updates: watch::Receiver<ProgressUpdate>,
+) {
+  let mut watch_progress_updates = Box::pin(watch_progress_updates);
+  let window_label = window.label().to_owned();
+  let app_handle = window.app_handle();
+  tauri::async_runtime::spawn(async move {
+    if let Some(window) = app_handle.get_window(&window_label) {
+      while let Ok(()) = watch_progress_updates.changed().await {
+        let current: &ProgressUpdate = &watch_progress_updates.borrow();
+        let event_name = if let ProgressUpdate::Finished { .. } = current {
+          PROGRESS_UPDATE_FINISHED_EVENT_NAME
+        } else {
+          PROGRESS_UPDATE_EVENT_NAME
+        };
+        _ = window.emit(event_name, current);
+      }
+    } else {
+      error!("Could not send updates to unknown window: {window_label}");
+    }
+    debug!("Progress update sender task complete");
+  });
+}
+
 #[tauri::command]
 fn set_everquest_dir(state: State<StateHandle>, new_dir: String) -> Result<LogQuestConfig, String> {
   state.update_config_and_select(|config| {
@@ -287,6 +570,33 @@ fn validate_gina_regex_with_context(pattern: String) -> Option<(Option<usize>, S
   }
 }
 
+#[tauri::command]
+fn validate_template(tmpl: String) -> Option<(Option<usize>, String)> {
+  TemplateString::from(tmpl)
+    .validate()
+    .err()
+    .map(|e| (Some(e.position()), e.to_string()))
+}
+
+#[tauri::command]
+fn test_trigger(
+  subject: TriggerTestSubject,
+  lines: Vec<String>,
+  character_name: String,
+  state: State<StateHandle>,
+) -> Result<Vec<TriggerTestResult>, String> {
+  let trigger = state
+    .select_triggers(|index| subject.resolve(index))
+    .map_err(|e| e.to_string())?;
+  let boxed_set = state.select_config(|config| config.boxed_set.clone());
+  Ok(test_bench::test_trigger(
+    &trigger,
+    &lines,
+    &character_name,
+    &boxed_set,
+  ))
+}
+
 #[tauri::command]
 async fn play_audio_file(
   path: String,
@@ -302,3 +612,18 @@ async fn play_audio_file(
 fn get_current_character(state: State<StateHandle>) -> Option<Character> {
   state.select_reactor(|reactor| reactor.current_character.clone())
 }
+
+/// Every character with a log file in the logs directory
+#[tauri::command]
+fn list_characters(state: State<StateHandle>) -> Result<Vec<Character>, String> {
+  known_characters(&state)
+}
+
+fn known_characters(state: &StateHandle) -> Result<Vec<Character>, String> {
+  let Some(logs_dir) = state.select_config(|config| config.logs_dir_path.clone()) else {
+    return Err("The EverQuest directory is not set".to_owned());
+  };
+  LogFileCursorCache::scan_dir(&logs_dir)
+    .map(|cursors| cursors.characters())
+    .map_err(|e| e.to_string())
+}
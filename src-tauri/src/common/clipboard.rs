@@ -1,10 +1,14 @@
+use crate::dry_run::{EffectRecorder, RecordedOutput};
 use arboard::Clipboard;
 use std::sync::Arc;
 use tokio::sync::Mutex as AsyncMutex;
 use tracing::{error, warn};
 
 #[derive(Clone)]
-pub struct ClipboardWriter(Result<Arc<AsyncMutex<Clipboard>>, String>);
+pub enum ClipboardWriter {
+  System(Result<Arc<AsyncMutex<Clipboard>>, String>),
+  Recording(EffectRecorder),
+}
 
 impl ClipboardWriter {
   pub fn new() -> Self {
@@ -15,18 +19,25 @@ impl ClipboardWriter {
         Err(e.to_string())
       }
     };
-    Self(clipboard_maybe)
+    Self::System(clipboard_maybe)
+  }
+
+  pub fn recording(recorder: EffectRecorder) -> Self {
+    Self::Recording(recorder)
   }
 
   pub async fn write_text(&self, text: &str) {
-    match &self.0 {
-      Ok(clipboard_lock) => {
+    match self {
+      Self::Recording(recorder) => {
+        recorder.record(RecordedOutput::CopyToClipboard(text.to_owned()));
+      }
+      Self::System(Ok(clipboard_lock)) => {
         let mut guard = clipboard_lock.lock().await;
         if let Err(e) = guard.set_text(text) {
           error!("Could not write text to clipboard due to error: {e:?}");
         }
       }
-      Err(init_err_msg) => {
+      Self::System(Err(init_err_msg)) => {
         warn!(
           "Clipboard writing is disabled due to initialization error ({init_err_msg}). Could not write text to clipboard: `{text}`"
         );
@@ -37,10 +48,10 @@ impl ClipboardWriter {
 
 impl std::fmt::Debug for ClipboardWriter {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-    if self.0.is_ok() {
-      f.write_str("AvailableClipboardWriter")
-    } else {
-      f.write_str("UnavailableClipboardWriter")
+    match self {
+      Self::System(Ok(_)) => f.write_str("AvailableClipboardWriter"),
+      Self::System(Err(_)) => f.write_str("UnavailableClipboardWriter"),
+      Self::Recording(_) => f.write_str("RecordingClipboardWriter"),
     }
   }
 }
@@ -0,0 +1,262 @@
+//! Timers and pauses get the current time and sleep through a Clock, so that they can run
+//! against a virtual timeline (e.g. when processing a recorded log file) instead of the
+//! system's wall-clock time.
+use super::timestamp::Timestamp;
+use std::{
+  sync::{Arc, Mutex},
+  time::Duration,
+};
+use tokio::sync::oneshot;
+
+/// How many times in a row a VirtualClock yields to other tasks without seeing them use the
+/// clock before it considers them settled
+const VIRTUAL_CLOCK_SETTLE_YIELDS: usize = 32;
+
+#[derive(Debug, Clone)]
+pub enum Clock {
+  System,
+  Virtual(Arc<VirtualClock>),
+}
+
+/// A VirtualClock only moves forward when `advance_to` or `run_until_idle` is called
+#[derive(Debug)]
+pub struct VirtualClock(Mutex<VirtualClockState>);
+
+#[derive(Debug)]
+struct VirtualClockState {
+  now: Timestamp,
+  sleepers: Vec<(Timestamp, oneshot::Sender<()>)>,
+  /// Sleepers that were woken but have not returned from their sleep yet
+  waking: usize,
+  /// Counts every use of the clock, so that `settle` can tell whether other tasks are still busy
+  activity: u64,
+}
+
+/// A point in time to sleep until. The System clock measures it on a monotonic clock, so that a
+/// change to the system's wall-clock time does not shorten or stretch a sleep.
+#[derive(Debug, Clone)]
+pub enum Deadline {
+  System(tokio::time::Instant),
+  Virtual(Timestamp),
+}
+
+impl Clock {
+  pub fn now(&self) -> Timestamp {
+    match self {
+      Self::System => Timestamp::now(),
+      Self::Virtual(clock) => clock.now(),
+    }
+  }
+
+  /// Sleeps until a time that is shared with something else, like the end time of a Timer
+  pub async fn sleep_until(&self, deadline: &Timestamp) {
+    match self {
+      Self::System => tokio::time::sleep(Timestamp::now().duration_until(deadline)).await,
+      Self::Virtual(clock) => clock.sleep_until(deadline).await,
+    }
+  }
+
+  pub fn deadline_after(&self, duration: Duration) -> Deadline {
+    match self {
+      Self::System => Deadline::System(tokio::time::Instant::now() + duration),
+      Self::Virtual(clock) => Deadline::Virtual(&clock.now() + duration),
+    }
+  }
+
+  pub async fn sleep_until_deadline(&self, deadline: &Deadline) {
+    match deadline {
+      Deadline::System(instant) => tokio::time::sleep_until(*instant).await,
+      Deadline::Virtual(timestamp) => self.sleep_until(timestamp).await,
+    }
+  }
+
+  pub async fn sleep(&self, duration: Duration) {
+    self
+      .sleep_until_deadline(&self.deadline_after(duration))
+      .await
+  }
+}
+
+impl VirtualClock {
+  pub fn new(start: Timestamp) -> Self {
+    Self(Mutex::new(VirtualClockState {
+      now: start,
+      sleepers: Vec::new(),
+      waking: 0,
+      activity: 0,
+    }))
+  }
+
+  pub fn now(&self) -> Timestamp {
+    self.lock().now.clone()
+  }
+
+  async fn sleep_until(&self, deadline: &Timestamp) {
+    let rx_wake = {
+      let mut state = self.lock();
+      if state.now >= *deadline {
+        return;
+      }
+      let (tx_wake, rx_wake) = oneshot::channel::<()>();
+      state.sleepers.push((deadline.clone(), tx_wake));
+      rx_wake
+    };
+    let mut wake = Wake {
+      clock: self,
+      rx_wake,
+    };
+    if (&mut wake.rx_wake).await.is_ok() {
+      self.lock().waking -= 1;
+    }
+  }
+
+  /// Moves time forward, waking sleepers in the order of their deadlines. The clock stops at
+  /// each deadline long enough for woken tasks to run, so anything they do (including sleeping
+  /// again) happens at the correct virtual time.
+  pub async fn advance_to(&self, target: &Timestamp) {
+    loop {
+      {
+        let mut state = self.lock();
+        let next_deadline = state
+          .sleepers
+          .iter()
+          .map(|(deadline, _)| deadline)
+          .filter(|deadline| *deadline <= target)
+          .min()
+          .cloned();
+        let Some(next_deadline) = next_deadline else {
+          if state.now < *target {
+            state.now = target.clone();
+          }
+          return;
+        };
+        if state.now < next_deadline {
+          state.now = next_deadline;
+        }
+        let now = state.now.clone();
+        let (due, pending): (Vec<_>, Vec<_>) = state
+          .sleepers
+          .drain(..)
+          .partition(|(deadline, _)| *deadline <= now);
+        state.sleepers = pending;
+        // Counted while the state is locked, so that a sleeper cannot resume before it is
+        for (_, tx_wake) in due {
+          if tx_wake.send(()).is_ok() {
+            state.waking += 1;
+          }
+        }
+      }
+      self.settle().await;
+    }
+  }
+
+  /// Advances time until nothing is sleeping on this clock anymore
+  pub async fn run_until_idle(&self) {
+    loop {
+      let last_deadline = self
+        .lock()
+        .sleepers
+        .iter()
+        .map(|(deadline, _)| deadline.clone())
+        .max();
+      let Some(last_deadline) = last_deadline else {
+        return;
+      };
+      self.advance_to(&last_deadline).await;
+    }
+  }
+
+  pub fn is_idle(&self) -> bool {
+    self.lock().sleepers.is_empty()
+  }
+
+  /// Lets other tasks react to anything done at the current time: waits until every woken
+  /// sleeper has resumed, and then until other tasks stop using the clock for a while
+  pub async fn settle(&self) {
+    let mut quiet_yields = 0;
+    let mut last_activity = None;
+    while quiet_yields < VIRTUAL_CLOCK_SETTLE_YIELDS {
+      tokio::task::yield_now().await;
+      let (waking, activity) = {
+        let state = self.0.lock().expect("VirtualClock poisoned");
+        (state.waking, state.activity)
+      };
+      if waking == 0 && last_activity == Some(activity) {
+        quiet_yields += 1;
+      } else {
+        quiet_yields = 0;
+      }
+      last_activity = Some(activity);
+    }
+  }
+
+  /// Sleepers whose futures were dropped (e.g. they lost a `select!`) are discarded here so
+  /// that they don't hold the clock back or pull it forward.
+  fn lock(&self) -> std::sync::MutexGuard<'_, VirtualClockState> {
+    let mut state = self.0.lock().expect("VirtualClock poisoned");
+    state.sleepers.retain(|(_, tx_wake)| !tx_wake.is_closed());
+    state.activity += 1;
+    state
+  }
+}
+
+/// The receiving end of a sleeper's wake-up. A sleeper that is dropped after it was woken, but
+/// before it noticed, still counts as having resumed.
+struct Wake<'a> {
+  clock: &'a VirtualClock,
+  rx_wake: oneshot::Receiver<()>,
+}
+
+impl Drop for Wake<'_> {
+  fn drop(&mut self) {
+    if self.rx_wake.try_recv().is_ok() {
+      self.clock.lock().waking -= 1;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Clock, VirtualClock};
+  use crate::common::timestamp::Timestamp;
+  use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+  };
+
+  #[tokio::test]
+  async fn test_virtual_clock_wakes_sleepers_in_order() {
+    let start = Timestamp::now();
+    let virtual_clock = Arc::new(VirtualClock::new(start.clone()));
+    let clock = Clock::Virtual(virtual_clock.clone());
+
+    let woken_at = Arc::new(Mutex::new(Vec::<(u64, Timestamp)>::new()));
+    for secs in [30, 10, 20] {
+      let clock = clock.clone();
+      let woken_at = woken_at.clone();
+      tokio::spawn(async move {
+        clock.sleep(Duration::from_secs(secs)).await;
+        woken_at.lock().unwrap().push((secs, clock.now()));
+      });
+    }
+    virtual_clock.settle().await;
+
+    virtual_clock
+      .advance_to(&(&start + Duration::from_secs(25)))
+      .await;
+    virtual_clock.settle().await;
+    assert_eq!(
+      *woken_at.lock().unwrap(),
+      vec![
+        (10, &start + Duration::from_secs(10)),
+        (20, &start + Duration::from_secs(20)),
+      ]
+    );
+    assert_eq!(clock.now(), &start + Duration::from_secs(25));
+
+    virtual_clock.run_until_idle().await;
+    virtual_clock.settle().await;
+    assert_eq!(woken_at.lock().unwrap().len(), 3);
+    assert_eq!(clock.now(), &start + Duration::from_secs(30));
+  }
+}
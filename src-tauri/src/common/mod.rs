@@ -1,4 +1,5 @@
 pub mod clipboard;
+pub mod clock;
 pub mod duration;
 pub mod progress_reporter;
 pub mod security;
@@ -126,9 +127,9 @@ where
   let path = path.as_ref();
   let path_string = path.to_string_lossy();
 
-  let path = if path_string.starts_with("~/") {
+  let path = if let Some(relative_path) = path_string.strip_prefix("~/") {
     if let Some(home_dir) = dirs::home_dir() {
-      home_dir.join(&path_string[2..])
+      home_dir.join(relative_path)
     } else {
       return Err(AbsolutePathResolutionError::UnknownHomeDir);
     }
@@ -138,7 +139,7 @@ where
 
   // Attempt to canonicalize, but if it fails then the directory probably
   // doesn't exist yet.
-  path.canonicalize().or_else(|_| Ok(path))
+  path.canonicalize().or(Ok(path))
 }
 
 /// formats numbers with thousands separators. e.g. 12345 = "12,345" and 12 = "12"
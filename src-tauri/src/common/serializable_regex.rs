@@ -1,5 +1,6 @@
 use fancy_regex::Regex;
 use serde::{Deserialize, Serialize, Serializer};
+use std::sync::{Arc, OnceLock};
 
 #[derive(Debug, Clone, ts_rs::TS)]
 #[ts(type = "string")]
@@ -7,6 +8,9 @@ pub struct SerializableRegex {
   pub pattern: String,
   #[ts(skip)]
   pub compiled: Regex,
+  /// Only compiled the first time a case-insensitive Matcher needs it
+  #[ts(skip)]
+  case_insensitive: Arc<OnceLock<Regex>>,
 }
 
 impl TryFrom<&str> for SerializableRegex {
@@ -14,9 +18,31 @@ impl TryFrom<&str> for SerializableRegex {
 
   fn try_from(pattern: &str) -> Result<Self, Self::Error> {
     let compiled = Regex::new(pattern)?;
-    let pattern = pattern.to_owned();
-    Ok(Self { pattern, compiled })
+    Ok(Self::new(pattern.to_owned(), compiled))
+  }
+}
+
+impl SerializableRegex {
+  fn new(pattern: String, compiled: Regex) -> Self {
+    Self {
+      pattern,
+      compiled,
+      case_insensitive: Arc::new(OnceLock::new()),
+    }
   }
+
+  /// Returns the same regex, but compiled to ignore case
+  pub fn case_insensitive(&self) -> &Regex {
+    self.case_insensitive.get_or_init(|| {
+      case_insensitive_regex(&self.pattern).unwrap_or_else(|| self.compiled.clone())
+    })
+  }
+}
+
+/// Compiles the pattern with the case-insensitive flag set for the whole pattern. This does not
+/// change the numbering of any capture groups. Returns None if the pattern does not compile.
+pub fn case_insensitive_regex(pattern: &str) -> Option<Regex> {
+  Regex::new(&format!("(?i){pattern}")).ok()
 }
 
 impl Eq for SerializableRegex {}
@@ -42,7 +68,7 @@ impl<'de> Deserialize<'de> for SerializableRegex {
   {
     let pattern: String = Deserialize::deserialize(deserializer)?;
     let compiled = Regex::new(&pattern).map_err(serde::de::Error::custom)?;
-    Ok(Self { pattern, compiled })
+    Ok(Self::new(pattern, compiled))
   }
 }
 
@@ -1,7 +1,7 @@
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use tokio::sync::watch;
 
-#[derive(Debug, Clone, PartialEq, Eq, ts_rs::TS)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, ts_rs::TS)]
 pub struct Timestamp(chrono::DateTime<chrono::Utc>);
 
 impl Timestamp {
@@ -28,6 +28,22 @@ impl std::ops::Add<&super::duration::Duration> for &Timestamp {
   }
 }
 
+impl std::ops::Add<std::time::Duration> for &Timestamp {
+  type Output = Timestamp;
+
+  fn add(self, rhs: std::time::Duration) -> Self::Output {
+    Timestamp(self.0.add(rhs))
+  }
+}
+
+impl std::ops::Sub<std::time::Duration> for &Timestamp {
+  type Output = Timestamp;
+
+  fn sub(self, rhs: std::time::Duration) -> Self::Output {
+    Timestamp(self.0 - rhs)
+  }
+}
+
 impl From<chrono::NaiveDateTime> for Timestamp {
   fn from(naive: chrono::NaiveDateTime) -> Self {
     Self(naive.and_utc())
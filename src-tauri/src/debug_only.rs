@@ -7,19 +7,30 @@ use crate::{
     LogQuestVersion, LOG_QUEST_VERSION, UUID,
   },
   logs::{
-    active_character_detection::Character,
-    log_event_broadcaster::{LogEventBroadcaster, NotifyError},
+    active_character_detection::{Character, CharacterDetectionReason, PinnedCharacter},
+    chat::{ChatMessage, ChatQuery},
+    combat::FightSummary,
+    log_event_broadcaster::{
+      LogEventBroadcaster, LogWatcherMode, NotifyError, DEFAULT_POLL_INTERVAL,
+    },
     log_file_cursor::LogFileCursor,
     log_line_stream::LogLineStream,
+    search::{LogSearchHit, LogSearchQuery},
+    zone_tracker::ZoneChange,
   },
   matchers::{self, MatchContext},
   reactor::EventLoop,
   state::{
-    state_tree::ReactorState,
+    attendance::AttendanceSnapshot,
+    loot_history::{LootExport, LootHistory},
+    state_tree::{ReactorState, TriggerTagActivation},
     timer_manager::{TimerCommand, TimerStateUpdate},
+    trigger_history::{TriggerFiring, TriggerStats},
   },
   triggers::{
     effects::{Effect, EffectWithID},
+    lint::TriggerLintIssue,
+    test_bench::{TriggerTestResult, TriggerTestSubject},
     timers::{Timer, TimerStartPolicy},
     trigger_index::{DataDelta, DataMutationError, Mutation, TriggerGroupDescendant, TriggerIndex},
     Trigger, TriggerGroup,
@@ -67,9 +78,27 @@ pub fn generate_typescript() -> Result<(), ts_rs::ExportError> {
   DataDelta::export_all_to(&out_dir)?;
   DataMutationError::export_all_to(&out_dir)?;
   ReactorState::export_all_to(&out_dir)?;
+  TriggerTagActivation::export_all_to(&out_dir)?;
   Character::export_all_to(&out_dir)?;
+  CharacterDetectionReason::export_all_to(&out_dir)?;
+  PinnedCharacter::export_all_to(&out_dir)?;
+  ZoneChange::export_all_to(&out_dir)?;
+  FightSummary::export_all_to(&out_dir)?;
+  ChatMessage::export_all_to(&out_dir)?;
+  ChatQuery::export_all_to(&out_dir)?;
+  LogSearchQuery::export_all_to(&out_dir)?;
+  LogSearchHit::export_all_to(&out_dir)?;
+  LogWatcherMode::export_all_to(&out_dir)?;
+  LootHistory::export_all_to(&out_dir)?;
+  LootExport::export_all_to(&out_dir)?;
+  AttendanceSnapshot::export_all_to(&out_dir)?;
   LogQuestVersion::export_all_to(&out_dir)?;
   SystemCommandInfo::export_all_to(&out_dir)?;
+  TriggerLintIssue::export_all_to(&out_dir)?;
+  TriggerTestSubject::export_all_to(&out_dir)?;
+  TriggerTestResult::export_all_to(&out_dir)?;
+  TriggerFiring::export_all_to(&out_dir)?;
+  TriggerStats::export_all_to(&out_dir)?;
 
   #[allow(non_snake_case)]
   let LQ_VERSION: LogQuestVersion = LOG_QUEST_VERSION.clone();
@@ -86,7 +115,11 @@ pub fn generate_typescript() -> Result<(), ts_rs::ExportError> {
       crate::ui::MAIN_WINDOW_LABEL,
       crate::ui::OVERLAY_WINDOW_LABEL,
       crate::ui::PROGRESS_UPDATE_EVENT_NAME,
-      crate::ui::PROGRESS_UPDATE_FINISHED_EVENT_NAME
+      crate::ui::PROGRESS_UPDATE_FINISHED_EVENT_NAME,
+      crate::ui::ZONE_CHANGED_EVENT_NAME,
+      crate::ui::COMBAT_FIGHT_UPDATED_EVENT_NAME,
+      crate::ui::COMBAT_FIGHT_FINISHED_EVENT_NAME,
+      crate::ui::LOG_SEARCH_HITS_EVENT_NAME
     ],
   );
 
@@ -201,6 +234,7 @@ pub fn generate_overlay_noise(event_loop: &EventLoop) {
       path: String::new(),
       position: 0,
     }),
+    Arc::new([]),
   );
 
   warn!("GENERATING OVERLAY MESSAGE NOISE");
@@ -208,7 +242,7 @@ pub fn generate_overlay_noise(event_loop: &EventLoop) {
   spawn(async move {
     loop {
       tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-      let message = format!("Now: {}", Timestamp::now().to_string());
+      let message = format!("Now: {}", Timestamp::now());
       warn!("Sending generated overlay message: {message}");
       context_.overlay_manager.message(message);
     }
@@ -277,62 +311,59 @@ pub fn generate_overlay_noise(event_loop: &EventLoop) {
   let context_ = context.clone();
   spawn(async move {
     let timer_name = "Reset Every 3 sec and I have a really long name";
+    warn!("GENERATING TIMER NOISE WITH NAME: {timer_name}");
+    let context = context_.clone();
+    let timer_manager = &context.timer_manager;
+    let id = timer_manager
+      .start_timer(
+        Timer {
+          name_tmpl: timer_name.into(),
+          duration: common::duration::Duration(10 * 1000),
+          repeats: false,
+          start_policy: TimerStartPolicy::StartAndReplacesAnyTimerOfTriggerWithNameTemplateMatching(
+            timer_name.into(),
+          ),
+          trigger_id: trigger_id.clone(),
+          tags: vec![],
+          effects: vec![],
+        },
+        context.clone(),
+      )
+      .await
+      .unwrap();
+
+    // let id_ = id.clone();
+    // let timer_manager_ = timer_manager.clone();
+    // spawn(async move {
+    //   loop {
+    //     _ = timer_manager_
+    //       .send(TimerCommand::SetHidden(id_.clone(), true))
+    //       .await;
+    //     tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    //     _ = timer_manager_
+    //       .send(TimerCommand::SetHidden(id_.clone(), false))
+    //       .await;
+    //     tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    //   }
+    // });
+
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(3));
     loop {
-      warn!("GENERATING TIMER NOISE WITH NAME: {timer_name}");
-      let context = context_.clone();
-      let timer_manager = &context.timer_manager;
-      let id = timer_manager
-        .start_timer(
-          Timer {
-            name_tmpl: timer_name.into(),
-            duration: common::duration::Duration(10 * 1000),
-            repeats: false,
-            start_policy:
-              TimerStartPolicy::StartAndReplacesAnyTimerOfTriggerWithNameTemplateMatching(
-                timer_name.into(),
-              ),
-            trigger_id: trigger_id.clone(),
-            tags: vec![],
-            effects: vec![],
-          },
-          context.clone(),
-        )
-        .await
-        .unwrap();
-
-      // let id_ = id.clone();
-      // let timer_manager_ = timer_manager.clone();
-      // spawn(async move {
-      //   loop {
-      //     _ = timer_manager_
-      //       .send(TimerCommand::SetHidden(id_.clone(), true))
-      //       .await;
-      //     tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-      //     _ = timer_manager_
-      //       .send(TimerCommand::SetHidden(id_.clone(), false))
-      //       .await;
-      //     tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-      //   }
-      // });
-
-      let mut interval = tokio::time::interval(std::time::Duration::from_secs(3));
-      loop {
-        interval.tick().await;
-        _ = timer_manager.send(TimerCommand::Restart(id.clone())).await;
-      }
+      interval.tick().await;
+      _ = timer_manager.send(TimerCommand::Restart(id.clone())).await;
     }
   });
 }
 
 /// This is mainly useful for debugging filesystem events
-pub fn tail(log_file_path: &std::path::Path) -> Result<(), NotifyError> {
+pub fn tail(log_file_path: &std::path::Path, mode: LogWatcherMode) -> Result<(), NotifyError> {
   info!("Watch log file events for {}", log_file_path.display());
   let rt = tokio::runtime::Runtime::new().unwrap();
-  let mut fs_events = LogEventBroadcaster::new(&log_file_path)?;
+  let mut fs_events = LogEventBroadcaster::new(log_file_path, mode, DEFAULT_POLL_INTERVAL)?;
   fs_events.start()?;
   let fs_event_rx = fs_events.subscribe();
 
-  let cursor = LogFileCursor::new(&log_file_path.to_string_lossy()).unwrap();
+  let cursor = LogFileCursor::new(log_file_path.to_string_lossy()).unwrap();
 
   rt.block_on(async move {
     let stream = LogLineStream::create(&cursor, fs_event_rx);
@@ -358,7 +389,7 @@ fn files_in_dir_with_extension(dir: &Path, extension: &str) -> Vec<PathBuf> {
     .collect()
 }
 
-fn delete_files(files: &Vec<PathBuf>) {
+fn delete_files(files: &[PathBuf]) {
   for file in files.iter() {
     fatal_if_err(fs::remove_file(file));
     info!("DELETED: {}", file.display());
@@ -0,0 +1,804 @@
+//! A dry run processes log lines through Triggers the same way the reactor does, except that
+//! every component an Effect would use to affect the outside world is replaced by an
+//! `EffectRecorder`. Time is kept by a virtual Clock that follows the log file's timestamps,
+//! so Timers and pauses play out as they would have in-game without waiting in real time, and
+//! a dry run over a recorded log file is repeatable.
+use crate::{
+  audio::AudioMixer,
+  common::{
+    clipboard::ClipboardWriter,
+    clock::{Clock, VirtualClock},
+    timestamp::Timestamp,
+    UUID,
+  },
+  logs::{
+    archive, chat, log_file_cursor::LogFileCursor, zone_tracker::ZoneTracker, Line,
+    LOG_FILENAME_PATTERN,
+  },
+  matchers::{BuiltInVariables, MatchContext},
+  reactor::{EventContext, ReactorEvent},
+  state::{
+    active_triggers::ActiveTriggers, overlay::OverlayManager, state_tree::ReactorState,
+    timer_manager::TimerManager,
+  },
+  triggers::{effects::EffectWithID, trigger_index::TriggerIndex},
+  tts::TTS,
+};
+use futures::{stream, Stream};
+use std::{
+  collections::HashSet,
+  fs,
+  future::Future,
+  io::{self, BufRead},
+  path::Path,
+  sync::{Arc, Mutex},
+};
+use tokio::{
+  select,
+  sync::{broadcast, mpsc},
+  task::JoinSet,
+};
+use tracing::debug;
+
+const DRY_RUN_CHANNEL_SIZE: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedEffect {
+  pub timestamp: Timestamp,
+  pub output: RecordedOutput,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordedOutput {
+  PlayAudioFile(String),
+  Speak {
+    text: String,
+    interrupt: bool,
+  },
+  SpeakStop,
+  CopyToClipboard(String),
+  TakeAttendanceSnapshot(String),
+  OverlayMessage(String),
+  RunSystemCommand {
+    command: String,
+    params: Vec<String>,
+    write_to_stdin: Option<String>,
+  },
+  EffectError(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct EffectRecorder {
+  clock: Clock,
+  records: Arc<Mutex<Vec<RecordedEffect>>>,
+  tx_lines: broadcast::Sender<Line>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum DryRunError {
+  #[error("Could not read log file")]
+  IOError(#[from] std::io::Error),
+}
+
+impl EffectRecorder {
+  /// Outputs are stamped with the given Clock's current time when they are recorded
+  pub fn new(clock: Clock) -> Self {
+    Self {
+      clock,
+      records: Arc::new(Mutex::new(Vec::new())),
+      tx_lines: broadcast::channel(DRY_RUN_CHANNEL_SIZE).0,
+    }
+  }
+
+  pub fn record(&self, output: RecordedOutput) {
+    let timestamp = self.clock.now();
+    debug!("DRY RUN [{timestamp}] {output}");
+    self
+      .records
+      .lock()
+      .expect("EffectRecorder records poisoned")
+      .push(RecordedEffect { timestamp, output });
+  }
+
+  pub fn records(&self) -> Vec<RecordedEffect> {
+    self
+      .records
+      .lock()
+      .expect("EffectRecorder records poisoned")
+      .clone()
+  }
+
+  /// Returns the lines of the dry run that come after this call, each one as the virtual Clock
+  /// reaches its timestamp. Effects that wait on the log file read these instead of the file,
+  /// which the dry run reads ahead of the Clock.
+  pub fn lines(&self) -> impl Stream<Item = Line> + Send + 'static {
+    stream::unfold(self.tx_lines.subscribe(), |mut rx| async move {
+      loop {
+        match rx.recv().await {
+          Ok(line) => return Some((line, rx)),
+          Err(broadcast::error::RecvError::Lagged(_)) => continue,
+          Err(broadcast::error::RecvError::Closed) => return None,
+        }
+      }
+    })
+  }
+
+  fn send_line(&self, line: &Line) {
+    // There is nobody to send to unless an Effect is waiting on the log file
+    _ = self.tx_lines.send(line.clone());
+  }
+
+  /// Returns a Sender that stands in for the Text-to-Speech engine's channel. Every utterance
+  /// is reported as finished immediately after it is recorded.
+  pub fn text_to_speech_channel(&self) -> mpsc::Sender<TTS> {
+    let (tx, mut rx) = mpsc::channel::<TTS>(DRY_RUN_CHANNEL_SIZE);
+    let recorder = self.clone();
+    tokio::spawn(async move {
+      while let Some(message) = rx.recv().await {
+        match message {
+          TTS::Speak {
+            text,
+            interrupt,
+            tx_done,
+          } => {
+            recorder.record(RecordedOutput::Speak { text, interrupt });
+            if let Some(tx_done) = Arc::into_inner(tx_done) {
+              _ = tx_done.send(());
+            }
+          }
+          TTS::StopSpeaking => recorder.record(RecordedOutput::SpeakStop),
+          TTS::SetVoice(_) => {}
+          TTS::Quit => break,
+        }
+      }
+    });
+    tx
+  }
+}
+
+/// Runs every line of the log file through the Triggers of the active TriggerTags and returns
+/// everything the Effects would have done. If no character name is given, it is taken from the
+/// file name. Like the reactor, TriggerTags with zone rules are turned on and off as the log
+/// enters their zones, unless they are in `active_trigger_tags`.
+///
+/// The virtual Clock is advanced to each line's timestamp before the line is matched, which
+/// wakes any Timers or pauses that would have finished in the meantime. The line is then given
+/// to the Effects that are waiting on the log file, and matched. After the last line, the Clock
+/// keeps running until nothing is waiting on it anymore.
+///
+/// This must run on a current-thread runtime: the Clock only knows that the Effects and Timers
+/// have reacted to a point in time once they have all had a turn on the same thread.
+pub async fn run_log_file(
+  log_file_path: &Path,
+  index: &TriggerIndex,
+  active_trigger_tags: HashSet<UUID>,
+  character_name: Option<String>,
+  boxed_set: &[String],
+) -> Result<Vec<RecordedEffect>, DryRunError> {
+  let path = log_file_path.to_string_lossy().into_owned();
+  let character_name = character_name.unwrap_or_else(|| character_name_from_path(&path));
+
+  let virtual_clock = Arc::new(VirtualClock::new(Timestamp::from(
+    chrono::DateTime::<chrono::Utc>::UNIX_EPOCH,
+  )));
+  let clock = Clock::Virtual(virtual_clock.clone());
+  let recorder = EffectRecorder::new(clock.clone());
+  let timer_manager = Arc::new(TimerManager::with_clock(clock.clone()));
+  let (reactor_tx, reactor_rx) = mpsc::channel::<ReactorEvent>(DRY_RUN_CHANNEL_SIZE);
+  let (tx_log_file_events, _) = broadcast::channel(DRY_RUN_CHANNEL_SIZE);
+
+  let base_context = EventContext {
+    clock,
+    overlay_manager: Arc::new(OverlayManager::recording(
+      timer_manager.clone(),
+      recorder.clone(),
+    )),
+    timer_manager: timer_manager.clone(),
+    mixer: Arc::new(AudioMixer::recording(recorder.clone())),
+    reactor_tx,
+    t2s_tx: recorder.text_to_speech_channel(),
+    match_context: Arc::new(MatchContext::empty(&character_name)),
+    cursor_after: Arc::new(LogFileCursor {
+      path: path.clone(),
+      position: 0,
+    }),
+    timer_context: None,
+    trigger_firing_id: None,
+    clipboard: ClipboardWriter::recording(recorder.clone()),
+    tx_log_file_events,
+    boxed_set: boxed_set.into(),
+    dry_run: Some(recorder.clone()),
+  };
+
+  // Archived lines are run first, as if the log file had never been archived. The cursor only
+  // counts the bytes of the log file itself, so it stays at the start of the log file until
+  // the archived lines have been run.
+  let readers: [(Box<dyn BufRead>, bool); 2] = [
+    (
+      Box::new(io::BufReader::new(archive::open_archives(log_file_path)?)),
+      false,
+    ),
+    (
+      Box::new(io::BufReader::new(fs::File::open(log_file_path)?)),
+      true,
+    ),
+  ];
+  let mut raw_line = Vec::new();
+  let mut position: u64 = 0;
+  let server = server_name_from_path(&path);
+  let mut zone_tracker = ZoneTracker::default();
+  let mut tags = ReactorState::default();
+  for id in active_trigger_tags {
+    tags.set_trigger_tag_activated(id, true);
+  }
+  let mut active_triggers =
+    ActiveTriggers::new(index, &tags.active_trigger_tags, boxed_set.to_vec());
+
+  let mut effects = EffectRunner {
+    reactor_rx,
+    running: JoinSet::new(),
+    recorder: recorder.clone(),
+  };
+
+  for (mut reader, is_log_file) in readers {
+    loop {
+      raw_line.clear();
+      if reader.read_until(b'\n', &mut raw_line)? == 0 {
+        break;
+      }
+      if is_log_file {
+        position += raw_line.len() as u64;
+      }
+      let Ok(line) = Line::from_bytes(&raw_line) else {
+        continue;
+      };
+      if let Some(timestamp) = line.timestamp() {
+        effects
+          .run_while(virtual_clock.advance_to(&timestamp))
+          .await;
+      }
+      recorder.send_line(&line);
+
+      let cursor_after = Arc::new(LogFileCursor {
+        path: path.clone(),
+        position,
+      });
+
+      if let Some(zone) = zone_tracker.track(&line.content) {
+        let (zone_scoped, in_zone) = index.zone_rule_trigger_tags(zone);
+        tags.apply_zone_rules(&zone_scoped, in_zone);
+        active_triggers = ActiveTriggers::new(index, &tags.active_trigger_tags, boxed_set.to_vec());
+      }
+
+      let matches = active_triggers.check(&line.content, &character_name);
+      let chat = (!matches.is_empty()).then(|| chat::classify(&line.content, &character_name));
+      for (trigger_index, mut match_context) in matches.into_iter() {
+        let trigger = active_triggers.get(trigger_index);
+        match_context.built_ins = BuiltInVariables::for_line(
+          &line.content,
+          Some(&line.raw_datetime),
+          &trigger.name,
+          chat.as_ref(),
+          server.as_deref(),
+          zone_tracker.current(),
+        );
+        let event_context = Arc::new(EventContext {
+          match_context: Arc::new(match_context),
+          cursor_after: cursor_after.clone(),
+          ..base_context.clone()
+        });
+        // Each Effect runs until it finishes or waits on the Clock before the next one starts, so
+        // that the Effects of a line are recorded in the order of their Triggers
+        for effect in trigger.effects.iter() {
+          effects.spawn(effect.clone(), event_context.clone());
+          effects.run_while(virtual_clock.settle()).await;
+        }
+      }
+      effects.run_while(virtual_clock.settle()).await;
+    }
+  }
+
+  // The TimerManager is asked for a subscription to be sure it has handled every command sent
+  // so far, which may have started more Timers or queued more Effects to fire.
+  loop {
+    effects.run_while(virtual_clock.run_until_idle()).await;
+    effects.run_while(virtual_clock.settle()).await;
+    _ = effects.run_while(timer_manager.subscribe()).await;
+    if virtual_clock.is_idle() && effects.reactor_rx.is_empty() {
+      break;
+    }
+  }
+
+  Ok(recorder.records())
+}
+
+/// Fires Effects, including those sent back through the reactor channel by Timers, while the
+/// virtual Clock is being driven.
+struct EffectRunner {
+  reactor_rx: mpsc::Receiver<ReactorEvent>,
+  running: JoinSet<()>,
+  recorder: EffectRecorder,
+}
+
+impl EffectRunner {
+  fn spawn(&mut self, effect: EffectWithID, context: Arc<EventContext>) {
+    self
+      .running
+      .spawn(fire(effect, context, self.recorder.clone()));
+  }
+
+  async fn run_while<T>(&mut self, fut: impl Future<Output = T>) -> T {
+    tokio::pin!(fut);
+    loop {
+      select! {
+        output = &mut fut => return output,
+        Some(event) = self.reactor_rx.recv() => {
+          if let ReactorEvent::ExecEffect { effect, event_context } = event {
+            self.spawn(effect, event_context);
+          }
+        }
+        Some(_) = self.running.join_next(), if !self.running.is_empty() => {}
+      }
+    }
+  }
+}
+
+/// This function is designed to be used from the CLI. It prints the records to STDOUT.
+pub fn print_records(records: &[RecordedEffect]) {
+  for record in records.iter() {
+    println!("[{}] {}", record.timestamp, record.output);
+  }
+}
+
+async fn fire(effect: EffectWithID, context: Arc<EventContext>, recorder: EffectRecorder) {
+  if let Err(effect_error) = effect.effect.ready().fire(context).await {
+    recorder.record(RecordedOutput::EffectError(effect_error.to_string()));
+  }
+}
+
+pub fn character_name_from_path(path: &str) -> String {
+  match LOG_FILENAME_PATTERN.captures(path) {
+    Ok(Some(captures)) => captures
+      .get(1)
+      .map(|m| m.as_str().to_owned())
+      .unwrap_or_default(),
+    _ => String::new(),
+  }
+}
+
+fn server_name_from_path(path: &str) -> Option<String> {
+  match LOG_FILENAME_PATTERN.captures(path) {
+    Ok(Some(captures)) => captures.get(2).map(|m| m.as_str().to_owned()),
+    _ => None,
+  }
+}
+
+impl std::fmt::Display for RecordedOutput {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::PlayAudioFile(path) => write!(f, "Play audio file: {path}"),
+      Self::Speak { text, interrupt } => {
+        if *interrupt {
+          write!(f, "Speak (interrupting): \"{text}\"")
+        } else {
+          write!(f, "Speak: \"{text}\"")
+        }
+      }
+      Self::SpeakStop => write!(f, "Stop speaking"),
+      Self::CopyToClipboard(text) => write!(f, "Copy to clipboard: \"{text}\""),
+      Self::TakeAttendanceSnapshot(label) => write!(f, "Take attendance snapshot: \"{label}\""),
+      Self::OverlayMessage(message) => write!(f, "Overlay message: \"{message}\""),
+      Self::RunSystemCommand {
+        command,
+        params,
+        write_to_stdin,
+      } => {
+        write!(f, "Run system command: `{command}")?;
+        for param in params.iter() {
+          write!(f, " {param}")?;
+        }
+        write!(f, "`")?;
+        if let Some(input) = write_to_stdin {
+          write!(f, " with STDIN \"{input}\"")?;
+        }
+        Ok(())
+      }
+      Self::EffectError(message) => write!(f, "ERROR: {message}"),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{run_log_file, RecordedOutput};
+  use crate::{
+    common::{duration::Duration, random_id, timestamp::Timestamp, UUID},
+    matchers::{FilterWithContext, MatchOptions, Matcher, MatcherWithContext},
+    triggers::{
+      effects::{Effect, EffectWithID},
+      timers::{Timer, TimerEffect, TimerStartPolicy},
+      trigger_index::{Mutation, TriggerIndex},
+      Trigger,
+    },
+  };
+  use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+  };
+
+  #[tokio::test]
+  async fn test_dry_run_fires_only_active_trigger_tags() {
+    let log_file_path = write_log_file(
+      "[Thu Jul 18 21:03:01 2024] Soandso tells you, 'inc'\r\n\
+       [Thu Jul 18 21:03:02 2024] You have entered The Plane of Fear.\r\n\
+       [Thu Jul 18 21:03:03 2024] Soandso tells you, 'inc'\r\n",
+    );
+    let speak_trigger = |text: &str| {
+      let mut trigger = sample_trigger(r"^{S} tells you, '(.+)'$", vec![speak(text)]);
+      trigger.name = text.to_owned();
+      trigger
+    };
+    let mut index = TriggerIndex::new();
+    let active = tag_triggers(&mut index, "Active", vec![speak_trigger("active")]);
+    let fear = tag_triggers(&mut index, "Fear", vec![speak_trigger("fear")]);
+    index
+      .mutate(Mutation::SetTriggerTagZones {
+        trigger_tag_id: fear,
+        zones: vec!["The Plane of Fear".into()],
+      })
+      .unwrap();
+    tag_triggers(&mut index, "Inactive", vec![speak_trigger("inactive")]);
+
+    let records = run_index(&log_file_path, &index, [active].into()).await;
+    _ = std::fs::remove_file(&log_file_path);
+
+    assert_eq!(
+      records,
+      vec![
+        (at("21:03:01"), spoken("active")),
+        (at("21:03:03"), spoken("active")),
+        (at("21:03:03"), spoken("fear")),
+      ]
+    );
+  }
+
+  #[tokio::test]
+  async fn test_dry_run_log_file() {
+    let log_file_path = write_log_file(
+      "[Thu Jul 18 21:03:01 2024] You gain experience!!\r\n\
+       [Thu Jul 18 21:03:05 2024] Vulak`Aerr begins to cast a spell.\r\n\
+       [Thu Jul 18 21:03:09 2024] Soandso tells you, 'inc'\r\n",
+    );
+
+    let triggers = vec![
+      sample_trigger(
+        r"^{S} begins to cast a spell\.$",
+        vec![Effect::Speak {
+          tmpl: "Slow incoming".into(),
+          interrupt: false,
+        }],
+      ),
+      sample_trigger(
+        r"^{S} tells you, '(.+)'$",
+        vec![Effect::Sequence(vec![
+          EffectWithID::new(Effect::OverlayMessage("${S}: ${1}".into())),
+          EffectWithID::new(Effect::CopyToClipboard("/tell ${S} ${C} is busy".into())),
+        ])],
+      ),
+    ];
+
+    let records = run(&log_file_path, triggers).await;
+    _ = std::fs::remove_file(&log_file_path);
+
+    assert_eq!(
+      records,
+      vec![
+        (
+          at("21:03:05"),
+          RecordedOutput::Speak {
+            text: "Slow incoming".into(),
+            interrupt: false
+          }
+        ),
+        (
+          at("21:03:09"),
+          RecordedOutput::OverlayMessage("Soandso: inc".into())
+        ),
+        (
+          at("21:03:09"),
+          RecordedOutput::CopyToClipboard("/tell Soandso Xenk is busy".into())
+        ),
+      ]
+    );
+  }
+
+  #[tokio::test]
+  async fn test_dry_run_timer_runs_on_log_time() {
+    let log_file_path = write_log_file("[Thu Jul 18 21:03:00 2024] Vulak`Aerr is mesmerized.\r\n");
+    let triggers = vec![timer_trigger(
+      TimerStartPolicy::AlwaysStartNewTimer,
+      vec![
+        Effect::Sequence(vec![
+          EffectWithID::new(Effect::ScopedTimerEffect(
+            TimerEffect::WaitUntilSecondsRemain(10),
+          )),
+          EffectWithID::new(speak("Mez ending on ${S}")),
+        ]),
+        speak_when_finished(),
+      ],
+    )];
+
+    let records = run(&log_file_path, triggers).await;
+    _ = std::fs::remove_file(&log_file_path);
+
+    assert_eq!(
+      records,
+      vec![
+        (at("21:03:20"), spoken("Mez ending on Vulak`Aerr")),
+        (at("21:03:30"), spoken("Mez over")),
+      ]
+    );
+  }
+
+  #[tokio::test]
+  async fn test_dry_run_timer_start_policies() {
+    let log_file_path = write_log_file(
+      "[Thu Jul 18 21:03:00 2024] Vulak`Aerr is mesmerized.\r\n\
+       [Thu Jul 18 21:03:10 2024] Vulak`Aerr is mesmerized.\r\n",
+    );
+
+    let always_start_new = run(
+      &log_file_path,
+      vec![timer_trigger(
+        TimerStartPolicy::AlwaysStartNewTimer,
+        vec![speak_when_finished()],
+      )],
+    )
+    .await;
+    assert_eq!(
+      always_start_new,
+      vec![
+        (at("21:03:30"), spoken("Mez over")),
+        (at("21:03:40"), spoken("Mez over")),
+      ]
+    );
+
+    let do_nothing_if_running = run(
+      &log_file_path,
+      vec![timer_trigger(
+        TimerStartPolicy::DoNothingIfTimerRunning,
+        vec![speak_when_finished()],
+      )],
+    )
+    .await;
+    assert_eq!(
+      do_nothing_if_running,
+      vec![(at("21:03:30"), spoken("Mez over"))]
+    );
+
+    let replaces_all = run(
+      &log_file_path,
+      vec![timer_trigger(
+        TimerStartPolicy::StartAndReplacesAllTimersOfTrigger,
+        vec![speak_when_finished()],
+      )],
+    )
+    .await;
+    assert_eq!(
+      replaces_all,
+      vec![
+        (at("21:03:10"), spoken("Mez over")),
+        (at("21:03:40"), spoken("Mez over")),
+      ]
+    );
+
+    _ = std::fs::remove_file(&log_file_path);
+  }
+
+  #[tokio::test]
+  async fn test_dry_run_timer_starts_after_a_start_was_skipped() {
+    // Skipping a start because of DoNothingIfTimerRunning must not stop the Timers from running
+    let log_file_path = write_log_file(
+      "[Thu Jul 18 21:03:00 2024] Vulak`Aerr is mesmerized.\r\n\
+       [Thu Jul 18 21:03:10 2024] Vulak`Aerr is mesmerized.\r\n\
+       [Thu Jul 18 21:03:35 2024] Vulak`Aerr is mesmerized.\r\n",
+    );
+
+    let records = run(
+      &log_file_path,
+      vec![timer_trigger(
+        TimerStartPolicy::DoNothingIfTimerRunning,
+        vec![speak_when_finished()],
+      )],
+    )
+    .await;
+    assert_eq!(
+      records,
+      vec![
+        (at("21:03:30"), spoken("Mez over")),
+        (at("21:04:05"), spoken("Mez over")),
+      ]
+    );
+
+    _ = std::fs::remove_file(&log_file_path);
+  }
+
+  #[tokio::test]
+  async fn test_dry_run_timer_restart_and_clear() {
+    let log_file_path = write_log_file("[Thu Jul 18 21:03:00 2024] Vulak`Aerr is mesmerized.\r\n");
+
+    let restarted = run(
+      &log_file_path,
+      vec![timer_trigger(
+        TimerStartPolicy::AlwaysStartNewTimer,
+        vec![
+          Effect::Sequence(vec![
+            EffectWithID::new(Effect::Pause(Duration::from_secs(5))),
+            EffectWithID::new(Effect::ScopedTimerEffect(TimerEffect::RestartTimer)),
+          ]),
+          speak_when_finished(),
+        ],
+      )],
+    )
+    .await;
+    assert_eq!(restarted, vec![(at("21:03:35"), spoken("Mez over"))]);
+
+    let cleared = run(
+      &log_file_path,
+      vec![timer_trigger(
+        TimerStartPolicy::AlwaysStartNewTimer,
+        vec![
+          Effect::Sequence(vec![
+            EffectWithID::new(Effect::Pause(Duration::from_secs(5))),
+            EffectWithID::new(Effect::ScopedTimerEffect(TimerEffect::ClearTimer)),
+          ]),
+          speak_when_finished(),
+        ],
+      )],
+    )
+    .await;
+    assert_eq!(cleared, vec![(at("21:03:05"), spoken("Mez over"))]);
+
+    _ = std::fs::remove_file(&log_file_path);
+  }
+
+  #[tokio::test]
+  async fn test_dry_run_timer_ends_when_filter_matches() {
+    let log_file_path = write_log_file(
+      "[Thu Jul 18 21:03:00 2024] Vulak`Aerr is mesmerized.\r\n\
+       [Thu Jul 18 21:03:04 2024] Soandso has been awakened by Xenk.\r\n\
+       [Thu Jul 18 21:03:12 2024] Vulak`Aerr has been awakened by Xenk.\r\n",
+    );
+    let awakened: FilterWithContext = vec![MatcherWithContext::GINA {
+      id: UUID::new(),
+      pattern: r"^${S} has been awakened by {C}\.$".to_owned(),
+      options: MatchOptions::default(),
+    }]
+    .into();
+
+    let records = run(
+      &log_file_path,
+      vec![timer_trigger(
+        TimerStartPolicy::AlwaysStartNewTimer,
+        vec![
+          Effect::Sequence(vec![
+            EffectWithID::new(Effect::ScopedTimerEffect(
+              TimerEffect::WaitUntilFilterMatches(awakened, None),
+            )),
+            EffectWithID::new(Effect::ScopedTimerEffect(TimerEffect::ClearTimer)),
+          ]),
+          speak_when_finished(),
+        ],
+      )],
+    )
+    .await;
+    _ = std::fs::remove_file(&log_file_path);
+
+    assert_eq!(records, vec![(at("21:03:12"), spoken("Mez over"))]);
+  }
+
+  /// Runs the log file with every Trigger in one active TriggerTag, and returns the records
+  /// sorted by time
+  async fn run(log_file_path: &Path, triggers: Vec<Trigger>) -> Vec<(String, RecordedOutput)> {
+    let mut index = TriggerIndex::new();
+    let tag_id = tag_triggers(&mut index, "Dry Run", triggers);
+    run_index(log_file_path, &index, [tag_id].into()).await
+  }
+
+  async fn run_index(
+    log_file_path: &Path,
+    index: &TriggerIndex,
+    active_trigger_tags: HashSet<UUID>,
+  ) -> Vec<(String, RecordedOutput)> {
+    let records = run_log_file(log_file_path, index, active_trigger_tags, None, &[])
+      .await
+      .unwrap();
+    let mut records: Vec<(Timestamp, RecordedOutput)> = records
+      .into_iter()
+      .map(|r| (r.timestamp, r.output))
+      .collect();
+    records.sort_by(|a, b| a.0.cmp(&b.0));
+    records
+      .into_iter()
+      .map(|(timestamp, output)| (timestamp.to_string(), output))
+      .collect()
+  }
+
+  fn tag_triggers(index: &mut TriggerIndex, tag_name: &str, triggers: Vec<Trigger>) -> UUID {
+    let tag_id = index.create_trigger_tag(tag_name).id;
+    for trigger in triggers {
+      let trigger_id = trigger.id.clone();
+      index.import_trigger(trigger);
+      index
+        .mutate(Mutation::TagTrigger {
+          trigger_id,
+          trigger_tag_id: tag_id.clone(),
+        })
+        .unwrap();
+    }
+    tag_id
+  }
+
+  fn write_log_file(contents: &str) -> PathBuf {
+    let log_file_path =
+      std::env::temp_dir().join(format!("eqlog_Xenk_{}.txt", random_id(8).to_lowercase()));
+    std::fs::write(&log_file_path, contents).unwrap();
+    log_file_path
+  }
+
+  fn at(time: &str) -> String {
+    let naive =
+      chrono::NaiveDateTime::parse_from_str(&format!("2024-07-18 {time}"), "%Y-%m-%d %H:%M:%S")
+        .unwrap();
+    Timestamp::from(naive).to_string()
+  }
+
+  fn speak(tmpl: &str) -> Effect {
+    Effect::Speak {
+      tmpl: tmpl.into(),
+      interrupt: false,
+    }
+  }
+
+  fn spoken(text: &str) -> RecordedOutput {
+    RecordedOutput::Speak {
+      text: text.into(),
+      interrupt: false,
+    }
+  }
+
+  fn speak_when_finished() -> Effect {
+    Effect::Sequence(vec![
+      EffectWithID::new(Effect::ScopedTimerEffect(TimerEffect::WaitUntilFinished)),
+      EffectWithID::new(speak("Mez over")),
+    ])
+  }
+
+  /// A Trigger that starts a 30-second Timer named after the mezzed NPC
+  fn timer_trigger(start_policy: TimerStartPolicy, timer_effects: Vec<Effect>) -> Trigger {
+    let mut trigger = sample_trigger(r"^{S} is mesmerized\.$", vec![]);
+    trigger.effects = vec![EffectWithID::new(Effect::StartTimer(Timer {
+      trigger_id: trigger.id.clone(),
+      name_tmpl: "Mez ${S}".into(),
+      tags: vec![],
+      duration: Duration::from_secs(30),
+      start_policy,
+      repeats: false,
+      effects: timer_effects.into_iter().map(EffectWithID::new).collect(),
+    }))];
+    trigger
+  }
+
+  fn sample_trigger(pattern: &str, effects: Vec<Effect>) -> Trigger {
+    Trigger {
+      id: UUID::new(),
+      parent_id: None,
+      name: "Dry Run Sample".into(),
+      comment: None,
+      filter: vec![Matcher::gina(pattern).unwrap()].into(),
+      effects: effects.into_iter().map(EffectWithID::new).collect(),
+      created_at: Timestamp::now(),
+      updated_at: Timestamp::now(),
+    }
+  }
+}
@@ -32,6 +32,7 @@ pub enum GINAConversionError {
   #[error("Encountered unknown Timer duration for timer with name `{0:?}`")]
   TimerDurationError(TemplateString),
   #[error("Encountered unknown Timer Start Behavior for timer with name `{0:?}`")]
+  #[allow(unused)]
   TimerStartPolicyError(TemplateString),
   #[error("Encountered an invalid Early Ender")]
   EarlyEnderPatternError,
@@ -56,7 +57,7 @@ impl GINATriggers {
       .collect();
 
     for gina_trigger_group in self.trigger_groups.into_iter() {
-      gina_trigger_group.convert_import(index, None, &category_tags, &import_time, progress)?;
+      gina_trigger_group.convert_import(index, None, &category_tags, import_time, progress)?;
     }
     Ok(())
   }
@@ -174,7 +175,8 @@ impl GINATrigger {
       }
       (Some(text), Some(false)) | (Some(text), None) => vec![matchers::Matcher::WholeLine {
         id: UUID::new(),
-        pattern: text.to_owned(),
+        pattern: text.into(),
+        options: matchers::MatchOptions::default(),
       }]
       .into(),
       _ => return Err(GINAConversionError::TriggerPatternError(trigger_name.to_owned()).into()),
@@ -226,7 +228,7 @@ impl GINATrigger {
 
         Some(GINATimerType::Stopwatch) => {
           let stopwatch = Stopwatch {
-            name: timer_name.into(),
+            name: timer_name,
             tags: vec![/* TODO! */],
             effects: {
               if let Some(terminator) = self.early_enders_to_terminator()? {
@@ -326,7 +328,7 @@ impl GINATrigger {
 
       vec![display_text, copy_text, tts, play_sound_file, timer]
         .into_iter()
-        .filter_map(|e| e)
+        .flatten()
         .map(EffectWithID::new)
         .collect()
     };
@@ -414,10 +416,12 @@ impl GINAEarlyEnder {
       (Some(true), Some(pattern)) => matchers::MatcherWithContext::GINA {
         id: UUID::new(),
         pattern: pattern.to_owned(),
+        options: matchers::MatchOptions::default(),
       },
       (Some(false), Some(line)) => matchers::MatcherWithContext::WholeLine {
         id: UUID::new(),
         pattern: line.to_owned(),
+        options: matchers::MatchOptions::default(),
       },
       _ => return Err(GINAConversionError::EarlyEnderPatternError),
     })
@@ -59,7 +59,7 @@ pub fn import_from_gina_export_file(
         {
           progress_reporter_.update(format!(
             "LogQuest conversion failed!\nError: {}",
-            gina_import_error.to_string()
+            gina_import_error
           ));
         } else {
           progress_reporter_.update("LogQuest conversion complete!\nReloading data");
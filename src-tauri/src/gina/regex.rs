@@ -1,8 +1,11 @@
 use crate::common::random_id;
-use crate::matchers::MatchContext;
+use crate::common::serializable_regex::case_insensitive_regex;
+use crate::matchers::{BuiltInVariables, MatchContext};
 use fancy_regex::{Captures, Regex};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::{HashMap, LinkedList};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
 use tracing::warn;
 
 lazy_static::lazy_static! {
@@ -35,18 +38,30 @@ lazy_static::lazy_static! {
 type ConditionsList = LinkedList<Box<dyn Fn(&Captures) -> bool + Send + Sync + 'static>>;
 struct Conditions(ConditionsList);
 
-#[derive(Debug, ts_rs::TS)]
+/// Time spent in each stage of `RegexGINA::check`, accumulated across calls to `check_with`
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CheckTimings {
+  pub regex: Duration,
+  /// Time spent in the closures that check numeric constraints like {N>=100}
+  pub conditions: Duration,
+}
+
+/// Cloning a RegexGINA is cheap; it does not re-compile the pattern
+#[derive(Debug, Clone, ts_rs::TS)]
 #[ts(type = "string")]
 pub struct RegexGINA {
   pub raw: String,
   #[ts(skip)]
   compiled: Regex,
+  /// Only compiled the first time a case-insensitive Matcher needs it
+  #[ts(skip)]
+  case_insensitive: Arc<OnceLock<Regex>>,
   #[ts(skip)]
   named_projections: HashMap<String, String>,
   #[ts(skip)]
   positional_projections: Vec<usize>,
   #[ts(skip)]
-  conditions: Conditions,
+  conditions: Arc<Conditions>,
 }
 
 impl TryFrom<&str> for RegexGINA {
@@ -129,9 +144,10 @@ impl RegexGINA {
     Ok(Self {
       raw: pattern.to_owned(),
       compiled,
+      case_insensitive: Arc::new(OnceLock::new()),
       named_projections,
       positional_projections,
-      conditions: Conditions(conditions),
+      conditions: Arc::new(Conditions(conditions)),
     })
   }
 
@@ -151,19 +167,48 @@ impl RegexGINA {
     Self::from_str_without_fixing_character_classes(&processed_pattern)
   }
 
+  /// Returns the names of all context variables (e.g. `${1}` or `${name}`) referenced by
+  /// a pattern meant for `from_str_with_context`.
+  pub fn context_references(pattern: &str) -> Vec<String> {
+    REGEX_REFERENCES
+      .captures_iter(pattern)
+      .filter_map(|captures| captures.ok())
+      .filter_map(|captures| captures.get(1).map(|m| m.as_str().to_owned()))
+      .collect()
+  }
+
+  /// The compiled pattern has the same capture names as `compiled`, so the projections
+  /// and conditions work for both
+  fn case_insensitive(&self) -> &Regex {
+    self.case_insensitive.get_or_init(|| {
+      case_insensitive_regex(self.compiled.as_str()).unwrap_or_else(|| self.compiled.clone())
+    })
+  }
+
+  /// The number of numeric constraints (e.g. {N>=100}) checked after the regex matches
+  pub fn condition_count(&self) -> usize {
+    self.conditions.0.len()
+  }
+
+  /// Returns true if a MatchContext created by this RegexGINA could have a value for the
+  /// given variable, which is either a group number or a capture name (e.g. "S1" or "C").
+  pub fn defines_variable(&self, var_name: &str) -> bool {
+    if let Ok(group_number) = var_name.parse::<usize>() {
+      group_number < self.positional_projections.len()
+    } else {
+      self
+        .named_projections
+        .values()
+        .any(|name| name.eq_ignore_ascii_case(var_name))
+    }
+  }
+
   fn interpolate_escaped_context_variables(pattern: &str, context: &MatchContext) -> String {
     REGEX_REFERENCES
       .replace_all(pattern, |captures: &Captures| {
         if let Some(group_reference_match) = captures.get(1) {
-          let group_reference = group_reference_match.as_str();
-          if let Ok(group_number) = group_reference.parse::<usize>() {
-            if let Some(group_value) = context.group(group_number) {
-              return fancy_regex::escape(group_value).into_owned();
-            }
-          } else {
-            if let Some(group_value) = context.named_value(group_reference) {
-              return fancy_regex::escape(group_value).into_owned();
-            }
+          if let Some(value) = context.variable(group_reference_match.as_str()) {
+            return fancy_regex::escape(&value).into_owned();
           }
         }
         String::new()
@@ -173,24 +218,54 @@ impl RegexGINA {
 
   /// Returns a MatchContext if the RegexGINA matches. A character name must be passed in
   /// because the regex could have a {C} token.
+  #[cfg(test)]
   pub fn check(&self, line: &str, character_name: &str) -> Option<MatchContext> {
-    let direct_captures: Captures = match self.compiled.captures(line) {
+    self.check_with(line, character_name, false, None)
+  }
+
+  /// The same as `check`, but optionally ignoring case, and adding the time spent in each
+  /// stage to `timings` if it is given.
+  pub fn check_with(
+    &self,
+    line: &str,
+    character_name: &str,
+    case_insensitive: bool,
+    mut timings: Option<&mut CheckTimings>,
+  ) -> Option<MatchContext> {
+    let compiled = if case_insensitive {
+      self.case_insensitive()
+    } else {
+      &self.compiled
+    };
+    let started_at = timings.as_ref().map(|_| Instant::now());
+    let captures = compiled.captures(line);
+    if let (Some(timings), Some(started_at)) = (timings.as_mut(), started_at) {
+      timings.regex += started_at.elapsed();
+    }
+    let direct_captures: Captures = match captures {
       Ok(Some(captures)) => captures,
       Ok(None) => return None,
       Err(_) => return None,
     };
 
-    for condition in self.conditions.0.iter() {
-      if !condition(&direct_captures) {
-        return None;
-      }
+    let started_at = timings.as_ref().map(|_| Instant::now());
+    let conditions_passed = self
+      .conditions
+      .0
+      .iter()
+      .all(|condition| condition(&direct_captures));
+    if let (Some(timings), Some(started_at)) = (timings.as_mut(), started_at) {
+      timings.conditions += started_at.elapsed();
+    }
+    if !conditions_passed {
+      return None;
     }
 
     let mut named_values = HashMap::<String, String>::new();
     for (capture_name, output_name) in self.named_projections.iter() {
       let Some(captured_value) = direct_captures
-        .name(&capture_name)
-        .and_then(|m| Some(m.as_str().to_owned()))
+        .name(capture_name)
+        .map(|m| m.as_str().to_owned())
       else {
         continue;
       };
@@ -220,6 +295,7 @@ impl RegexGINA {
       group_values,
       named_values,
       character_name: character_name.to_owned(),
+      built_ins: BuiltInVariables::default(),
     })
   }
 
@@ -278,7 +354,7 @@ impl RegexGINA {
   /// having an auto-fixer for this is crucial for a proper GINA import. This function takes in a regex
   /// pattern and escapes any of these invalid "-" characters in character classes.
   fn fix_possibly_invalid_character_classes(pattern: &str) -> String {
-    let all_fixed = CHARACTER_CLASS_CONTENTS.replace_all(&pattern, |cc_captures: &Captures| {
+    let all_fixed = CHARACTER_CLASS_CONTENTS.replace_all(pattern, |cc_captures: &Captures| {
       let whole_match = cc_captures.get(0).unwrap().as_str().to_owned(); // unwrap should be safe here
 
       let Some(inner_match) = cc_captures.get(1) else {
@@ -342,12 +418,6 @@ impl<'de> Deserialize<'de> for RegexGINA {
   }
 }
 
-impl Clone for RegexGINA {
-  fn clone(&self) -> Self {
-    self.raw.as_str().try_into().unwrap() // unwrap is safe since raw has been compiled before
-  }
-}
-
 impl Eq for RegexGINA {}
 impl PartialEq for RegexGINA {
   fn eq(&self, other: &Self) -> bool {
@@ -559,6 +629,13 @@ mod tests {
     assert!(re
       .check("Goner has been slain by Vulak`Aerr!", TOON)
       .is_none());
+
+    let mut context = create_context("^You have entered (.+)\\.$", "You have entered Oasis.");
+    context.built_ins.zone = Some("Oasis of Marr".into());
+    let re = RegexGINA::from_str_with_context(r"^${C} leaves ${ZONE}\.$", &context).unwrap();
+    assert!(re
+      .check(&format!("{TOON} leaves Oasis of Marr."), TOON)
+      .is_some());
   }
 
   #[test]
@@ -613,7 +690,7 @@ mod tests {
   fn assert_pattern_does_not_match(pattern: &str, texts: &[&str]) {
     let regex_gina: RegexGINA = pattern.try_into().expect("Invalid regex pattern");
     for text in texts {
-      assert!(regex_gina.check(*text, TOON).is_none());
+      assert!(regex_gina.check(text, TOON).is_none());
     }
   }
 
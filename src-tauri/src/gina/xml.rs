@@ -106,10 +106,8 @@ fn parse_trigger_group<R: std::io::Read>(
         "EnableByDefault" => trigger_group.enable_by_default = parse_bool(data),
         _ => {}
       },
-      Ok(XmlEvent::EndElement { name }) => {
-        if name.local_name == "TriggerGroup" {
-          break;
-        }
+      Ok(XmlEvent::EndElement { name }) if name.local_name == "TriggerGroup" => {
+        break;
       }
       Err(e) => return Err(GINAParseError::XMLError(e)),
       _ => {}
@@ -190,10 +188,8 @@ fn parse_trigger<R: std::io::Read>(
         "UseFastCheck" => trigger.use_fast_check = parse_bool(data),
         _ => {}
       },
-      Ok(XmlEvent::EndElement { name }) => {
-        if name.local_name == "Trigger" {
-          break;
-        }
+      Ok(XmlEvent::EndElement { name }) if name.local_name == "Trigger" => {
+        break;
       }
       Err(e) => return Err(GINAParseError::XMLError(e)),
       _ => {}
@@ -223,10 +219,10 @@ fn parse_timer_trigger<R: std::io::Read>(
         "PlayMediaFile" => timer_trigger.play_media_file = parse_bool(data),
         _ => {}
       },
-      Ok(XmlEvent::EndElement { name }) => {
-        if name.local_name == "TimerEndingTrigger" || name.local_name == "TimerEndedTrigger" {
-          break;
-        }
+      Ok(XmlEvent::EndElement { name })
+        if (name.local_name == "TimerEndingTrigger" || name.local_name == "TimerEndedTrigger") =>
+      {
+        break;
       }
       Err(e) => return Err(GINAParseError::XMLError(e)),
       _ => {}
@@ -252,10 +248,8 @@ fn parse_early_ender<R: std::io::Read>(
         "EnableRegex" => early_ender.enable_regex = parse_bool(data),
         _ => {}
       },
-      Ok(XmlEvent::EndElement { name }) => {
-        if name.local_name == "EarlyEnder" {
-          break;
-        }
+      Ok(XmlEvent::EndElement { name }) if name.local_name == "EarlyEnder" => {
+        break;
       }
       Err(e) => return Err(GINAParseError::XMLError(e)),
       _ => {}
@@ -2,44 +2,89 @@ use super::log_event_broadcaster::NotifyError;
 use super::{LogFileEvent, LOG_FILENAME_PATTERN};
 use crate::common::shutdown::quitter;
 use futures::FutureExt as _;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
 use tauri::async_runtime::spawn;
 use tokio::select;
 use tokio::sync::{broadcast, oneshot, watch};
+use tokio::time::Instant;
 use tracing::{debug, error, info, warn};
 
-#[derive(Debug, Clone, Serialize, ts_rs::TS)]
+#[derive(Debug, Clone, PartialEq, Serialize, ts_rs::TS)]
 pub struct Character {
   pub name: String,
-  #[allow(unused)]
   pub server: String,
   pub log_file_path: String,
 }
 
+/// By default, another character only becomes the active one once the log file of the active
+/// character has not been written to for this long, so that a boxed character whose log file is
+/// written to now and then does not take over
+pub const DEFAULT_CHARACTER_SWITCH_DELAY: Duration = Duration::from_secs(5);
+
+/// How many times the log file of another character must be written to since the active
+/// character's log file was, before it can take over. A single line (e.g. a spell wearing off
+/// a boxed character) is not enough, however long the active character has been idle.
+pub const CANDIDATE_WRITES_TO_SWITCH: u32 = 3;
+
+/// Why the active character is the active one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ts_rs::TS)]
+pub enum CharacterDetectionReason {
+  /// Its log file was written to while no character was active
+  LogWritten,
+  /// Its log file kept being written to after the log file of the previously active character
+  /// had not been for the switch delay
+  PreviousCharacterIdle,
+  /// It is pinned in the config, so the log files that are written to do not matter
+  Pinned,
+}
+
+/// A character that is always the active one, regardless of which log files are written to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ts_rs::TS)]
+pub struct PinnedCharacter {
+  pub name: String,
+  pub server: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectedCharacter {
+  pub character: Character,
+  pub reason: CharacterDetectionReason,
+}
+
 pub struct ActiveCharacterDetector {
-  watcher: watch::Receiver<Option<Character>>,
+  watcher: watch::Receiver<Option<DetectedCharacter>>,
+  pinner: watch::Sender<Option<Character>>,
   stopper: oneshot::Sender<()>,
 }
 
 impl ActiveCharacterDetector {
-  pub fn start(subscription: broadcast::Receiver<Result<LogFileEvent, NotifyError>>) -> Self {
-    let (change_sender, change_receiver) = watch::channel::<Option<Character>>(None);
+  pub fn start(
+    subscription: broadcast::Receiver<Result<LogFileEvent, NotifyError>>,
+    pinned: Option<Character>,
+    switch_delay: Duration,
+  ) -> Self {
+    let (change_sender, change_receiver) = watch::channel::<Option<DetectedCharacter>>(None);
+    let (pin_sender, pin_receiver) = watch::channel::<Option<Character>>(pinned);
     let (stop_sender, stop_receiver) = oneshot::channel::<()>();
 
     debug!("Spawning tokio task");
     spawn(determine_active_character_from_file_events_async(
       subscription,
+      Detection::new(switch_delay),
+      pin_receiver,
       change_sender,
       stop_receiver,
     ));
 
     Self {
       watcher: change_receiver,
+      pinner: pin_sender,
       stopper: stop_sender,
     }
   }
 
-  pub fn current(&self) -> Option<Character> {
+  pub fn current(&self) -> Option<DetectedCharacter> {
     self.watcher.borrow().clone()
   }
 
@@ -49,6 +94,15 @@ impl ActiveCharacterDetector {
     self.watcher.changed()
   }
 
+  /// Makes the given character the active one until it is unpinned with `None`
+  pub fn pin(&self, character: Option<Character>) {
+    self.pinner.send_if_modified(|pinned| {
+      let modified = *pinned != character;
+      *pinned = character;
+      modified
+    });
+  }
+
   pub fn stop(self) {
     debug!("Sending stop signal");
     _ = self.stopper.send(());
@@ -76,24 +130,200 @@ impl Character {
       log_file_path: input.to_owned(),
     }
   }
+
+  /// The characters of the given log file paths, sorted by name and server
+  pub fn all_from<'a>(log_file_paths: impl Iterator<Item = &'a String>) -> Vec<Self> {
+    let mut characters: Vec<Self> = log_file_paths.map(|path| Self::from(path)).collect();
+    characters.sort_by(|a, b| (&a.name, &a.server).cmp(&(&b.name, &b.server)));
+    characters
+  }
+}
+
+impl PinnedCharacter {
+  /// Finds the pinned character among the characters with a log file, ignoring case. A pin that
+  /// does not match a log file is never used to build a path.
+  pub fn find_in(&self, characters: Vec<Character>) -> Option<Character> {
+    characters.into_iter().find(|character| {
+      character.name.eq_ignore_ascii_case(&self.name)
+        && character.server.eq_ignore_ascii_case(&self.server)
+    })
+  }
+}
+
+/// Decides which character is active from the log files that are written to. Another character
+/// only takes over once its log file has been written to `CANDIDATE_WRITES_TO_SWITCH` times and
+/// the active character's log file has gone quiet for the switch delay.
+struct Detection {
+  switch_delay: Duration,
+  pinned: bool,
+  current: Option<DetectedCharacter>,
+  /// When the log file of the current character was last written to
+  current_written_at: Instant,
+  /// The log file of another character that was written to most recently
+  candidate: Option<Candidate>,
+}
+
+/// Only counts the writes since the current character's log file was last written to
+struct Candidate {
+  path: String,
+  writes: u32,
+  last_written_at: Instant,
+}
+
+impl Detection {
+  fn new(switch_delay: Duration) -> Self {
+    Self {
+      switch_delay,
+      pinned: false,
+      current: None,
+      current_written_at: Instant::now(),
+      candidate: None,
+    }
+  }
+
+  fn current_path(&self) -> Option<&str> {
+    self
+      .current
+      .as_ref()
+      .map(|current| current.character.log_file_path.as_str())
+  }
+
+  /// Each of these returns whether the active character changed
+  fn written(&mut self, path: &str, now: Instant) -> bool {
+    if self.pinned {
+      return false;
+    }
+    match self.current_path() {
+      Some(current) if current == path => {
+        self.current_written_at = now;
+        self.candidate = None;
+        false
+      }
+      Some(_) => {
+        match &mut self.candidate {
+          Some(candidate) if candidate.path == path => {
+            candidate.writes += 1;
+            candidate.last_written_at = now;
+          }
+          _ => {
+            self.candidate = Some(Candidate {
+              path: path.to_owned(),
+              writes: 1,
+              last_written_at: now,
+            })
+          }
+        }
+        self.switch_if_idle(now)
+      }
+      None => {
+        self.switch_to(path, now, CharacterDetectionReason::LogWritten);
+        true
+      }
+    }
+  }
+
+  /// When the log file of a pinned character is created again after it could not be followed,
+  /// the pinned character is announced again so that it is followed
+  fn created(&mut self, path: &str, now: Instant) -> bool {
+    if self.pinned {
+      return self.current_path() == Some(path);
+    }
+    self.written(path, now)
+  }
+
+  fn deleted(&mut self, path: &str) -> bool {
+    if self
+      .candidate
+      .as_ref()
+      .is_some_and(|candidate| candidate.path == path)
+    {
+      self.candidate = None;
+    }
+    if self.pinned || self.current_path() != Some(path) {
+      return false;
+    }
+    self.current = None;
+    true
+  }
+
+  fn switch_if_idle(&mut self, now: Instant) -> bool {
+    match self.deadline() {
+      Some(deadline) if deadline <= now => {
+        let candidate = self.candidate.take().expect("deadline without a candidate");
+        self.switch_to(
+          &candidate.path,
+          candidate.last_written_at,
+          CharacterDetectionReason::PreviousCharacterIdle,
+        );
+        true
+      }
+      _ => false,
+    }
+  }
+
+  /// When the candidate takes over unless the current character's log file is written to first
+  fn deadline(&self) -> Option<Instant> {
+    self
+      .candidate
+      .as_ref()
+      .filter(|candidate| candidate.writes >= CANDIDATE_WRITES_TO_SWITCH)
+      .map(|_| self.current_written_at + self.switch_delay)
+  }
+
+  fn pin(&mut self, pinned: Option<Character>) -> bool {
+    let was_pinned = self.pinned;
+    self.pinned = pinned.is_some();
+    self.candidate = None;
+    match pinned {
+      Some(character) => {
+        let pinned = DetectedCharacter {
+          character,
+          reason: CharacterDetectionReason::Pinned,
+        };
+        let changed = self.current.as_ref() != Some(&pinned);
+        self.current = Some(pinned);
+        changed
+      }
+      // Whichever log file is written to next is the active one again
+      None if was_pinned => self.current.take().is_some(),
+      None => false,
+    }
+  }
+
+  fn switch_to(&mut self, path: &str, written_at: Instant, reason: CharacterDetectionReason) {
+    self.current = Some(DetectedCharacter {
+      character: Character::from(path),
+      reason,
+    });
+    self.current_written_at = written_at;
+  }
 }
 
 async fn determine_active_character_from_file_events_async(
   mut rx_fs_events: broadcast::Receiver<Result<LogFileEvent, NotifyError>>,
-  change_sender: tokio::sync::watch::Sender<Option<Character>>,
+  mut detection: Detection,
+  mut pin_receiver: watch::Receiver<Option<Character>>,
+  change_sender: tokio::sync::watch::Sender<Option<DetectedCharacter>>,
   stop_receiver: tokio::sync::oneshot::Receiver<()>,
 ) {
   debug!("Started async active character detector task");
-  let mut current_active_file: Option<String> = None;
 
   // the fuse makes the oneshot receiver usable in a loop (avoiding a move error with previous loop iterations)
   let mut stop_receiver = stop_receiver.fuse();
 
+  let send_change = |detection: &Detection| {
+    info!("Sending active character change: {:#?}", detection.current);
+    change_sender.send(detection.current.clone())
+  };
+  if detection.pin(pin_receiver.borrow_and_update().clone()) && send_change(&detection).is_err() {
+    return;
+  }
+
   let mut quit = quitter();
   debug!("Starting select loop for LogFileEvents");
   loop {
-    // TODO: This should maybe debounce a timeout if a file hasn't been updated after N seconds.
-    select! {
+    let deadline = detection.deadline();
+    let changed = select! {
         _ = &mut quit => {
           debug!("ActiveCharacterDetector QUITTING");
           break;
@@ -103,6 +333,18 @@ async fn determine_active_character_from_file_events_async(
           break;
         },
 
+        pin_change = pin_receiver.changed() => {
+          if pin_change.is_err() {
+            debug!("Character pin channel closed");
+            break;
+          }
+          detection.pin(pin_receiver.borrow_and_update().clone())
+        }
+
+        _ = sleep_until_deadline(deadline) => {
+          detection.switch_if_idle(Instant::now())
+        }
+
         log_file_event = rx_fs_events.recv() => {
           debug!("Got a new LogFileEvent: {log_file_event:?}");
           match log_file_event {
@@ -114,42 +356,125 @@ async fn determine_active_character_from_file_events_async(
 
             Err(broadcast::error::RecvError::Lagged(num_behind)) => {
               warn!("Active Character Detector lagged behind filesystem events by {num_behind} messages");
+              false
             }
 
-            Ok(Ok(LogFileEvent::Created(event_path)) | Ok(LogFileEvent::Updated(event_path))) => {
+            Ok(Ok(LogFileEvent::Created(event_path))) => {
+              debug!("Active Character Detector encountered a Created event for {}", &event_path);
+              detection.created(&event_path, Instant::now())
+            },
+
+            Ok(Ok(LogFileEvent::Updated(event_path))) => {
               debug!("Active Character Detector encountered an event for {}", &event_path);
-              if let Some(current) = current_active_file.as_deref() {
-                if current == &event_path {
-                  continue;
-                }
-              }
-              let cnws = Character::from(&event_path);
-              info!("Sending active character change: {cnws:#?}");
-              if let Err(e) = change_sender.send(Some(cnws)) {
-                warn!("Couldn't send a change: {e:#?}");
-                break;
-              }
-              current_active_file = Some(event_path);
+              detection.written(&event_path, Instant::now())
             },
 
+            Ok(Ok(LogFileEvent::Rotated(rotated_path))) => {
+              // Replacing or truncating a log file (e.g. when archiving it) does not mean its
+              // character is being played. EverQuest writing to it afterwards does.
+              debug!("Active Character Detector ignoring a Rotated event for {rotated_path}");
+              false
+            }
+
             Ok(Ok(LogFileEvent::Deleted(deleted_path))) => {
               debug!("Active Character Detector encountered a Deleted event for {}", &deleted_path);
-              if let Some(current) = current_active_file.as_deref() {
-                if current == deleted_path {
-                  current_active_file = None;
-                  if let Err(e) = change_sender.send(None) {
-                    warn!("Couldn't send a change: {e:#?}");
-                    break;
-                  }
-                }
-              }
+              detection.deleted(&deleted_path)
             }
             Ok(Err(notify_error)) => {
               error!("Encountered a Notify error: {notify_error:?}") ;
+              false
             }
           }
         }
+    };
+    if changed {
+      if let Err(e) = send_change(&detection) {
+        warn!("Couldn't send a change: {e:#?}");
+        break;
+      }
     }
   }
   debug!("Character change detection loop complete");
 }
+
+async fn sleep_until_deadline(deadline: Option<Instant>) {
+  match deadline {
+    Some(deadline) => tokio::time::sleep_until(deadline).await,
+    None => std::future::pending().await,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{CharacterDetectionReason, Detection};
+  use std::time::Duration;
+  use tokio::time::Instant;
+
+  const XENK: &str = "/logs/eqlog_Xenk_P1999Green.txt";
+  const BOXED: &str = "/logs/eqlog_Boxed_P1999Green.txt";
+
+  fn current_name(detection: &Detection) -> Option<&str> {
+    detection
+      .current
+      .as_ref()
+      .map(|current| current.character.name.as_str())
+  }
+
+  #[test]
+  fn test_detection() {
+    let start = Instant::now();
+    let at = |secs: u64| start + Duration::from_secs(secs);
+    let mut detection = Detection::new(Duration::from_secs(5));
+
+    assert!(detection.written(XENK, at(0)));
+    assert_eq!(current_name(&detection), Some("Xenk"));
+
+    // The boxed character does not take over while Xenk's log keeps being written
+    assert!(!detection.written(BOXED, at(2)));
+    assert!(!detection.written(XENK, at(4)));
+    assert_eq!(detection.deadline(), None);
+
+    // ...or with a single write, however long Xenk's log has been quiet
+    assert!(!detection.written(BOXED, at(6)));
+    assert!(!detection.switch_if_idle(at(60)));
+    assert_eq!(detection.deadline(), None);
+
+    // ...but it does once its log is written to repeatedly and Xenk's log has been quiet for the
+    // switch delay
+    assert!(!detection.written(XENK, at(61)));
+    assert!(!detection.written(BOXED, at(62)));
+    assert!(!detection.written(BOXED, at(63)));
+    assert!(!detection.written(BOXED, at(64)));
+    assert_eq!(detection.deadline(), Some(at(66)));
+    assert!(!detection.switch_if_idle(at(65)));
+    assert!(detection.switch_if_idle(at(66)));
+    assert_eq!(current_name(&detection), Some("Boxed"));
+    assert_eq!(
+      detection.current.as_ref().unwrap().reason,
+      CharacterDetectionReason::PreviousCharacterIdle
+    );
+    assert_eq!(detection.deadline(), None);
+
+    assert!(detection.deleted(BOXED));
+    assert_eq!(current_name(&detection), None);
+  }
+
+  #[test]
+  fn test_pinned_detection() {
+    let start = Instant::now();
+    let mut detection = Detection::new(Duration::ZERO);
+    let xenk = super::Character::from(XENK);
+
+    assert!(detection.pin(Some(xenk.clone())));
+    assert!(!detection.pin(Some(xenk)));
+    assert!(!detection.written(BOXED, start));
+    assert!(!detection.deleted(XENK));
+    assert_eq!(current_name(&detection), Some("Xenk"));
+    assert!(!detection.created(BOXED, start));
+    assert!(detection.created(XENK, start));
+
+    assert!(detection.pin(None));
+    assert!(detection.written(BOXED, start));
+    assert_eq!(current_name(&detection), Some("Boxed"));
+  }
+}
@@ -0,0 +1,376 @@
+//! Archiving moves the old lines of EverQuest log files into gzipped archives in an Archive
+//! directory next to the log files, so that logs that have grown to gigabytes stay fast to scan
+//! and seek. Each archive holds the lines of one log file up to the date in its file name, and
+//! `open_with_archives` reads them back ahead of the lines that are still in the log file.
+//!
+//! EverQuest keeps writing to a log file for as long as its character is logged in, so only the
+//! log files of characters that are not being played are archived, and the archive-logs command
+//! refuses to run while the LogQuest app is running.
+use super::{search::for_each_line_in, Line, LOG_FILENAME_PATTERN};
+use crate::common::timestamp::Timestamp;
+use fancy_regex::Regex;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use std::{
+  fs,
+  io::{self, Read as _, Seek as _, SeekFrom, Write as _},
+  path::{Path, PathBuf},
+  time::{Duration, SystemTime},
+};
+use tracing::{info, warn};
+
+pub const LOG_ARCHIVE_DIR_NAME: &str = "Archive";
+
+/// How many days of lines are left in a log file when `log_archive_after_days` is not set
+pub const DEFAULT_ARCHIVE_AFTER_DAYS: u32 = 30;
+
+/// A log file that was written to more recently than this probably belongs to a character that
+/// is still logged in, so it is left alone
+const MIN_IDLE_TIME: Duration = Duration::from_secs(60 * 60);
+
+const ARCHIVE_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// How much of the rest of a log file is moved to its start at a time
+const MOVE_CHUNK_SIZE: usize = 64 * 1024;
+
+lazy_static::lazy_static! {
+  pub static ref ARCHIVE_FILENAME_PATTERN: Regex =
+    Regex::new(r"(?:\A|[\\/])eqlog_([^_]+)_(.+)_(\d{4}-\d{2}-\d{2})\.txt\.gz$").unwrap();
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum LogArchiveError {
+  #[error("Could not read or write a log file or archive")]
+  IOError(#[from] io::Error),
+
+  #[error("The log file is open in another program: {0}")]
+  LogFileInUse(String),
+
+  #[error("An archive with the same name already exists: {0}")]
+  ArchiveExists(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct ArchivedLog {
+  pub log_file_path: PathBuf,
+  pub archive_path: PathBuf,
+  /// How many bytes were moved out of the log file
+  pub archived_len: u64,
+}
+
+pub fn archive_dir(logs_dir: &Path) -> PathBuf {
+  logs_dir.join(LOG_ARCHIVE_DIR_NAME)
+}
+
+/// Archives the lines that are more than `keep_days` days old in each log file of the logs
+/// directory, or only in those of one character. The `followed` log file and those written to
+/// recently are skipped. A log file that cannot be archived is left as it was.
+pub fn archive_logs(
+  logs_dir: &Path,
+  keep_days: u32,
+  character_name: Option<&str>,
+  followed: Option<&Path>,
+) -> io::Result<Vec<ArchivedLog>> {
+  let Some(cutoff) = chrono::Local::now()
+    .date_naive()
+    .checked_sub_days(chrono::Days::new(keep_days.into()))
+    .and_then(|date| date.and_hms_opt(0, 0, 0))
+  else {
+    return Ok(Vec::new());
+  };
+  let cutoff = Timestamp::from(cutoff);
+  let followed = followed.and_then(|path| path.canonicalize().ok());
+  let archive_dir = archive_dir(logs_dir);
+
+  let mut archived = Vec::new();
+  for entry in fs::read_dir(logs_dir)? {
+    let path = entry?.path();
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let Ok(Some(caps)) = LOG_FILENAME_PATTERN.captures(&file_name) else {
+      continue;
+    };
+    if character_name.is_some_and(|wanted| !wanted.eq_ignore_ascii_case(&caps[1])) {
+      continue;
+    }
+    if followed.is_some() && path.canonicalize().ok() == followed {
+      info!("Not archiving the followed log file {}", path.display());
+      continue;
+    }
+    if written_to_recently(&path) {
+      info!(
+        "Not archiving the recently used log file {}",
+        path.display()
+      );
+      continue;
+    }
+    match archive_log_file(&path, &archive_dir, &cutoff) {
+      Ok(Some(archived_log)) => archived.push(archived_log),
+      Ok(None) => {}
+      Err(e) => warn!("Could not archive {}: {e}", path.display()),
+    }
+  }
+  Ok(archived)
+}
+
+/// Moves the lines of a log file that were logged before `cutoff` into a new archive, and
+/// returns None if there were none. The rest of the log file is then moved to its start and the
+/// file is cut short, in place and under an exclusive lock, so a log file is never replaced by
+/// another file that a program that still has it open would not write to. On Windows, a log file
+/// that EverQuest has open cannot be archived at all.
+pub fn archive_log_file(
+  log_file_path: &Path,
+  archive_dir: &Path,
+  cutoff: &Timestamp,
+) -> Result<Option<ArchivedLog>, LogArchiveError> {
+  let in_use = || LogArchiveError::LogFileInUse(log_file_path.display().to_string());
+  let mut log_file = match open_exclusively(log_file_path) {
+    Ok(file) => file,
+    Err(e) if is_sharing_violation(&e) => return Err(in_use()),
+    Err(e) => return Err(e.into()),
+  };
+  match log_file.try_lock() {
+    Ok(()) => {}
+    Err(fs::TryLockError::WouldBlock) => return Err(in_use()),
+    Err(fs::TryLockError::Error(e)) => return Err(e.into()),
+  }
+
+  let mut split = None;
+  let mut last_archived = None;
+  let reader = io::BufReader::new(log_file.try_clone()?);
+  let end = for_each_line_in(reader, 0, None, |offset, raw_line| {
+    let Some(logged_at) = Line::from(raw_line).ok().and_then(|line| line.timestamp()) else {
+      return true;
+    };
+    if logged_at >= *cutoff {
+      split = Some(offset);
+      return false;
+    }
+    last_archived = Some(logged_at);
+    true
+  })?;
+  let split = split.unwrap_or(end);
+  let Some(last_archived) = last_archived.filter(|_| split > 0) else {
+    return Ok(None);
+  };
+
+  let file_stem = log_file_path
+    .file_stem()
+    .unwrap_or_default()
+    .to_string_lossy();
+  let archive_date = chrono::DateTime::<chrono::Utc>::from(last_archived)
+    .format(ARCHIVE_DATE_FORMAT)
+    .to_string();
+  fs::create_dir_all(archive_dir)?;
+  let archive_path = archive_dir.join(format!("{file_stem}_{archive_date}.txt.gz"));
+  let archive_file = match fs::File::create_new(&archive_path) {
+    Ok(file) => file,
+    Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+      return Err(LogArchiveError::ArchiveExists(
+        archive_path.display().to_string(),
+      ));
+    }
+    Err(e) => return Err(e.into()),
+  };
+
+  if let Err(e) = write_archive(&mut log_file, archive_file, split) {
+    _ = fs::remove_file(&archive_path);
+    return Err(e.into());
+  }
+  // Once the log file starts being rewritten, the archive holds the only copy of its old lines,
+  // so it is kept even if the rewriting fails
+  move_to_start(&mut log_file, split)?;
+  info!(
+    "Archived {split} bytes of {} to {}",
+    log_file_path.display(),
+    archive_path.display()
+  );
+  Ok(Some(ArchivedLog {
+    log_file_path: log_file_path.to_owned(),
+    archive_path,
+    archived_len: split,
+  }))
+}
+
+/// The archives of a log file, oldest first
+pub fn archives_of(log_file_path: &Path) -> io::Result<Vec<PathBuf>> {
+  let Some(logs_dir) = log_file_path.parent() else {
+    return Ok(Vec::new());
+  };
+  let file_stem = log_file_path
+    .file_stem()
+    .unwrap_or_default()
+    .to_string_lossy();
+  let entries = match fs::read_dir(archive_dir(logs_dir)) {
+    Ok(entries) => entries,
+    Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+    Err(e) => return Err(e),
+  };
+  let mut archives = Vec::new();
+  for entry in entries {
+    let path = entry?.path();
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let Ok(Some(caps)) = ARCHIVE_FILENAME_PATTERN.captures(&file_name) else {
+      continue;
+    };
+    if file_stem == format!("eqlog_{}_{}", &caps[1], &caps[2]) {
+      archives.push(path);
+    }
+  }
+  archives.sort();
+  Ok(archives)
+}
+
+/// Opens an archive to read the lines in it
+pub fn open_archive(archive_path: &Path) -> io::Result<io::BufReader<GzDecoder<fs::File>>> {
+  Ok(io::BufReader::new(GzDecoder::new(fs::File::open(
+    archive_path,
+  )?)))
+}
+
+/// Opens a log file to read it from the start, including the lines that were archived from it
+pub fn open_with_archives(
+  log_file_path: &Path,
+) -> io::Result<io::BufReader<Box<dyn io::Read + Send>>> {
+  let archives = open_archives(log_file_path)?;
+  let log_file = fs::File::open(log_file_path)?;
+  Ok(io::BufReader::new(Box::new(archives.chain(log_file))))
+}
+
+/// Opens all the archives of a log file to read them in order, without the log file itself
+pub fn open_archives(log_file_path: &Path) -> io::Result<Box<dyn io::Read + Send>> {
+  let mut reader: Box<dyn io::Read + Send> = Box::new(io::empty());
+  for archive_path in archives_of(log_file_path)? {
+    let archive = GzDecoder::new(fs::File::open(archive_path)?);
+    reader = Box::new(reader.chain(archive));
+  }
+  Ok(reader)
+}
+
+#[cfg(unix)]
+fn open_exclusively(path: &Path) -> io::Result<fs::File> {
+  fs::OpenOptions::new().read(true).write(true).open(path)
+}
+
+/// Other programs can still read the log file, but opening it fails while another program has
+/// it open for writing, and it cannot be written to or deleted until it is closed
+#[cfg(windows)]
+fn open_exclusively(path: &Path) -> io::Result<fs::File> {
+  const FILE_SHARE_READ: u32 = 0x1;
+  use std::os::windows::fs::OpenOptionsExt as _;
+  fs::OpenOptions::new()
+    .read(true)
+    .write(true)
+    .share_mode(FILE_SHARE_READ)
+    .open(path)
+}
+
+fn is_sharing_violation(e: &io::Error) -> bool {
+  const ERROR_SHARING_VIOLATION: i32 = 32;
+  cfg!(windows) && e.raw_os_error() == Some(ERROR_SHARING_VIOLATION)
+}
+
+fn write_archive(log_file: &mut fs::File, archive_file: fs::File, len: u64) -> io::Result<()> {
+  log_file.seek(SeekFrom::Start(0))?;
+  let mut encoder = GzEncoder::new(archive_file, Compression::default());
+  io::copy(&mut (&*log_file).take(len), &mut encoder)?;
+  encoder.finish()?.sync_all()
+}
+
+/// Moves everything from `start` to the end of the file to the start of the file, and cuts the
+/// file short after it
+fn move_to_start(file: &mut fs::File, start: u64) -> io::Result<()> {
+  let mut buffer = vec![0; MOVE_CHUNK_SIZE];
+  let mut read_at = start;
+  let mut write_at = 0;
+  loop {
+    file.seek(SeekFrom::Start(read_at))?;
+    let read = file.read(&mut buffer)?;
+    if read == 0 {
+      break;
+    }
+    file.seek(SeekFrom::Start(write_at))?;
+    file.write_all(&buffer[..read])?;
+    read_at += read as u64;
+    write_at += read as u64;
+  }
+  file.set_len(write_at)?;
+  file.sync_all()
+}
+
+fn written_to_recently(path: &Path) -> bool {
+  fs::metadata(path)
+    .and_then(|metadata| metadata.modified())
+    .is_ok_and(|modified| {
+      SystemTime::now()
+        .duration_since(modified)
+        .is_ok_and(|idle| idle < MIN_IDLE_TIME)
+        || modified > SystemTime::now()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{archive_dir, archive_log_file, archives_of, open_with_archives};
+  use crate::{
+    common::random_id,
+    logs::{search::parse_date_bound, FileIdentity},
+  };
+  use std::{fs, io::BufRead as _};
+
+  #[test]
+  fn test_archive_log_file() {
+    let logs_dir = std::env::temp_dir().join(format!("logs_{}", random_id(8)));
+    fs::create_dir_all(&logs_dir).unwrap();
+    let log_file_path = logs_dir.join("eqlog_Xenk_P1999Green.txt");
+    fs::write(
+      &log_file_path,
+      "[Wed Jul 17 23:59:59 2024] Soandso tells you, 'hello'\r\n\
+       [Thu Jul 18 17:35:14 2024] You have entered East Commonlands.\r\n\
+       [Fri Jul 19 00:00:00 2024] Soandso tells you, 'goodbye'\r\n\
+       [Fri Jul 19 00:00:01 2024] You ",
+    )
+    .unwrap();
+    let archive_dir = archive_dir(&logs_dir);
+    let cutoff = parse_date_bound("2024-07-19", false).unwrap();
+
+    let identity = FileIdentity::of(&fs::metadata(&log_file_path).unwrap());
+    let archived = archive_log_file(&log_file_path, &archive_dir, &cutoff)
+      .unwrap()
+      .unwrap();
+    // The log file is rewritten in place rather than replaced
+    assert!(identity == FileIdentity::of(&fs::metadata(&log_file_path).unwrap()));
+    assert_eq!(
+      archived.archive_path,
+      archive_dir.join("eqlog_Xenk_P1999Green_2024-07-18.txt.gz")
+    );
+    assert_eq!(
+      fs::read_to_string(&log_file_path).unwrap(),
+      "[Fri Jul 19 00:00:00 2024] Soandso tells you, 'goodbye'\r\n\
+       [Fri Jul 19 00:00:01 2024] You "
+    );
+    assert_eq!(
+      archives_of(&log_file_path).unwrap(),
+      vec![archived.archive_path]
+    );
+    // Nothing is left to archive
+    assert!(archive_log_file(&log_file_path, &archive_dir, &cutoff)
+      .unwrap()
+      .is_none());
+
+    let lines: Vec<String> = open_with_archives(&log_file_path)
+      .unwrap()
+      .lines()
+      .map(|line| line.unwrap())
+      .collect();
+    assert_eq!(
+      lines,
+      vec![
+        "[Wed Jul 17 23:59:59 2024] Soandso tells you, 'hello'",
+        "[Thu Jul 18 17:35:14 2024] You have entered East Commonlands.",
+        "[Fri Jul 19 00:00:00 2024] Soandso tells you, 'goodbye'",
+        "[Fri Jul 19 00:00:01 2024] You ",
+      ]
+    );
+
+    fs::remove_dir_all(&logs_dir).unwrap();
+  }
+}
@@ -0,0 +1,262 @@
+//! Classifies each log line by the chat channel it was said in and who said it. Lines that are
+//! not chat are either combat (damage and heals) or system messages. "You" as the speaker is
+//! replaced with the name of the character whose log it is.
+use super::{archive, combat::events::parse_combat_line, read_log_lines};
+use crate::common::timestamp::Timestamp;
+use fancy_regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{io, path::Path};
+
+lazy_static::lazy_static! {
+  /// Verbs that are written differently when the character is the speaker are listed together
+  static ref CHAT: Regex = Regex::new(
+    r"^(?<speaker>[^,']+?) (?:(?<tell>tells you|told you|told [^,']+)|(?<group>tells the group|tell your party)|(?<guild>tells the guild|say to your guild)|(?<raid>tells the raid|tell your raid)|(?<ooc>says? out of character)|(?<say>says?)|(?<shout>shouts?)|(?<auction>auctions?)|tells? (?<channel>[^,':]+):\d+),\s+'(?<message>.*)'$",
+  )
+  .unwrap();
+}
+
+#[derive(
+  Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ts_rs::TS, clap::ValueEnum,
+)]
+pub enum ChatChannel {
+  Tell,
+  Group,
+  Guild,
+  Raid,
+  Say,
+  Shout,
+  Auction,
+  OOC,
+  /// A named chat channel that was joined with `/join`, e.g. "General"
+  Channel,
+  /// Any line that is neither chat nor combat
+  System,
+  Combat,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatLine {
+  pub channel: ChatChannel,
+  /// Only named chat channels have a name
+  pub channel_name: Option<String>,
+  /// Only chat has a speaker
+  pub speaker: Option<String>,
+  /// What was said, or the whole line if it is not chat
+  pub message: String,
+}
+
+/// A classified line from a log file, as found by `search_log_file`
+#[derive(Debug, Clone, Serialize, ts_rs::TS)]
+pub struct ChatMessage {
+  pub logged_at: Timestamp,
+  pub channel: ChatChannel,
+  pub channel_name: Option<String>,
+  pub speaker: Option<String>,
+  pub message: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, ts_rs::TS)]
+pub struct ChatQuery {
+  /// Any channel if empty
+  pub channels: Vec<ChatChannel>,
+  /// Case-insensitive, and `None` is any speaker
+  pub speaker: Option<String>,
+}
+
+pub fn classify(line_content: &str, character_name: &str) -> ChatLine {
+  // The quote is checked first because it rules out almost every line that is not chat
+  if line_content.contains(", '") || line_content.contains(",  '") {
+    if let Ok(Some(caps)) = CHAT.captures(line_content) {
+      let channel = if caps.name("tell").is_some() {
+        ChatChannel::Tell
+      } else if caps.name("group").is_some() {
+        ChatChannel::Group
+      } else if caps.name("guild").is_some() {
+        ChatChannel::Guild
+      } else if caps.name("raid").is_some() {
+        ChatChannel::Raid
+      } else if caps.name("ooc").is_some() {
+        ChatChannel::OOC
+      } else if caps.name("say").is_some() {
+        ChatChannel::Say
+      } else if caps.name("shout").is_some() {
+        ChatChannel::Shout
+      } else if caps.name("auction").is_some() {
+        ChatChannel::Auction
+      } else {
+        ChatChannel::Channel
+      };
+      let speaker = match &caps["speaker"] {
+        "You" => character_name,
+        speaker => speaker,
+      };
+      return ChatLine {
+        channel,
+        channel_name: caps.name("channel").map(|name| name.as_str().to_owned()),
+        speaker: Some(speaker.to_owned()),
+        message: caps["message"].to_owned(),
+      };
+    }
+  }
+
+  let channel = if parse_combat_line(line_content, character_name).is_some() {
+    ChatChannel::Combat
+  } else {
+    ChatChannel::System
+  };
+  ChatLine {
+    channel,
+    channel_name: None,
+    speaker: None,
+    message: line_content.to_owned(),
+  }
+}
+
+impl ChatLine {
+  /// The value of `${CHANNEL}`, which is the name of a named chat channel
+  pub fn channel_label(&self) -> String {
+    channel_label(self.channel, self.channel_name.as_deref())
+  }
+}
+
+impl ChatQuery {
+  pub fn accepts(&self, chat: &ChatLine) -> bool {
+    (self.channels.is_empty() || self.channels.contains(&chat.channel))
+      && self.speaker.as_ref().is_none_or(|wanted| {
+        chat
+          .speaker
+          .as_ref()
+          .is_some_and(|speaker| speaker.eq_ignore_ascii_case(wanted))
+      })
+  }
+}
+
+/// Reads a whole log file from beginning to end, including the lines that were archived from it,
+/// returning the lines that the query accepts
+pub fn search_log_file(
+  log_file_path: &Path,
+  character_name: &str,
+  query: &ChatQuery,
+) -> io::Result<Vec<ChatMessage>> {
+  let mut messages = Vec::new();
+  for line in read_log_lines(archive::open_with_archives(log_file_path)?) {
+    let line = line?;
+    let chat = classify(&line.content, character_name);
+    if !query.accepts(&chat) {
+      continue;
+    }
+    let Some(logged_at) = line.timestamp() else {
+      continue;
+    };
+    messages.push(ChatMessage {
+      logged_at,
+      channel: chat.channel,
+      channel_name: chat.channel_name,
+      speaker: chat.speaker,
+      message: chat.message,
+    });
+  }
+  Ok(messages)
+}
+
+pub fn print_messages(messages: &[ChatMessage]) {
+  for message in messages.iter() {
+    let channel = channel_label(message.channel, message.channel_name.as_deref());
+    match &message.speaker {
+      Some(speaker) => println!(
+        "[{}] [{channel}] {speaker}: {}",
+        message.logged_at, message.message
+      ),
+      None => println!("[{}] [{channel}] {}", message.logged_at, message.message),
+    }
+  }
+}
+
+fn channel_label(channel: ChatChannel, channel_name: Option<&str>) -> String {
+  match channel_name {
+    Some(name) => name.to_owned(),
+    None => format!("{channel:?}"),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{classify, ChatChannel, ChatQuery};
+
+  #[test]
+  fn test_classify() {
+    let classified = |content: &str| {
+      let chat = classify(content, "Xenk");
+      (
+        chat.channel,
+        chat.channel_label(),
+        chat.speaker,
+        chat.message,
+      )
+    };
+    let chat = |channel, label: &str, speaker: &str, message: &str| {
+      (
+        channel,
+        label.to_owned(),
+        Some(speaker.to_owned()),
+        message.to_owned(),
+      )
+    };
+    assert_eq!(
+      classified("Soandso tells you, 'need a rez'"),
+      chat(ChatChannel::Tell, "Tell", "Soandso", "need a rez")
+    );
+    assert_eq!(
+      classified("You told Soandso, 'omw'"),
+      chat(ChatChannel::Tell, "Tell", "Xenk", "omw")
+    );
+    assert_eq!(
+      classified("You tell your party, 'inc, 'ware adds'"),
+      chat(ChatChannel::Group, "Group", "Xenk", "inc, 'ware adds")
+    );
+    assert_eq!(
+      classified("Soandso tells the raid,  'move in'"),
+      chat(ChatChannel::Raid, "Raid", "Soandso", "move in")
+    );
+    assert_eq!(
+      classified("You say to your guild, 'gz'"),
+      chat(ChatChannel::Guild, "Guild", "Xenk", "gz")
+    );
+    assert_eq!(
+      classified("Soandso says out of character, 'LFG'"),
+      chat(ChatChannel::OOC, "OOC", "Soandso", "LFG")
+    );
+    assert_eq!(
+      classified("a gnoll pup says, 'Bark!'"),
+      chat(ChatChannel::Say, "Say", "a gnoll pup", "Bark!")
+    );
+    assert_eq!(
+      classified("Soandso auctions, 'WTS Bone Chips'"),
+      chat(ChatChannel::Auction, "Auction", "Soandso", "WTS Bone Chips")
+    );
+    assert_eq!(
+      classified("Soandso tells General:3, 'hello'"),
+      chat(ChatChannel::Channel, "General", "Soandso", "hello")
+    );
+
+    let not_chat = classify("You slash a gnoll pup for 10 points of damage.", "Xenk");
+    assert_eq!(
+      (not_chat.channel, not_chat.speaker),
+      (ChatChannel::Combat, None)
+    );
+    let not_chat = classify("You have entered East Commonlands.", "Xenk");
+    assert_eq!(not_chat.channel, ChatChannel::System);
+  }
+
+  #[test]
+  fn test_chat_query() {
+    let query = ChatQuery {
+      channels: vec![ChatChannel::Tell, ChatChannel::Group],
+      speaker: Some("soandso".into()),
+    };
+    assert!(query.accepts(&classify("Soandso tells you, 'hi'", "Xenk")));
+    assert!(!query.accepts(&classify("Soandso tells the guild, 'hi'", "Xenk")));
+    assert!(!query.accepts(&classify("Bobo tells you, 'hi'", "Xenk")));
+    assert!(ChatQuery::default().accepts(&classify("You gain experience!!", "Xenk")));
+  }
+}
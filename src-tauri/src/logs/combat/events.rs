@@ -0,0 +1,237 @@
+//! Recognizes the log lines EverQuest writes for melee, spell and damage-over-time damage, and
+//! for heals. Both the live server formats and the older (e.g. Project 1999) formats are
+//! understood. "You" in any position is replaced with the name of the character whose log it is.
+use fancy_regex::{Captures, Regex};
+
+lazy_static::lazy_static! {
+  static ref DAMAGE_OVER_TIME: Regex = Regex::new(
+    r"^(?<target>.+?) ha(?:s|ve) taken (?<amount>\d+) damage from (?:your (?<own_spell>.+?)|(?<spell>.+?) by (?<source>.+?))\.",
+  )
+  .unwrap();
+
+  static ref SPELL: Regex = Regex::new(
+    r"^(?<source>.+?) hits? (?<target>.+?) for (?<amount>\d+) points? of [\w-]+ damage by (?<spell>.+?)\.",
+  )
+  .unwrap();
+
+  /// Older servers do not say who cast the spell, but they only show non-melee damage that was
+  /// done by the character themself
+  static ref NON_MELEE: Regex = Regex::new(
+    r"^(?<target>.+?) (?:was|were) hit by non-melee for (?<amount>\d+) points? of damage\.",
+  )
+  .unwrap();
+
+  static ref MELEE: Regex = Regex::new(
+    r"^(?<source>.+?) (?:hits?|slash(?:es)?|pierces?|crush(?:es)?|kicks?|bash(?:es)?|punch(?:es)?|bites?|claws?|backstabs?|strikes?|mauls?|gores?|stings?|smash(?:es)?|rends?|slams?|slices?|shoots?|frenz(?:y|ies) on) (?<target>.+?) for (?<amount>\d+) points? of (?:(?<non_melee>non-melee) )?damage\.",
+  )
+  .unwrap();
+
+  static ref HEAL: Regex = Regex::new(
+    r"^(?<source>.+?) healed (?<target>.+?)(?: over time)? for (?<amount>\d+)(?: \(\d+\))? hit points?(?: by (?<spell>.+?))?\.",
+  )
+  .unwrap();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CombatEventKind {
+  Melee,
+  Spell,
+  DamageOverTime,
+  Heal,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CombatEvent {
+  pub kind: CombatEventKind,
+  pub source: String,
+  pub target: String,
+  pub amount: u64,
+  pub spell: Option<String>,
+}
+
+impl CombatEvent {
+  pub fn is_damage(&self) -> bool {
+    self.kind != CombatEventKind::Heal
+  }
+}
+
+/// Returns the damage or heal in the line, if it is one
+pub fn parse_combat_line(line_content: &str, character_name: &str) -> Option<CombatEvent> {
+  // Every line recognized below contains one of these, and most lines contain neither. Chat
+  // messages are skipped, since anyone can say something that looks like damage.
+  if line_content.contains(", '") {
+    None
+  } else if line_content.contains(" damage") {
+    parse_damage(line_content, character_name)
+  } else if line_content.contains(" healed ") {
+    parse_heal(line_content, character_name)
+  } else {
+    None
+  }
+}
+
+fn parse_damage(line_content: &str, character_name: &str) -> Option<CombatEvent> {
+  if let Some(caps) = captures(&DAMAGE_OVER_TIME, line_content) {
+    let (source, spell) = match caps.name("own_spell") {
+      Some(own_spell) => (character_name, own_spell.as_str()),
+      None => (&caps["source"], &caps["spell"]),
+    };
+    return event(
+      CombatEventKind::DamageOverTime,
+      source,
+      &caps,
+      Some(spell),
+      character_name,
+    );
+  }
+  if let Some(caps) = captures(&SPELL, line_content) {
+    let spell = caps.name("spell").map(|spell| spell.as_str());
+    return event(
+      CombatEventKind::Spell,
+      &caps["source"],
+      &caps,
+      spell,
+      character_name,
+    );
+  }
+  if let Some(caps) = captures(&NON_MELEE, line_content) {
+    return event(
+      CombatEventKind::Spell,
+      character_name,
+      &caps,
+      None,
+      character_name,
+    );
+  }
+  if let Some(caps) = captures(&MELEE, line_content) {
+    let kind = if caps.name("non_melee").is_some() {
+      CombatEventKind::Spell
+    } else {
+      CombatEventKind::Melee
+    };
+    return event(kind, &caps["source"], &caps, None, character_name);
+  }
+  None
+}
+
+fn parse_heal(line_content: &str, character_name: &str) -> Option<CombatEvent> {
+  let caps = captures(&HEAL, line_content)?;
+  let spell = caps.name("spell").map(|spell| spell.as_str());
+  event(
+    CombatEventKind::Heal,
+    &caps["source"],
+    &caps,
+    spell,
+    character_name,
+  )
+}
+
+fn captures<'t>(regex: &Regex, line_content: &'t str) -> Option<Captures<'t>> {
+  regex.captures(line_content).ok().flatten()
+}
+
+/// Builds the event from the `target` and `amount` groups of the captures
+fn event(
+  kind: CombatEventKind,
+  source: &str,
+  caps: &Captures,
+  spell: Option<&str>,
+  character_name: &str,
+) -> Option<CombatEvent> {
+  Some(CombatEvent {
+    kind,
+    source: name_of(source, character_name),
+    target: name_of(&caps["target"], character_name),
+    amount: caps["amount"].parse().ok()?,
+    spell: spell.map(str::to_owned),
+  })
+}
+
+fn name_of(who: &str, character_name: &str) -> String {
+  if who.eq_ignore_ascii_case("you") || who.eq_ignore_ascii_case("yourself") {
+    character_name.to_owned()
+  } else {
+    who.to_owned()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{parse_combat_line, CombatEventKind};
+
+  fn parsed(line: &str) -> Option<(CombatEventKind, String, String, u64, Option<String>)> {
+    parse_combat_line(line, "Xenk").map(|e| (e.kind, e.source, e.target, e.amount, e.spell))
+  }
+
+  #[test]
+  fn test_parse_combat_line() {
+    use CombatEventKind::*;
+    let s = |s: &str| s.to_owned();
+
+    assert_eq!(
+      parsed("You slash a gnoll pup for 12 points of damage."),
+      Some((Melee, s("Xenk"), s("a gnoll pup"), 12, None))
+    );
+    assert_eq!(
+      parsed("A gnoll pup bites YOU for 3 points of damage."),
+      Some((Melee, s("A gnoll pup"), s("Xenk"), 3, None))
+    );
+    assert_eq!(
+      parsed("Soandso frenzies on a gnoll pup for 40 points of damage. (Critical)"),
+      Some((Melee, s("Soandso"), s("a gnoll pup"), 40, None))
+    );
+    assert_eq!(
+      parsed("You hit a gnoll pup for 500 points of fire damage by Flame Lick."),
+      Some((
+        Spell,
+        s("Xenk"),
+        s("a gnoll pup"),
+        500,
+        Some(s("Flame Lick"))
+      ))
+    );
+    assert_eq!(
+      parsed("a gnoll pup was hit by non-melee for 50 points of damage."),
+      Some((Spell, s("Xenk"), s("a gnoll pup"), 50, None))
+    );
+    assert_eq!(
+      parsed("a gnoll pup has taken 120 damage from your Flame Lick."),
+      Some((
+        DamageOverTime,
+        s("Xenk"),
+        s("a gnoll pup"),
+        120,
+        Some(s("Flame Lick"))
+      ))
+    );
+    assert_eq!(
+      parsed("You have taken 30 damage from Poison Bolt by a gnoll shaman."),
+      Some((
+        DamageOverTime,
+        s("a gnoll shaman"),
+        s("Xenk"),
+        30,
+        Some(s("Poison Bolt"))
+      ))
+    );
+    assert_eq!(
+      parsed("Soandso healed you for 500 (1000) hit points by Light Healing."),
+      Some((Heal, s("Soandso"), s("Xenk"), 500, Some(s("Light Healing"))))
+    );
+    assert_eq!(
+      parsed("You healed Soandso over time for 85 hit points by Elixir of Healing I."),
+      Some((
+        Heal,
+        s("Xenk"),
+        s("Soandso"),
+        85,
+        Some(s("Elixir of Healing I"))
+      ))
+    );
+    assert_eq!(parsed("You try to slash a gnoll pup, but miss!"), None);
+    assert_eq!(
+      parsed("Soandso tells the group, 'I hit it for 9000 points of damage.'"),
+      None
+    );
+  }
+}
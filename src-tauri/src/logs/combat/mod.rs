@@ -0,0 +1,309 @@
+//! Parses the damage and healing in a character's log into fights. A fight starts with the first
+//! damage line and ends once no damage has been logged for the inactivity period. Heals are only
+//! counted while a fight is going on.
+pub mod events;
+
+use self::events::{parse_combat_line, CombatEvent, CombatEventKind};
+use super::{archive, read_log_lines, Line};
+use crate::common::{format_integer, timestamp::Timestamp};
+use serde::Serialize;
+use std::{collections::HashMap, io, path::Path, time::Duration};
+
+pub const DEFAULT_FIGHT_INACTIVITY: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone)]
+pub enum CombatUpdate {
+  /// Sent at most once per second (of log time) while a fight is going on
+  FightUpdated(FightSummary),
+  FightFinished(FightSummary),
+}
+
+#[derive(Debug, Clone, Serialize, ts_rs::TS)]
+pub struct FightSummary {
+  pub fight_id: u32,
+  pub character_name: String,
+  pub started_at: Timestamp,
+  pub ended_at: Timestamp,
+  /// Log timestamps only have a resolution of one second, so this is at least 1
+  pub duration_seconds: u32,
+  #[ts(type = "number")]
+  pub total_damage: u64,
+  #[ts(type = "number")]
+  pub total_healing: u64,
+  pub dps: f64,
+  pub hps: f64,
+  /// Sorted by the most damage done
+  pub sources: Vec<SourceSummary>,
+}
+
+/// The damage and healing done by a single character, pet or NPC during a fight
+#[derive(Debug, Clone, Serialize, ts_rs::TS)]
+pub struct SourceSummary {
+  pub name: String,
+  #[ts(type = "number")]
+  pub damage: u64,
+  #[ts(type = "number")]
+  pub healing: u64,
+  pub dps: f64,
+  pub hps: f64,
+  pub hits: u32,
+  pub heals: u32,
+}
+
+pub struct CombatParser {
+  character_name: String,
+  inactivity: Duration,
+  fights_started: u32,
+  current: Option<Fight>,
+  /// When the latest FightUpdated was sent
+  last_update_at: Option<Timestamp>,
+}
+
+struct Fight {
+  id: u32,
+  started_at: Timestamp,
+  last_damage_at: Timestamp,
+  sources: HashMap<String, SourceTotals>,
+}
+
+#[derive(Default)]
+struct SourceTotals {
+  damage: u64,
+  healing: u64,
+  hits: u32,
+  heals: u32,
+}
+
+impl CombatParser {
+  pub fn new(character_name: &str, inactivity: Duration) -> Self {
+    Self {
+      character_name: character_name.to_owned(),
+      inactivity,
+      fights_started: 0,
+      current: None,
+      last_update_at: None,
+    }
+  }
+
+  /// Because a fight only ends when a later line shows that the inactivity period has passed,
+  /// the FightFinished for a fight can come with the line that starts the next one.
+  pub fn process(&mut self, line: &Line) -> Vec<CombatUpdate> {
+    let event = parse_combat_line(&line.content, &self.character_name);
+    if event.is_none() && self.current.is_none() {
+      return Vec::new();
+    }
+    let Some(timestamp) = line.timestamp() else {
+      return Vec::new();
+    };
+
+    let mut updates = Vec::new();
+    if self
+      .current
+      .as_ref()
+      .is_some_and(|fight| fight.last_damage_at.duration_until(&timestamp) > self.inactivity)
+    {
+      updates.extend(self.finish().map(CombatUpdate::FightFinished));
+    }
+
+    let Some(event) = event else {
+      return updates;
+    };
+    if !event.is_damage() && self.current.is_none() {
+      return updates;
+    }
+    let fight = self.current.get_or_insert_with(|| {
+      self.fights_started += 1;
+      Fight::new(self.fights_started, timestamp.clone())
+    });
+    fight.record(&event, &timestamp);
+
+    if self.last_update_at.as_ref() != Some(&timestamp) {
+      updates.push(CombatUpdate::FightUpdated(
+        fight.summary(&self.character_name),
+      ));
+      self.last_update_at = Some(timestamp);
+    }
+    updates
+  }
+
+  /// Ends the current fight without waiting for the inactivity period, e.g. at the end of a file
+  pub fn finish(&mut self) -> Option<FightSummary> {
+    let fight = self.current.take()?;
+    Some(fight.summary(&self.character_name))
+  }
+}
+
+impl Fight {
+  fn new(id: u32, started_at: Timestamp) -> Self {
+    Self {
+      id,
+      last_damage_at: started_at.clone(),
+      started_at,
+      sources: HashMap::new(),
+    }
+  }
+
+  fn record(&mut self, event: &CombatEvent, timestamp: &Timestamp) {
+    let totals = self.sources.entry(event.source.clone()).or_default();
+    if event.kind == CombatEventKind::Heal {
+      totals.healing += event.amount;
+      totals.heals += 1;
+    } else {
+      totals.damage += event.amount;
+      totals.hits += 1;
+      self.last_damage_at = timestamp.clone();
+    }
+  }
+
+  fn summary(&self, character_name: &str) -> FightSummary {
+    let duration_seconds = (self
+      .started_at
+      .duration_until(&self.last_damage_at)
+      .as_secs()
+      + 1)
+      .try_into()
+      .unwrap_or(u32::MAX);
+    let per_second = |amount: u64| amount as f64 / duration_seconds as f64;
+
+    let mut sources: Vec<SourceSummary> = self
+      .sources
+      .iter()
+      .map(|(name, totals)| SourceSummary {
+        name: name.clone(),
+        damage: totals.damage,
+        healing: totals.healing,
+        dps: per_second(totals.damage),
+        hps: per_second(totals.healing),
+        hits: totals.hits,
+        heals: totals.heals,
+      })
+      .collect();
+    sources.sort_by(|a, b| {
+      b.damage
+        .cmp(&a.damage)
+        .then(b.healing.cmp(&a.healing))
+        .then(a.name.cmp(&b.name))
+    });
+
+    let total_damage = sources.iter().map(|source| source.damage).sum();
+    let total_healing = sources.iter().map(|source| source.healing).sum();
+    FightSummary {
+      fight_id: self.id,
+      character_name: character_name.to_owned(),
+      started_at: self.started_at.clone(),
+      ended_at: self.last_damage_at.clone(),
+      duration_seconds,
+      total_damage,
+      total_healing,
+      dps: per_second(total_damage),
+      hps: per_second(total_healing),
+      sources,
+    }
+  }
+}
+
+/// Parses a whole log file from beginning to end, including the lines that were archived from
+/// it, returning every fight in it
+pub fn parse_log_file(
+  log_file_path: &Path,
+  character_name: &str,
+  inactivity: Duration,
+) -> io::Result<Vec<FightSummary>> {
+  let mut parser = CombatParser::new(character_name, inactivity);
+  let mut fights = Vec::new();
+  for line in read_log_lines(archive::open_with_archives(log_file_path)?) {
+    let line = line?;
+    for update in parser.process(&line) {
+      if let CombatUpdate::FightFinished(fight) = update {
+        fights.push(fight);
+      }
+    }
+  }
+  fights.extend(parser.finish());
+  Ok(fights)
+}
+
+pub fn print_fights(fights: &[FightSummary], top: usize) {
+  for fight in fights.iter() {
+    println!(
+      "Fight {} from {} to {} ({}s): {} damage ({:.1} DPS), {} healing ({:.1} HPS)",
+      fight.fight_id,
+      fight.started_at,
+      fight.ended_at,
+      fight.duration_seconds,
+      format_integer(fight.total_damage as usize),
+      fight.dps,
+      format_integer(fight.total_healing as usize),
+      fight.hps,
+    );
+    for source in fight.sources.iter().take(top) {
+      println!(
+        "{:>14} damage {:>10.1} DPS {:>12} healing {:>10.1} HPS  {}",
+        format_integer(source.damage as usize),
+        source.dps,
+        format_integer(source.healing as usize),
+        source.hps,
+        source.name,
+      );
+    }
+    println!();
+  }
+  println!("{} fights", fights.len());
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{CombatParser, CombatUpdate, DEFAULT_FIGHT_INACTIVITY};
+  use crate::logs::Line;
+
+  #[test]
+  fn test_fights_are_segmented_by_inactivity() {
+    let log = [
+      "[Thu Jul 18 17:35:00 2024] Soandso healed you for 100 hit points by Light Healing.",
+      "[Thu Jul 18 17:35:01 2024] You slash a gnoll pup for 10 points of damage.",
+      "[Thu Jul 18 17:35:01 2024] A gnoll pup bites YOU for 4 points of damage.",
+      "[Thu Jul 18 17:35:02 2024] Soandso healed you for 20 hit points by Light Healing.",
+      "[Thu Jul 18 17:35:04 2024] You slash a gnoll pup for 20 points of damage.",
+      "[Thu Jul 18 17:35:10 2024] You gain experience!!",
+      "[Thu Jul 18 17:35:30 2024] a gnoll scout was hit by non-melee for 50 points of damage.",
+    ];
+    let mut parser = CombatParser::new("Xenk", DEFAULT_FIGHT_INACTIVITY);
+    let updates: Vec<CombatUpdate> = log
+      .iter()
+      .flat_map(|raw| parser.process(&Line::from(raw).unwrap()))
+      .collect();
+
+    let finished: Vec<_> = updates
+      .iter()
+      .filter_map(|update| match update {
+        CombatUpdate::FightFinished(summary) => Some(summary),
+        CombatUpdate::FightUpdated(_) => None,
+      })
+      .collect();
+    assert_eq!(finished.len(), 1);
+    let first = finished[0];
+    assert_eq!(first.fight_id, 1);
+    assert_eq!(first.duration_seconds, 4);
+    assert_eq!(first.total_damage, 34);
+    assert_eq!(first.total_healing, 20);
+    assert_eq!(first.sources[0].name, "Xenk");
+    assert_eq!(first.sources[0].damage, 30);
+    assert_eq!(first.sources[0].dps, 7.5);
+    assert_eq!(first.sources[0].hits, 2);
+    assert_eq!(first.sources[2].name, "Soandso");
+    assert_eq!(first.sources[2].hps, 5.0);
+
+    // One update per second of log time that had combat in it, for both fights
+    let updated = updates
+      .iter()
+      .filter(|update| matches!(update, CombatUpdate::FightUpdated(_)))
+      .count();
+    assert_eq!(updated, 4);
+
+    let second = parser.finish().unwrap();
+    assert_eq!(second.fight_id, 2);
+    assert_eq!(second.duration_seconds, 1);
+    assert_eq!(second.total_damage, 50);
+    assert!(parser.finish().is_none());
+  }
+}
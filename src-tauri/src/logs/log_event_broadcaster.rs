@@ -1,15 +1,50 @@
-use super::{LogFileEvent, FILESYSTEM_EVENT_QUEUE_SIZE, LOG_FILENAME_PATTERN};
-use notify::{RecommendedWatcher, Watcher};
+use super::{FileIdentity, LogFileEvent, FILESYSTEM_EVENT_QUEUE_SIZE, LOG_FILENAME_PATTERN};
+use notify::{PollWatcher, Watcher};
+use serde::{Deserialize, Serialize};
 use std::{
+  collections::HashMap,
   path::{Path, PathBuf},
-  sync::Arc,
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+  },
+  thread,
+  time::Duration,
 };
 use tokio::sync::broadcast;
 use tracing::{debug, error};
 
+/// How often the log files are checked in the Poll and Hybrid modes, unless the config says
+/// otherwise
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(1000);
+
+/// The poll interval in the config is never shorter than this, so that polling cannot spin
+pub const MIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+type EventHandler = Arc<dyn Fn(Result<notify::Event, notify::Error>) + Send + Sync>;
+
+#[derive(
+  Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ts_rs::TS, clap::ValueEnum,
+)]
+pub enum LogWatcherMode {
+  /// Uses the filesystem notifications of the operating system
+  #[default]
+  Native,
+  /// Checks the logs directory for changes at an interval, for filesystems that do not send
+  /// notifications (e.g. network shares, or some under Wine)
+  Poll,
+  /// Uses the filesystem notifications, and also checks the size of each log file at an
+  /// interval in case a notification was lost
+  Hybrid,
+}
+
 pub struct LogEventBroadcaster {
   logs_dir: PathBuf,
-  watcher: RecommendedWatcher,
+  watcher: Box<dyn Watcher + Send + Sync>,
+  handler: EventHandler,
+  /// Only set in the Hybrid mode
+  heartbeat_interval: Option<Duration>,
+  heartbeat: Option<SizeHeartbeat>,
   tx: broadcast::Sender<Result<LogFileEvent, NotifyError>>,
 }
 
@@ -23,19 +58,33 @@ impl From<notify::Error> for NotifyError {
 }
 
 impl LogEventBroadcaster {
-  pub fn new(logs_dir: &Path) -> Result<Self, NotifyError> {
+  pub fn new(
+    logs_dir: &Path,
+    mode: LogWatcherMode,
+    poll_interval: Duration,
+  ) -> Result<Self, NotifyError> {
     let (tx, _rx) =
       broadcast::channel::<Result<LogFileEvent, NotifyError>>(FILESYSTEM_EVENT_QUEUE_SIZE);
-    let callback = new_notify_event_handler(tx.clone());
-    // TODO! Should use a notify::Config
-    let watcher = notify::recommended_watcher(callback)?;
+    let handler: EventHandler = Arc::new(new_notify_event_handler(tx.clone()));
+    let watcher: Box<dyn Watcher + Send + Sync> = match mode {
+      LogWatcherMode::Native | LogWatcherMode::Hybrid => {
+        Box::new(notify::recommended_watcher(forward_to(&handler))?)
+      }
+      LogWatcherMode::Poll => {
+        let config = notify::Config::default().with_poll_interval(poll_interval);
+        Box::new(PollWatcher::new(forward_to(&handler), config)?)
+      }
+    };
     debug!(
-      "Created (unstarted) LogEventBroadcaster for dir: {}",
+      "Created (unstarted) {mode:?} LogEventBroadcaster for dir: {}",
       logs_dir.display()
     );
     Ok(Self {
       logs_dir: logs_dir.to_owned(),
       watcher,
+      handler,
+      heartbeat_interval: (mode == LogWatcherMode::Hybrid).then_some(poll_interval),
+      heartbeat: None,
       tx,
     })
   }
@@ -44,7 +93,15 @@ impl LogEventBroadcaster {
     debug!("Starting LogEventBroadcaster");
     self
       .watcher
-      .watch(&self.logs_dir, notify::RecursiveMode::NonRecursive)
+      .watch(&self.logs_dir, notify::RecursiveMode::NonRecursive)?;
+    if let Some(interval) = self.heartbeat_interval {
+      self.heartbeat = Some(SizeHeartbeat::start(
+        &self.logs_dir,
+        interval,
+        self.handler.clone(),
+      ));
+    }
+    Ok(())
   }
 
   pub fn subscribe(&self) -> broadcast::Receiver<Result<LogFileEvent, NotifyError>> {
@@ -52,6 +109,7 @@ impl LogEventBroadcaster {
   }
 
   pub fn stop(mut self) -> Result<(), notify::Error> {
+    self.heartbeat = None;
     self.watcher.unwatch(&self.logs_dir)
   }
 
@@ -60,50 +118,78 @@ impl LogEventBroadcaster {
   }
 }
 
+fn forward_to(
+  handler: &EventHandler,
+) -> impl Fn(Result<notify::Event, notify::Error>) + Send + 'static {
+  let handler = handler.clone();
+  move |res| handler(res)
+}
+
 fn new_notify_event_handler(
   sender: broadcast::Sender<Result<LogFileEvent, NotifyError>>,
-) -> impl Fn(Result<notify::Event, notify::Error>) {
-  use notify::event::{CreateKind, EventKind, ModifyKind, RemoveKind};
+) -> impl Fn(Result<notify::Event, notify::Error>) + Send + Sync + 'static {
+  use notify::event::{CreateKind, EventKind, MetadataKind, ModifyKind, RemoveKind};
+  let rotations = Mutex::new(RotationTracker::default());
   move |res: Result<notify::Event, notify::Error>| {
+    let send = |e: LogFileEvent| {
+      _ = sender.send(Ok(e));
+    };
     match res {
-      // This does not handle the case of file-rename events. The only reason someone
-      // might do that would be to archive a log by renaming it to something that isn't
-      // a LOG_FILE_PATTERN. I can't imagine a scenario when someone would rename a file
-      // from one filename that matched LOG_FILE_PATTERN to another file that matched
-      // LOG_FILE_PATTERN (especially while LQ is running).
       Ok(event) => match event.kind {
-        EventKind::Create(CreateKind::File) => {
+        // The PollWatcher only knows that something was created, and that the last write time of
+        // a file changed
+        EventKind::Create(CreateKind::File | CreateKind::Any) => {
           debug!("Filesystem Create event");
-          event
-            .paths
-            .iter()
-            .filter(|p| is_valid_log_file_name(p))
-            .map(|p| LogFileEvent::Created(path_string(p)))
-            .for_each(|e| {
-              _ = sender.send(Ok(e));
-            });
+          let mut rotations = rotations.lock().expect("ROTATION TRACKER POISONED");
+          for path in event.paths.iter().filter(|p| is_valid_log_file_name(p)) {
+            if rotations.check(path) {
+              send(LogFileEvent::Rotated(path_string(path)));
+            } else {
+              send(LogFileEvent::Created(path_string(path)));
+            }
+          }
         }
-        EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Any) => {
+        EventKind::Modify(
+          ModifyKind::Data(_) | ModifyKind::Any | ModifyKind::Metadata(MetadataKind::WriteTime),
+        ) => {
           // debug!("Filesystem Modify event");
-          event
-            .paths
-            .iter()
-            .filter(|p| is_valid_log_file_name(p))
-            .map(|p| LogFileEvent::Updated(path_string(p)))
-            .for_each(|e| {
-              _ = sender.send(Ok(e));
-            });
+          let mut rotations = rotations.lock().expect("ROTATION TRACKER POISONED");
+          for path in event.paths.iter().filter(|p| is_valid_log_file_name(p)) {
+            // A file that shrank was not written to by EverQuest, so it is not an Update that
+            // would make its character the active one
+            if rotations.check(path) {
+              debug!("Log file was truncated or replaced: {}", path.display());
+              send(LogFileEvent::Rotated(path_string(path)));
+            } else {
+              send(LogFileEvent::Updated(path_string(path)));
+            }
+          }
         }
-        EventKind::Remove(RemoveKind::File) => {
+        // A log file that is renamed away is gone as far as LogQuest is concerned, and a file
+        // that is renamed to the name of a log file replaces whatever was there before
+        EventKind::Modify(ModifyKind::Name(rename_mode)) => {
+          debug!("Filesystem Rename event");
+          let mut rotations = rotations.lock().expect("ROTATION TRACKER POISONED");
+          for (index, path) in event.paths.iter().enumerate() {
+            if !is_valid_log_file_name(path) {
+              continue;
+            }
+            if renamed_away(rename_mode, index, path) {
+              rotations.forget(path);
+              send(LogFileEvent::Deleted(path_string(path)));
+            } else {
+              rotations.check(path);
+              send(LogFileEvent::Rotated(path_string(path)));
+            }
+          }
+        }
+        EventKind::Remove(RemoveKind::File | RemoveKind::Any) => {
           debug!("Filesystem Remove event");
-          event
-            .paths
-            .iter()
-            .filter(|p| is_valid_log_file_name(p))
-            .map(|p| LogFileEvent::Deleted(path_string(p)))
-            .for_each(|e| {
-              _ = sender.send(Ok(e));
-            });
+          let mut rotations = rotations.lock().expect("ROTATION TRACKER POISONED");
+          for path in event.paths.iter().filter(|p| is_valid_log_file_name(p)) {
+            rotations.forget(path);
+            send(LogFileEvent::Deleted(path_string(path)));
+          }
         }
         _ => {}
       },
@@ -115,6 +201,110 @@ fn new_notify_event_handler(
   }
 }
 
+/// Remembers the size and identity of each log file that an event was seen for, to notice
+/// when one gets smaller or is replaced by another file
+#[derive(Default)]
+struct RotationTracker {
+  files: HashMap<PathBuf, (u64, FileIdentity)>,
+}
+
+impl RotationTracker {
+  /// Returns true if the file is shorter than it was, or is not the same file
+  fn check(&mut self, path: &Path) -> bool {
+    let Ok(metadata) = path.metadata() else {
+      self.forget(path);
+      return false;
+    };
+    let current = (metadata.len(), FileIdentity::of(&metadata));
+    match self.files.insert(path.to_owned(), current) {
+      Some((len, identity)) => current.0 < len || current.1 != identity,
+      None => false,
+    }
+  }
+
+  fn forget(&mut self, path: &Path) {
+    self.files.remove(path);
+  }
+}
+
+/// Some platforms do not say which side of the rename a path was on, in which case the path
+/// was renamed away if there is no longer a file there
+fn renamed_away(rename_mode: notify::event::RenameMode, index: usize, path: &Path) -> bool {
+  use notify::event::RenameMode;
+  match rename_mode {
+    RenameMode::From => true,
+    RenameMode::To => false,
+    RenameMode::Both => index == 0,
+    RenameMode::Any | RenameMode::Other => !path.exists(),
+  }
+}
+
+/// Checks the size of every log file at an interval, and reports the ones that changed as if the
+/// filesystem had sent a notification for them
+struct SizeHeartbeat {
+  stopped: Arc<AtomicBool>,
+}
+
+impl SizeHeartbeat {
+  fn start(logs_dir: &Path, interval: Duration, handler: EventHandler) -> Self {
+    use notify::event::{DataChange, EventKind, ModifyKind};
+    let stopped = Arc::new(AtomicBool::new(false));
+    let stopped_ = stopped.clone();
+    let logs_dir = logs_dir.to_owned();
+    thread::spawn(move || {
+      let mut sizes = HashMap::new();
+      while !stopped_.load(Ordering::Relaxed) {
+        for path in changed_sizes(&logs_dir, &mut sizes) {
+          let kind = EventKind::Modify(ModifyKind::Data(DataChange::Size));
+          handler(Ok(notify::Event::new(kind).add_path(path)));
+        }
+        thread::sleep(interval);
+      }
+      debug!("Log file size heartbeat stopped");
+    });
+    Self { stopped }
+  }
+}
+
+impl Drop for SizeHeartbeat {
+  fn drop(&mut self) {
+    self.stopped.store(true, Ordering::Relaxed);
+  }
+}
+
+/// Returns the log files whose size is different from the last time, and records their sizes.
+/// The first size of each log file is only recorded. The path can also be a single log file.
+fn changed_sizes(logs_dir: &Path, sizes: &mut HashMap<PathBuf, u64>) -> Vec<PathBuf> {
+  let paths: Vec<PathBuf> = match logs_dir.read_dir() {
+    Ok(entries) => entries
+      .filter_map(|entry| entry.ok())
+      .map(|entry| entry.path())
+      .collect(),
+    Err(_) if logs_dir.is_file() => vec![logs_dir.to_owned()],
+    Err(e) => {
+      error!(
+        "Could not read {} to check the log file sizes: {e}",
+        logs_dir.display()
+      );
+      return Vec::new();
+    }
+  };
+  let mut changed = Vec::new();
+  for path in paths.into_iter().filter(|p| is_valid_log_file_name(p)) {
+    let Ok(size) = path.metadata().map(|metadata| metadata.len()) else {
+      sizes.remove(&path);
+      continue;
+    };
+    if sizes
+      .insert(path.clone(), size)
+      .is_some_and(|last| last != size)
+    {
+      changed.push(path);
+    }
+  }
+  changed
+}
+
 fn is_valid_log_file_name(path: &Path) -> bool {
   let path = path.to_string_lossy();
   LOG_FILENAME_PATTERN.is_match(&path).is_ok_and(|b| b)
@@ -127,3 +317,62 @@ fn path_string(path: &Path) -> String {
     .to_string_lossy()
     .into_owned()
 }
+
+#[cfg(test)]
+mod tests {
+  use super::{changed_sizes, RotationTracker};
+  use crate::common::random_id;
+  use std::{collections::HashMap, fs, io::Write as _};
+
+  #[test]
+  fn test_rotation_tracker() {
+    let dir = std::env::temp_dir().join(format!("logs_{}", random_id(8)));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("eqlog_Xenk_P1999Green.txt");
+    fs::write(&path, "[Thu Jul 18 17:35:14 2024] You gain experience!!\n").unwrap();
+
+    let mut tracker = RotationTracker::default();
+    assert!(!tracker.check(&path));
+    let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+    writeln!(file, "[Thu Jul 18 17:35:15 2024] You gain experience!!").unwrap();
+    assert!(!tracker.check(&path));
+
+    // Truncated
+    fs::write(&path, "").unwrap();
+    assert!(tracker.check(&path));
+    assert!(!tracker.check(&path));
+
+    // Replaced by a longer file
+    let replacement = dir.join("replacement.txt");
+    fs::write(
+      &replacement,
+      "[Thu Jul 18 17:35:16 2024] You gain experience!!\n",
+    )
+    .unwrap();
+    fs::rename(&replacement, &path).unwrap();
+    assert!(tracker.check(&path));
+
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn test_changed_sizes() {
+    let dir = std::env::temp_dir().join(format!("logs_{}", random_id(8)));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("eqlog_Xenk_P1999Green.txt");
+    fs::write(&path, "[Thu Jul 18 17:35:14 2024] You gain experience!!\n").unwrap();
+    fs::write(dir.join("notes.txt"), "").unwrap();
+
+    let mut sizes = HashMap::new();
+    assert!(changed_sizes(&dir, &mut sizes).is_empty());
+    assert!(changed_sizes(&dir, &mut sizes).is_empty());
+    let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+    writeln!(file, "[Thu Jul 18 17:35:15 2024] You gain experience!!").unwrap();
+    fs::write(dir.join("notes.txt"), "hello").unwrap();
+    assert_eq!(changed_sizes(&dir, &mut sizes), vec![path.clone()]);
+    // A single log file can be checked too
+    assert!(changed_sizes(&path, &mut sizes).is_empty());
+
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
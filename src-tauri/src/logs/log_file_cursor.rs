@@ -1,4 +1,4 @@
-use super::LOG_FILENAME_PATTERN;
+use super::{active_character_detection::Character, LOG_FILENAME_PATTERN};
 use std::{collections::HashMap, path::Path};
 use tracing::error;
 
@@ -56,6 +56,11 @@ impl LogFileCursorCache {
     }
   }
 
+  /// The characters of every log file in the logs directory
+  pub fn characters(&self) -> Vec<Character> {
+    Character::all_from(self.cursors.keys())
+  }
+
   pub fn reset_cursor_position<P>(&mut self, path: P)
   where
     P: AsRef<str>,
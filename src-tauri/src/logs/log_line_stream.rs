@@ -2,38 +2,82 @@ use super::{
   log_event_broadcaster::NotifyError, log_file_cursor::LogFileCursor, Line, LogFileEvent,
 };
 use crate::common::shutdown::quitter;
+use futures::task::AtomicWaker;
 use std::path::Path;
 use std::{
+  io::{Seek as _, SeekFrom},
   pin::Pin,
-  sync::Arc,
-  task::{Poll, Waker},
-};
-use tokio::{
-  io::{AsyncBufReadExt as _, AsyncSeekExt as _},
-  spawn,
-  sync::broadcast,
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+  },
+  task::{ready, Poll},
 };
+use tokio::{io::AsyncBufRead, sync::broadcast};
 use tokio_util::sync::CancellationToken;
-use tracing::{debug, error};
+use tracing::{debug, error, info};
+
+/// Reads a log file a line at a time as raw bytes, because the lines are not always UTF-8 and a
+/// line that is not should not end the stream
+struct LinesReader {
+  reader: tokio::io::BufReader<tokio::fs::File>,
+  /// The start of a line whose end has not been written yet
+  partial_line: Vec<u8>,
+}
+
+impl LinesReader {
+  /// Returns the next complete line with its line ending, or None at the end of the file. A line
+  /// that is still being written is held back until its newline is written.
+  fn poll_next_line(
+    &mut self,
+    cx: &mut std::task::Context<'_>,
+  ) -> Poll<std::io::Result<Option<Vec<u8>>>> {
+    loop {
+      let buf = ready!(Pin::new(&mut self.reader).poll_fill_buf(cx))?;
+      if buf.is_empty() {
+        return Poll::Ready(Ok(None));
+      }
+      let consumed = match buf.iter().position(|byte| *byte == b'\n') {
+        Some(newline) => {
+          self.partial_line.extend_from_slice(&buf[..=newline]);
+          newline + 1
+        }
+        None => {
+          self.partial_line.extend_from_slice(buf);
+          buf.len()
+        }
+      };
+      Pin::new(&mut self.reader).consume(consumed);
+      if self.partial_line.last() == Some(&b'\n') {
+        return Poll::Ready(Ok(Some(std::mem::take(&mut self.partial_line))));
+      }
+    }
+  }
+}
 
 pub struct LogLineStream {
   pub cursor: LogFileCursor,
-  reader: tokio_stream::wrappers::LinesStream<tokio::io::BufReader<tokio::fs::File>>,
+  reader: LinesReader,
+  /// Set when the log file was truncated or replaced, so that it is reopened once the reader
+  /// reaches the end of what it has open
+  rotated: Arc<AtomicBool>,
   cancel_token: CancellationToken,
-  waker_maybe: Arc<tokio::sync::Mutex<Option<Waker>>>,
+  /// Registered before the stream waits at the end of the file, so that an event that arrives
+  /// right after the last read still wakes it
+  waker: Arc<AtomicWaker>,
 }
 
 impl LogLineStream {
   #[cfg(unix)]
-  async fn platform_specific_open<P>(path: P) -> tokio::io::Result<tokio::fs::File>
+  fn platform_specific_open<P>(path: P) -> std::io::Result<std::fs::File>
   where
     P: AsRef<Path>,
   {
-    tokio::fs::OpenOptions::new().read(true).open(path).await
+    std::fs::OpenOptions::new().read(true).open(path)
   }
 
   #[cfg(windows)]
-  async fn platform_specific_open<P>(path: P) -> tokio::io::Result<tokio::fs::File>
+  fn platform_specific_open<P>(path: P) -> std::io::Result<std::fs::File>
   where
     P: AsRef<Path>,
   {
@@ -43,73 +87,87 @@ impl LogLineStream {
     const FILE_SHARE_DELETE: u32 = 0x4;
     const SHARE_MODE: u32 = FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE;
 
-    tokio::fs::OpenOptions::new()
+    use std::os::windows::fs::OpenOptionsExt as _;
+    std::fs::OpenOptions::new()
       .read(true)
       .share_mode(SHARE_MODE)
       .open(path)
-      .await
+  }
+
+  /// Opens the file for reading from `position`, or from the end of the file if it is shorter
+  /// than that. Returns the position that reading starts from.
+  fn open_at(path: &str, position: u64) -> std::io::Result<(LinesReader, u64)> {
+    let mut file = Self::platform_specific_open(path)?;
+    let position = position.min(file.metadata()?.len());
+    file.seek(SeekFrom::Start(position))?;
+    let reader = LinesReader {
+      reader: tokio::io::BufReader::new(tokio::fs::File::from_std(file)),
+      partial_line: Vec::new(),
+    };
+    Ok((reader, position))
+  }
+
+  /// The LogEventBroadcaster only notices that a log file got shorter if it saw the file's
+  /// size before, so the size is also checked whenever the end of the file is reached
+  fn truncated(&self) -> bool {
+    std::fs::metadata(&self.cursor.path).is_ok_and(|metadata| metadata.len() < self.cursor.position)
   }
 
   pub async fn create(
     cursor: &LogFileCursor,
     rx_log_file_events: broadcast::Receiver<Result<LogFileEvent, NotifyError>>,
   ) -> tokio::io::Result<Self> {
-    let file = Self::platform_specific_open(&cursor.path).await?;
-    let mut reader = tokio::io::BufReader::new(file);
-
     debug!(
       "LogLineStream seeking to position {} for file {}",
       cursor.position, cursor.path
     );
-    reader
-      .seek(std::io::SeekFrom::Start(cursor.position))
-      .await?;
-
-    let reader = tokio_stream::wrappers::LinesStream::new(reader.lines());
+    // A cursor past the end of the file is from before the file was truncated or archived
+    let (reader, position) = Self::open_at(&cursor.path, cursor.position)?;
+    let cursor = LogFileCursor {
+      path: cursor.path.clone(),
+      position,
+    };
 
-    let waker_maybe = Arc::new(tokio::sync::Mutex::new(None::<Waker>));
+    let waker = Arc::new(AtomicWaker::new());
+    let rotated = Arc::new(AtomicBool::new(false));
 
     let cancel_token = CancellationToken::new();
 
     tokio::spawn(Self::wake_when_modified(
       cursor.path.clone(),
-      waker_maybe.clone(),
+      waker.clone(),
+      rotated.clone(),
       cancel_token.clone(),
       rx_log_file_events,
     ));
 
     Ok(Self {
-      cursor: cursor.to_owned(),
+      cursor,
       reader,
-      waker_maybe,
+      rotated,
+      waker,
       cancel_token,
     })
   }
 
   async fn wake_when_modified(
     followed_file: String,
-    waker: Arc<tokio::sync::Mutex<Option<Waker>>>,
+    waker: Arc<AtomicWaker>,
+    rotated: Arc<AtomicBool>,
     cancel_token: CancellationToken,
     mut subscription: broadcast::Receiver<Result<LogFileEvent, NotifyError>>,
   ) {
-    let wake_waiting_waker = || async {
-      let mut guard = waker.lock().await;
-      if let Some(waker) = guard.take() {
-        waker.wake();
-      }
-    };
-
     let mut quit = quitter();
     loop {
       tokio::select! {
         () = &mut quit => {
           debug!("LogLineStream QUITTING for file {followed_file}");
-          wake_waiting_waker().await;
+          waker.wake();
           break;
         }
         () = cancel_token.cancelled() => {
           debug!("LogLineStream cancelled for file: {followed_file}");
-          wake_waiting_waker().await;
+          waker.wake();
           break;
         }
         log_file_event = subscription.recv() => match log_file_event {
@@ -118,7 +176,12 @@ impl LogLineStream {
             LogFileEvent::Created(event_file)
           )) if event_file == followed_file => {
             debug!("Waking LogLineStream due to modify event for file: {event_file:?}");
-            wake_waiting_waker().await;
+            waker.wake();
+          }
+          Ok(Ok(LogFileEvent::Rotated(event_file))) if event_file == followed_file => {
+            debug!("Waking LogLineStream to reopen rotated file: {event_file:?}");
+            rotated.store(true, Ordering::Release);
+            waker.wake();
           }
           Ok(Ok(LogFileEvent::Deleted(event_file))) if event_file == followed_file => {
             error!("Log file got deleted while it was being watched: {followed_file}");
@@ -155,40 +218,53 @@ impl futures::Stream for LogLineStream {
         debug!("LogLineStream cancelled for file {}", self.cursor.path);
         return Poll::Ready(None);
       }
-      match Pin::new(&mut self.reader).poll_next(cx) {
+      match self.reader.poll_next_line(cx) {
         Poll::Pending => return Poll::Pending,
-        Poll::Ready(Some(Err(io_error))) => {
+        Poll::Ready(Err(io_error)) => {
           error!(
             "Terminating LogLineStream due to IO error while polling {} [ ERROR = {io_error:?} ]",
             self.cursor.path
           );
           return Poll::Ready(None);
         }
-        Poll::Ready(Some(Ok(line))) => {
-          let line_len = line.len();
-          self.cursor.position += 1 + line_len as u64;
-
-          let line: &str = if line.ends_with("\r") {
-            self.cursor.position += 1;
-            &line[..(line_len - 1)]
-          } else {
-            &line
-          };
-
-          if let Ok(parsed_line) = Line::from(line) {
+        Poll::Ready(Ok(Some(raw_line))) => {
+          self.cursor.position += raw_line.len() as u64;
+          if let Ok(parsed_line) = Line::from_bytes(&raw_line) {
             return Poll::Ready(Some((parsed_line, self.cursor.clone())));
           } else {
             // line failed to parse; drop the data and continue with loop
           }
         }
-        Poll::Ready(None) => {
+        Poll::Ready(Ok(None)) => {
+          if self.rotated.swap(false, Ordering::AcqRel) || self.truncated() {
+            // Whatever the file holds now cannot be told apart from what was already read (e.g.
+            // the recent lines that the log archiver leaves in place), so the file is followed
+            // from its end, and lines written before it is reopened are not matched
+            info!(
+              "Reopening {} at its end because it was truncated or replaced",
+              self.cursor.path
+            );
+            match Self::open_at(&self.cursor.path, u64::MAX) {
+              Ok((reader, position)) => {
+                self.reader = reader;
+                self.cursor.position = position;
+                continue;
+              }
+              Err(io_error) => {
+                error!(
+                  "Terminating LogLineStream because {} could not be reopened [ ERROR = {io_error:?} ]",
+                  self.cursor.path
+                );
+                return Poll::Ready(None);
+              }
+            }
+          }
           // EOF reached; let a notify event wake up this future later
-          let waker = cx.waker().to_owned();
-          let wake_state = self.waker_maybe.clone();
-          spawn(async move {
-            let mut guard = wake_state.lock().await;
-            *guard = Some(waker);
-          });
+          self.waker.register(cx.waker());
+          if self.rotated.load(Ordering::Acquire) {
+            // The file was rotated after the check above, before the waker was registered
+            continue;
+          }
           return Poll::Pending;
         }
       }
@@ -202,3 +278,165 @@ impl Drop for LogLineStream {
     self.cancel_token.cancel();
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::LogLineStream;
+  use crate::{
+    common::random_id,
+    logs::{log_event_broadcaster::NotifyError, log_file_cursor::LogFileCursor, LogFileEvent},
+  };
+  use futures::StreamExt as _;
+  use std::{fs, io::Write as _, time::Duration};
+  use tokio::{sync::broadcast, time::timeout};
+
+  async fn next_line(stream: &mut LogLineStream) -> String {
+    let next = timeout(Duration::from_secs(5), stream.next()).await;
+    next.unwrap().unwrap().0.content
+  }
+
+  #[tokio::test(flavor = "multi_thread")]
+  async fn test_non_utf8_lines() {
+    let path = std::env::temp_dir().join(format!("eqlog_Xenk_{}.txt", random_id(8)));
+    fs::write(&path, "").unwrap();
+    let path_string = path.to_string_lossy().into_owned();
+    let (tx, _rx) = broadcast::channel::<Result<LogFileEvent, _>>(16);
+    let cursor = LogFileCursor::new(&path_string).unwrap();
+    let mut stream = LogLineStream::create(&cursor, tx.subscribe())
+      .await
+      .unwrap();
+
+    let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+    file
+      .write_all(b"[Thu Jul 18 17:35:15 2024] Ren\xe9e tells you, 'hi'\r\n")
+      .unwrap();
+    assert_eq!(next_line(&mut stream).await, "Renée tells you, 'hi'");
+
+    // The cursor only moves past a line once its newline is written
+    file
+      .write_all("[Thu Jul 18 17:35:16 2024] Renée".as_bytes())
+      .unwrap();
+    let partial = timeout(Duration::from_millis(200), stream.next()).await;
+    assert!(partial.is_err());
+    file.write_all(b" tells you, 'bye'\n").unwrap();
+    tx.send(Ok(LogFileEvent::Updated(path_string.clone())))
+      .unwrap();
+    let (line, cursor) = timeout(Duration::from_secs(5), stream.next())
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!(line.content, "Renée tells you, 'bye'");
+    assert_eq!(cursor.position, fs::metadata(&path).unwrap().len());
+
+    drop(file);
+    fs::remove_file(&path).unwrap();
+  }
+
+  #[tokio::test(flavor = "multi_thread")]
+  async fn test_follow_through_truncation() {
+    let path = std::env::temp_dir().join(format!("eqlog_Xenk_{}.txt", random_id(8)));
+    fs::write(&path, "[Thu Jul 18 17:35:14 2024] You gain experience!!\n").unwrap();
+    let path_string = path.to_string_lossy().into_owned();
+    let (tx, _rx) = broadcast::channel::<Result<LogFileEvent, _>>(16);
+    let cursor = LogFileCursor::new(&path_string).unwrap();
+    let mut stream = LogLineStream::create(&cursor, tx.subscribe())
+      .await
+      .unwrap();
+
+    let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+    writeln!(
+      file,
+      "[Thu Jul 18 17:35:15 2024] You have entered East Commonlands."
+    )
+    .unwrap();
+    assert_eq!(
+      next_line(&mut stream).await,
+      "You have entered East Commonlands."
+    );
+
+    // What a truncated file holds when it is reopened is not matched again...
+    fs::write(
+      &path,
+      "[Thu Jul 18 17:35:15 2024] You have entered East Commonlands.\n",
+    )
+    .unwrap();
+    let rotated = LogFileEvent::Rotated(path_string.clone());
+    tx.send(Ok(rotated.clone())).unwrap();
+    assert!(timeout(Duration::from_millis(200), stream.next())
+      .await
+      .is_err());
+    // ...but what is written to it afterwards is
+    append_line(
+      &path,
+      &tx,
+      "[Thu Jul 18 17:40:00 2024] Welcome to EverQuest!",
+    );
+    assert_eq!(next_line(&mut stream).await, "Welcome to EverQuest!");
+
+    // A file that replaces the log file is followed from its end too, even when it is longer
+    let replacement = path.with_extension("tmp");
+    let old_lines = "[Thu Jul 18 17:41:00 2024] You gain experience!!\n".repeat(10);
+    fs::write(&replacement, old_lines).unwrap();
+    fs::rename(&replacement, &path).unwrap();
+    tx.send(Ok(rotated)).unwrap();
+    assert!(timeout(Duration::from_millis(200), stream.next())
+      .await
+      .is_err());
+    append_line(
+      &path,
+      &tx,
+      "[Thu Jul 18 17:42:00 2024] You have been slain!",
+    );
+    assert_eq!(next_line(&mut stream).await, "You have been slain!");
+
+    drop(file);
+    fs::remove_file(&path).unwrap();
+  }
+
+  #[tokio::test(flavor = "multi_thread")]
+  async fn test_follow_through_unreported_truncation() {
+    let path = std::env::temp_dir().join(format!("eqlog_Xenk_{}.txt", random_id(8)));
+    fs::write(&path, "").unwrap();
+    let path_string = path.to_string_lossy().into_owned();
+    let (tx, _rx) = broadcast::channel::<Result<LogFileEvent, _>>(16);
+    let cursor = LogFileCursor::new(&path_string).unwrap();
+    let mut stream = LogLineStream::create(&cursor, tx.subscribe())
+      .await
+      .unwrap();
+    let old_lines = "[Thu Jul 18 17:35:14 2024] You gain experience!!\n".repeat(10);
+    fs::write(&path, &old_lines).unwrap();
+    tx.send(Ok(LogFileEvent::Updated(path_string.clone())))
+      .unwrap();
+    for _ in 0..10 {
+      assert_eq!(next_line(&mut stream).await, "You gain experience!!");
+    }
+
+    // The truncation is only reported as an Update, as it is the first time the broadcaster
+    // sees the file
+    fs::write(&path, "[Thu Jul 18 17:35:15 2024] You gain experience!!\n").unwrap();
+    tx.send(Ok(LogFileEvent::Updated(path_string.clone())))
+      .unwrap();
+    assert!(timeout(Duration::from_millis(200), stream.next())
+      .await
+      .is_err());
+    append_line(
+      &path,
+      &tx,
+      "[Thu Jul 18 17:40:00 2024] Welcome to EverQuest!",
+    );
+    assert_eq!(next_line(&mut stream).await, "Welcome to EverQuest!");
+
+    fs::remove_file(&path).unwrap();
+  }
+
+  fn append_line(
+    path: &std::path::Path,
+    tx: &broadcast::Sender<Result<LogFileEvent, NotifyError>>,
+    line: &str,
+  ) {
+    let mut file = fs::OpenOptions::new().append(true).open(path).unwrap();
+    writeln!(file, "{line}").unwrap();
+    let path_string = path.to_string_lossy().into_owned();
+    tx.send(Ok(LogFileEvent::Updated(path_string))).unwrap();
+  }
+}
@@ -0,0 +1,260 @@
+//! Recognizes loot messages and the results of `/random` in a character's log, and groups the
+//! rolls into roll sessions so that the winner of each can be picked.
+use super::Line;
+use crate::common::timestamp::Timestamp;
+use fancy_regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Rolls with the same range belong to the same session if they are made within this long of
+/// the first roll of the session
+pub const DEFAULT_ROLL_SESSION_WINDOW: Duration = Duration::from_secs(60);
+
+lazy_static::lazy_static! {
+  static ref LOOTED: Regex = Regex::new(
+    r"^--(?<looter>\w+) ha(?:s|ve) looted (?<item>.+?)(?: from (?<corpse>.+?)(?:'s|`s) corpse)?\.--$",
+  )
+  .unwrap();
+
+  static ref ROLL_RESULT: Regex = Regex::new(
+    r"^\*\*It could have been any number from (?<low>\d+) to (?<high>\d+), but this time it turned up a (?<result>\d+)\.$",
+  )
+  .unwrap();
+}
+
+const ROLL_ANNOUNCEMENT_PREFIX: &str = "**A Magic Die is rolled by ";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ts_rs::TS)]
+pub struct LootDrop {
+  pub looted_at: Timestamp,
+  pub looter: String,
+  pub item: String,
+  pub quantity: u32,
+  /// Only newer servers say which corpse the item was looted from
+  pub corpse: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ts_rs::TS)]
+pub struct Roll {
+  pub rolled_at: Timestamp,
+  pub roller: String,
+  pub low: u32,
+  pub high: u32,
+  pub result: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ts_rs::TS)]
+pub struct RollSession {
+  pub started_at: Timestamp,
+  pub low: u32,
+  pub high: u32,
+  pub rolls: Vec<Roll>,
+  /// Whoever rolled the highest; more than one if there was a tie. Only the first roll of each
+  /// roller counts.
+  pub winners: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "variant", content = "value")]
+pub enum LootRecord {
+  Looted(LootDrop),
+  Rolled(Roll),
+}
+
+/// EverQuest writes each `/random` as two lines: one with who rolled, then one with the result
+pub struct LootTracker {
+  character_name: String,
+  pending_roller: Option<String>,
+}
+
+impl LootTracker {
+  pub fn new(character_name: &str) -> Self {
+    Self {
+      character_name: character_name.to_owned(),
+      pending_roller: None,
+    }
+  }
+
+  pub fn process(&mut self, line: &Line) -> Option<LootRecord> {
+    let content = line.content.as_str();
+    if !content.starts_with("--") && !content.starts_with("**") {
+      self.pending_roller = None;
+      return None;
+    }
+
+    if let Some(roller) = content
+      .strip_prefix(ROLL_ANNOUNCEMENT_PREFIX)
+      .and_then(|rest| rest.strip_suffix('.'))
+    {
+      self.pending_roller = Some(roller.to_owned());
+      return None;
+    }
+
+    let pending_roller = self.pending_roller.take();
+    if let Ok(Some(caps)) = ROLL_RESULT.captures(content) {
+      return Some(LootRecord::Rolled(Roll {
+        rolled_at: line.timestamp()?,
+        roller: pending_roller?,
+        low: caps["low"].parse().ok()?,
+        high: caps["high"].parse().ok()?,
+        result: caps["result"].parse().ok()?,
+      }));
+    }
+
+    let caps = LOOTED.captures(content).ok().flatten()?;
+    let looter = match &caps["looter"] {
+      "You" => self.character_name.clone(),
+      looter => looter.to_owned(),
+    };
+    let (quantity, item) = quantity_and_item(&caps["item"]);
+    Some(LootRecord::Looted(LootDrop {
+      looted_at: line.timestamp()?,
+      looter,
+      item: item.to_owned(),
+      quantity,
+      corpse: caps.name("corpse").map(|corpse| corpse.as_str().to_owned()),
+    }))
+  }
+}
+
+/// Splits "a Bone Chip" into (1, "Bone Chip") and "2 Bone Chips" into (2, "Bone Chips")
+fn quantity_and_item(looted: &str) -> (u32, &str) {
+  if let Some(item) = looted
+    .strip_prefix("a ")
+    .or_else(|| looted.strip_prefix("an "))
+  {
+    return (1, item);
+  }
+  match looted.split_once(' ') {
+    Some((count, item)) => match count.parse() {
+      Ok(quantity) => (quantity, item),
+      Err(_) => (1, looted),
+    },
+    None => (1, looted),
+  }
+}
+
+/// Groups rolls (oldest first) into sessions. A roll joins the latest session with the same
+/// range if that session started within the window, otherwise it starts a new one.
+pub fn roll_sessions(rolls: &[Roll], window: Duration) -> Vec<RollSession> {
+  let mut sessions: Vec<RollSession> = Vec::new();
+  for roll in rolls.iter() {
+    let open_session = sessions.iter_mut().rev().find(|session| {
+      session.low == roll.low
+        && session.high == roll.high
+        && session.started_at.duration_until(&roll.rolled_at) <= window
+    });
+    match open_session {
+      Some(session) => session.rolls.push(roll.clone()),
+      None => sessions.push(RollSession {
+        started_at: roll.rolled_at.clone(),
+        low: roll.low,
+        high: roll.high,
+        rolls: vec![roll.clone()],
+        winners: Vec::new(),
+      }),
+    }
+  }
+  for session in sessions.iter_mut() {
+    session.winners = winners(&session.rolls);
+  }
+  sessions
+}
+
+fn winners(rolls: &[Roll]) -> Vec<String> {
+  let mut counted: Vec<&Roll> = Vec::with_capacity(rolls.len());
+  for roll in rolls.iter() {
+    if !counted.iter().any(|other| other.roller == roll.roller) {
+      counted.push(roll);
+    }
+  }
+  let Some(highest) = counted.iter().map(|roll| roll.result).max() else {
+    return Vec::new();
+  };
+  counted
+    .into_iter()
+    .filter(|roll| roll.result == highest)
+    .map(|roll| roll.roller.clone())
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{roll_sessions, LootRecord, LootTracker, DEFAULT_ROLL_SESSION_WINDOW};
+  use crate::logs::Line;
+
+  fn records(log: &[&str]) -> Vec<LootRecord> {
+    let mut tracker = LootTracker::new("Xenk");
+    log
+      .iter()
+      .filter_map(|raw| tracker.process(&Line::from(raw).unwrap()))
+      .collect()
+  }
+
+  #[test]
+  fn test_loot_lines() {
+    let records = records(&[
+      "[Thu Jul 18 17:35:14 2024] --You have looted a Bone Chip.--",
+      "[Thu Jul 18 17:35:15 2024] --Soandso has looted 2 Bone Chips from a decaying skeleton's corpse.--",
+      "[Thu Jul 18 17:35:16 2024] --You have looted an Ale from a gnoll`s corpse.--",
+      "[Thu Jul 18 17:35:17 2024] Soandso says, '--You have looted a Bone Chip.--'",
+    ]);
+    let drops: Vec<_> = records
+      .into_iter()
+      .map(|record| match record {
+        LootRecord::Looted(drop) => (drop.looter, drop.item, drop.quantity, drop.corpse),
+        LootRecord::Rolled(_) => panic!("Unexpected roll"),
+      })
+      .collect();
+    let s = |s: &str| s.to_owned();
+    assert_eq!(
+      drops,
+      vec![
+        (s("Xenk"), s("Bone Chip"), 1, None),
+        (
+          s("Soandso"),
+          s("Bone Chips"),
+          2,
+          Some(s("a decaying skeleton"))
+        ),
+        (s("Xenk"), s("Ale"), 1, Some(s("a gnoll"))),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_roll_sessions() {
+    let rolls: Vec<_> = records(&[
+      "[Thu Jul 18 17:35:00 2024] **A Magic Die is rolled by Soandso.",
+      "[Thu Jul 18 17:35:00 2024] **It could have been any number from 0 to 100, but this time it turned up a 42.",
+      "[Thu Jul 18 17:35:02 2024] **A Magic Die is rolled by Xenk.",
+      "[Thu Jul 18 17:35:02 2024] **It could have been any number from 0 to 333, but this time it turned up a 300.",
+      "[Thu Jul 18 17:35:05 2024] **A Magic Die is rolled by Bobo.",
+      "[Thu Jul 18 17:35:05 2024] **It could have been any number from 0 to 100, but this time it turned up a 77.",
+      "[Thu Jul 18 17:35:06 2024] **A Magic Die is rolled by Bobo.",
+      "[Thu Jul 18 17:35:06 2024] **It could have been any number from 0 to 100, but this time it turned up a 99.",
+      "[Thu Jul 18 17:35:07 2024] **A Magic Die is rolled by Xenk.",
+      "[Thu Jul 18 17:35:07 2024] **It could have been any number from 0 to 100, but this time it turned up a 77.",
+      "[Thu Jul 18 17:37:00 2024] **A Magic Die is rolled by Soandso.",
+      "[Thu Jul 18 17:37:00 2024] **It could have been any number from 0 to 100, but this time it turned up a 5.",
+    ])
+    .into_iter()
+    .map(|record| match record {
+      LootRecord::Rolled(roll) => roll,
+      LootRecord::Looted(_) => panic!("Unexpected loot"),
+    })
+    .collect();
+    assert_eq!(rolls.len(), 6);
+
+    let sessions = roll_sessions(&rolls, DEFAULT_ROLL_SESSION_WINDOW);
+    assert_eq!(sessions.len(), 3);
+    assert_eq!((sessions[0].high, sessions[0].rolls.len()), (100, 4));
+    // Bobo's second roll does not count, so it is a tie
+    assert_eq!(sessions[0].winners, vec!["Bobo", "Xenk"]);
+    assert_eq!(
+      (sessions[1].high, sessions[1].winners.clone()),
+      (333, vec!["Xenk".to_owned()])
+    );
+    assert_eq!(sessions[2].winners, vec!["Soandso"]);
+  }
+}
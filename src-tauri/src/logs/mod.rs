@@ -1,16 +1,33 @@
 pub mod active_character_detection;
+pub mod archive;
+pub mod chat;
+pub mod combat;
 pub mod log_event_broadcaster;
 pub mod log_file_cursor;
 pub mod log_line_stream;
+pub mod loot;
+pub mod search;
+pub mod who;
+pub mod zone_tracker;
 
+use crate::common::timestamp::Timestamp;
 use fancy_regex::Regex;
+use std::borrow::Cow;
+use std::io::{self, BufRead};
 
 /// This determines how many Lines and LogFileEvents can be buffered
 const FILESYSTEM_EVENT_QUEUE_SIZE: usize = 500;
 
+/// The format of the datetime between the brackets at the start of every log line
+const LOG_DATETIME_FORMAT: &str = "%a %b %d %H:%M:%S %Y";
+
 #[derive(thiserror::Error, Debug, Clone)]
 #[error("Could not parse log file line: `{0}`")]
-struct LogLineParseError(String);
+pub(crate) struct LogLineParseError(String);
+
+#[derive(thiserror::Error, Debug, Clone)]
+#[error("Not an EverQuest character name: `{0}`")]
+pub struct InvalidCharacterName(pub String);
 
 lazy_static::lazy_static! {
   pub static ref LOG_FILENAME_PATTERN: Regex =
@@ -22,6 +39,52 @@ pub enum LogFileEvent {
   Created(String),
   Updated(String),
   Deleted(String),
+  /// The log file was truncated, or replaced by another file (e.g. one renamed to its name), so
+  /// any position in it that was read up to no longer applies. This is sent in place of the
+  /// Created or Updated event for the same change, since it was not EverQuest writing to it.
+  Rotated(String),
+}
+
+/// Tells apart the different files that had the same path at different times, e.g. a log file
+/// and the new file that replaced it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FileIdentity {
+  #[cfg(unix)]
+  device: u64,
+  #[cfg(unix)]
+  inode: u64,
+  /// Windows has no stable file ID in the standard library, so the creation time is used
+  #[cfg(windows)]
+  created: u64,
+}
+
+impl FileIdentity {
+  #[cfg(unix)]
+  pub(crate) fn of(metadata: &std::fs::Metadata) -> Self {
+    use std::os::unix::fs::MetadataExt as _;
+    Self {
+      device: metadata.dev(),
+      inode: metadata.ino(),
+    }
+  }
+
+  #[cfg(windows)]
+  pub(crate) fn of(metadata: &std::fs::Metadata) -> Self {
+    use std::os::windows::fs::MetadataExt as _;
+    Self {
+      created: metadata.creation_time(),
+    }
+  }
+}
+
+/// EverQuest character names are made of letters only. Anything else given as a character name
+/// (e.g. by the UI) must not be used to build a file path.
+pub fn validate_character_name(name: &str) -> Result<&str, InvalidCharacterName> {
+  if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphabetic()) {
+    Ok(name)
+  } else {
+    Err(InvalidCharacterName(name.to_owned()))
+  }
 }
 
 /// An EverQuest log line looks like the following:
@@ -30,14 +93,13 @@ pub enum LogFileEvent {
 #[derive(Debug, Clone)]
 pub struct Line {
   pub content: String,
-  #[allow(unused)]
   pub raw_datetime: String,
 }
 
 impl Line {
   // This method does not use regular expressions to separate the datetime from the content because it
   // is in the critical path of the application and the logic is dead-simple.
-  fn from(raw_line: &str) -> Result<Self, LogLineParseError> {
+  pub(crate) fn from(raw_line: &str) -> Result<Self, LogLineParseError> {
     if !raw_line.starts_with("[") {
       return Err(LogLineParseError(raw_line.to_owned()));
     }
@@ -52,4 +114,77 @@ impl Line {
       raw_datetime,
     })
   }
+
+  /// Parses a line as it was read from a log file, with or without its line ending
+  pub(crate) fn from_bytes(raw_line: &[u8]) -> Result<Self, LogLineParseError> {
+    Self::from(decode_log_line(raw_line).trim_end_matches(['\r', '\n']))
+  }
+
+  /// EverQuest writes log timestamps in local time without a zone, so the returned Timestamp
+  /// holds the local wall-clock time as if it were UTC.
+  pub fn timestamp(&self) -> Option<Timestamp> {
+    chrono::NaiveDateTime::parse_from_str(&self.raw_datetime, LOG_DATETIME_FORMAT)
+      .ok()
+      .map(Timestamp::from)
+  }
+}
+
+/// EverQuest usually writes UTF-8, but some item and player names end up in the log files as
+/// Windows-1252 (e.g. `é` as the single byte 0xE9). Lines that are not valid UTF-8 are decoded as
+/// Windows-1252, which maps every byte to some character, so no line is ever lost to its encoding.
+pub(crate) fn decode_log_line(raw_line: &[u8]) -> Cow<'_, str> {
+  match std::str::from_utf8(raw_line) {
+    Ok(line) => Cow::Borrowed(line),
+    Err(_) => {
+      encoding_rs::WINDOWS_1252
+        .decode_without_bom_handling(raw_line)
+        .0
+    }
+  }
+}
+
+/// Reads the lines of a log file that are in the log line format, decoding each one the way
+/// `decode_log_line` does, so that no log reader stops at a line that is not UTF-8
+pub(crate) fn read_log_lines<R: BufRead>(mut reader: R) -> impl Iterator<Item = io::Result<Line>> {
+  let mut raw_line = Vec::new();
+  std::iter::from_fn(move || loop {
+    raw_line.clear();
+    match reader.read_until(b'\n', &mut raw_line) {
+      Ok(0) => return None,
+      Ok(_) => {
+        if let Ok(line) = Line::from_bytes(&raw_line) {
+          return Some(Ok(line));
+        }
+      }
+      Err(e) => return Some(Err(e)),
+    }
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{read_log_lines, validate_character_name};
+
+  #[test]
+  fn test_validate_character_name() {
+    assert!(validate_character_name("Xenk").is_ok());
+    assert!(validate_character_name("").is_err());
+    assert!(validate_character_name("../../x").is_err());
+    assert!(validate_character_name("Xenk.jsonl").is_err());
+    assert!(validate_character_name("Xenk/Other").is_err());
+  }
+
+  #[test]
+  fn test_read_log_lines() {
+    let log: &[u8] = b"[Thu Jul 18 17:35:14 2024] Ren\xe9e tells you, 'hi'\r\n\
+      not a log line\n\
+      [Thu Jul 18 17:35:15 2024] You gain experience!!\n";
+    let contents: Vec<String> = read_log_lines(log)
+      .map(|line| line.unwrap().content)
+      .collect();
+    assert_eq!(
+      contents,
+      vec!["Renée tells you, 'hi'", "You gain experience!!"]
+    );
+  }
 }
@@ -0,0 +1,142 @@
+//! The LogSearchIndex records where each hour of log lines starts in each log file. Log files
+//! are only ever appended to, so bringing the index up to date only reads what was logged since
+//! the last search. The index assumes that the lines of a log file are in chronological order,
+//! which they are unless the system clock was changed while EverQuest was running.
+use super::for_each_line;
+use crate::{common::timestamp::Timestamp, logs::Line};
+use serde::{Deserialize, Serialize};
+use std::{
+  collections::HashMap,
+  fs, io,
+  path::{Path, PathBuf},
+};
+use tracing::warn;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LogSearchIndex {
+  files: HashMap<PathBuf, FileIndex>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FileIndex {
+  /// How many bytes of the file have been indexed, which always ends with a complete line
+  indexed_len: u64,
+  /// The first line of the file, which tells it apart from a file that replaced it (e.g. after
+  /// the log was archived) even once the new file has grown past `indexed_len`
+  #[serde(default)]
+  first_line: String,
+  /// The first line of each hour, in the order they are in the file
+  hours: Vec<HourStart>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct HourStart {
+  /// Hours since the Unix epoch, in the local time of the log
+  hour: i64,
+  offset: u64,
+}
+
+impl LogSearchIndex {
+  /// An index that is missing or cannot be read is the same as an empty one, which is rebuilt
+  /// by the next search
+  pub fn load(path: &Path) -> Self {
+    let Ok(json) = fs::read_to_string(path) else {
+      return Self::default();
+    };
+    serde_json::from_str(&json).unwrap_or_else(|e| {
+      warn!(
+        "Discarding unreadable log search index {}: {e}",
+        path.display()
+      );
+      Self::default()
+    })
+  }
+
+  pub fn save(&self, path: &Path) -> io::Result<()> {
+    let json = serde_json::to_string(self).map_err(io::Error::from)?;
+    fs::write(path, json)
+  }
+
+  /// Forgets the log files that were deleted or moved
+  pub(super) fn retain_existing(&mut self) {
+    self.files.retain(|path, _| path.exists());
+  }
+
+  /// Indexes whatever was logged to the file since it was last indexed. A file that is shorter
+  /// than what was indexed, or that starts with a different line, was truncated or replaced, so
+  /// it is indexed again from the start.
+  pub fn update(&mut self, path: &Path) -> io::Result<&FileIndex> {
+    let len = fs::metadata(path)?.len();
+    let first_line = read_first_line(path)?;
+    let file_index = self.files.entry(path.to_owned()).or_default();
+    if len < file_index.indexed_len || first_line != file_index.first_line {
+      *file_index = FileIndex {
+        first_line,
+        ..FileIndex::default()
+      };
+    }
+    if len > file_index.indexed_len {
+      let hours = &mut file_index.hours;
+      let mut last_hour = hours.last().map(|hour_start| hour_start.hour);
+      file_index.indexed_len = for_each_line(path, file_index.indexed_len, None, |offset, raw| {
+        let Some(timestamp) = Line::from(raw).ok().and_then(|line| line.timestamp()) else {
+          return true;
+        };
+        let hour = hour_of(&timestamp);
+        if last_hour != Some(hour) {
+          hours.push(HourStart { hour, offset });
+          last_hour = Some(hour);
+        }
+        true
+      })?;
+    }
+    Ok(file_index)
+  }
+}
+
+impl FileIndex {
+  /// The bytes of the file that can have lines logged in the date range. The end is `None` if
+  /// the range goes on to the end of the file.
+  pub fn byte_range(
+    &self,
+    from: Option<&Timestamp>,
+    until: Option<&Timestamp>,
+  ) -> (u64, Option<u64>) {
+    let start = match from {
+      Some(from) => {
+        let from_hour = hour_of(from);
+        self
+          .hours
+          .iter()
+          .find(|hour_start| hour_start.hour >= from_hour)
+          .map_or(self.indexed_len, |hour_start| hour_start.offset)
+      }
+      None => 0,
+    };
+    let end = until.and_then(|until| {
+      let until_hour = hour_of(until);
+      self
+        .hours
+        .iter()
+        .find(|hour_start| hour_start.offset >= start && hour_start.hour > until_hour)
+        .map(|hour_start| hour_start.offset)
+    });
+    (start, end)
+  }
+}
+
+/// Empty if the file does not have a complete line yet
+fn read_first_line(path: &Path) -> io::Result<String> {
+  let mut first_line = String::new();
+  for_each_line(path, 0, None, |_, line| {
+    first_line = line.to_owned();
+    false
+  })?;
+  Ok(first_line)
+}
+
+fn hour_of(timestamp: &Timestamp) -> i64 {
+  chrono::DateTime::<chrono::Utc>::from(timestamp.clone())
+    .timestamp()
+    .div_euclid(3600)
+}
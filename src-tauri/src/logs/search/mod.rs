@@ -0,0 +1,411 @@
+//! Searches the lines of every EverQuest log file in the logs directory with a Matcher. Lines
+//! can be limited to a range of log timestamps, which the LogSearchIndex can turn into a range
+//! of bytes to read so that repeated searches of a large log do not read all of it. The archives
+//! of the log files are searched too, ahead of the lines that are still in each log file.
+pub mod index;
+
+use self::index::LogSearchIndex;
+use super::{
+  archive::{self, ARCHIVE_FILENAME_PATTERN},
+  decode_log_line, Line, LOG_FILENAME_PATTERN,
+};
+use crate::{
+  common::{progress_reporter::ProgressReporter, timestamp::Timestamp},
+  matchers::{LineToMatch, Matcher},
+};
+use fancy_regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{
+  fs,
+  io::{self, BufRead, Seek as _},
+  path::{Path, PathBuf},
+};
+use tracing::warn;
+
+#[derive(Debug, Clone, Deserialize, ts_rs::TS)]
+pub struct LogSearchQuery {
+  pub matcher: Matcher,
+  /// Only lines logged at or after this
+  pub from: Option<Timestamp>,
+  /// Only lines logged before this
+  pub until: Option<Timestamp>,
+  /// Only the log files of this character (case-insensitive), or of every character if None
+  pub character_name: Option<String>,
+  /// The search stops after this many hits
+  pub limit: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, ts_rs::TS)]
+pub struct LogSearchHit {
+  pub log_file_path: String,
+  pub character_name: String,
+  pub server: String,
+  /// Where the line starts in the log file, in bytes
+  #[ts(type = "number")]
+  pub offset: u64,
+  pub logged_at: Timestamp,
+  /// The content of the line without the timestamp
+  pub line: String,
+}
+
+struct LogFile {
+  path: PathBuf,
+  character_name: String,
+  server: String,
+  /// Archives are not indexed, so they are always read whole
+  archived: bool,
+}
+
+/// Calls `on_hit` with each matching line, in the order of the characters and then of the
+/// lines, and returns how many lines matched. If an index is given, it is brought up to date
+/// with the log files and used to skip the parts of them that are outside of the date range.
+pub fn search_logs<F>(
+  logs_dir: &Path,
+  query: &LogSearchQuery,
+  mut index: Option<&mut LogSearchIndex>,
+  progress: &ProgressReporter,
+  mut on_hit: F,
+) -> io::Result<u32>
+where
+  F: FnMut(LogSearchHit),
+{
+  let log_files = list_log_files(logs_dir, query.character_name.as_deref())?;
+  if let Some(index) = index.as_mut() {
+    index.retain_existing();
+  }
+
+  let mut hit_count: u32 = 0;
+  for (n, log_file) in log_files.iter().enumerate() {
+    if query.limit.is_some_and(|limit| hit_count >= limit) {
+      break;
+    }
+    let file_name = log_file
+      .path
+      .file_name()
+      .unwrap_or_default()
+      .to_string_lossy();
+    progress.update(format!(
+      "Searching {file_name} ({} of {})",
+      n + 1,
+      log_files.len()
+    ));
+
+    if query
+      .from
+      .as_ref()
+      .is_some_and(|from| modified_before(&log_file.path, from))
+    {
+      continue;
+    }
+    let (start, end) = match index.as_mut().filter(|_| !log_file.archived) {
+      Some(index) => match index.update(&log_file.path) {
+        Ok(file_index) => file_index.byte_range(query.from.as_ref(), query.until.as_ref()),
+        Err(e) => {
+          warn!("Could not index {}: {e:?}", log_file.path.display());
+          (0, None)
+        }
+      },
+      None => (0, None),
+    };
+
+    let on_line = |offset, raw_line: &str| {
+      let Ok(line) = Line::from(raw_line) else {
+        return true;
+      };
+      let Some(logged_at) = line.timestamp() else {
+        return true;
+      };
+      if query.from.as_ref().is_some_and(|from| logged_at < *from)
+        || query
+          .until
+          .as_ref()
+          .is_some_and(|until| logged_at >= *until)
+      {
+        return true;
+      }
+      let line_to_match = LineToMatch::new(&line.content, &log_file.character_name, &[]);
+      if query.matcher.check_line(&line_to_match).is_some() {
+        hit_count += 1;
+        on_hit(LogSearchHit {
+          log_file_path: log_file.path.to_string_lossy().into_owned(),
+          character_name: log_file.character_name.clone(),
+          server: log_file.server.clone(),
+          offset,
+          logged_at,
+          line: line.content,
+        });
+      }
+      query.limit.is_none_or(|limit| hit_count < limit)
+    };
+    if log_file.archived {
+      for_each_line_in(archive::open_archive(&log_file.path)?, 0, None, on_line)?;
+    } else {
+      for_each_line(&log_file.path, start, end, on_line)?;
+    }
+  }
+
+  progress.finished(format!(
+    "Found {hit_count} matching lines in {} log files",
+    log_files.len()
+  ));
+  Ok(hit_count)
+}
+
+fn list_log_files(logs_dir: &Path, character_name: Option<&str>) -> io::Result<Vec<LogFile>> {
+  let mut log_files = Vec::new();
+  list_log_files_in(logs_dir, false, character_name, &mut log_files)?;
+  match list_log_files_in(
+    &archive::archive_dir(logs_dir),
+    true,
+    character_name,
+    &mut log_files,
+  ) {
+    Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+    _ => {}
+  }
+  log_files.sort_by(|a, b| {
+    (&a.character_name, &a.server, !a.archived, &a.path).cmp(&(
+      &b.character_name,
+      &b.server,
+      !b.archived,
+      &b.path,
+    ))
+  });
+  Ok(log_files)
+}
+
+fn list_log_files_in(
+  dir: &Path,
+  archived: bool,
+  character_name: Option<&str>,
+  log_files: &mut Vec<LogFile>,
+) -> io::Result<()> {
+  let file_name_pattern: &Regex = if archived {
+    &ARCHIVE_FILENAME_PATTERN
+  } else {
+    &LOG_FILENAME_PATTERN
+  };
+  for entry in fs::read_dir(dir)? {
+    let entry = entry?;
+    let file_name = entry.file_name().to_string_lossy().into_owned();
+    let Ok(Some(caps)) = file_name_pattern.captures(&file_name) else {
+      continue;
+    };
+    let (name, server) = (caps[1].to_owned(), caps[2].to_owned());
+    if character_name.is_some_and(|wanted| !wanted.eq_ignore_ascii_case(&name)) {
+      continue;
+    }
+    log_files.push(LogFile {
+      path: entry.path(),
+      character_name: name,
+      server,
+      archived,
+    });
+  }
+  Ok(())
+}
+
+/// Nothing in a log file can have been logged after the file was last modified. The timestamps
+/// of log lines are in local time, so the modification time is compared as local time too.
+fn modified_before(path: &Path, timestamp: &Timestamp) -> bool {
+  let Ok(modified) = fs::metadata(path).and_then(|metadata| metadata.modified()) else {
+    return false;
+  };
+  let modified = chrono::DateTime::<chrono::Local>::from(modified).naive_local();
+  Timestamp::from(modified) < *timestamp
+}
+
+/// Calls `f` with the byte offset and text of each complete line from `start` up to `end` (or
+/// the end of the file) until it returns false. Returns the offset after the last line that was
+/// read. Text that is not valid UTF-8 is replaced rather than failing the whole file.
+pub(crate) fn for_each_line<F>(path: &Path, start: u64, end: Option<u64>, f: F) -> io::Result<u64>
+where
+  F: FnMut(u64, &str) -> bool,
+{
+  let mut file = fs::File::open(path)?;
+  file.seek(io::SeekFrom::Start(start))?;
+  for_each_line_in(io::BufReader::new(file), start, end, f)
+}
+
+/// The same as `for_each_line`, but for a reader that is already at `start`
+pub(crate) fn for_each_line_in<R, F>(
+  mut reader: R,
+  start: u64,
+  end: Option<u64>,
+  mut f: F,
+) -> io::Result<u64>
+where
+  R: BufRead,
+  F: FnMut(u64, &str) -> bool,
+{
+  let mut offset = start;
+  let mut buf = Vec::new();
+  loop {
+    if end.is_some_and(|end| offset >= end) {
+      break;
+    }
+    buf.clear();
+    let read = reader.read_until(b'\n', &mut buf)?;
+    if read == 0 || buf.last() != Some(&b'\n') {
+      break; // a partial line is still being written
+    }
+    let raw_line = decode_log_line(&buf);
+    let keep_going = f(offset, raw_line.trim_end_matches(['\r', '\n']));
+    offset += read as u64;
+    if !keep_going {
+      break;
+    }
+  }
+  Ok(offset)
+}
+
+/// Parses `YYYY-MM-DD` or `YYYY-MM-DD HH:MM:SS` in local time, like log timestamps. A date
+/// alone is the start of that day, or the end of it if `end_of_day` is true.
+pub fn parse_date_bound(value: &str, end_of_day: bool) -> Option<Timestamp> {
+  let value = value.trim();
+  if let Ok(datetime) = chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S") {
+    return Some(Timestamp::from(datetime));
+  }
+  let date = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+  let date = if end_of_day { date.succ_opt()? } else { date };
+  Some(Timestamp::from(date.and_hms_opt(0, 0, 0)?))
+}
+
+pub fn print_hit(hit: &LogSearchHit) {
+  let file_name = Path::new(&hit.log_file_path)
+    .file_name()
+    .unwrap_or_default()
+    .to_string_lossy();
+  println!(
+    "{file_name}:{}: [{}] {}",
+    hit.offset, hit.logged_at, hit.line
+  );
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{index::LogSearchIndex, parse_date_bound, search_logs, LogSearchQuery};
+  use crate::{
+    common::{progress_reporter::ProgressReporter, random_id},
+    matchers::Matcher,
+  };
+  use std::fs;
+
+  #[test]
+  fn test_search_logs() {
+    let logs_dir = std::env::temp_dir().join(format!("logs_{}", random_id(8)));
+    fs::create_dir_all(&logs_dir).unwrap();
+    fs::write(
+      logs_dir.join("eqlog_Xenk_P1999Green.txt"),
+      "[Wed Jul 17 23:59:59 2024] Soandso tells you, 'hello'\r\n\
+       [Thu Jul 18 17:35:14 2024] You have entered East Commonlands.\r\n\
+       [Thu Jul 18 17:35:15 2024] Soandso tells you, 'hi again'\r\n\
+       [Fri Jul 19 00:00:00 2024] Soandso tells you, 'goodbye'\r\n",
+    )
+    .unwrap();
+    fs::write(
+      logs_dir.join("eqlog_Bobo_P1999Green.txt"),
+      "[Thu Jul 18 17:35:16 2024] Soandso tells you, 'hey Bobo'\n",
+    )
+    .unwrap();
+    fs::write(logs_dir.join("notes.txt"), "Soandso tells you, 'hi'\n").unwrap();
+
+    let (progress, _rx_progress) = ProgressReporter::new();
+    let mut query = LogSearchQuery {
+      matcher: Matcher::gina(r"^{S} tells you, '(.+)'$").unwrap(),
+      from: parse_date_bound("2024-07-18", false),
+      until: parse_date_bound("2024-07-18", true),
+      character_name: None,
+      limit: None,
+    };
+    let search = |query: &LogSearchQuery, index: Option<&mut LogSearchIndex>| {
+      let mut hits = Vec::new();
+      search_logs(&logs_dir, query, index, &progress, |hit| {
+        hits.push((hit.character_name, hit.offset, hit.line))
+      })
+      .unwrap();
+      hits
+    };
+
+    let expected = vec![
+      (
+        "Bobo".to_owned(),
+        0,
+        "Soandso tells you, 'hey Bobo'".to_owned(),
+      ),
+      (
+        "Xenk".to_owned(),
+        118,
+        "Soandso tells you, 'hi again'".to_owned(),
+      ),
+    ];
+    assert_eq!(search(&query, None), expected);
+    let mut index = LogSearchIndex::default();
+    assert_eq!(search(&query, Some(&mut index)), expected);
+    // The second time, the index is already up to date
+    assert_eq!(search(&query, Some(&mut index)), expected);
+
+    query.character_name = Some("xenk".into());
+    query.from = None;
+    query.limit = Some(1);
+    assert_eq!(
+      search(&query, Some(&mut index)),
+      vec![(
+        "Xenk".to_owned(),
+        0,
+        "Soandso tells you, 'hello'".to_owned()
+      )]
+    );
+
+    fs::remove_dir_all(&logs_dir).unwrap();
+  }
+
+  #[test]
+  fn test_search_index_replaced_log_file() {
+    let logs_dir = std::env::temp_dir().join(format!("logs_{}", random_id(8)));
+    fs::create_dir_all(&logs_dir).unwrap();
+    let log_file_path = logs_dir.join("eqlog_Xenk_P1999Green.txt");
+    fs::write(
+      &log_file_path,
+      "[Wed Jul 17 23:59:59 2024] Soandso tells you, 'hello'\n",
+    )
+    .unwrap();
+
+    let (progress, _rx_progress) = ProgressReporter::new();
+    let query = LogSearchQuery {
+      matcher: Matcher::gina(r"^{S} tells you, '(.+)'$").unwrap(),
+      from: parse_date_bound("2024-07-18", false),
+      until: None,
+      character_name: None,
+      limit: None,
+    };
+    let mut index = LogSearchIndex::default();
+    let mut search = || {
+      let mut hits = Vec::new();
+      search_logs(&logs_dir, &query, Some(&mut index), &progress, |hit| {
+        hits.push((hit.offset, hit.line))
+      })
+      .unwrap();
+      hits
+    };
+    assert_eq!(search(), vec![]);
+
+    // The new file is longer than what was indexed of the old one, so only its first line shows
+    // that it is a different file
+    fs::write(
+      &log_file_path,
+      "[Thu Jul 18 17:35:14 2024] Soandso tells you, 'hi again'\n\
+       [Thu Jul 18 17:35:15 2024] Soandso tells you, 'goodbye'\n",
+    )
+    .unwrap();
+    assert_eq!(
+      search(),
+      vec![
+        (0, "Soandso tells you, 'hi again'".to_owned()),
+        (57, "Soandso tells you, 'goodbye'".to_owned())
+      ]
+    );
+
+    fs::remove_dir_all(&logs_dir).unwrap();
+  }
+}
@@ -0,0 +1,291 @@
+//! Parses the player lists that `/who` (and its variants, e.g. `/who all guild`) and the raid
+//! list write to the log. A `/who` listing starts with a "Players on EverQuest:" header, has one
+//! line per player, and ends with a summary like "There are 54 players in The Plane of Fear." A
+//! raid list starts with a "Players in your raid:" header, has one line per raid member with the
+//! columns of the raid window (group, name, level, class and rank), and ends with a summary like
+//! "There are 54 players in your raid."
+use super::Line;
+use crate::common::timestamp::Timestamp;
+use fancy_regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const LISTING_HEADERS: &[&str] = &["Players on EverQuest:", "Players in EverQuest:"];
+const RAID_LISTING_HEADERS: &[&str] = &["Players in your raid:", "Players in raid:"];
+
+/// A listing is logged all at once, so one whose lines stop for longer than this is abandoned
+/// (e.g. because it was cut off before its summary line)
+const LISTING_MAX_GAP: Duration = Duration::from_secs(2);
+
+lazy_static::lazy_static! {
+  static ref PLAYER: Regex = Regex::new(
+    r"^\s*(?:\*\s*GM[^*]*\*\s*)?(?:AFK\s+)?(?:<LINKDEAD>\s*)?\[(?:(?<level>\d+) (?<title>[^\]\(]+?)(?: \((?<class>[^)]+)\))?|ANONYMOUS)\] (?<name>[A-Za-z]+)(?: \((?<race>[^)]+)\))?(?: +<(?<guild>[^>]+)>)?",
+  )
+  .unwrap();
+
+  static ref RAID_MEMBER: Regex = Regex::new(
+    r"^\s*(?<group>\d+)\s+(?<name>[A-Za-z]+)\s+(?<level>\d+)\s+(?<class>[A-Za-z]+(?: [A-Za-z]+)?)(?:\s+(?:Raid Leader|Group Leader|Raid Officer|Looter|Marked NPC|Main Tank|Main Assist|Puller))*\s*$",
+  )
+  .unwrap();
+
+  static ref SUMMARY: Regex = Regex::new(
+    r"^There (?:are|is) (?<count>\d+|no) players? in (?<location>.+?)\.$",
+  )
+  .unwrap();
+}
+
+/// The level titles that older servers show in place of the class name
+const CLASS_TITLES: &[(&str, &[&str])] = &[
+  ("Bard", &["Minstrel", "Troubadour", "Virtuoso"]),
+  ("Beastlord", &["Primalist", "Animist", "Savage Lord"]),
+  ("Berserker", &["Brawler", "Vehement", "Rager"]),
+  ("Cleric", &["Vicar", "Templar", "High Priest"]),
+  ("Druid", &["Wanderer", "Preserver", "Hierophant"]),
+  ("Enchanter", &["Illusionist", "Beguiler", "Phantasmist"]),
+  ("Magician", &["Elementalist", "Conjurer", "Arch Mage"]),
+  ("Monk", &["Disciple", "Master", "Grandmaster"]),
+  ("Necromancer", &["Heretic", "Defiler", "Warlock"]),
+  ("Paladin", &["Cavalier", "Knight", "Crusader"]),
+  ("Ranger", &["Pathfinder", "Outrider", "Warder"]),
+  ("Rogue", &["Rake", "Blackguard", "Assassin"]),
+  ("Shadow Knight", &["Reaver", "Revenant", "Grave Lord"]),
+  ("Shaman", &["Mystic", "Luminary", "Oracle"]),
+  ("Warrior", &["Champion", "Myrmidon", "Warlord"]),
+  ("Wizard", &["Channeler", "Evoker", "Sorcerer"]),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ts_rs::TS)]
+pub struct WhoEntry {
+  pub name: String,
+  /// Anonymous players do not show their level, class or race
+  pub level: Option<u32>,
+  pub class: Option<String>,
+  pub race: Option<String>,
+  pub guild: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ts_rs::TS)]
+pub struct WhoListing {
+  /// When the summary line was logged
+  pub listed_at: Timestamp,
+  /// The zone, or "EverQuest" for a `/who all`
+  pub location: String,
+  /// The number of players EverQuest said there were, which can be more than the number of
+  /// entries if the listing was cut short
+  pub reported_count: u32,
+  pub entries: Vec<WhoEntry>,
+}
+
+#[derive(Debug, Default)]
+pub struct WhoParser {
+  /// The listing that is being read, if one has started
+  pending: Option<PendingListing>,
+}
+
+#[derive(Debug)]
+struct PendingListing {
+  entries: Vec<WhoEntry>,
+  is_raid: bool,
+  /// When the last line of the listing was logged
+  last_line_at: Option<Timestamp>,
+}
+
+impl WhoParser {
+  /// Returns the listing when its summary line is reached. Lines from other messages that are
+  /// logged in the middle of a listing are skipped, but a listing that goes quiet for longer
+  /// than LISTING_MAX_GAP is dropped.
+  pub fn process(&mut self, line: &Line) -> Option<WhoListing> {
+    let content = line.content.as_str();
+    let timestamp = line.timestamp();
+    let is_raid_header = RAID_LISTING_HEADERS.contains(&content);
+    if is_raid_header || LISTING_HEADERS.contains(&content) {
+      self.pending = Some(PendingListing {
+        entries: Vec::new(),
+        is_raid: is_raid_header,
+        last_line_at: timestamp,
+      });
+      return None;
+    }
+    let pending = self.pending.as_mut()?;
+    if let (Some(last_line_at), Some(timestamp)) = (&pending.last_line_at, &timestamp) {
+      if last_line_at.duration_until(timestamp) > LISTING_MAX_GAP {
+        self.pending = None;
+        return None;
+      }
+    }
+
+    let entry = if pending.is_raid {
+      parse_raid_member(content)
+    } else {
+      parse_player(content)
+    };
+    if let Some(entry) = entry {
+      pending.entries.push(entry);
+      pending.last_line_at = timestamp.or(pending.last_line_at.take());
+      return None;
+    }
+
+    let caps = SUMMARY.captures(content).ok().flatten()?;
+    let entries = self.pending.take()?.entries;
+    Some(WhoListing {
+      listed_at: timestamp?,
+      location: caps["location"].to_owned(),
+      reported_count: caps["count"].parse().unwrap_or(0),
+      entries,
+    })
+  }
+}
+
+fn parse_player(content: &str) -> Option<WhoEntry> {
+  let caps = PLAYER.captures(content).ok().flatten()?;
+  let title = caps.name("title").map(|title| title.as_str());
+  let class = caps
+    .name("class")
+    .map(|class| class.as_str())
+    .or_else(|| title.map(class_of_title));
+  Some(WhoEntry {
+    name: caps["name"].to_owned(),
+    level: caps
+      .name("level")
+      .and_then(|level| level.as_str().parse().ok()),
+    class: class.map(str::to_owned),
+    race: caps.name("race").map(|race| race.as_str().to_owned()),
+    guild: caps.name("guild").map(|guild| guild.as_str().to_owned()),
+  })
+}
+
+/// The raid list does not show races or guilds
+fn parse_raid_member(content: &str) -> Option<WhoEntry> {
+  let caps = RAID_MEMBER.captures(content).ok().flatten()?;
+  Some(WhoEntry {
+    name: caps["name"].to_owned(),
+    level: caps["level"].parse().ok(),
+    class: Some(caps["class"].to_owned()),
+    race: None,
+    guild: None,
+  })
+}
+
+/// A title that is not known is assumed to be the name of the class
+fn class_of_title(title: &str) -> &str {
+  CLASS_TITLES
+    .iter()
+    .find(|(_, titles)| titles.contains(&title))
+    .map_or(title, |(class, _)| class)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{WhoEntry, WhoParser};
+  use crate::logs::Line;
+
+  #[test]
+  fn test_who_listing() {
+    let log = [
+      "[Thu Jul 18 17:35:14 2024] Players on EverQuest:",
+      "[Thu Jul 18 17:35:14 2024] ---------------------------",
+      "[Thu Jul 18 17:35:14 2024] [60 Warlord] Soandso (Barbarian) <Tinkering Guild>",
+      "[Thu Jul 18 17:35:14 2024] Bobo tells the guild, 'brb'",
+      "[Thu Jul 18 17:35:14 2024] [ANONYMOUS] Bobo  <Tinkering Guild>",
+      "[Thu Jul 18 17:35:14 2024]  AFK [50 Shadow Knight] Xenk (Dark Elf)",
+      "[Thu Jul 18 17:35:14 2024] [105 Duskblade (Shadow Knight)] Vex (Iksar) <Tinkering Guild> ZONE: fearplane",
+      "[Thu Jul 18 17:35:14 2024] There are 4 players in The Plane of Fear.",
+      "[Thu Jul 18 17:35:20 2024] There are 4 players in The Plane of Fear.",
+    ];
+    let mut parser = WhoParser::default();
+    let listings: Vec<_> = log
+      .iter()
+      .filter_map(|raw| parser.process(&Line::from(raw).unwrap()))
+      .collect();
+    assert_eq!(listings.len(), 1);
+
+    let listing = &listings[0];
+    assert_eq!(listing.location, "The Plane of Fear");
+    assert_eq!(listing.reported_count, 4);
+    let entry =
+      |name: &str, level, class: Option<&str>, race: Option<&str>, guild: Option<&str>| WhoEntry {
+        name: name.to_owned(),
+        level,
+        class: class.map(str::to_owned),
+        race: race.map(str::to_owned),
+        guild: guild.map(str::to_owned),
+      };
+    assert_eq!(
+      listing.entries,
+      vec![
+        entry(
+          "Soandso",
+          Some(60),
+          Some("Warrior"),
+          Some("Barbarian"),
+          Some("Tinkering Guild")
+        ),
+        entry("Bobo", None, None, None, Some("Tinkering Guild")),
+        entry(
+          "Xenk",
+          Some(50),
+          Some("Shadow Knight"),
+          Some("Dark Elf"),
+          None
+        ),
+        entry(
+          "Vex",
+          Some(105),
+          Some("Shadow Knight"),
+          Some("Iksar"),
+          Some("Tinkering Guild")
+        ),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_raid_listing() {
+    let log = [
+      "[Thu Jul 18 20:00:00 2024] Players in your raid:",
+      "[Thu Jul 18 20:00:00 2024] 1 Soandso 60 Warrior Raid Leader",
+      "[Thu Jul 18 20:00:00 2024] 1 Bobo 59 Shadow Knight Group Leader Looter",
+      "[Thu Jul 18 20:00:00 2024] Bobo tells the raid, 'inc'",
+      "[Thu Jul 18 20:00:00 2024] 2 Xenk 60 Enchanter",
+      "[Thu Jul 18 20:00:00 2024] There are 3 players in your raid.",
+    ];
+    let mut parser = WhoParser::default();
+    let listings: Vec<_> = log
+      .iter()
+      .filter_map(|raw| parser.process(&Line::from(raw).unwrap()))
+      .collect();
+    assert_eq!(listings.len(), 1);
+    assert_eq!(listings[0].location, "your raid");
+    let member = |name: &str, level, class: &str| WhoEntry {
+      name: name.to_owned(),
+      level: Some(level),
+      class: Some(class.to_owned()),
+      race: None,
+      guild: None,
+    };
+    assert_eq!(
+      listings[0].entries,
+      vec![
+        member("Soandso", 60, "Warrior"),
+        member("Bobo", 59, "Shadow Knight"),
+        member("Xenk", 60, "Enchanter"),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_listing_without_summary_is_dropped() {
+    let log = [
+      "[Thu Jul 18 17:35:14 2024] Players on EverQuest:",
+      "[Thu Jul 18 17:35:14 2024] [60 Warlord] Soandso (Barbarian) <Tinkering Guild>",
+      "[Thu Jul 18 17:40:00 2024] [50 Shadow Knight] Xenk (Dark Elf)",
+      "[Thu Jul 18 17:40:00 2024] There are 2 players in The Plane of Fear.",
+    ];
+    let mut parser = WhoParser::default();
+    let listings: Vec<_> = log
+      .iter()
+      .filter_map(|raw| parser.process(&Line::from(raw).unwrap()))
+      .collect();
+    assert!(listings.is_empty());
+  }
+}
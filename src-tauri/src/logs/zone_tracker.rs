@@ -0,0 +1,227 @@
+use super::{
+  archive, decode_log_line, log_file_cursor::LogFileCursor, search::for_each_line_in, Line,
+};
+use serde::Serialize;
+use std::{
+  fs,
+  io::{self, Read as _, Seek as _, SeekFrom},
+  path::Path,
+};
+
+/// How far back from the cursor `ZoneTracker::seed` looks for the latest zone change
+const MAX_SEED_SCAN_BYTES: u64 = 16 * 1024 * 1024;
+
+const SEED_SCAN_CHUNK_SIZE: u64 = 64 * 1024;
+
+/// Messages that start like a zone change but are not one
+const NOT_ZONE_NAMES: &[&str] = &[
+  "an area where levitation effects do not function",
+  "an Arena (PvP) area",
+];
+
+#[derive(Debug, Clone, Serialize, ts_rs::TS)]
+pub struct ZoneChange {
+  pub character_name: String,
+  pub zone: String,
+}
+
+/// Keeps track of the zone a character is in while their log file is followed
+#[derive(Debug, Default)]
+pub struct ZoneTracker {
+  current: Option<String>,
+}
+
+impl ZoneTracker {
+  /// Starts with the zone from the latest zone change before the cursor, so the zone is known
+  /// before the character zones again. This reads the file backward from the cursor, and
+  /// gives up after `MAX_SEED_SCAN_BYTES`. If there is no zone change in a shorter log file,
+  /// the latest archive of it that has one is read instead.
+  pub fn seed(cursor: &LogFileCursor) -> io::Result<Self> {
+    let mut current = latest_zone_before(cursor)?;
+    let scanned_whole_file =
+      cursor.position.min(fs::metadata(&cursor.path)?.len()) <= MAX_SEED_SCAN_BYTES;
+    if current.is_none() && scanned_whole_file {
+      current = latest_zone_in_archives(Path::new(&cursor.path))?;
+    }
+    Ok(Self { current })
+  }
+
+  /// Returns the new zone if the line is a zone change
+  pub fn track(&mut self, line_content: &str) -> Option<&str> {
+    let zone_name = zone_entered(line_content)?;
+    self.current = Some(zone_name.to_owned());
+    self.current.as_deref()
+  }
+
+  pub fn current(&self) -> Option<&str> {
+    self.current.as_deref()
+  }
+}
+
+/// Returns the name of the zone if the line is a "You have entered <Zone>." message
+pub fn zone_entered(line_content: &str) -> Option<&str> {
+  let zone_name = line_content
+    .strip_prefix("You have entered ")?
+    .strip_suffix('.')?;
+  if zone_name.is_empty() || NOT_ZONE_NAMES.contains(&zone_name) {
+    None
+  } else {
+    Some(zone_name)
+  }
+}
+
+fn latest_zone_before(cursor: &LogFileCursor) -> io::Result<Option<String>> {
+  let mut file = fs::File::open(&cursor.path)?;
+  let end = cursor.position.min(file.metadata()?.len());
+  let scan_start = end.saturating_sub(MAX_SEED_SCAN_BYTES);
+
+  let mut chunk_end = end;
+  // The start of a line that continues into the chunk that was read before this one
+  let mut partial_line: Vec<u8> = Vec::new();
+  while chunk_end > scan_start {
+    let chunk_start = chunk_end
+      .saturating_sub(SEED_SCAN_CHUNK_SIZE)
+      .max(scan_start);
+    let mut buffer = vec![0; (chunk_end - chunk_start) as usize];
+    file.seek(SeekFrom::Start(chunk_start))?;
+    file.read_exact(&mut buffer)?;
+    buffer.extend_from_slice(&partial_line);
+
+    // Everything up to the first newline could be the end of a line in the next chunk
+    let complete_lines_start = if chunk_start > scan_start {
+      buffer
+        .iter()
+        .position(|byte| *byte == b'\n')
+        .map_or(buffer.len(), |newline| newline + 1)
+    } else {
+      0
+    };
+    for raw_line in buffer[complete_lines_start..].rsplit(|byte| *byte == b'\n') {
+      let raw_line = decode_log_line(raw_line);
+      if let Ok(line) = Line::from(raw_line.trim_end_matches('\r')) {
+        if let Some(zone_name) = zone_entered(&line.content) {
+          return Ok(Some(zone_name.to_owned()));
+        }
+      }
+    }
+    buffer.truncate(complete_lines_start);
+    partial_line = buffer;
+    chunk_end = chunk_start;
+  }
+  Ok(None)
+}
+
+/// Archives are compressed, so each one is read from the start, newest first
+fn latest_zone_in_archives(log_file_path: &Path) -> io::Result<Option<String>> {
+  for archive_path in archive::archives_of(log_file_path)?.iter().rev() {
+    let mut latest = None;
+    for_each_line_in(
+      archive::open_archive(archive_path)?,
+      0,
+      None,
+      |_, raw_line| {
+        if let Some(zone_name) = Line::from(raw_line)
+          .ok()
+          .and_then(|line| zone_entered(&line.content).map(str::to_owned))
+        {
+          latest = Some(zone_name);
+        }
+        true
+      },
+    )?;
+    if latest.is_some() {
+      return Ok(latest);
+    }
+  }
+  Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{zone_entered, ZoneTracker, SEED_SCAN_CHUNK_SIZE};
+  use crate::{
+    common::random_id,
+    logs::{
+      archive::{archive_dir, archive_log_file},
+      log_file_cursor::LogFileCursor,
+      search::parse_date_bound,
+    },
+  };
+
+  #[test]
+  fn test_zone_entered() {
+    assert_eq!(
+      zone_entered("You have entered The Plane of Knowledge."),
+      Some("The Plane of Knowledge")
+    );
+    assert_eq!(
+      zone_entered("You have entered East Commonlands."),
+      Some("East Commonlands")
+    );
+    assert_eq!(zone_entered("You have entered an Arena (PvP) area."), None);
+    assert_eq!(
+      zone_entered("You have entered an area where levitation effects do not function."),
+      None
+    );
+    assert_eq!(
+      zone_entered("Soandso says, 'You have entered Qeynos.'"),
+      None
+    );
+  }
+
+  #[test]
+  fn test_seed_from_end_of_log() {
+    let filler = "[Thu Jul 18 17:35:14 2024] You gain experience!!\r\n"
+      .repeat(2 * SEED_SCAN_CHUNK_SIZE as usize / 40);
+    let contents = format!(
+      "[Thu Jul 18 17:30:00 2024] You have entered Oasis of Marr.\r\n\
+       {filler}\
+       [Thu Jul 18 17:35:15 2024] You have entered North Ro.\r\n\
+       {filler}\
+       [Thu Jul 18 17:40:00 2024] You have entered an Arena (PvP) area.\r\n"
+    );
+    let path = std::env::temp_dir().join(format!("eqlog_Xenk_{}.txt", random_id(8)));
+    std::fs::write(&path, &contents).unwrap();
+    let path = path.to_string_lossy().into_owned();
+
+    let tracker = ZoneTracker::seed(&LogFileCursor::new(&path).unwrap()).unwrap();
+    assert_eq!(tracker.current(), Some("North Ro"));
+
+    let before_north_ro = contents.find("[Thu Jul 18 17:35:15").unwrap() as u64;
+    let cursor = LogFileCursor {
+      path: path.clone(),
+      position: before_north_ro,
+    };
+    let mut tracker = ZoneTracker::seed(&cursor).unwrap();
+    assert_eq!(tracker.current(), Some("Oasis of Marr"));
+
+    assert_eq!(tracker.track("You gain experience!!"), None);
+    assert_eq!(tracker.track("You have entered Qeynos."), Some("Qeynos"));
+    assert_eq!(tracker.current(), Some("Qeynos"));
+
+    std::fs::remove_file(&path).unwrap();
+  }
+
+  #[test]
+  fn test_seed_from_archive() {
+    let logs_dir = std::env::temp_dir().join(format!("logs_{}", random_id(8)));
+    std::fs::create_dir_all(&logs_dir).unwrap();
+    let path = logs_dir.join("eqlog_Xenk_P1999Green.txt");
+    std::fs::write(
+      &path,
+      "[Wed Jul 17 23:00:00 2024] You have entered North Ro.\r\n\
+       [Fri Jul 19 00:00:00 2024] You gain experience!!\r\n",
+    )
+    .unwrap();
+    let cutoff = parse_date_bound("2024-07-19", false).unwrap();
+    archive_log_file(&path, &archive_dir(&logs_dir), &cutoff)
+      .unwrap()
+      .unwrap();
+
+    let path = path.to_string_lossy().into_owned();
+    let tracker = ZoneTracker::seed(&LogFileCursor::new(&path).unwrap()).unwrap();
+    assert_eq!(tracker.current(), Some("North Ro"));
+
+    std::fs::remove_dir_all(&logs_dir).unwrap();
+  }
+}
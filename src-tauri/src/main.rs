@@ -1,5 +1,13 @@
 // The next line prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+// Acronyms and repeated prefixes/suffixes in names are kept since many of them are
+// serialized and shared with the frontend, and most of the error types carry a
+// fancy_regex::Error, which is large but only on the (rare) failure path.
+#![allow(
+  clippy::upper_case_acronyms,
+  clippy::enum_variant_names,
+  clippy::result_large_err
+)]
 
 #[cfg(debug_assertions)]
 mod debug_only;
@@ -8,6 +16,7 @@ mod audio;
 mod cli;
 mod commands;
 mod common;
+mod dry_run;
 mod gina;
 mod logs;
 mod matchers;
@@ -19,12 +28,14 @@ mod ui;
 
 use crate::state::config;
 use cli::cmd_with_optional_env_override;
-use cli::{CLICommand, StartCommand, TTSCommand};
-use common::fatal_if_err;
+use cli::{CLICommand, GrepCommand, StartCommand, TTSCommand};
+use common::{fatal_if_err, progress_reporter::ProgressReporter, UUID};
 use state::config::LogQuestConfig;
 use state::state_handle::StateHandle;
 use state::state_tree::StateTree;
+use std::path::PathBuf;
 use tracing_subscriber::EnvFilter;
+use triggers::test_bench::{TriggerTestError, TriggerTestSubject};
 use triggers::TriggerLoadOrCreateError;
 
 const DEFAULT_LOG_LEVEL: &str = "debug";
@@ -35,6 +46,34 @@ enum AppStartError {
   ConfigError(#[from] config::ConfigLoadOrCreateError),
   #[error(transparent)]
   TriggerError(#[from] TriggerLoadOrCreateError),
+  #[error(transparent)]
+  TriggerTestFailed(#[from] TriggerTestError),
+  #[error("Could not read STDIN")]
+  ReadStdin(#[source] std::io::Error),
+  #[error(transparent)]
+  DryRunFailed(#[from] dry_run::DryRunError),
+  #[error(transparent)]
+  BenchmarkFailed(#[from] triggers::benchmark::BenchmarkError),
+  #[error("Could not read log file")]
+  ReadLogFile(#[source] std::io::Error),
+  #[error(transparent)]
+  AttendanceFailed(#[from] state::attendance::AttendanceError),
+  #[error("Invalid pattern")]
+  InvalidPattern(#[source] fancy_regex::Error),
+  #[error("Invalid date: `{0}`")]
+  InvalidDate(String),
+  #[error("The EverQuest logs directory is not known. Set the EverQuest directory in LogQuest or use --logs-dir.")]
+  NoLogsDir,
+  #[error("LogQuest is running. Close it first, or turn on log archiving in its settings.")]
+  AppRunning,
+  #[error("Could not lock the LogQuest configuration directory")]
+  LockApp(#[source] std::io::Error),
+  #[error("Could not save the log search index")]
+  SaveSearchIndex(#[source] std::io::Error),
+  #[error("Could not start the dry run")]
+  StartDryRun(#[source] std::io::Error),
+  #[error("No TriggerTag is named `{0}`")]
+  UnknownTriggerTag(String),
 }
 
 fn init_tracing() {
@@ -51,6 +90,100 @@ fn main() {
 
     CLICommand::PrintAudioDevices => audio::print_audio_devices(), // returns `never`
 
+    CLICommand::Lint {
+      config_dir_override,
+    } => fatal_if_err(lint(config_dir_override)),
+
+    CLICommand::TestTrigger {
+      trigger_id,
+      lines,
+      character_name,
+      config_dir_override,
+    } => fatal_if_err(test_trigger(
+      trigger_id,
+      lines,
+      character_name,
+      config_dir_override,
+    )),
+
+    CLICommand::DryRun {
+      log_file,
+      character_name,
+      trigger_tags,
+      config_dir_override,
+    } => fatal_if_err(dry_run(
+      log_file,
+      character_name,
+      trigger_tags,
+      config_dir_override,
+    )),
+
+    CLICommand::Benchmark {
+      log_file,
+      character_name,
+      top,
+      config_dir_override,
+    } => fatal_if_err(benchmark(
+      log_file,
+      character_name,
+      top,
+      config_dir_override,
+    )),
+
+    CLICommand::Parse {
+      log_file,
+      character_name,
+      inactivity,
+      top,
+    } => fatal_if_err(parse(log_file, character_name, inactivity, top)),
+
+    CLICommand::Attendance {
+      log_file,
+      character_name,
+      label,
+      save,
+      config_dir_override,
+    } => fatal_if_err(attendance(
+      log_file,
+      character_name,
+      label,
+      save,
+      config_dir_override,
+    )),
+
+    CLICommand::Chat {
+      log_file,
+      character_name,
+      channels,
+      speaker,
+    } => fatal_if_err(chat(log_file, character_name, channels, speaker)),
+
+    CLICommand::Grep(grep_command) => fatal_if_err(grep(grep_command)),
+
+    CLICommand::ArchiveLogs {
+      keep_days,
+      character_name,
+      logs_dir_override,
+      config_dir_override,
+    } => fatal_if_err(archive_logs(
+      keep_days,
+      character_name,
+      logs_dir_override,
+      config_dir_override,
+    )),
+
+    CLICommand::Characters {
+      logs_dir_override,
+      config_dir_override,
+    } => fatal_if_err(list_characters(logs_dir_override, config_dir_override)),
+
+    CLICommand::History {
+      trigger_id,
+      limit,
+      stats,
+      config_dir_override,
+    } => fatal_if_err(history(trigger_id, limit, stats, config_dir_override)),
+
     CLICommand::TTS(tts) => match tts {
       TTSCommand::Speak { message, voice } => fatal_if_err(tts::speak_once(message, voice)),
       TTSCommand::ListVoices => tts::print_voices(),
@@ -60,7 +193,7 @@ fn main() {
     CLICommand::TypeScript => fatal_if_err(debug_only::generate_typescript()),
 
     #[cfg(debug_assertions)]
-    CLICommand::Tail { file } => fatal_if_err(debug_only::tail(&file)),
+    CLICommand::Tail { file, mode } => fatal_if_err(debug_only::tail(&file, mode)),
   };
 }
 
@@ -71,6 +204,7 @@ fn start(start_command: StartCommand) -> Result<(), AppStartError> {
     config_dir_override,
     logs_dir_override,
     overlay_mode,
+    log_watcher_mode_override,
     ..
   } = start_command;
 
@@ -78,13 +212,333 @@ fn start(start_command: StartCommand) -> Result<(), AppStartError> {
 
   let config_dir = config::get_config_dir_with_optional_override(config_dir_override);
   let config = LogQuestConfig::load_or_create_in_dir(&config_dir, &logs_dir_override)?;
+  // Held until the app exits
+  let _app_lock = match config.lock_app() {
+    Ok(Some(app_lock)) => Some(app_lock),
+    Ok(None) => {
+      tracing::warn!("Another LogQuest process is already running");
+      None
+    }
+    Err(e) => {
+      tracing::error!("Could not lock the LogQuest configuration directory: {e:?}");
+      None
+    }
+  };
   let triggers = triggers::load_or_create_relative_to_config(&config)?; // TODO: Need to report JSON parse errors somewhere
-  let state_tree = StateTree::new(config, triggers, overlay_mode, overlay_dev_tools);
+  let state_tree = StateTree::new(
+    config,
+    triggers,
+    overlay_mode,
+    overlay_dev_tools,
+    log_watcher_mode_override,
+  );
   let state_handle = StateHandle::new(state_tree);
   ui::launch(state_handle);
   Ok(())
 }
 
+fn lint(config_dir_override: Option<PathBuf>) -> Result<(), AppStartError> {
+  let config_dir = config::get_config_dir_with_optional_override(config_dir_override);
+  let config = LogQuestConfig::load_or_create_in_dir(&config_dir, &None)?;
+  let triggers = triggers::load_or_create_relative_to_config(&config)?;
+  triggers::lint::print_report(&triggers::lint::lint(&triggers, None)) // returns `never`
+}
+
+fn test_trigger(
+  trigger_id: String,
+  lines: Vec<String>,
+  character_name: String,
+  config_dir_override: Option<PathBuf>,
+) -> Result<(), AppStartError> {
+  let config_dir = config::get_config_dir_with_optional_override(config_dir_override);
+  let config = LogQuestConfig::load_or_create_in_dir(&config_dir, &None)?;
+  let triggers = triggers::load_or_create_relative_to_config(&config)?;
+  let trigger =
+    TriggerTestSubject::Saved(UUID::from_str_unchecked(&trigger_id)).resolve(&triggers)?;
+  let lines = if lines.is_empty() {
+    std::io::stdin()
+      .lines()
+      .collect::<Result<Vec<String>, _>>()
+      .map_err(AppStartError::ReadStdin)?
+  } else {
+    lines
+  };
+  let results =
+    triggers::test_bench::test_trigger(&trigger, &lines, &character_name, &config.boxed_set);
+  triggers::test_bench::print_results(&results);
+  Ok(())
+}
+
+fn dry_run(
+  log_file: PathBuf,
+  character_name: Option<String>,
+  trigger_tags: Vec<String>,
+  config_dir_override: Option<PathBuf>,
+) -> Result<(), AppStartError> {
+  let config_dir = config::get_config_dir_with_optional_override(config_dir_override);
+  let config = LogQuestConfig::load_or_create_in_dir(&config_dir, &None)?;
+  let triggers = triggers::load_or_create_relative_to_config(&config)?;
+  let active_trigger_tags = trigger_tags
+    .iter()
+    .map(|id_or_name| match triggers.find_trigger_tag(id_or_name) {
+      Some(tag) => Ok(tag.id.clone()),
+      None => Err(AppStartError::UnknownTriggerTag(id_or_name.clone())),
+    })
+    .collect::<Result<_, _>>()?;
+  let runtime = tokio::runtime::Builder::new_current_thread()
+    .enable_all()
+    .build()
+    .map_err(AppStartError::StartDryRun)?;
+  let records = runtime.block_on(dry_run::run_log_file(
+    &log_file,
+    &triggers,
+    active_trigger_tags,
+    character_name,
+    &config.boxed_set,
+  ))?;
+  dry_run::print_records(&records);
+  Ok(())
+}
+
+fn benchmark(
+  log_file: PathBuf,
+  character_name: Option<String>,
+  top: usize,
+  config_dir_override: Option<PathBuf>,
+) -> Result<(), AppStartError> {
+  use triggers::benchmark::{self, BenchmarkError};
+
+  let config_dir = config::get_config_dir_with_optional_override(config_dir_override);
+  let config = LogQuestConfig::load_or_create_in_dir(&config_dir, &None)?;
+  let all_triggers = config
+    .load_all_triggers()
+    .map_err(BenchmarkError::LoadTriggers)?;
+  let character_name = character_name
+    .unwrap_or_else(|| dry_run::character_name_from_path(&log_file.to_string_lossy()));
+  let lines = benchmark::read_log_lines(&log_file).map_err(BenchmarkError::ReadLogFile)?;
+  let report = benchmark::run(all_triggers, &lines, &character_name);
+  benchmark::print_report(&report, top);
+  Ok(())
+}
+
+fn parse(
+  log_file: PathBuf,
+  character_name: Option<String>,
+  inactivity: u64,
+  top: usize,
+) -> Result<(), AppStartError> {
+  let character_name = character_name
+    .unwrap_or_else(|| dry_run::character_name_from_path(&log_file.to_string_lossy()));
+  let fights = logs::combat::parse_log_file(
+    &log_file,
+    &character_name,
+    std::time::Duration::from_secs(inactivity),
+  )
+  .map_err(AppStartError::ReadLogFile)?;
+  logs::combat::print_fights(&fights, top);
+  Ok(())
+}
+
+fn attendance(
+  log_file: PathBuf,
+  character_name: Option<String>,
+  label: String,
+  save: bool,
+  config_dir_override: Option<PathBuf>,
+) -> Result<(), AppStartError> {
+  use state::attendance::{self, AttendanceError};
+
+  let character_name = character_name
+    .unwrap_or_else(|| dry_run::character_name_from_path(&log_file.to_string_lossy()));
+  let snapshots = attendance::snapshots_in_log_file(&log_file, &character_name, &label)
+    .map_err(AppStartError::ReadLogFile)?;
+  if save {
+    let config_dir = config::get_config_dir_with_optional_override(config_dir_override);
+    let config = LogQuestConfig::load_or_create_in_dir(&config_dir, &None)?;
+    let snapshots_path = config.attendance_snapshots_file_path();
+    for snapshot in snapshots.iter() {
+      attendance::append_snapshot(&snapshots_path, snapshot)?;
+    }
+  }
+  attendance::write_csv(&snapshots, std::io::stdout()).map_err(AttendanceError::from)?;
+  Ok(())
+}
+
+fn chat(
+  log_file: PathBuf,
+  character_name: Option<String>,
+  channels: Vec<logs::chat::ChatChannel>,
+  speaker: Option<String>,
+) -> Result<(), AppStartError> {
+  let character_name = character_name
+    .unwrap_or_else(|| dry_run::character_name_from_path(&log_file.to_string_lossy()));
+  let query = logs::chat::ChatQuery { channels, speaker };
+  let messages = logs::chat::search_log_file(&log_file, &character_name, &query)
+    .map_err(AppStartError::ReadLogFile)?;
+  logs::chat::print_messages(&messages);
+  Ok(())
+}
+
+fn grep(grep_command: GrepCommand) -> Result<(), AppStartError> {
+  use logs::search::{self, index::LogSearchIndex, LogSearchQuery};
+
+  let GrepCommand {
+    pattern,
+    regex,
+    case_insensitive,
+    character_name,
+    from,
+    until,
+    limit,
+    index,
+    logs_dir_override,
+    config_dir_override,
+  } = grep_command;
+
+  let config_dir = config::get_config_dir_with_optional_override(config_dir_override);
+  let config = LogQuestConfig::load_or_create_in_dir(&config_dir, &logs_dir_override)?;
+  let logs_dir = config
+    .logs_dir_path
+    .clone()
+    .ok_or(AppStartError::NoLogsDir)?;
+
+  let options = matchers::MatchOptions {
+    case_insensitive,
+    ..Default::default()
+  };
+  let matcher = if regex {
+    matchers::Matcher::Pattern {
+      id: UUID::new(),
+      pattern: pattern
+        .as_str()
+        .try_into()
+        .map_err(AppStartError::InvalidPattern)?,
+      options,
+    }
+  } else {
+    matchers::Matcher::GINA {
+      id: UUID::new(),
+      pattern: pattern
+        .as_str()
+        .try_into()
+        .map_err(AppStartError::InvalidPattern)?,
+      options,
+    }
+  };
+  let date_bound = |value: Option<String>, end_of_day: bool| {
+    value
+      .map(|value| {
+        search::parse_date_bound(&value, end_of_day).ok_or(AppStartError::InvalidDate(value))
+      })
+      .transpose()
+  };
+  let query = LogSearchQuery {
+    matcher,
+    from: date_bound(from, false)?,
+    until: date_bound(until, true)?,
+    character_name,
+    limit,
+  };
+
+  let index_path = config.log_search_index_file_path();
+  let mut index = index.then(|| LogSearchIndex::load(&index_path));
+  let (progress_reporter, _watch_progress_updates) = ProgressReporter::new();
+  search::search_logs(
+    &logs_dir,
+    &query,
+    index.as_mut(),
+    &progress_reporter,
+    |hit| search::print_hit(&hit),
+  )
+  .map_err(AppStartError::ReadLogFile)?;
+  if let Some(index) = index {
+    index
+      .save(&index_path)
+      .map_err(AppStartError::SaveSearchIndex)?;
+  }
+  Ok(())
+}
+
+fn archive_logs(
+  keep_days: Option<u32>,
+  character_name: Option<String>,
+  logs_dir_override: Option<PathBuf>,
+  config_dir_override: Option<PathBuf>,
+) -> Result<(), AppStartError> {
+  let config_dir = config::get_config_dir_with_optional_override(config_dir_override);
+  let config = LogQuestConfig::load_or_create_in_dir(&config_dir, &logs_dir_override)?;
+  let logs_dir = config
+    .logs_dir_path
+    .clone()
+    .ok_or(AppStartError::NoLogsDir)?;
+  let keep_days = keep_days.unwrap_or_else(|| config.log_archive_keep_days());
+  // The app would not know which of its log files were archived, and the log file it follows
+  // could be among them
+  let Some(_app_lock) = config.lock_app().map_err(AppStartError::LockApp)? else {
+    return Err(AppStartError::AppRunning);
+  };
+
+  let archived = logs::archive::archive_logs(&logs_dir, keep_days, character_name.as_deref(), None)
+    .map_err(AppStartError::ReadLogFile)?;
+  for archived_log in archived.iter() {
+    println!(
+      "Archived {} bytes of {} to {}",
+      common::format_integer(archived_log.archived_len as usize),
+      archived_log.log_file_path.display(),
+      archived_log.archive_path.display()
+    );
+  }
+  println!("Archived {} log files", archived.len());
+  Ok(())
+}
+
+fn list_characters(
+  logs_dir_override: Option<PathBuf>,
+  config_dir_override: Option<PathBuf>,
+) -> Result<(), AppStartError> {
+  let config_dir = config::get_config_dir_with_optional_override(config_dir_override);
+  let config = LogQuestConfig::load_or_create_in_dir(&config_dir, &logs_dir_override)?;
+  let logs_dir = config
+    .logs_dir_path
+    .clone()
+    .ok_or(AppStartError::NoLogsDir)?;
+
+  let cursors = logs::log_file_cursor::LogFileCursorCache::scan_dir(&logs_dir)
+    .map_err(AppStartError::ReadLogFile)?;
+  let pinned = config.pinned_character(&logs_dir);
+  for character in cursors.characters() {
+    let is_pinned = pinned
+      .as_ref()
+      .is_some_and(|pinned| (&pinned.name, &pinned.server) == (&character.name, &character.server));
+    println!(
+      "{} ({}){}",
+      character.name,
+      character.server,
+      if is_pinned { " [pinned]" } else { "" }
+    );
+  }
+  Ok(())
+}
+
+fn history(
+  trigger_id: Option<String>,
+  limit: usize,
+  stats: bool,
+  config_dir_override: Option<PathBuf>,
+) -> Result<(), AppStartError> {
+  let config_dir = config::get_config_dir_with_optional_override(config_dir_override);
+  let config = LogQuestConfig::load_or_create_in_dir(&config_dir, &None)?;
+  let journal_path = config.trigger_history_journal_file_path();
+  if stats {
+    let triggers = triggers::load_or_create_relative_to_config(&config)?;
+    state::trigger_history::print_journal_stats(&journal_path, &triggers);
+  } else {
+    let trigger_id = trigger_id.as_deref().map(UUID::from_str_unchecked);
+    state::trigger_history::print_journal(&journal_path, trigger_id.as_ref(), limit);
+  }
+  Ok(())
+}
+
 #[cfg(debug_assertions)]
 fn overlay_devtools_from(start_command: &StartCommand) -> bool {
   start_command.overlay_dev_tools
@@ -97,8 +551,7 @@ fn overlay_devtools_from(start_command: &StartCommand) -> bool {
 
 fn print_banner() {
   println!(
-    "{}",
-    r#"
+    "
 ▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄
              ┓     ┏┓
              ┃ ┏┓┏┓┃┃┓┏┏┓┏╋
@@ -107,6 +560,6 @@ fn print_banner() {
            the Deluxe Toolbox
          for EverQuest enjoyers
 ▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄
-"#
+"
   )
 }
@@ -1,8 +1,12 @@
 use crate::common::{serializable_regex::SerializableRegex, UUID};
-use crate::gina::regex::RegexGINA;
+use crate::gina::regex::{CheckTimings, RegexGINA};
+use crate::logs::chat::{self, ChatChannel, ChatLine};
 use fancy_regex::{Captures, Regex};
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cell::OnceCell;
 use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
 use tracing::error;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ts_rs::TS)]
@@ -11,11 +15,54 @@ pub struct Filter(Vec<Matcher>);
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ts_rs::TS)]
 pub struct FilterWithContext(Vec<MatcherWithContext>);
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
 pub struct MatchContext {
   pub group_values: Vec<Option<String>>,
   pub named_values: HashMap<String, String>,
   pub character_name: String,
+  #[serde(default)]
+  pub built_ins: BuiltInVariables,
+}
+
+/// The names that templates and context patterns can use to reference a BuiltInVariable.
+/// A capture with the same name takes precedence over the built-in value.
+pub const BUILT_IN_VARIABLE_NAMES: &[&str] = &[
+  "C",
+  "SERVER",
+  "LINE",
+  "TIMESTAMP",
+  "TRIGGER",
+  "ZONE",
+  "CHANNEL",
+  "SPEAKER",
+  "TIMER",
+  "REMAINING",
+];
+
+/// Values about the environment of a match rather than the matched text. They are filled in
+/// by whatever reacts to the line, so any of them can be missing (e.g. the timer values
+/// outside of a Timer's Effects).
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ts_rs::TS)]
+pub struct BuiltInVariables {
+  /// `${SERVER}`
+  pub server: Option<String>,
+  /// `${LINE}` is the content of the line without the timestamp
+  pub line: Option<String>,
+  /// `${TIMESTAMP}` is the timestamp of the line as written in the log file
+  pub log_timestamp: Option<String>,
+  /// `${TRIGGER}`
+  pub trigger_name: Option<String>,
+  /// `${ZONE}` is taken from the latest "You have entered <Zone>." line
+  pub zone: Option<String>,
+  /// `${CHANNEL}` is the chat channel of the line (e.g. "Tell", or the name of a named channel),
+  /// or "Combat" or "System" if it is not chat
+  pub channel: Option<String>,
+  /// `${SPEAKER}` is who said the line, if it is chat
+  pub speaker: Option<String>,
+  /// `${TIMER}`
+  pub timer_name: Option<String>,
+  /// `${REMAINING}` is in whole seconds, so it can be piped to `duration`
+  pub timer_remaining_seconds: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ts_rs::TS)]
@@ -24,22 +71,90 @@ pub struct MatchContext {
 pub enum Matcher {
   WholeLine {
     id: UUID,
-    pattern: String,
+    pattern: LiteralPattern,
+    #[serde(default)]
+    options: MatchOptions,
   },
   PartialLine {
     id: UUID,
-    pattern: String,
+    pattern: LiteralPattern,
+    #[serde(default)]
+    options: MatchOptions,
   },
   Pattern {
     id: UUID,
     pattern: SerializableRegex,
+    #[serde(default)]
+    options: MatchOptions,
   },
   GINA {
     id: UUID,
     pattern: RegexGINA,
+    #[serde(default)]
+    options: MatchOptions,
   },
 }
 
+/// The text of a WholeLine or PartialLine Matcher. It is serialized as the text alone.
+#[derive(Debug, Clone, ts_rs::TS)]
+#[ts(type = "string")]
+pub struct LiteralPattern {
+  pub raw: String,
+  /// Normalized by the Matcher's options the first time the Matcher is checked
+  #[ts(skip)]
+  normalized: Arc<OnceLock<String>>,
+}
+
+/// Options that change how a Matcher compares the line. The whitespace and punctuation options
+/// are applied to the line before it is matched (and to the pattern of WholeLine and
+/// PartialLine Matchers), so regex patterns should be written to match the normalized line.
+/// Captured values keep the case they have in the line.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, ts_rs::TS)]
+pub struct MatchOptions {
+  #[serde(default)]
+  pub case_insensitive: bool,
+  /// Runs of whitespace are treated as a single space, and leading/trailing whitespace is ignored
+  #[serde(default)]
+  pub collapse_whitespace: bool,
+  /// Any of `.!?,;:` (and whitespace) at the end of the line is ignored
+  #[serde(default)]
+  pub ignore_trailing_punctuation: bool,
+  /// Checked before the pattern, so that e.g. the text of a Trigger that someone pasted into
+  /// OOC does not fire it
+  #[serde(default)]
+  pub constraints: Option<ChatConstraints>,
+}
+
+/// Restricts a Matcher to lines of certain chat channels or speakers. A speaker can be given as
+/// `${C}` (or `{C}`) for the character whose log it is, and speakers are compared
+/// case-insensitively.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, ts_rs::TS)]
+pub struct ChatConstraints {
+  /// Any channel if empty
+  #[serde(default)]
+  pub channels: Vec<ChatChannel>,
+  /// Any speaker if empty. Lines that are not chat have no speaker, so they never match.
+  #[serde(default)]
+  pub allowed_speakers: Vec<String>,
+  #[serde(default)]
+  pub blocked_speakers: Vec<String>,
+  /// Only chat said by the character or by another character of the boxed set matches
+  #[serde(default)]
+  pub from_boxed_set: bool,
+}
+
+/// A line that is being checked against Matchers. The line is only classified by chat channel
+/// when a Matcher with ChatConstraints is checked, and only lowercased when a case-insensitive
+/// WholeLine or PartialLine Matcher is checked, and then only once.
+pub struct LineToMatch<'a> {
+  pub content: &'a str,
+  pub character_name: &'a str,
+  /// The other characters that are played at the same time as this one
+  pub boxed_set: &'a [String],
+  chat: OnceCell<ChatLine>,
+  lowercase: OnceCell<String>,
+}
+
 /// The key difference between MatcherWithContext and Matcher is that some
 /// MatcherWithContext variants store a String instead of a pre-compiled Regex.
 /// This is because a WatchUntilFilterMatches effect might back-reference captures
@@ -52,10 +167,30 @@ pub enum Matcher {
 #[serde(tag = "variant", content = "value")]
 #[ts(tag = "variant", content = "value")]
 pub enum MatcherWithContext {
-  WholeLine { id: UUID, pattern: String },
-  PartialLine { id: UUID, pattern: String },
-  Pattern { id: UUID, pattern: String },
-  GINA { id: UUID, pattern: String },
+  WholeLine {
+    id: UUID,
+    pattern: String,
+    #[serde(default)]
+    options: MatchOptions,
+  },
+  PartialLine {
+    id: UUID,
+    pattern: String,
+    #[serde(default)]
+    options: MatchOptions,
+  },
+  Pattern {
+    id: UUID,
+    pattern: String,
+    #[serde(default)]
+    options: MatchOptions,
+  },
+  GINA {
+    id: UUID,
+    pattern: String,
+    #[serde(default)]
+    options: MatchOptions,
+  },
 }
 
 impl From<Vec<Matcher>> for Filter {
@@ -71,30 +206,49 @@ impl From<Vec<MatcherWithContext>> for FilterWithContext {
 }
 
 impl Filter {
-  pub fn check(&self, line: &str, character_name: &str) -> Option<MatchContext> {
-    self
-      .0
-      .iter()
-      .find_map(|matcher| matcher.check(line, character_name))
+  pub fn matchers(&self) -> &[Matcher] {
+    &self.0
+  }
+
+  pub fn check_line(&self, line: &LineToMatch) -> Option<MatchContext> {
+    self.0.iter().find_map(|matcher| matcher.check_line(line))
   }
 }
 
 impl FilterWithContext {
+  pub fn matchers(&self) -> &[MatcherWithContext] {
+    &self.0
+  }
+
   pub fn compile_with_context(&self, context: &MatchContext) -> Filter {
     self
       .0
       .iter()
       .map(|matcher_with_context| {
         match matcher_with_context {
-          MatcherWithContext::WholeLine { id, pattern } => Matcher::WholeLine {
+          MatcherWithContext::WholeLine {
+            id,
+            pattern,
+            options,
+          } => Matcher::WholeLine {
             id: id.to_owned(),
-            pattern: pattern.to_owned(),
+            pattern: pattern.as_str().into(),
+            options: options.to_owned(),
           },
-          MatcherWithContext::PartialLine { id, pattern } => Matcher::PartialLine {
+          MatcherWithContext::PartialLine {
+            id,
+            pattern,
+            options,
+          } => Matcher::PartialLine {
             id: id.to_owned(),
-            pattern: pattern.to_owned(),
+            pattern: pattern.as_str().into(),
+            options: options.to_owned(),
           },
-          MatcherWithContext::Pattern { id, pattern } => {
+          MatcherWithContext::Pattern {
+            id,
+            pattern,
+            options,
+          } => {
             // TODO: IMPLEMENT A CONTEXT-LOOKUP SYNTAX FOR LQ PATTERNS. THIS CODE IS JUST TEMPORARY
             let serializable_regex: SerializableRegex =
               pattern.as_str().try_into().unwrap_or_else(|_| {
@@ -104,9 +258,14 @@ impl FilterWithContext {
             Matcher::Pattern {
               id: id.to_owned(),
               pattern: serializable_regex,
+              options: options.to_owned(),
             }
           }
-          MatcherWithContext::GINA { id, pattern } => {
+          MatcherWithContext::GINA {
+            id,
+            pattern,
+            options,
+          } => {
             // TODO: compile_with_context should probably have an error type that means
             // "partially successful" and still encapsulates a filter with the matchers
             // that didn't fail to convert. This is probably not very necessary considering
@@ -114,13 +273,14 @@ impl FilterWithContext {
             // might possibly happen would be importing an older version of a Triggers file
             // or something like that; since it's so unlikely this recovery logic isn't so bad.
             let regex_gina =
-              RegexGINA::from_str_with_context(pattern, &context).unwrap_or_else(|_| {
+              RegexGINA::from_str_with_context(pattern, context).unwrap_or_else(|_| {
                 error!(r#"INVALID REGEX IN MatcherWithContext::GINA("{pattern}")"#);
                 "^(?!)$".try_into().unwrap() // unwrap is safe here
               });
             Matcher::GINA {
               id: id.to_owned(),
               pattern: regex_gina,
+              options: options.to_owned(),
             }
           }
         }
@@ -130,36 +290,115 @@ impl FilterWithContext {
   }
 }
 
+impl MatcherWithContext {
+  pub fn raw_pattern(&self) -> &str {
+    match self {
+      Self::WholeLine { pattern, .. }
+      | Self::PartialLine { pattern, .. }
+      | Self::Pattern { pattern, .. }
+      | Self::GINA { pattern, .. } => pattern,
+    }
+  }
+}
+
 impl Matcher {
   pub fn gina(pattern: &str) -> Result<Self, fancy_regex::Error> {
     Ok(Self::GINA {
       id: UUID::new(),
       pattern: pattern.try_into()?,
+      options: MatchOptions::default(),
     })
   }
 
-  pub fn check(&self, line: &str, character_name: &str) -> Option<MatchContext> {
+  pub fn id(&self) -> &UUID {
     match self {
-      Self::WholeLine { pattern, .. } => {
-        if line == pattern {
-          Some(MatchContext::empty(character_name))
+      Self::WholeLine { id, .. }
+      | Self::PartialLine { id, .. }
+      | Self::Pattern { id, .. }
+      | Self::GINA { id, .. } => id,
+    }
+  }
+
+  pub fn options(&self) -> &MatchOptions {
+    match self {
+      Self::WholeLine { options, .. }
+      | Self::PartialLine { options, .. }
+      | Self::Pattern { options, .. }
+      | Self::GINA { options, .. } => options,
+    }
+  }
+
+  pub fn raw_pattern(&self) -> &str {
+    match self {
+      Self::WholeLine { pattern, .. } | Self::PartialLine { pattern, .. } => &pattern.raw,
+      Self::Pattern { pattern, .. } => &pattern.pattern,
+      Self::GINA { pattern, .. } => &pattern.raw,
+    }
+  }
+
+  /// Returns true if a MatchContext created by this Matcher could have a value for the
+  /// given template variable, which is either a group number or a capture name.
+  pub fn defines_variable(&self, var_name: &str) -> bool {
+    match self {
+      Self::WholeLine { .. } | Self::PartialLine { .. } => false,
+      Self::Pattern { pattern, .. } => {
+        let re: &Regex = &pattern.compiled;
+        if let Ok(group_number) = var_name.parse::<usize>() {
+          group_number < re.captures_len()
         } else {
-          None
+          re.capture_names()
+            .flatten()
+            .any(|name| name.eq_ignore_ascii_case(var_name))
         }
       }
-      Self::PartialLine { pattern, .. } => {
-        if line.contains(pattern) {
-          Some(MatchContext::empty(character_name))
-        } else {
-          None
-        }
+      Self::GINA { pattern, .. } => pattern.defines_variable(var_name),
+    }
+  }
+
+  /// Only for tests, since a line is checked with the boxed set everywhere else
+  #[cfg(test)]
+  pub fn check(&self, line: &str, character_name: &str) -> Option<MatchContext> {
+    self.check_line(&LineToMatch::new(line, character_name, &[]))
+  }
+
+  pub fn check_line(&self, line: &LineToMatch) -> Option<MatchContext> {
+    self.check_with_timings(line, None)
+  }
+
+  /// The same as `check_line`, but a GINA Matcher adds the time spent in each stage to `timings`
+  pub fn check_with_timings(
+    &self,
+    line: &LineToMatch,
+    timings: Option<&mut CheckTimings>,
+  ) -> Option<MatchContext> {
+    let options = self.options();
+    if let Some(constraints) = &options.constraints {
+      if !constraints.accepts(line) {
+        return None;
+      }
+    }
+    let character_name = line.character_name;
+    match self {
+      Self::WholeLine { pattern, .. } => {
+        let line = options.normalize_literal(line);
+        (line == pattern.normalized(options)).then(|| MatchContext::empty(character_name))
       }
+      Self::PartialLine { pattern, .. } => options
+        .normalize_literal(line)
+        .contains(pattern.normalized(options))
+        .then(|| MatchContext::empty(character_name)),
       Self::Pattern {
         pattern: serializable_regex,
         ..
       } => {
-        let re: &Regex = &serializable_regex.compiled;
-        if let Ok(Some(captures)) = re.captures(line) {
+        let re: &Regex = if options.case_insensitive {
+          serializable_regex.case_insensitive()
+        } else {
+          &serializable_regex.compiled
+        };
+        // Without any options, this borrows the line as-is
+        let line = options.normalize(line.content);
+        if let Ok(Some(captures)) = re.captures(&line) {
           Some(MatchContext::from_captures(&captures, re, character_name))
         } else {
           None
@@ -168,30 +407,203 @@ impl Matcher {
       Self::GINA {
         pattern: regex_gina,
         ..
-      } => regex_gina.check(line, character_name),
+      } => {
+        let line = options.normalize(line.content);
+        regex_gina.check_with(&line, character_name, options.case_insensitive, timings)
+      }
     }
   }
 }
 
+impl MatchOptions {
+  /// Applies the whitespace and punctuation options, but not case folding
+  pub fn normalize<'a>(&self, text: &'a str) -> Cow<'a, str> {
+    let mut text = Cow::Borrowed(text);
+    if self.collapse_whitespace {
+      text = Cow::Owned(text.split_whitespace().collect::<Vec<&str>>().join(" "));
+    }
+    if self.ignore_trailing_punctuation {
+      text = match text {
+        Cow::Borrowed(text) => Cow::Borrowed(trim_trailing_punctuation(text)),
+        Cow::Owned(text) => Cow::Owned(trim_trailing_punctuation(&text).to_owned()),
+      };
+    }
+    text
+  }
+
+  /// Applies all of the options, including case folding, for comparing the line to the
+  /// pattern of a WholeLine or PartialLine Matcher
+  fn normalize_literal<'a>(&self, line: &'a LineToMatch) -> Cow<'a, str> {
+    if self.case_insensitive {
+      self.normalize(line.lowercase())
+    } else {
+      self.normalize(line.content)
+    }
+  }
+}
+
+impl LiteralPattern {
+  /// Returns the pattern with all of the options applied, the same way as `normalize_literal`
+  /// does to the line
+  fn normalized(&self, options: &MatchOptions) -> &str {
+    self.normalized.get_or_init(|| {
+      let normalized = options.normalize(&self.raw);
+      if options.case_insensitive {
+        normalized.to_lowercase()
+      } else {
+        normalized.into_owned()
+      }
+    })
+  }
+}
+
+impl From<&str> for LiteralPattern {
+  fn from(raw: &str) -> Self {
+    raw.to_owned().into()
+  }
+}
+
+impl From<String> for LiteralPattern {
+  fn from(raw: String) -> Self {
+    Self {
+      raw,
+      normalized: Arc::new(OnceLock::new()),
+    }
+  }
+}
+
+impl Eq for LiteralPattern {}
+impl PartialEq for LiteralPattern {
+  fn eq(&self, other: &Self) -> bool {
+    self.raw == other.raw
+  }
+}
+
+impl Serialize for LiteralPattern {
+  fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+  where
+    S: serde::Serializer,
+  {
+    serializer.serialize_str(&self.raw)
+  }
+}
+
+impl<'de> Deserialize<'de> for LiteralPattern {
+  fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+  where
+    D: serde::Deserializer<'de>,
+  {
+    let raw: String = Deserialize::deserialize(deserializer)?;
+    Ok(raw.into())
+  }
+}
+
+impl ChatConstraints {
+  pub fn accepts(&self, line: &LineToMatch) -> bool {
+    let chat = line.chat();
+    if !self.channels.is_empty() && !self.channels.contains(&chat.channel) {
+      return false;
+    }
+    let said_by = |name: &String| {
+      let name = match name.trim() {
+        "${C}" | "{C}" | "${c}" | "{c}" => line.character_name,
+        name => name,
+      };
+      chat
+        .speaker
+        .as_ref()
+        .is_some_and(|speaker| speaker.eq_ignore_ascii_case(name))
+    };
+    if !self.allowed_speakers.is_empty() && !self.allowed_speakers.iter().any(said_by) {
+      return false;
+    }
+    if self.blocked_speakers.iter().any(said_by) {
+      return false;
+    }
+    if self.from_boxed_set
+      && !chat
+        .speaker
+        .as_ref()
+        .is_some_and(|speaker| speaker.eq_ignore_ascii_case(line.character_name))
+      && !line.boxed_set.iter().any(said_by)
+    {
+      return false;
+    }
+    true
+  }
+}
+
+impl BuiltInVariables {
+  /// The values that are known when a Trigger matches a line. The server and zone are not
+  /// known when the line is not read from a log file, and the timer values are filled in by
+  /// the Timer.
+  pub fn for_line(
+    line: &str,
+    log_timestamp: Option<&str>,
+    trigger_name: &str,
+    chat: Option<&ChatLine>,
+    server: Option<&str>,
+    zone: Option<&str>,
+  ) -> Self {
+    Self {
+      server: server.map(str::to_owned),
+      line: Some(line.to_owned()),
+      log_timestamp: log_timestamp.map(str::to_owned),
+      trigger_name: Some(trigger_name.to_owned()),
+      zone: zone.map(str::to_owned),
+      channel: chat.map(ChatLine::channel_label),
+      speaker: chat.and_then(|chat| chat.speaker.clone()),
+      ..Default::default()
+    }
+  }
+}
+
+impl<'a> LineToMatch<'a> {
+  pub fn new(content: &'a str, character_name: &'a str, boxed_set: &'a [String]) -> Self {
+    Self {
+      content,
+      character_name,
+      boxed_set,
+      chat: OnceCell::new(),
+      lowercase: OnceCell::new(),
+    }
+  }
+
+  pub fn chat(&self) -> &ChatLine {
+    self
+      .chat
+      .get_or_init(|| chat::classify(self.content, self.character_name))
+  }
+
+  fn lowercase(&self) -> &str {
+    self.lowercase.get_or_init(|| self.content.to_lowercase())
+  }
+}
+
+fn trim_trailing_punctuation(text: &str) -> &str {
+  text.trim_end_matches(|c: char| c.is_whitespace() || ".!?,;:".contains(c))
+}
+
 impl MatchContext {
   fn from_captures(captures: &Captures, re: &Regex, character_name: &str) -> Self {
     let group_values = captures
       .iter()
       .map(|option| option.map(|match_| match_.as_str().to_owned()))
       .collect();
-    let named_values = re.capture_names().filter_map(|c| c).fold(
-      HashMap::<String, String>::new(),
-      |mut memo, name| {
-        if let Some(match_) = captures.name(name) {
-          memo.insert(name.to_uppercase(), match_.as_str().to_owned());
-        }
-        memo
-      },
-    );
+    let named_values =
+      re.capture_names()
+        .flatten()
+        .fold(HashMap::<String, String>::new(), |mut memo, name| {
+          if let Some(match_) = captures.name(name) {
+            memo.insert(name.to_uppercase(), match_.as_str().to_owned());
+          }
+          memo
+        });
     Self {
       group_values,
       named_values,
       character_name: character_name.to_owned(),
+      built_ins: BuiltInVariables::default(),
     }
   }
 
@@ -210,19 +622,49 @@ impl MatchContext {
       .map(|s| s.as_str())
   }
 
+  /// Looks up a group number, a capture name, or one of the BUILT_IN_VARIABLE_NAMES
+  pub fn variable(&self, var_name: &str) -> Option<Cow<'_, str>> {
+    if let Ok(group_number) = var_name.parse::<usize>() {
+      return self.group(group_number).map(Cow::Borrowed);
+    }
+    if let Some(value) = self.named_value(var_name) {
+      return Some(Cow::Borrowed(value));
+    }
+    let built_ins = &self.built_ins;
+    let value = match var_name.to_uppercase().as_str() {
+      "C" => Some(&self.character_name),
+      "SERVER" => built_ins.server.as_ref(),
+      "LINE" => built_ins.line.as_ref(),
+      "TIMESTAMP" => built_ins.log_timestamp.as_ref(),
+      "TRIGGER" => built_ins.trigger_name.as_ref(),
+      "ZONE" => built_ins.zone.as_ref(),
+      "CHANNEL" => built_ins.channel.as_ref(),
+      "SPEAKER" => built_ins.speaker.as_ref(),
+      "TIMER" => built_ins.timer_name.as_ref(),
+      "REMAINING" => {
+        return built_ins
+          .timer_remaining_seconds
+          .map(|seconds| Cow::Owned(seconds.to_string()))
+      }
+      _ => None,
+    };
+    value.map(|value| Cow::Borrowed(value.as_str()))
+  }
+
   pub fn empty(character_name: &str) -> Self {
     Self {
       group_values: Vec::with_capacity(0),
       named_values: HashMap::with_capacity(0),
       character_name: character_name.to_owned(),
+      built_ins: BuiltInVariables::default(),
     }
   }
 }
 
 #[cfg(test)]
 mod tests {
-  use super::{Filter, Matcher, MatcherWithContext};
-  use crate::{common::UUID, matchers::FilterWithContext};
+  use super::{ChatConstraints, Filter, LineToMatch, MatchOptions, Matcher, MatcherWithContext};
+  use crate::{common::UUID, logs::chat::ChatChannel, matchers::FilterWithContext};
 
   #[test]
   fn test_gina_matchers_with_context() {
@@ -234,12 +676,17 @@ mod tests {
       .into();
 
     let context = first_matcher
-      .check("Bristlebane hits YOU for 1000 points of damage", toon)
+      .check_line(&LineToMatch::new(
+        "Bristlebane hits YOU for 1000 points of damage",
+        toon,
+        &[],
+      ))
       .expect("Regex did not match!");
 
     let filter_with_context: FilterWithContext = vec![MatcherWithContext::GINA {
       id: UUID::new(),
       pattern: r"^${1} has been slain by (?<whom>{C})".to_owned(),
+      options: MatchOptions::default(),
     }]
     .into();
 
@@ -247,7 +694,11 @@ mod tests {
 
     // This API is a little weird because the character name is provided in the context and as a param to check
     let next_context = compiled_filter_with_context
-      .check(&format!("Bristlebane has been slain by {toon}"), toon)
+      .check_line(&LineToMatch::new(
+        &format!("Bristlebane has been slain by {toon}"),
+        toon,
+        &[],
+      ))
       .unwrap();
 
     let by_group_number = next_context
@@ -264,4 +715,108 @@ mod tests {
     assert_eq!(by_group_name, toon);
     assert_eq!(by_character_name, toon);
   }
+
+  #[test]
+  fn test_match_options() {
+    let options = MatchOptions {
+      case_insensitive: true,
+      collapse_whitespace: true,
+      ignore_trailing_punctuation: true,
+      ..MatchOptions::default()
+    };
+    let whole_line = Matcher::WholeLine {
+      id: UUID::new(),
+      pattern: "You feel   yourself starting to appear.".into(),
+      options: options.clone(),
+    };
+    assert!(whole_line
+      .check("you feel yourself starting to appear!!", "Xenk")
+      .is_some());
+    assert!(whole_line
+      .check("You feel yourself starting to vanish.", "Xenk")
+      .is_none());
+
+    let partial_line = Matcher::PartialLine {
+      id: UUID::new(),
+      pattern: "LOOT".into(),
+      options: MatchOptions {
+        case_insensitive: true,
+        ..MatchOptions::default()
+      },
+    };
+    assert!(partial_line
+      .check("Soandso says, 'loot time'", "Xenk")
+      .is_some());
+
+    let gina = Matcher::GINA {
+      id: UUID::new(),
+      pattern: r"^{S} tells you, '(.+)'$".try_into().unwrap(),
+      options,
+    };
+    let context = gina
+      .check("SOANDSO  TELLS YOU,  'Need A Rez'.", "Xenk")
+      .unwrap();
+    assert_eq!(context.named_value("S"), Some("SOANDSO"));
+    assert_eq!(context.group(1), Some("Need A Rez"));
+
+    // Without options, matching is exact
+    let exact = Matcher::WholeLine {
+      id: UUID::new(),
+      pattern: "Hello".into(),
+      options: MatchOptions::default(),
+    };
+    assert!(exact.check("hello", "Xenk").is_none());
+    assert!(exact.check("Hello", "Xenk").is_some());
+  }
+
+  #[test]
+  fn test_chat_constraints() {
+    let constrained = |constraints: ChatConstraints| Matcher::PartialLine {
+      id: UUID::new(),
+      pattern: "Gate now".into(),
+      options: MatchOptions {
+        constraints: Some(constraints),
+        ..MatchOptions::default()
+      },
+    };
+    let boxed_set = vec!["Bobo".to_owned()];
+    let check = |matcher: &Matcher, line: &str| {
+      matcher
+        .check_line(&LineToMatch::new(line, "Xenk", &boxed_set))
+        .is_some()
+    };
+
+    let not_ooc = constrained(ChatConstraints {
+      channels: vec![ChatChannel::Tell, ChatChannel::Group],
+      ..ChatConstraints::default()
+    });
+    assert!(check(&not_ooc, "Soandso tells the group, 'Gate now'"));
+    assert!(!check(
+      &not_ooc,
+      "Soandso says out of character, 'Gate now'"
+    ));
+
+    let not_me = constrained(ChatConstraints {
+      blocked_speakers: vec!["${C}".to_owned()],
+      ..ChatConstraints::default()
+    });
+    assert!(!check(&not_me, "You tell your party, 'Gate now'"));
+    assert!(check(&not_me, "Soandso tells the group, 'Gate now'"));
+
+    let only_soandso = constrained(ChatConstraints {
+      allowed_speakers: vec!["soandso".to_owned()],
+      ..ChatConstraints::default()
+    });
+    assert!(check(&only_soandso, "Soandso tells you, 'Gate now'"));
+    assert!(!check(&only_soandso, "Bobo tells you, 'Gate now'"));
+    assert!(!check(&only_soandso, "Gate now"));
+
+    let boxed = constrained(ChatConstraints {
+      from_boxed_set: true,
+      ..ChatConstraints::default()
+    });
+    assert!(check(&boxed, "Bobo tells you, 'Gate now'"));
+    assert!(check(&boxed, "You tell your party, 'Gate now'"));
+    assert!(!check(&boxed, "Soandso tells you, 'Gate now'"));
+  }
 }
@@ -1,24 +1,40 @@
 use crate::{
   audio::AudioMixer,
-  common::{clipboard::ClipboardWriter, shutdown::quitter},
+  common::{
+    clipboard::ClipboardWriter, clock::Clock, shutdown::quitter, timestamp::Timestamp, UUID,
+  },
+  dry_run::EffectRecorder,
   logs::{
-    active_character_detection::{ActiveCharacterDetector, Character},
+    active_character_detection::{ActiveCharacterDetector, DetectedCharacter},
+    archive, chat,
+    combat::{CombatParser, CombatUpdate, DEFAULT_FIGHT_INACTIVITY},
     log_event_broadcaster::{LogEventBroadcaster, NotifyError},
     log_file_cursor::{LogFileCursor, LogFileCursorCache},
     log_line_stream::LogLineStream,
+    loot::LootTracker,
+    who::WhoParser,
+    zone_tracker::ZoneTracker,
     Line, LogFileEvent,
   },
-  matchers::MatchContext,
+  matchers::{BuiltInVariables, MatchContext},
   state::{
+    loot_history::LootJournal,
     overlay::OverlayManager,
     state_handle::StateHandle,
     timer_manager::{TimerContext, TimerManager},
+    trigger_history::{TriggerEffectError, TriggerFiring},
+  },
+  triggers::{
+    effects::EffectWithID, template_string::TemplateString, worker_pool::TriggerWorkerPool,
   },
-  triggers::{effects::EffectWithID, Trigger},
   tts::TTS,
 };
 use futures::StreamExt as _;
-use std::sync::Arc;
+use std::{
+  path::PathBuf,
+  sync::Arc,
+  time::{Duration, Instant},
+};
 use tauri::async_runtime::spawn;
 use tokio::sync::{broadcast, mpsc};
 use tokio::{select, sync::oneshot};
@@ -26,8 +42,14 @@ use tracing::{debug, error, info, warn};
 
 const REACTOR_EVENT_QUEUE_DEPTH: usize = 1000;
 
+/// When `log_archiving` is enabled, the log files are first archived this long after the reactor
+/// starts, and then once every LOG_ARCHIVE_INTERVAL
+const FIRST_LOG_ARCHIVE_DELAY: Duration = Duration::from_secs(10 * 60);
+const LOG_ARCHIVE_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
 #[derive(Debug, Clone)]
 pub struct EventContext {
+  pub clock: Clock,
   pub timer_manager: Arc<TimerManager>,
   pub overlay_manager: Arc<OverlayManager>,
   pub mixer: Arc<AudioMixer>,
@@ -36,18 +58,33 @@ pub struct EventContext {
   pub match_context: Arc<MatchContext>,
   pub cursor_after: Arc<LogFileCursor>,
   pub timer_context: Option<TimerContext>,
+  /// Identifies the entry in the TriggerHistory that any Effect errors are recorded to
+  pub trigger_firing_id: Option<UUID>,
   pub clipboard: ClipboardWriter,
   pub tx_log_file_events: broadcast::Sender<Result<LogFileEvent, NotifyError>>,
+  /// The boxed set that the line was matched with, for the Filters that Effects check against
+  /// later lines (e.g. WaitUntilFilterMatches)
+  pub boxed_set: Arc<[String]>,
+  /// This is only set during a dry run, for Effects that do not go through any of the
+  /// components above (e.g. system commands)
+  pub dry_run: Option<EffectRecorder>,
 }
 
 #[derive(Debug)]
 pub enum ReactorEvent {
-  SetActiveCharacter(Option<Character>),
+  /// The state only says that the character is the current one once its log file is opened
+  SetActiveCharacter(Option<DetectedCharacter>),
   ExecEffect {
     effect: EffectWithID,
     event_context: Arc<EventContext>,
   },
   TestAudioFile(String),
+  /// Saves the most recent `/who` listing with the given label
+  TakeAttendanceSnapshot(String),
+  /// Archives the old lines of every log file except the followed one
+  ArchiveLogs,
+  /// The log files whose old lines were archived, which are now shorter than their cursors
+  LogsArchived(Vec<PathBuf>),
 }
 
 pub struct EventLoop {
@@ -61,6 +98,17 @@ pub struct EventLoop {
   timer_manager: Arc<TimerManager>,
   overlay_manager: Arc<OverlayManager>,
   clipboard: ClipboardWriter,
+  /// Only present if `parallel_trigger_evaluation` is enabled in the config
+  trigger_workers: Option<TriggerWorkerPool>,
+  /// Follows the zone of the current character
+  zone_tracker: ZoneTracker,
+  /// Only present if `combat_parsing` is enabled in the config and a character is followed
+  combat_parser: Option<CombatParser>,
+  combat_parsing: bool,
+  /// Only present if `loot_tracking` is enabled in the config and a character is followed
+  loot_tracker: Option<(LootTracker, LootJournal)>,
+  loot_tracking: bool,
+  who_parser: WhoParser,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -133,11 +181,23 @@ pub fn start(
   let Some(logs_dir) = state.select_config(|config| config.logs_dir_path.clone()) else {
     return Err(ReactorStartError::NoLogsDir);
   };
-  let cursors = LogFileCursorCache::scan_dir(&logs_dir)
-    .map_err(|io_err| ReactorStartError::LogsDirIOError(io_err))?;
-
-  let log_events = LogEventBroadcaster::new(&logs_dir)?;
-  let active_detector = ActiveCharacterDetector::start(log_events.subscribe());
+  let cursors =
+    LogFileCursorCache::scan_dir(&logs_dir).map_err(ReactorStartError::LogsDirIOError)?;
+
+  let (watcher_mode, poll_interval) =
+    state.select_config(|config| (config.log_watcher_mode, config.log_watcher_poll_interval()));
+  let watcher_mode = state
+    .select_reactor(|r| r.log_watcher_mode_override)
+    .unwrap_or(watcher_mode);
+  let log_events = LogEventBroadcaster::new(&logs_dir, watcher_mode, poll_interval)?;
+  let (pinned, switch_delay) = state.select_config(|config| {
+    (
+      config.pinned_character(&logs_dir),
+      config.character_switch_delay(),
+    )
+  });
+  let active_detector =
+    ActiveCharacterDetector::start(log_events.subscribe(), pinned, switch_delay);
   let (reactor_tx, reactor_rx) = mpsc::channel::<ReactorEvent>(REACTOR_EVENT_QUEUE_DEPTH);
 
   let trigger_count = state.select_triggers(|index| index.trigger_count());
@@ -149,6 +209,10 @@ pub fn start(
     reactor_tx.clone(),
   ));
 
+  if state.select_config(|config| config.log_archiving) {
+    _ = spawn(archive_logs_periodically(reactor_tx.clone()));
+  }
+
   let reactor_tx_ = reactor_tx.clone();
 
   spawn(async move {
@@ -182,6 +246,11 @@ impl EventLoop {
     let t2s_tx = create_tts_engine(state.clone());
     let mixer = Arc::new(AudioMixer::new());
     let clipboard = ClipboardWriter::new();
+    let trigger_workers = state
+      .select_config(|config| config.parallel_trigger_evaluation)
+      .then(TriggerWorkerPool::new);
+    let (combat_parsing, loot_tracking) =
+      state.select_config(|config| (config.combat_parsing, config.loot_tracking));
     Self {
       state,
       cursors,
@@ -193,6 +262,13 @@ impl EventLoop {
       timer_manager,
       overlay_manager,
       clipboard,
+      trigger_workers,
+      zone_tracker: ZoneTracker::default(),
+      combat_parser: None,
+      combat_parsing,
+      loot_tracker: None,
+      loot_tracking,
+      who_parser: WhoParser::default(),
     }
   }
 
@@ -219,31 +295,81 @@ impl EventLoop {
         reactor_event = self.reactor_rx.recv() => {
           match reactor_event {
             None => break,
-            Some(ReactorEvent::SetActiveCharacter(Some(new_char))) => {
+            Some(ReactorEvent::SetActiveCharacter(Some(DetectedCharacter { character: new_char, reason }))) => {
+              // If the new character's log file cannot be followed, no character is followed,
+              // rather than the previous one while the state says otherwise
               let Ok(cursor) = self.cursors.get_cursor_and_mark_size_stale(&new_char.log_file_path) else {
                 error!("IO error determining file size {} - Ignoring file", new_char.log_file_path);
+                _ = self.reactor_tx.send(ReactorEvent::SetActiveCharacter(None)).await;
                 continue;
               };
               let Ok(line_stream) = LogLineStream::create(&cursor, self.log_events.subscribe()).await else {
                 error!("IO error trying to create LogLineStream for {}", new_char.log_file_path);
+                _ = self.reactor_tx.send(ReactorEvent::SetActiveCharacter(None)).await;
                 continue;
               };
 
               line_stream_maybe = Some(line_stream);
+              self.who_parser = WhoParser::default();
+
+              let seed_cursor = cursor.clone();
+              self.zone_tracker = match tokio::task::spawn_blocking(move || ZoneTracker::seed(&seed_cursor)).await {
+                Ok(Ok(zone_tracker)) => zone_tracker,
+                Ok(Err(e)) => {
+                  error!("IO error looking for the current zone in {}: {e:?}", new_char.log_file_path);
+                  ZoneTracker::default()
+                }
+                Err(e) => {
+                  error!("Could not look for the current zone in {}: {e:?}", new_char.log_file_path);
+                  ZoneTracker::default()
+                }
+              };
+              self.state.clear_zone_rules();
+              if let Some(zone) = self.zone_tracker.current() {
+                self.state.set_character_zone(&new_char.name, zone);
+              }
+              self.restart_combat_parser(Some(&new_char.name));
+              self.restart_loot_tracker(Some(&new_char.name));
 
               info!("Setting new current character in reactor state: {new_char:?}");
-              self.state.update_reactor(|r| r.current_character = Some(new_char));
+              self.state.update_reactor(|r| {
+                r.current_character = Some(new_char);
+                r.current_character_reason = Some(reason);
+              });
             }
             Some(ReactorEvent::SetActiveCharacter(None)) => {
               if let Some(line_stream) = line_stream_maybe.take() {
                 self.cursors.reset_cursor_position(&line_stream.cursor.path);
               }
+              self.zone_tracker = ZoneTracker::default();
+              self.state.clear_zone_rules();
+              self.restart_combat_parser(None);
+              self.restart_loot_tracker(None);
               info!("Setting reactor state to have no current character");
-              self.state.update_reactor(|r| r.current_character = None);
+              self.state.update_reactor(|r| {
+                r.current_character = None;
+                r.current_character_reason = None;
+              });
             }
             Some(ReactorEvent::ExecEffect{effect, event_context}) => {
               self.exec_effect(effect, event_context).await;
             }
+            Some(ReactorEvent::TakeAttendanceSnapshot(label)) => {
+              match self.state.take_attendance_snapshot(&label) {
+                Ok(snapshot) => info!("Took attendance snapshot `{label}` of {} players", snapshot.players.len()),
+                Err(e) => error!("Could not take attendance snapshot `{label}`: {e}"),
+              }
+            }
+            Some(ReactorEvent::ArchiveLogs) => {
+              let followed = line_stream_maybe.as_ref().map(|line_stream| PathBuf::from(&line_stream.cursor.path));
+              self.archive_logs(followed);
+            }
+            Some(ReactorEvent::LogsArchived(log_file_paths)) => {
+              for path in log_file_paths {
+                let path = path.canonicalize().unwrap_or(path);
+                self.cursors.reset_cursor_position(path.to_string_lossy());
+              }
+            }
             Some(ReactorEvent::TestAudioFile(file_path)) => {
               let mixer = self.mixer.clone();
               spawn(async move {
@@ -267,7 +393,14 @@ impl EventLoop {
               if let Some(line_stream) = line_stream_maybe.take() {
                 self.cursors.reset_cursor_position(&line_stream.cursor.path);
               }
-              self.state.update_reactor(|r| r.current_character = None);
+              self.zone_tracker = ZoneTracker::default();
+              self.state.clear_zone_rules();
+              self.restart_combat_parser(None);
+              self.restart_loot_tracker(None);
+              self.state.update_reactor(|r| {
+                r.current_character = None;
+                r.current_character_reason = None;
+              });
             }
           }
         }
@@ -277,48 +410,207 @@ impl EventLoop {
     debug!("Event Loop finished");
   }
 
-  async fn react_to_line(&self, line: Line, cursor_after: LogFileCursor) {
-    self.state.with_reactor(|reactor_state| {
-      self.state.select_triggers(|index| {
-        let Some(character) = &reactor_state.current_character else {
-          warn!("Cannot process line! No current character detected!");
-          return;
-        };
-        let cursor_after = Arc::new(cursor_after);
-        let active_triggers: Vec<&Trigger> =
-          index.get_distinct_triggers_tagged_by_any_of(reactor_state.active_trigger_tags.iter());
-        for trigger in active_triggers.into_iter() {
-          if let Some(match_context) = trigger.filter.check(&line.content, &character.name) {
-            let match_context = Arc::new(match_context);
-            for effect in trigger.effects.iter() {
-              debug!("TRIGGER EFFECT: {effect:?}");
-              self.send(ReactorEvent::ExecEffect {
-                effect: effect.clone(),
-                event_context: self
-                  .create_event_context(match_context.clone(), cursor_after.clone()),
-              });
-            }
-          }
-        }
-      });
-    });
+  /// Finishes any fight that was still going on, then starts parsing the combat of the given
+  /// character if `combat_parsing` is enabled
+  fn restart_combat_parser(&mut self, character_name: Option<&str>) {
+    if let Some(fight) = self
+      .combat_parser
+      .take()
+      .and_then(|mut parser| parser.finish())
+    {
+      _ = self
+        .state
+        .combat_updates
+        .send(CombatUpdate::FightFinished(fight));
+    }
+    if self.combat_parsing {
+      self.combat_parser =
+        character_name.map(|name| CombatParser::new(name, DEFAULT_FIGHT_INACTIVITY));
+    }
+  }
+
+  fn restart_loot_tracker(&mut self, character_name: Option<&str>) {
+    self.loot_tracker = None;
+    let Some(character_name) = character_name.filter(|_| self.loot_tracking) else {
+      return;
+    };
+    let path = match self
+      .state
+      .select_config(|config| config.loot_history_file_path(character_name))
+    {
+      Ok(path) => path,
+      Err(e) => {
+        error!("Not tracking loot: {e}");
+        return;
+      }
+    };
+    match LootJournal::open(&path) {
+      Ok(journal) => self.loot_tracker = Some((LootTracker::new(character_name), journal)),
+      Err(e) => error!("Could not open loot history {}: {e:?}", path.display()),
+    }
   }
 
-  fn send(&self, event: ReactorEvent) {
-    let tx = self.reactor_tx.clone();
+  /// Archives in the background, then resets the cursors of the log files that were archived
+  fn archive_logs(&self, followed: Option<PathBuf>) {
+    let Some((logs_dir, keep_days)) = self.state.select_config(|config| {
+      config
+        .logs_dir_path
+        .clone()
+        .map(|logs_dir| (logs_dir, config.log_archive_keep_days()))
+    }) else {
+      return;
+    };
+    let reactor_tx = self.reactor_tx.clone();
     spawn(async move {
-      if let Err(_) = tx.send(event).await {
-        error!("Tried to send a message to the Reactor but its channel is closed!");
+      let archived = tokio::task::spawn_blocking(move || {
+        archive::archive_logs(&logs_dir, keep_days, None, followed.as_deref())
+      })
+      .await;
+      match archived {
+        Ok(Ok(archived)) if archived.is_empty() => debug!("No log files needed to be archived"),
+        Ok(Ok(archived)) => {
+          info!("Archived the old lines of {} log files", archived.len());
+          let log_file_paths = archived
+            .into_iter()
+            .map(|archived_log| archived_log.log_file_path)
+            .collect();
+          _ = reactor_tx
+            .send(ReactorEvent::LogsArchived(log_file_paths))
+            .await;
+        }
+        Ok(Err(e)) => error!("Could not archive the log files: {e:?}"),
+        Err(e) => error!("Log archiving did not finish: {e:?}"),
       }
     });
   }
 
+  async fn react_to_line(&mut self, line: Line, cursor_after: LogFileCursor) {
+    let received_at = Instant::now();
+    let Some(character) = self.state.select_reactor(|r| r.current_character.clone()) else {
+      warn!("Cannot process line! No current character detected!");
+      return;
+    };
+    if let Some(zone) = self.zone_tracker.track(&line.content) {
+      info!("{} entered {zone}", character.name);
+      self.state.set_character_zone(&character.name, zone);
+    }
+    if let Some(combat_parser) = self.combat_parser.as_mut() {
+      for update in combat_parser.process(&line) {
+        _ = self.state.combat_updates.send(update);
+      }
+    }
+    if let Some(listing) = self.who_parser.process(&line) {
+      self.state.set_latest_who_listing(&character.name, listing);
+    }
+    if let Some((loot_tracker, journal)) = self.loot_tracker.as_mut() {
+      if let Some(record) = loot_tracker.process(&line) {
+        journal.append(&record);
+      }
+    }
+    let active_triggers = self.state.active_triggers();
+    let matches = match &self.trigger_workers {
+      Some(workers) => {
+        workers
+          .check(&active_triggers, &line.content, &character.name)
+          .await
+      }
+      None => active_triggers.check(&line.content, &character.name),
+    };
+    // Most lines do not match any Trigger, so only the ones that do are classified
+    let chat = (!matches.is_empty()).then(|| chat::classify(&line.content, &character.name));
+    let cursor_after = Arc::new(cursor_after);
+    let mut firings: Vec<TriggerFiring> = Vec::with_capacity(matches.len());
+    let mut effect_events: Vec<ReactorEvent> = Vec::new();
+    for (index, mut match_context) in matches.into_iter() {
+      let trigger = active_triggers.get(index);
+      match_context.built_ins = BuiltInVariables::for_line(
+        &line.content,
+        Some(&line.raw_datetime),
+        &trigger.name,
+        chat.as_ref(),
+        Some(&character.server),
+        self.zone_tracker.current(),
+      );
+      let firing = TriggerFiring {
+        id: UUID::new(),
+        trigger_id: trigger.id.clone(),
+        trigger_name: trigger.name.clone(),
+        character_name: character.name.clone(),
+        fired_at: Timestamp::now(),
+        line: line.content.clone(),
+        captures: match_context.clone(),
+        effect_errors: Vec::new(),
+        latency_micros: received_at
+          .elapsed()
+          .as_micros()
+          .try_into()
+          .unwrap_or(u32::MAX),
+      };
+      let event_context = self
+        .create_event_context(
+          Arc::new(match_context),
+          cursor_after.clone(),
+          active_triggers.boxed_set(),
+        )
+        .with_trigger_firing_id(firing.id.clone());
+      firings.push(firing);
+      for effect in trigger.effects.iter() {
+        debug!("TRIGGER EFFECT: {effect:?}");
+        effect_events.push(ReactorEvent::ExecEffect {
+          effect: effect.clone(),
+          event_context: event_context.clone(),
+        });
+      }
+    }
+    if !firings.is_empty() {
+      self.state.update_trigger_history(|history| {
+        for firing in firings.into_iter() {
+          history.record(firing);
+        }
+      });
+    }
+    self.send_in_order(effect_events);
+  }
+
+  /// Queues the events on the reactor's own channel in the given order, so that the Effects of a
+  /// line start in the order their Triggers matched, and before those of the next line. This does
+  /// not wait for the channel, because the reactor is what drains it. If the channel is full, the
+  /// rest are sent together from a task, still in order.
+  fn send_in_order(&self, events: Vec<ReactorEvent>) {
+    let mut events = events.into_iter();
+    while let Some(event) = events.next() {
+      match self.reactor_tx.try_send(event) {
+        Ok(()) => {}
+        Err(mpsc::error::TrySendError::Full(event)) => {
+          warn!("The reactor's event queue is full; queueing the rest of the Effects from a task");
+          let reactor_tx = self.reactor_tx.clone();
+          let events: Vec<ReactorEvent> = std::iter::once(event).chain(events).collect();
+          spawn(async move {
+            for event in events {
+              if reactor_tx.send(event).await.is_err() {
+                error!("Tried to send a message to the Reactor but its channel is closed!");
+                return;
+              }
+            }
+          });
+          return;
+        }
+        Err(mpsc::error::TrySendError::Closed(_)) => {
+          error!("Tried to send a message to the Reactor but its channel is closed!");
+          return;
+        }
+      }
+    }
+  }
+
   pub fn create_event_context(
     &self,
     match_context: Arc<MatchContext>,
     cursor_after: Arc<LogFileCursor>,
+    boxed_set: Arc<[String]>,
   ) -> Arc<EventContext> {
     Arc::new(EventContext {
+      clock: Clock::System,
       reactor_tx: self.reactor_tx.clone(),
       timer_manager: self.timer_manager.clone(),
       overlay_manager: self.overlay_manager.clone(),
@@ -327,15 +619,31 @@ impl EventLoop {
       match_context,
       cursor_after,
       timer_context: None,
+      trigger_firing_id: None,
       clipboard: self.clipboard.clone(),
       tx_log_file_events: self.log_events.sender(),
+      boxed_set,
+      dry_run: None,
     })
   }
 
   async fn exec_effect(&self, effect_with_id: EffectWithID, event_context: Arc<EventContext>) {
+    let state = self.state.clone();
     spawn(async move {
+      let trigger_firing_id = event_context.trigger_firing_id.clone();
       if let Err(effect_error) = effect_with_id.effect.ready().fire(event_context).await {
         error!("Encountered error executing Effect: {effect_error:?}");
+        if let Some(firing_id) = trigger_firing_id {
+          state.update_trigger_history(|history| {
+            history.record_effect_error(
+              &firing_id,
+              TriggerEffectError {
+                effect_id: effect_with_id.id,
+                message: effect_error.to_string(),
+              },
+            )
+          });
+        }
       }
     });
   }
@@ -348,6 +656,29 @@ impl EventContext {
       ..self.clone()
     })
   }
+
+  /// Renders the template with the MatchContext, plus the built-in variables of the Timer that
+  /// this context belongs to, if any. The remaining time is taken as of rendering.
+  pub fn render(&self, tmpl: &TemplateString) -> String {
+    let Some(timer_context) = &self.timer_context else {
+      return tmpl.render(&self.match_context);
+    };
+    let remaining = self
+      .clock
+      .now()
+      .duration_until(&timer_context.end_time.get());
+    let mut match_context = (*self.match_context).clone();
+    match_context.built_ins.timer_name = Some(timer_context.name.clone());
+    match_context.built_ins.timer_remaining_seconds = Some(remaining.as_secs_f64().ceil() as u32);
+    tmpl.render(&match_context)
+  }
+
+  pub fn with_trigger_firing_id(&self, trigger_firing_id: UUID) -> Arc<Self> {
+    Arc::new(Self {
+      trigger_firing_id: Some(trigger_firing_id),
+      ..self.clone()
+    })
+  }
 }
 
 async fn react_to_active_character_change(
@@ -358,18 +689,23 @@ async fn react_to_active_character_change(
   let mut quit = quitter();
   debug!("Initializing reactor active character change detector");
   loop {
+    let config_updated = state_handle.config_updated.notified();
     select! {
       _ = &mut quit => {
         break;
       }
+      _ = config_updated => {
+        // Pinning a character takes effect right away
+        let pinned = state_handle.select_config(|config| {
+          config.logs_dir_path.as_deref().and_then(|logs_dir| config.pinned_character(logs_dir))
+        });
+        active_character_detector.pin(pinned);
+      }
       _signal = active_character_detector.changed() => {
-        let new_current_char = active_character_detector.current();
-        if let Err(mpsc::error::SendError(_)) = tx.send(ReactorEvent::SetActiveCharacter(new_current_char.clone())).await {
+        let detected = active_character_detector.current();
+        if let Err(mpsc::error::SendError(_)) = tx.send(ReactorEvent::SetActiveCharacter(detected)).await {
           break;
         }
-        state_handle.update_reactor(|state| {
-          state.current_character = new_current_char;
-        });
       }
     }
   }
@@ -377,6 +713,26 @@ async fn react_to_active_character_change(
   active_character_detector.stop();
 }
 
+async fn archive_logs_periodically(tx: mpsc::Sender<ReactorEvent>) {
+  let mut quit = quitter();
+  let mut interval = tokio::time::interval_at(
+    tokio::time::Instant::now() + FIRST_LOG_ARCHIVE_DELAY,
+    LOG_ARCHIVE_INTERVAL,
+  );
+  loop {
+    select! {
+      _ = &mut quit => {
+        break;
+      }
+      _ = interval.tick() => {
+        if let Err(mpsc::error::SendError(_)) = tx.send(ReactorEvent::ArchiveLogs).await {
+          break;
+        }
+      }
+    }
+  }
+}
+
 fn create_tts_engine(state: StateHandle) -> mpsc::Sender<TTS> {
   let (tx, rx) = mpsc::channel::<TTS>(100);
   if let Err(e) = crate::tts::spawn(state, rx) {
@@ -0,0 +1,94 @@
+use crate::{
+  common::UUID,
+  matchers::{LineToMatch, MatchContext},
+  triggers::{prefilter::TriggerPrefilter, trigger_index::TriggerIndex, Trigger},
+};
+use std::{collections::HashSet, sync::Arc};
+
+/// An immutable snapshot of the Triggers in the active TriggerTags, along with the prefilter
+/// built from them. The reactor matches lines against this instead of the TriggerIndex so
+/// that it doesn't hold any state locks while matching. A new snapshot is built whenever the
+/// triggers or the active TriggerTags change; see `StateHandle::active_triggers`.
+pub struct ActiveTriggers {
+  triggers: Vec<Trigger>,
+  prefilter: TriggerPrefilter,
+  /// Taken from the config, for Matchers whose ChatConstraints only accept the boxed set
+  boxed_set: Arc<[String]>,
+}
+
+impl ActiveTriggers {
+  pub fn new(
+    index: &TriggerIndex,
+    active_trigger_tags: &HashSet<UUID>,
+    boxed_set: Vec<String>,
+  ) -> Self {
+    let triggers: Vec<Trigger> = index
+      .get_distinct_triggers_tagged_by_any_of(active_trigger_tags.iter())
+      .into_iter()
+      .cloned()
+      .collect();
+    Self {
+      boxed_set: boxed_set.into(),
+      ..Self::from_triggers(triggers)
+    }
+  }
+
+  pub fn from_triggers(triggers: Vec<Trigger>) -> Self {
+    let prefilter = TriggerPrefilter::new(triggers.iter());
+    Self {
+      triggers,
+      prefilter,
+      boxed_set: Arc::new([]),
+    }
+  }
+
+  /// Checks the Filter of every candidate Trigger against the line. Returns the positions of
+  /// the Triggers that matched, in order.
+  pub fn check(&self, line: &str, character_name: &str) -> Vec<(usize, MatchContext)> {
+    self.check_each(self.candidate_indices(line), line, character_name)
+  }
+
+  /// Checks the Filters of only the Triggers at the given positions
+  pub fn check_each<I>(
+    &self,
+    indices: I,
+    line: &str,
+    character_name: &str,
+  ) -> Vec<(usize, MatchContext)>
+  where
+    I: IntoIterator<Item = usize>,
+  {
+    let line = LineToMatch::new(line, character_name, &self.boxed_set);
+    indices
+      .into_iter()
+      .filter_map(|index| {
+        self.triggers[index]
+          .filter
+          .check_line(&line)
+          .map(|match_context| (index, match_context))
+      })
+      .collect()
+  }
+
+  /// Returns the positions of the Triggers whose Filters could match the line
+  pub fn candidate_indices(&self, line: &str) -> Vec<usize> {
+    self.prefilter.candidate_indices(line)
+  }
+
+  pub fn get(&self, index: usize) -> &Trigger {
+    &self.triggers[index]
+  }
+
+  pub fn len(&self) -> usize {
+    self.triggers.len()
+  }
+
+  /// For the Effects of the matched Triggers, which can check more Filters against later lines
+  pub fn boxed_set(&self) -> Arc<[String]> {
+    self.boxed_set.clone()
+  }
+
+  pub fn prefilter(&self) -> &TriggerPrefilter {
+    &self.prefilter
+  }
+}
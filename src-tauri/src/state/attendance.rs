@@ -0,0 +1,188 @@
+//! Attendance snapshots are `/who` listings that were saved, e.g. to record who was present for
+//! a raid. They are appended to a JSON Lines file in the data directory.
+use crate::{
+  common::{timestamp::Timestamp, UUID},
+  logs::{
+    archive, read_log_lines,
+    who::{WhoEntry, WhoListing, WhoParser},
+  },
+};
+use serde::{Deserialize, Serialize};
+use std::{
+  fs,
+  io::{self, BufRead as _, Write as _},
+  path::Path,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ts_rs::TS)]
+pub struct AttendanceSnapshot {
+  pub id: UUID,
+  pub label: String,
+  /// The character whose log the listing was read from
+  pub character_name: String,
+  pub listed_at: Timestamp,
+  pub location: String,
+  /// The number of players EverQuest said there were
+  pub reported_count: u32,
+  pub players: Vec<WhoEntry>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum AttendanceError {
+  #[error("No /who output has been seen in the log yet")]
+  NoListing,
+
+  #[error("Could not read or write the attendance snapshots")]
+  IOError(#[from] io::Error),
+
+  #[error("Could not write the CSV file")]
+  WriteCSV(#[from] csv::Error),
+}
+
+impl AttendanceSnapshot {
+  pub fn new(label: &str, character_name: &str, listing: WhoListing) -> Self {
+    Self {
+      id: UUID::new(),
+      label: label.to_owned(),
+      character_name: character_name.to_owned(),
+      listed_at: listing.listed_at,
+      location: listing.location,
+      reported_count: listing.reported_count,
+      players: listing.entries,
+    }
+  }
+}
+
+pub fn append_snapshot(path: &Path, snapshot: &AttendanceSnapshot) -> Result<(), AttendanceError> {
+  let json = serde_json::to_string(snapshot).map_err(io::Error::from)?;
+  let mut file = fs::OpenOptions::new()
+    .create(true)
+    .append(true)
+    .open(path)?;
+  writeln!(file, "{json}")?;
+  Ok(())
+}
+
+/// Returns the snapshots oldest first. Lines that cannot be parsed are skipped.
+pub fn read_snapshots(path: &Path) -> io::Result<Vec<AttendanceSnapshot>> {
+  let file = match fs::File::open(path) {
+    Ok(file) => file,
+    Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+    Err(e) => return Err(e),
+  };
+  let mut snapshots = Vec::new();
+  for line in io::BufReader::new(file).lines() {
+    if let Ok(snapshot) = serde_json::from_str(&line?) {
+      snapshots.push(snapshot);
+    }
+  }
+  Ok(snapshots)
+}
+
+/// Takes a snapshot of every `/who` listing in a log file, e.g. to go back over past raids
+pub fn snapshots_in_log_file(
+  log_file_path: &Path,
+  character_name: &str,
+  label: &str,
+) -> io::Result<Vec<AttendanceSnapshot>> {
+  let mut parser = WhoParser::default();
+  let mut snapshots = Vec::new();
+  for line in read_log_lines(archive::open_with_archives(log_file_path)?) {
+    let line = line?;
+    if let Some(listing) = parser.process(&line) {
+      snapshots.push(AttendanceSnapshot::new(label, character_name, listing));
+    }
+  }
+  Ok(snapshots)
+}
+
+/// Each player in each snapshot is one row
+pub fn write_csv<W: io::Write>(
+  snapshots: &[AttendanceSnapshot],
+  writer: W,
+) -> Result<(), csv::Error> {
+  let mut csv = csv::Writer::from_writer(writer);
+  csv.write_record([
+    "Listed At",
+    "Label",
+    "Location",
+    "Name",
+    "Level",
+    "Class",
+    "Race",
+    "Guild",
+  ])?;
+  for snapshot in snapshots.iter() {
+    let listed_at = snapshot.listed_at.to_string();
+    for player in snapshot.players.iter() {
+      csv.write_record([
+        listed_at.as_str(),
+        snapshot.label.as_str(),
+        snapshot.location.as_str(),
+        player.name.as_str(),
+        &player
+          .level
+          .map(|level| level.to_string())
+          .unwrap_or_default(),
+        player.class.as_deref().unwrap_or_default(),
+        player.race.as_deref().unwrap_or_default(),
+        player.guild.as_deref().unwrap_or_default(),
+      ])?;
+    }
+  }
+  csv.flush()?;
+  Ok(())
+}
+
+/// Exports the snapshots with the given IDs, or all of them if none are given
+pub fn export_csv(
+  snapshots_path: &Path,
+  snapshot_ids: &[UUID],
+  csv_path: &Path,
+) -> Result<(), AttendanceError> {
+  let mut snapshots = read_snapshots(snapshots_path)?;
+  if !snapshot_ids.is_empty() {
+    snapshots.retain(|snapshot| snapshot_ids.contains(&snapshot.id));
+  }
+  write_csv(&snapshots, fs::File::create(csv_path)?)?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{append_snapshot, read_snapshots, write_csv, AttendanceSnapshot};
+  use crate::{
+    common::random_id,
+    logs::{who::WhoParser, Line},
+  };
+
+  #[test]
+  fn test_snapshots_round_trip_and_csv() {
+    let mut parser = WhoParser::default();
+    let listing = [
+      "[Thu Jul 18 17:35:14 2024] Players on EverQuest:",
+      "[Thu Jul 18 17:35:14 2024] [60 Oracle] Soandso (Troll) <Tinkering Guild>",
+      "[Thu Jul 18 17:35:14 2024] [ANONYMOUS] Bobo ",
+      "[Thu Jul 18 17:35:14 2024] There are 2 players in The Plane of Fear.",
+    ]
+    .iter()
+    .find_map(|raw| parser.process(&Line::from(raw).unwrap()))
+    .unwrap();
+
+    let path = std::env::temp_dir().join(format!("attendance_{}.jsonl", random_id(8)));
+    let snapshot = AttendanceSnapshot::new("Fear", "Xenk", listing);
+    append_snapshot(&path, &snapshot).unwrap();
+    assert_eq!(read_snapshots(&path).unwrap(), vec![snapshot.clone()]);
+
+    let mut csv = Vec::new();
+    write_csv(&[snapshot], &mut csv).unwrap();
+    assert_eq!(
+      String::from_utf8(csv).unwrap(),
+      "Listed At,Label,Location,Name,Level,Class,Race,Guild\n\
+       2024-07-18T17:35:14.000Z,Fear,The Plane of Fear,Soandso,60,Shaman,Troll,Tinkering Guild\n\
+       2024-07-18T17:35:14.000Z,Fear,The Plane of Fear,Bobo,,,,\n"
+    );
+
+    std::fs::remove_file(&path).unwrap();
+  }
+}
@@ -1,8 +1,24 @@
+//! The LogQuest config file, and the paths of the files that LogQuest keeps in its data
+//! directory.
+//!
+//! The reactor only reads these settings when it starts, so a change to them takes effect the
+//! next time LogQuest is started: `parallel_trigger_evaluation`, `combat_parsing`,
+//! `loot_tracking`, `log_archiving`, `log_watcher_mode`, `log_watcher_poll_interval_ms` and
+//! `character_switch_delay_secs`.
 use crate::common::shutdown::critical_path;
 use crate::common::{
   absolute_path_handling_tilde, fatal_error, format_integer, LogQuestVersion, LOG_QUEST_VERSION,
   UUID, UUID_LEN,
 };
+use crate::logs::active_character_detection::{
+  Character, PinnedCharacter, DEFAULT_CHARACTER_SWITCH_DELAY,
+};
+use crate::logs::archive::DEFAULT_ARCHIVE_AFTER_DAYS;
+use crate::logs::log_event_broadcaster::{
+  LogWatcherMode, DEFAULT_POLL_INTERVAL, MIN_POLL_INTERVAL,
+};
+use crate::logs::log_file_cursor::LogFileCursorCache;
+use crate::logs::{validate_character_name, InvalidCharacterName};
 use crate::triggers::trigger_index::{
   is_compatible_triggers_import_version, DataMutationError, TriggerGroupDescendant, TriggerIndex,
   TriggerTag,
@@ -12,6 +28,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::io::{BufReader, Write as _};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use std::{fs, io};
 use tracing::error;
 use tracing::{debug, info};
@@ -22,6 +39,11 @@ const TRIGGERS_DIR_NAME: &str = "Triggers";
 const TRIGGER_GROUPS_DIR_NAME: &str = "Groups";
 const TRIGGER_TAGS_DIR_NAME: &str = "TriggerTags";
 const TOP_LEVEL_FILE_NAME: &str = "tree.json";
+const TRIGGER_HISTORY_JOURNAL_FILE_NAME: &str = "trigger_history.jsonl";
+const LOOT_HISTORY_DIR_NAME: &str = "Loot";
+const ATTENDANCE_SNAPSHOTS_FILE_NAME: &str = "attendance.jsonl";
+const LOG_SEARCH_INDEX_FILE_NAME: &str = "log_search_index.json";
+const APP_LOCK_FILE_NAME: &str = "LogQuest.lock";
 
 #[derive(thiserror::Error, Debug)]
 pub enum EverQuestDirectoryError {
@@ -105,16 +127,69 @@ pub struct LogQuestConfig {
   #[serde(skip)]
   #[ts(skip)]
   pub logs_dir_path: Option<PathBuf>,
+
+  /// When enabled, every Trigger firing is also appended to a journal file in the data directory
+  #[serde(default)]
+  pub trigger_history_journal: bool,
+
+  /// When enabled, each line is checked against the active Triggers on a pool of worker
+  /// threads instead of one at a time
+  #[serde(default)]
+  pub parallel_trigger_evaluation: bool,
+
+  /// When enabled, the damage and healing in the followed character's log are parsed into
+  /// fights
+  #[serde(default)]
+  pub combat_parsing: bool,
+
+  /// When enabled, the loot and `/random` rolls in the followed character's log are saved to
+  /// the character's loot history
+  #[serde(default)]
+  pub loot_tracking: bool,
+
+  /// The names of the other characters that are played alongside whichever one is active, for
+  /// Matchers that only accept chat from the boxed set
+  #[serde(default)]
+  pub boxed_set: Vec<String>,
+
+  /// When enabled, the old lines of the log files of characters that are not being played are
+  /// moved into compressed archives in the Logs/Archive directory every few hours
+  #[serde(default)]
+  pub log_archiving: bool,
+
+  /// How many days of lines are left in a log file when it is archived
+  #[serde(default)]
+  pub log_archive_after_days: Option<u32>,
+
+  /// How the logs directory is watched for changes. The Poll and Hybrid modes are for
+  /// filesystems that do not reliably send change notifications.
+  #[serde(default)]
+  pub log_watcher_mode: LogWatcherMode,
+
+  /// How often the log files are checked in the Poll and Hybrid modes. Anything shorter than
+  /// MIN_POLL_INTERVAL is raised to it.
+  #[serde(default)]
+  pub log_watcher_poll_interval_ms: Option<u32>,
+
+  /// How long the log file of the active character must go without being written to before the
+  /// character of another log file that keeps being written to becomes the active one
+  #[serde(default)]
+  pub character_switch_delay_secs: Option<u32>,
+
+  /// When set, this character is always the active one, whichever log files are written to
+  // This is kept last because TOML tables have to come after the plain values
+  #[serde(default)]
+  pub pinned_character: Option<PinnedCharacter>,
 }
 
 impl LogQuestConfig {
   /// Given the config directory, load the LogQuestConfig from it or
   /// create a new one on-disk and return it.
   pub fn load_or_create_in_dir(
-    config_dir: &PathBuf,
+    config_dir: &Path,
     logs_dir_override: &Option<PathBuf>,
   ) -> Result<LogQuestConfig, ConfigLoadOrCreateError> {
-    let config_path = config_dir.join(&CONFIG_FILE_NAME);
+    let config_path = config_dir.join(CONFIG_FILE_NAME);
     let config = if config_path.exists() {
       info!("Loading configuration from {}", config_dir.display());
       LogQuestConfig::load_from_file_path(&config_path, logs_dir_override)?
@@ -136,6 +211,17 @@ impl LogQuestConfig {
       config_file_path: config_file_path.to_owned(),
       logs_dir_path: None,
       everquest_directory: None,
+      trigger_history_journal: false,
+      parallel_trigger_evaluation: false,
+      combat_parsing: false,
+      loot_tracking: false,
+      boxed_set: Vec::new(),
+      log_archiving: false,
+      log_archive_after_days: None,
+      log_watcher_mode: LogWatcherMode::default(),
+      log_watcher_poll_interval_ms: None,
+      character_switch_delay_secs: None,
+      pinned_character: None,
     }
   }
 
@@ -152,14 +238,14 @@ impl LogQuestConfig {
     config.logs_dir_path = match (&config.everquest_directory, logs_dir_override) {
       (_, Some(overridden)) => {
         let Ok(logs_dir) = absolute_path_handling_tilde(overridden) else {
-          fatal_error(&format!(
+          fatal_error(format!(
             "Could not determine absolute path of log dir override param: {}",
             overridden.display()
           ));
         };
         Some(logs_dir)
       }
-      (Some(eq_dir), _) => default_logs_dir_from_eq_dir(&eq_dir).ok(),
+      (Some(eq_dir), _) => default_logs_dir_from_eq_dir(eq_dir).ok(),
       _ => None,
     };
     config.config_file_path = path.to_owned();
@@ -181,12 +267,12 @@ impl LogQuestConfig {
   pub fn save_trigger_index(&self, index: &TriggerIndex) -> Result<(), TriggersSaveError> {
     self.remove_id_files_not_in_iter(&self.triggers_dir_path(), index.triggers.keys())?;
     for trigger in index.triggers.values() {
-      self.save_trigger(&trigger)?;
+      self.save_trigger(trigger)?;
     }
 
     self.remove_id_files_not_in_iter(&self.trigger_groups_dir_path(), index.groups.keys())?;
     for group in index.groups.values() {
-      self.save_trigger_group(&group)?;
+      self.save_trigger_group(group)?;
     }
 
     self.remove_id_files_not_in_iter(&self.trigger_tags_dir_path(), index.trigger_tags.keys())?;
@@ -262,7 +348,7 @@ impl LogQuestConfig {
     let json_bytes = pretty_json.into_bytes();
     let json_size = json_bytes.len();
 
-    let mut file = fs::File::create(&path)?;
+    let mut file = fs::File::create(path)?;
     file.write_all(&json_bytes)?;
     file.flush()?;
 
@@ -281,7 +367,7 @@ impl LogQuestConfig {
       .into_iter()
       .map(|id| self.load_trigger_file(&id))
       .collect();
-    Ok(triggers?)
+    triggers
   }
 
   pub fn load_all_trigger_groups(&self) -> Result<Vec<TriggerGroup>, TriggerLoadError> {
@@ -290,7 +376,7 @@ impl LogQuestConfig {
       .into_iter()
       .map(|id| self.load_trigger_group_file(&id))
       .collect();
-    Ok(groups?)
+    groups
   }
 
   pub fn load_all_trigger_tags(&self) -> Result<Vec<TriggerTag>, TriggerLoadError> {
@@ -299,7 +385,7 @@ impl LogQuestConfig {
       .into_iter()
       .map(|id| self.load_trigger_tag_file(&id))
       .collect();
-    Ok(tags?)
+    tags
   }
 
   pub fn load_trigger_file(&self, id: &UUID) -> Result<Trigger, TriggerLoadError> {
@@ -374,6 +460,66 @@ impl LogQuestConfig {
     self.data_dir_path().join(TOP_LEVEL_FILE_NAME)
   }
 
+  /// Returns `None` if the journal is not enabled
+  pub fn trigger_history_journal_path(&self) -> Option<PathBuf> {
+    self.trigger_history_journal.then(|| {
+      ensure_dir_exists(self.data_dir_path());
+      self.trigger_history_journal_file_path()
+    })
+  }
+
+  /// The journal may exist from when it was previously enabled, even if it is not now
+  pub fn trigger_history_journal_file_path(&self) -> PathBuf {
+    self.data_dir_path().join(TRIGGER_HISTORY_JOURNAL_FILE_NAME)
+  }
+
+  pub fn loot_history_file_path(
+    &self,
+    character_name: &str,
+  ) -> Result<PathBuf, InvalidCharacterName> {
+    let character_name = validate_character_name(character_name)?;
+    Ok(
+      ensure_dir_exists(self.data_dir_path().join(LOOT_HISTORY_DIR_NAME))
+        .join(format!("{character_name}.jsonl")),
+    )
+  }
+
+  pub fn attendance_snapshots_file_path(&self) -> PathBuf {
+    ensure_dir_exists(self.data_dir_path()).join(ATTENDANCE_SNAPSHOTS_FILE_NAME)
+  }
+
+  pub fn log_search_index_file_path(&self) -> PathBuf {
+    ensure_dir_exists(self.data_dir_path()).join(LOG_SEARCH_INDEX_FILE_NAME)
+  }
+
+  pub fn log_archive_keep_days(&self) -> u32 {
+    self
+      .log_archive_after_days
+      .unwrap_or(DEFAULT_ARCHIVE_AFTER_DAYS)
+  }
+
+  pub fn character_switch_delay(&self) -> Duration {
+    self
+      .character_switch_delay_secs
+      .map_or(DEFAULT_CHARACTER_SWITCH_DELAY, |secs| {
+        Duration::from_secs(secs.into())
+      })
+  }
+
+  /// The pinned character, if it has a log file in the logs directory
+  pub fn pinned_character(&self, logs_dir: &Path) -> Option<Character> {
+    let pinned = self.pinned_character.as_ref()?;
+    let cursors = LogFileCursorCache::scan_dir(logs_dir).ok()?;
+    pinned.find_in(cursors.characters())
+  }
+
+  pub fn log_watcher_poll_interval(&self) -> Duration {
+    self
+      .log_watcher_poll_interval_ms
+      .map_or(DEFAULT_POLL_INTERVAL, |ms| Duration::from_millis(ms.into()))
+      .max(MIN_POLL_INTERVAL)
+  }
+
   fn data_dir_path(&self) -> PathBuf {
     self.config_dir_path().join(DATA_DIR_NAME)
   }
@@ -390,6 +536,22 @@ impl LogQuestConfig {
     ensure_dir_exists(self.data_dir_path().join(TRIGGER_TAGS_DIR_NAME))
   }
 
+  /// Locks a file in the config directory for as long as the returned File is open, or returns
+  /// None if another LogQuest process holds the lock. The app holds it while it runs, so that
+  /// the commands that must not run alongside it (e.g. archive-logs) can tell.
+  pub fn lock_app(&self) -> io::Result<Option<fs::File>> {
+    let file = fs::OpenOptions::new()
+      .create(true)
+      .truncate(false)
+      .write(true)
+      .open(self.config_dir_path().join(APP_LOCK_FILE_NAME))?;
+    match file.try_lock() {
+      Ok(()) => Ok(Some(file)),
+      Err(fs::TryLockError::WouldBlock) => Ok(None),
+      Err(fs::TryLockError::Error(e)) => Err(e),
+    }
+  }
+
   fn config_dir_path(&self) -> PathBuf {
     self
       .config_file_path
@@ -423,7 +585,7 @@ fn parse_json_file_with_version_check<T>(path: &Path, key: &str) -> Result<T, Tr
 where
   T: for<'de> Deserialize<'de>,
 {
-  let file = fs::File::open(&path)?;
+  let file = fs::File::open(path)?;
 
   let path_string = move || path.display().to_string();
 
@@ -469,7 +631,7 @@ where
 pub fn get_config_dir_with_optional_override(path_override: Option<PathBuf>) -> PathBuf {
   let config_dir = path_override.unwrap_or_else(default_config_dir);
   let Ok(config_dir) = absolute_path_handling_tilde(&config_dir) else {
-    fatal_error(&format!(
+    fatal_error(format!(
       "Could not determine absolute path of config dir: {}",
       config_dir.display()
     ));
@@ -530,10 +692,8 @@ where
       path_ref.display()
     ));
   } else if !path_ref.is_dir() {
-    fs::create_dir_all(&path_ref).expect(&format!(
-      "Could not create directory: {}",
-      path_ref.display()
-    ));
+    fs::create_dir_all(path_ref)
+      .unwrap_or_else(|_| panic!("Could not create directory: {}", path_ref.display()));
   }
   path
 }
@@ -0,0 +1,194 @@
+//! The loot history of each character is kept in its own JSON Lines file in the data directory.
+//! Only the loot and the individual rolls are saved; the roll sessions are grouped again
+//! whenever the history is read.
+use crate::logs::loot::{
+  roll_sessions, LootDrop, LootRecord, RollSession, DEFAULT_ROLL_SESSION_WINDOW,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+  fs,
+  io::{self, BufRead as _, Write as _},
+  path::{Path, PathBuf},
+};
+use tracing::error;
+
+#[derive(Debug, Clone, Default, Serialize, ts_rs::TS)]
+pub struct LootHistory {
+  /// Oldest first
+  pub drops: Vec<LootDrop>,
+  /// Oldest first
+  pub roll_sessions: Vec<RollSession>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ts_rs::TS)]
+pub enum LootExport {
+  Drops,
+  Rolls,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum LootExportError {
+  #[error("Could not read the loot history")]
+  ReadHistory(#[source] io::Error),
+
+  #[error("Could not write the CSV file")]
+  WriteCSV(#[from] csv::Error),
+}
+
+/// Appends each LootRecord to the loot history file of a character
+#[derive(Debug)]
+pub struct LootJournal {
+  path: PathBuf,
+  file: fs::File,
+}
+
+impl LootJournal {
+  pub fn open(path: &Path) -> io::Result<Self> {
+    let file = fs::OpenOptions::new()
+      .create(true)
+      .append(true)
+      .open(path)?;
+    Ok(Self {
+      path: path.to_owned(),
+      file,
+    })
+  }
+
+  pub fn append(&mut self, record: &LootRecord) {
+    let result = serde_json::to_string(record)
+      .map_err(io::Error::from)
+      .and_then(|json| writeln!(self.file, "{json}"));
+    if let Err(e) = result {
+      error!(
+        "Could not write to loot history {}! Error: {e:?}",
+        self.path.display()
+      );
+    }
+  }
+}
+
+/// Reads the loot history of a character. A character that never looted or rolled anything has
+/// no file, which is the same as an empty history. Lines that cannot be parsed are skipped.
+pub fn read_loot_history(path: &Path) -> io::Result<LootHistory> {
+  let file = match fs::File::open(path) {
+    Ok(file) => file,
+    Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(LootHistory::default()),
+    Err(e) => return Err(e),
+  };
+  let mut drops = Vec::new();
+  let mut rolls = Vec::new();
+  for line in io::BufReader::new(file).lines() {
+    match serde_json::from_str::<LootRecord>(&line?) {
+      Ok(LootRecord::Looted(drop)) => drops.push(drop),
+      Ok(LootRecord::Rolled(roll)) => rolls.push(roll),
+      Err(_) => {}
+    }
+  }
+  Ok(LootHistory {
+    drops,
+    roll_sessions: roll_sessions(&rolls, DEFAULT_ROLL_SESSION_WINDOW),
+  })
+}
+
+/// When exporting rolls, each roll is a row next to the session it was a part of
+pub fn write_csv<W: io::Write>(
+  history: &LootHistory,
+  export: LootExport,
+  writer: W,
+) -> Result<(), csv::Error> {
+  let mut csv = csv::Writer::from_writer(writer);
+  match export {
+    LootExport::Drops => {
+      csv.write_record(["Looted At", "Looter", "Item", "Quantity", "Corpse"])?;
+      for drop in history.drops.iter() {
+        csv.write_record([
+          drop.looted_at.to_string(),
+          drop.looter.clone(),
+          drop.item.clone(),
+          drop.quantity.to_string(),
+          drop.corpse.clone().unwrap_or_default(),
+        ])?;
+      }
+    }
+    LootExport::Rolls => {
+      csv.write_record([
+        "Session Started At",
+        "Range",
+        "Winners",
+        "Rolled At",
+        "Roller",
+        "Result",
+      ])?;
+      for session in history.roll_sessions.iter() {
+        let range = format!("{}-{}", session.low, session.high);
+        let winners = session.winners.join(" ");
+        for roll in session.rolls.iter() {
+          csv.write_record([
+            session.started_at.to_string(),
+            range.clone(),
+            winners.clone(),
+            roll.rolled_at.to_string(),
+            roll.roller.clone(),
+            roll.result.to_string(),
+          ])?;
+        }
+      }
+    }
+  }
+  csv.flush()?;
+  Ok(())
+}
+
+pub fn export_csv(
+  history_path: &Path,
+  export: LootExport,
+  csv_path: &Path,
+) -> Result<(), LootExportError> {
+  let history = read_loot_history(history_path).map_err(LootExportError::ReadHistory)?;
+  let file = fs::File::create(csv_path).map_err(csv::Error::from)?;
+  write_csv(&history, export, file)?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{read_loot_history, write_csv, LootExport, LootJournal};
+  use crate::{
+    common::random_id,
+    logs::{loot::LootTracker, Line},
+  };
+
+  #[test]
+  fn test_journal_round_trip_and_csv() {
+    let path = std::env::temp_dir().join(format!("loot_{}.jsonl", random_id(8)));
+    let mut journal = LootJournal::open(&path).unwrap();
+    let mut tracker = LootTracker::new("Xenk");
+    for raw in [
+      "[Thu Jul 18 17:35:14 2024] --You have looted a Bone Chip.--",
+      "[Thu Jul 18 17:35:20 2024] **A Magic Die is rolled by Soandso.",
+      "[Thu Jul 18 17:35:20 2024] **It could have been any number from 0 to 100, but this time it turned up a 42.",
+    ] {
+      if let Some(record) = tracker.process(&Line::from(raw).unwrap()) {
+        journal.append(&record);
+      }
+    }
+
+    let history = read_loot_history(&path).unwrap();
+    assert_eq!(history.drops.len(), 1);
+    assert_eq!(history.roll_sessions.len(), 1);
+
+    let mut drops_csv = Vec::new();
+    write_csv(&history, LootExport::Drops, &mut drops_csv).unwrap();
+    assert_eq!(
+      String::from_utf8(drops_csv).unwrap(),
+      "Looted At,Looter,Item,Quantity,Corpse\n2024-07-18T17:35:14.000Z,Xenk,Bone Chip,1,\n"
+    );
+    let mut rolls_csv = Vec::new();
+    write_csv(&history, LootExport::Rolls, &mut rolls_csv).unwrap();
+    assert!(String::from_utf8(rolls_csv)
+      .unwrap()
+      .ends_with("\n2024-07-18T17:35:20.000Z,0-100,Soandso,2024-07-18T17:35:20.000Z,Soandso,42\n"));
+
+    std::fs::remove_file(&path).unwrap();
+  }
+}
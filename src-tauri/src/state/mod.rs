@@ -1,5 +1,9 @@
+pub mod active_triggers;
+pub mod attendance;
 pub mod config;
+pub mod loot_history;
 pub mod overlay;
 pub mod state_handle;
 pub mod state_tree;
 pub mod timer_manager;
+pub mod trigger_history;
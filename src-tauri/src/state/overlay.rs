@@ -1,5 +1,6 @@
 use super::timer_manager::{TimerLifetime, TimerManager, TimerStateUpdate};
 use crate::common::shutdown::quitter;
+use crate::dry_run::{EffectRecorder, RecordedOutput};
 use serde::{Deserialize, Serialize};
 use std::{
   collections::HashMap,
@@ -28,15 +29,30 @@ pub enum OverlayMode {
 
 #[derive(Debug)]
 pub struct OverlayManager {
-  app: AppHandle,
+  target: OverlayTarget,
   timer_manager: Arc<TimerManager>,
   emitters: Mutex<HashMap<String, oneshot::Sender<()>>>,
 }
 
+#[derive(Debug)]
+enum OverlayTarget {
+  App(Box<AppHandle>),
+  Recording(EffectRecorder),
+}
+
 impl OverlayManager {
   pub fn new(app: AppHandle, timer_manager: Arc<TimerManager>) -> Self {
     Self {
-      app,
+      target: OverlayTarget::App(Box::new(app)),
+      timer_manager,
+      emitters: Mutex::new(HashMap::new()),
+    }
+  }
+
+  /// Creates an OverlayManager that records overlay messages instead of showing them
+  pub fn recording(timer_manager: Arc<TimerManager>, recorder: EffectRecorder) -> Self {
+    Self {
+      target: OverlayTarget::Recording(recorder),
       timer_manager,
       emitters: Mutex::new(HashMap::new()),
     }
@@ -44,9 +60,12 @@ impl OverlayManager {
 
   pub async fn start_emitter(&self, window_label: &str) -> Vec<TimerLifetime> {
     let (timer_lifetimes, timer_state_updates_subscription) = self.timer_manager.subscribe().await;
+    let OverlayTarget::App(app) = &self.target else {
+      return timer_lifetimes;
+    };
     let (tx_stop, rx_stop) = oneshot::channel::<()>();
     spawn(emitter_loop(
-      self.app.clone(),
+      (**app).clone(),
       window_label.to_owned(),
       timer_state_updates_subscription,
       rx_stop,
@@ -64,7 +83,12 @@ impl OverlayManager {
   }
 
   pub fn message(&self, message: String) {
-    _ = self.app.emit_all(OVERLAY_MESSAGE_EVENT_NAME, message);
+    match &self.target {
+      OverlayTarget::App(app) => _ = app.emit_all(OVERLAY_MESSAGE_EVENT_NAME, message),
+      OverlayTarget::Recording(recorder) => {
+        recorder.record(RecordedOutput::OverlayMessage(message))
+      }
+    }
   }
 }
 
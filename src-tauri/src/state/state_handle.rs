@@ -1,10 +1,17 @@
+use super::active_triggers::ActiveTriggers;
+use super::attendance::{self, AttendanceError, AttendanceSnapshot};
 use super::config::{LogQuestConfig, TriggersSaveError};
-use super::state_tree::{OverlayState, ReactorState, StateTree};
-use crate::common::shutdown::critical_path;
+use super::state_tree::{OverlayState, ReactorState, StateTree, TriggerTagActivation};
+use super::trigger_history::TriggerHistory;
+use crate::common::{shutdown::critical_path, UUID};
+use crate::logs::{combat::CombatUpdate, who::WhoListing, zone_tracker::ZoneChange};
 use crate::triggers::trigger_index::{DataDelta, DataMutationError, TriggerIndex};
 use std::sync::{Arc, Mutex};
-use tokio::sync::Notify;
-use tracing::{error, info};
+use tokio::sync::{broadcast, Notify};
+use tracing::{debug, error, info};
+
+const ZONE_CHANGED_QUEUE_DEPTH: usize = 16;
+const COMBAT_UPDATES_QUEUE_DEPTH: usize = 64;
 
 /// `StateHandle` provides helper methods for accessing `Mutex`-locked branches
 /// of the `StateTree`. There are three different ways to access a branch...
@@ -21,15 +28,68 @@ use tracing::{error, info};
 pub struct StateHandle {
   tree: Arc<StateTree>,
   pub config_updated: Arc<Notify>,
+  /// Sent whenever the zone of a character in `ReactorState::character_zones` changes
+  pub zone_changed: broadcast::Sender<ZoneChange>,
+  /// Sent by the reactor's CombatParser, if `combat_parsing` is enabled in the config
+  pub combat_updates: broadcast::Sender<CombatUpdate>,
+  /// Rebuilt whenever the triggers, the active trigger tags or the boxed set might have changed
+  active_triggers: Arc<Mutex<Arc<ActiveTriggers>>>,
+  /// Held while a new ActiveTriggers snapshot is built, so that the last one swapped in is
+  /// built from the latest state
+  active_triggers_rebuild: Arc<Mutex<()>>,
 }
 
 impl StateHandle {
   pub fn new(state_tree: StateTree) -> Self {
     let config_updated = Arc::new(Notify::new());
-    Self {
+    let (zone_changed, _) = broadcast::channel(ZONE_CHANGED_QUEUE_DEPTH);
+    let (combat_updates, _) = broadcast::channel(COMBAT_UPDATES_QUEUE_DEPTH);
+    let state_handle = Self {
       tree: Arc::new(state_tree),
       config_updated,
-    }
+      zone_changed,
+      combat_updates,
+      active_triggers: Arc::new(Mutex::new(Arc::new(ActiveTriggers::from_triggers(
+        Vec::new(),
+      )))),
+      active_triggers_rebuild: Arc::new(Mutex::new(())),
+    };
+    state_handle.active_triggers_changed();
+    state_handle
+  }
+
+  /// Returns the latest snapshot of the active Triggers. The snapshot can be used for as long
+  /// as needed without holding any locks on the `StateTree`.
+  pub fn active_triggers(&self) -> Arc<ActiveTriggers> {
+    self
+      .active_triggers
+      .lock()
+      .expect("ActiveTriggers mutex poisoned!")
+      .clone()
+  }
+
+  /// Builds a new snapshot of the active Triggers and swaps it in. This is done as soon as
+  /// anything the snapshot is built from changes, so that matching a line never has to wait
+  /// for a rebuild.
+  fn active_triggers_changed(&self) {
+    let _rebuilding = self
+      .active_triggers_rebuild
+      .lock()
+      .expect("ActiveTriggers rebuild mutex poisoned!");
+    let active_trigger_tags = self.select_reactor(|r| r.active_trigger_tags.clone());
+    let boxed_set = self.select_config(|config| config.boxed_set.clone());
+    let active_triggers = Arc::new(
+      self.select_triggers(|index| ActiveTriggers::new(index, &active_trigger_tags, boxed_set)),
+    );
+    debug!(
+      "Built snapshot of {} active triggers with {} prefilter literals",
+      active_triggers.len(),
+      active_triggers.prefilter().literal_count()
+    );
+    *self
+      .active_triggers
+      .lock()
+      .expect("ActiveTriggers mutex poisoned!") = active_triggers;
   }
 
   pub fn with_config<F>(&self, reader: F)
@@ -39,13 +99,6 @@ impl StateHandle {
     self.with_branch(&self.tree.config, reader);
   }
 
-  pub fn with_reactor<F>(&self, reader: F)
-  where
-    F: FnOnce(&ReactorState),
-  {
-    self.with_branch(&self.tree.reactor, reader);
-  }
-
   // pub fn with_triggers<F>(&self, reader: F)
   // where
   //   F: FnOnce(&TriggerIndex),
@@ -81,6 +134,22 @@ impl StateHandle {
     self.select_branch(&self.tree.reactor, selector)
   }
 
+  pub fn select_trigger_history<F, T>(&self, selector: F) -> T
+  where
+    F: FnOnce(&TriggerHistory) -> T,
+  {
+    self.select_branch(&self.tree.trigger_history, selector)
+  }
+
+  pub fn update_trigger_history<F>(&self, func: F)
+  where
+    F: for<'a> FnOnce(&'a mut TriggerHistory),
+  {
+    self.update_branch(&self.tree.trigger_history, func);
+  }
+
+  /// Does not rebuild the active Triggers, so the active TriggerTags must only be changed
+  /// through the methods that do, like `set_trigger_tag_activated` or `set_character_zone`
   pub fn update_reactor_and_select<F, T>(&self, selector: F) -> T
   where
     F: FnOnce(&mut ReactorState) -> T,
@@ -88,6 +157,8 @@ impl StateHandle {
     self.update_branch_and_select(&self.tree.reactor, selector)
   }
 
+  /// Does not rebuild the active Triggers, so the active TriggerTags must only be changed
+  /// through the methods that do, like `set_trigger_tag_activated` or `set_character_zone`
   pub fn update_reactor<F>(&self, func: F)
   where
     F: for<'a> FnOnce(&'a mut ReactorState),
@@ -95,11 +166,83 @@ impl StateHandle {
     self.update_branch(&self.tree.reactor, func);
   }
 
+  pub fn set_trigger_tag_activated(&self, id: UUID, activated: bool) -> TriggerTagActivation {
+    let activation = self.update_reactor_and_select(|reactor| {
+      reactor.set_trigger_tag_activated(id, activated);
+      reactor.trigger_tag_activation()
+    });
+    self.active_triggers_changed();
+    activation
+  }
+
+  pub fn activate_created_trigger_tag(&self, id: UUID) -> TriggerTagActivation {
+    let activation = self.update_reactor_and_select(|reactor| {
+      reactor.activate_created_trigger_tag(id);
+      reactor.trigger_tag_activation()
+    });
+    self.active_triggers_changed();
+    activation
+  }
+
+  /// Forgets the zone the TriggerTags were turned on for, when the current character changes
+  pub fn clear_zone_rules(&self) {
+    self.update_reactor(|reactor| reactor.clear_zone_rules());
+    self.active_triggers_changed();
+  }
+
+  /// Records the zone of the current character, applies the zone rules of the TriggerTags, and
+  /// notifies `zone_changed` if it is a different zone. The active Triggers are only rebuilt if
+  /// the zone rules turned any TriggerTags on or off.
+  pub fn set_character_zone(&self, character_name: &str, zone: &str) {
+    let (zone_scoped, in_zone) = self.select_triggers(|index| index.zone_rule_trigger_tags(zone));
+    let (changed, activation_changed) = self.update_reactor_and_select(|reactor| {
+      let active_before = reactor.active_trigger_tags.clone();
+      reactor.apply_zone_rules(&zone_scoped, in_zone);
+      let previous = reactor
+        .character_zones
+        .insert(character_name.to_owned(), zone.to_owned());
+      (
+        previous.as_deref() != Some(zone),
+        reactor.active_trigger_tags != active_before,
+      )
+    });
+    if activation_changed {
+      self.active_triggers_changed();
+    }
+    if changed {
+      // There may not be any subscribers, which is not an error
+      _ = self.zone_changed.send(ZoneChange {
+        character_name: character_name.to_owned(),
+        zone: zone.to_owned(),
+      });
+    }
+  }
+
+  pub fn set_latest_who_listing(&self, character_name: &str, listing: WhoListing) {
+    self.update_reactor(|reactor| {
+      reactor.latest_who_listing = Some((character_name.to_owned(), listing));
+    });
+  }
+
+  /// Saves the most recent `/who` listing as an attendance snapshot
+  pub fn take_attendance_snapshot(
+    &self,
+    label: &str,
+  ) -> Result<AttendanceSnapshot, AttendanceError> {
+    let (character_name, listing) = self
+      .select_reactor(|reactor| reactor.latest_who_listing.clone())
+      .ok_or(AttendanceError::NoListing)?;
+    let snapshot = AttendanceSnapshot::new(label, &character_name, listing);
+    let path = self.select_config(|config| config.attendance_snapshots_file_path());
+    attendance::append_snapshot(&path, &snapshot)?;
+    Ok(snapshot)
+  }
+
   pub fn mutate_index<F>(&self, func: F) -> Result<Vec<DataDelta>, TriggersSaveError>
   where
     F: FnOnce(&mut TriggerIndex) -> Result<Vec<DataDelta>, DataMutationError>,
   {
-    self.update_branch_and_select(&self.tree.triggers, |index| {
+    let result = self.update_branch_and_select(&self.tree.triggers, |index| {
       self.select_config(|config| {
         critical_path(|| {
           let deltas = func(index)?;
@@ -108,7 +251,7 @@ impl StateHandle {
               DataDelta::TopLevelChanged(top_level) => {
                 config.save_top_level(top_level)?;
               }
-              DataDelta::TriggerSaved(trigger) => config.save_trigger(&trigger)?,
+              DataDelta::TriggerSaved(trigger) => config.save_trigger(trigger)?,
               DataDelta::TriggerDeleted(trigger_id) => {
                 config.delete_trigger_file(trigger_id)?;
               }
@@ -136,7 +279,8 @@ impl StateHandle {
               DataDelta::TriggerTagged { trigger_tag_id, .. }
               | DataDelta::TriggerUntagged { trigger_tag_id, .. }
               | DataDelta::TriggerTagTriggersChanged { trigger_tag_id, .. }
-              | DataDelta::TriggerTagRenamed(trigger_tag_id, _) => {
+              | DataDelta::TriggerTagRenamed(trigger_tag_id, _)
+              | DataDelta::TriggerTagZonesChanged { trigger_tag_id, .. } => {
                 if let Some(trigger_tag) = index.trigger_tags.get(trigger_tag_id) {
                   config.save_trigger_tag(trigger_tag)?;
                 }
@@ -146,7 +290,42 @@ impl StateHandle {
           Ok(deltas)
         })
       })
-    })
+    });
+    if let Ok(deltas) = &result {
+      let deleted_trigger_tags: Vec<&UUID> = deltas
+        .iter()
+        .filter_map(|delta| match delta {
+          DataDelta::TriggerTagDeleted(trigger_tag_id) => Some(trigger_tag_id),
+          _ => None,
+        })
+        .collect();
+      if !deleted_trigger_tags.is_empty() {
+        self.update_reactor(|reactor| {
+          for trigger_tag_id in deleted_trigger_tags {
+            reactor.forget_trigger_tag(trigger_tag_id);
+          }
+        });
+      }
+      if deltas
+        .iter()
+        .any(|delta| matches!(delta, DataDelta::TriggerTagZonesChanged { .. }))
+      {
+        self.reapply_zone_rules();
+      }
+    }
+    self.active_triggers_changed();
+    result
+  }
+
+  /// Applies the zone rules for the zone the current character is already in, after the zones
+  /// of a TriggerTag changed
+  fn reapply_zone_rules(&self) {
+    let Some(zone) = self.select_reactor(|reactor| reactor.current_zone().map(str::to_owned))
+    else {
+      return;
+    };
+    let (zone_scoped, in_zone) = self.select_triggers(|index| index.zone_rule_trigger_tags(&zone));
+    self.update_reactor(|reactor| reactor.apply_zone_rules(&zone_scoped, in_zone));
   }
 
   pub fn bulk_update_triggers<F>(&self, func: F)
@@ -163,6 +342,7 @@ impl StateHandle {
         }
       });
     });
+    self.active_triggers_changed();
   }
 
   pub fn update_overlay<F>(&self, func: F)
@@ -202,12 +382,22 @@ impl StateHandle {
     })
   }
 
+  /// The boxed set is part of the active Triggers snapshot, so this rebuilds it
+  pub fn set_boxed_set(&self, character_names: Vec<String>) -> LogQuestConfig {
+    let config = self.update_config_and_select(|config| {
+      config.boxed_set = character_names;
+      config.clone()
+    });
+    self.active_triggers_changed();
+    config
+  }
+
   fn with_branch<F, B>(&self, branch: &Mutex<B>, reader: F)
   where
     F: FnOnce(&B),
   {
     let guard = branch.lock().expect("State mutex poisoned!");
-    let value: &B = &*guard;
+    let value: &B = &guard;
     reader(value);
   }
 
@@ -216,7 +406,7 @@ impl StateHandle {
     F: FnOnce(&B) -> T,
   {
     let mut guard = branch.lock().expect("State mutex poisoned!");
-    let value: &mut B = &mut *guard;
+    let value: &mut B = &mut guard;
     func(value)
   }
 
@@ -226,7 +416,6 @@ impl StateHandle {
   {
     self.update_branch_and_select(branch, |b: &mut B| {
       func(b);
-      ()
     });
   }
 
@@ -235,7 +424,7 @@ impl StateHandle {
     F: FnOnce(&mut B) -> R,
   {
     let mut guard = branch.lock().expect("State mutex poisoned!");
-    let value: &mut B = &mut *guard;
+    let value: &mut B = &mut guard;
     func(value)
   }
 }
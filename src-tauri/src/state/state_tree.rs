@@ -1,24 +1,67 @@
 use super::config::LogQuestConfig;
 use super::overlay::OverlayMode;
+use super::trigger_history::TriggerHistory;
 use crate::triggers::trigger_index::TriggerIndex;
-use crate::{common::UUID, logs::active_character_detection::Character};
+use crate::{
+  common::UUID,
+  logs::{
+    active_character_detection::{Character, CharacterDetectionReason},
+    log_event_broadcaster::LogWatcherMode,
+    who::WhoListing,
+  },
+};
 use serde::Serialize;
-use std::{collections::HashSet, sync::Mutex};
+use std::{
+  collections::{HashMap, HashSet},
+  sync::Mutex,
+};
 
 pub const DEFAULT_OVERLAY_OPACITY: u8 = 75;
 
 pub struct StateTree {
-  // TODO: SHOULD THESE BE Arc<AsyncRwLock<..> INSTEAD OF Mutex<..> ??
+  // The reactor does not hold these locks while matching lines; it works from an
+  // ActiveTriggers snapshot instead (see StateHandle::active_triggers)
   pub config: Mutex<LogQuestConfig>,
   pub reactor: Mutex<ReactorState>,
   pub triggers: Mutex<TriggerIndex>,
   pub overlay: Mutex<OverlayState>,
+  pub trigger_history: Mutex<TriggerHistory>,
 }
 
-#[derive(Debug, Clone, Serialize, ts_rs::TS)]
+#[derive(Debug, Clone, Default, Serialize, ts_rs::TS)]
 pub struct ReactorState {
   pub current_character: Option<Character>,
+  /// Why the current character was detected as the active one
+  pub current_character_reason: Option<CharacterDetectionReason>,
+  pub active_trigger_tags: HashSet<UUID>,
+  /// The last known zone of each character whose log file has been followed, by name
+  pub character_zones: HashMap<String, String>,
+  /// TriggerTags that were turned on or off by hand this session. Zone rules do not change
+  /// these until LogQuest is restarted.
+  pub trigger_tag_overrides: HashMap<UUID, bool>,
+  /// TriggerTags that list the zone the current character is in
+  pub zone_activated_trigger_tags: HashSet<UUID>,
+  /// The most recent `/who` listing in the log, and the character whose log it was in
+  #[serde(skip)]
+  #[ts(skip)]
+  pub latest_who_listing: Option<(String, WhoListing)>,
+  /// Given at the CLI, in place of the watcher mode in the config, for this session only
+  #[serde(skip)]
+  #[ts(skip)]
+  pub log_watcher_mode_override: Option<LogWatcherMode>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ts_rs::TS)]
+pub enum TriggerTagActivationReason {
+  ManualToggle,
+  ZoneRule,
+}
+
+#[derive(Debug, Clone, Serialize, ts_rs::TS)]
+pub struct TriggerTagActivation {
   pub active_trigger_tags: HashSet<UUID>,
+  /// Why each of the active TriggerTags is active
+  pub reasons: HashMap<UUID, TriggerTagActivationReason>,
 }
 
 #[derive(Debug, Clone, Serialize, ts_rs::TS)]
@@ -42,21 +85,100 @@ impl StateTree {
     trigger_index: TriggerIndex,
     overlay_mode: OverlayMode,
     overlay_dev_tools: bool,
+    log_watcher_mode_override: Option<LogWatcherMode>,
   ) -> StateTree {
+    let trigger_history = TriggerHistory::new(app_config.trigger_history_journal_path().as_deref());
     Self {
       config: Mutex::new(app_config),
       triggers: Mutex::new(trigger_index),
-      reactor: Mutex::new(ReactorState::new()),
+      reactor: Mutex::new(ReactorState {
+        log_watcher_mode_override,
+        ..Default::default()
+      }),
       overlay: Mutex::new(OverlayState::new(overlay_mode, overlay_dev_tools)),
+      trigger_history: Mutex::new(trigger_history),
     }
   }
 }
 
 impl ReactorState {
-  fn new() -> Self {
-    Self {
-      current_character: None,
-      active_trigger_tags: HashSet::new(),
+  /// A manual toggle is sticky; zone rules will not change the TriggerTag for the rest of the
+  /// session
+  pub fn set_trigger_tag_activated(&mut self, id: UUID, activated: bool) {
+    if activated {
+      self.active_trigger_tags.insert(id.clone());
+    } else {
+      self.active_trigger_tags.remove(&id);
+    }
+    self.trigger_tag_overrides.insert(id, activated);
+  }
+
+  /// Turns on a TriggerTag that was just created. Unlike a manual toggle, this leaves the
+  /// TriggerTag to its zone rules if it is given any later.
+  pub fn activate_created_trigger_tag(&mut self, id: UUID) {
+    self.active_trigger_tags.insert(id);
+  }
+
+  /// Drops everything known about a TriggerTag that was deleted
+  pub fn forget_trigger_tag(&mut self, id: &UUID) {
+    self.active_trigger_tags.remove(id);
+    self.trigger_tag_overrides.remove(id);
+    self.zone_activated_trigger_tags.remove(id);
+  }
+
+  /// Turns off the TriggerTags that zone rules turned on, when there is a new current character
+  /// whose zone is not known yet
+  pub fn clear_zone_rules(&mut self) {
+    for id in self.zone_activated_trigger_tags.drain() {
+      if !self.trigger_tag_overrides.contains_key(&id) {
+        self.active_trigger_tags.remove(&id);
+      }
+    }
+  }
+
+  /// `zone_scoped` are the TriggerTags that list any zones, and `in_zone` are the ones that list
+  /// the zone the current character is in. A TriggerTag that a zone rule turned on is turned off
+  /// again if it no longer lists any zones.
+  pub fn apply_zone_rules(&mut self, zone_scoped: &HashSet<UUID>, in_zone: HashSet<UUID>) {
+    for id in zone_scoped.union(&self.zone_activated_trigger_tags) {
+      if self.trigger_tag_overrides.contains_key(id) {
+        continue;
+      }
+      if in_zone.contains(id) {
+        self.active_trigger_tags.insert(id.clone());
+      } else {
+        self.active_trigger_tags.remove(id);
+      }
+    }
+    self.zone_activated_trigger_tags = in_zone;
+  }
+
+  /// The last known zone of the current character
+  pub fn current_zone(&self) -> Option<&str> {
+    let character = self.current_character.as_ref()?;
+    self
+      .character_zones
+      .get(&character.name)
+      .map(String::as_str)
+  }
+
+  pub fn trigger_tag_activation(&self) -> TriggerTagActivation {
+    let reasons = self
+      .active_trigger_tags
+      .iter()
+      .map(|id| {
+        let manually_activated = self.trigger_tag_overrides.get(id) == Some(&true);
+        let reason = if !manually_activated && self.zone_activated_trigger_tags.contains(id) {
+          TriggerTagActivationReason::ZoneRule
+        } else {
+          TriggerTagActivationReason::ManualToggle
+        };
+        (id.clone(), reason)
+      })
+      .collect();
+    TriggerTagActivation {
+      active_trigger_tags: self.active_trigger_tags.clone(),
+      reasons,
     }
   }
 }
@@ -71,3 +193,88 @@ impl OverlayState {
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::{ReactorState, TriggerTagActivationReason};
+  use crate::common::UUID;
+  use std::collections::HashSet;
+
+  #[test]
+  fn test_zone_rules_and_manual_overrides() {
+    let (raid, ldon, always) = (UUID::new(), UUID::new(), UUID::new());
+    let zone_scoped: HashSet<UUID> = [raid.clone(), ldon.clone()].into();
+    let mut reactor = ReactorState::default();
+    reactor.set_trigger_tag_activated(always.clone(), true);
+
+    reactor.apply_zone_rules(&zone_scoped, [raid.clone()].into());
+    let activation = reactor.trigger_tag_activation();
+    assert_eq!(
+      activation.active_trigger_tags,
+      [raid.clone(), always.clone()].into()
+    );
+    assert_eq!(
+      activation.reasons[&raid],
+      TriggerTagActivationReason::ZoneRule
+    );
+    assert_eq!(
+      activation.reasons[&always],
+      TriggerTagActivationReason::ManualToggle
+    );
+
+    reactor.apply_zone_rules(&zone_scoped, [ldon.clone()].into());
+    assert_eq!(
+      reactor.active_trigger_tags,
+      [ldon.clone(), always.clone()].into()
+    );
+
+    // Manual toggles stick when the zone changes
+    reactor.set_trigger_tag_activated(ldon.clone(), false);
+    reactor.set_trigger_tag_activated(raid.clone(), true);
+    reactor.apply_zone_rules(&zone_scoped, [ldon.clone()].into());
+    let activation = reactor.trigger_tag_activation();
+    assert_eq!(
+      activation.active_trigger_tags,
+      [raid.clone(), always].into()
+    );
+    assert_eq!(
+      activation.reasons[&raid],
+      TriggerTagActivationReason::ManualToggle
+    );
+  }
+
+  #[test]
+  fn test_zone_rules_after_zones_changed() {
+    let (raid, ldon) = (UUID::new(), UUID::new());
+    let mut reactor = ReactorState::default();
+    reactor.apply_zone_rules(&[raid.clone()].into(), [raid.clone()].into());
+    assert_eq!(reactor.active_trigger_tags, [raid.clone()].into());
+
+    // The raid TriggerTag no longer lists any zones, and the LDoN TriggerTag now lists the zone
+    reactor.apply_zone_rules(&[ldon.clone()].into(), [ldon.clone()].into());
+    assert_eq!(reactor.active_trigger_tags, [ldon].into());
+  }
+
+  #[test]
+  fn test_zone_rules_for_created_and_deleted_trigger_tags() {
+    let (created, deleted) = (UUID::new(), UUID::new());
+    let mut reactor = ReactorState::default();
+    reactor.activate_created_trigger_tag(created.clone());
+    reactor.set_trigger_tag_activated(deleted.clone(), true);
+    reactor.forget_trigger_tag(&deleted);
+    assert_eq!(reactor.active_trigger_tags, [created.clone()].into());
+    assert!(reactor.trigger_tag_overrides.is_empty());
+
+    // The created TriggerTag was not toggled by hand, so zone rules apply to it
+    let zone_scoped: HashSet<UUID> = [created.clone()].into();
+    reactor.apply_zone_rules(&zone_scoped, HashSet::new());
+    assert!(reactor.active_trigger_tags.is_empty());
+    reactor.apply_zone_rules(&zone_scoped, [created.clone()].into());
+    assert_eq!(reactor.active_trigger_tags, [created].into());
+
+    // A new current character starts without the TriggerTags of the last one's zone
+    reactor.clear_zone_rules();
+    assert!(reactor.active_trigger_tags.is_empty());
+    assert!(reactor.zone_activated_trigger_tags.is_empty());
+  }
+}
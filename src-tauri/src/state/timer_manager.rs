@@ -1,5 +1,6 @@
 use crate::{
   common::{
+    clock::Clock,
     duration::Duration,
     fatal_error,
     shutdown::quitter,
@@ -12,15 +13,13 @@ use crate::{
 use serde::Serialize;
 use std::{
   collections::HashMap,
+  future::Future,
   sync::{atomic::Ordering, Arc},
 };
 use std::{iter::once, sync::atomic::AtomicBool};
 use tauri::async_runtime::spawn;
+use tokio::sync::{broadcast, mpsc, oneshot};
 use tokio::{select, sync::Notify};
-use tokio::{
-  sync::{broadcast, mpsc, oneshot},
-  time::Instant,
-};
 use tracing::{debug, error, info};
 
 const TIMER_COMMAND_CHANNEL_SIZE: usize = 50;
@@ -30,28 +29,28 @@ const RESET_TIMER_CHANNEL_SIZE: usize = 5;
 #[derive(Debug)]
 pub struct TimerManager {
   tx_commands: mpsc::Sender<TimerCommand>,
+  clock: Clock,
 }
 
+/// A snapshot of the `TimerLifetimes` along with a subscription to every change after it
+type TimerSubscription = (
+  Vec<TimerLifetime>,
+  Arc<broadcast::Receiver<TimerStateUpdate>>,
+);
+
 pub enum TimerCommand {
-  Begin(TimerLifetime),
+  Begin(Box<TimerLifetime>),
   Terminate(UUID),
   SetHidden(UUID, bool),
   Restart(UUID),
-  CreateSubscription(
-    Arc<
-      oneshot::Sender<(
-        Vec<TimerLifetime>,
-        Arc<broadcast::Receiver<TimerStateUpdate>>,
-      )>,
-    >,
-  ),
+  CreateSubscription(Arc<oneshot::Sender<TimerSubscription>>),
 }
 
 #[derive(Debug, Clone, Serialize, ts_rs::TS)]
 #[serde(tag = "variant", content = "value")]
 #[ts(tag = "variant", content = "value")]
 pub enum TimerStateUpdate {
-  TimerAdded(TimerLifetime),
+  TimerAdded(Box<TimerLifetime>),
   TimerKilled(UUID),
   TimerHiddenUpdated(UUID, bool),
   TimerRestarted {
@@ -97,6 +96,7 @@ impl TimerLifetime {
 #[derive(Debug, Clone)]
 pub struct TimerContext {
   pub timer_id: UUID,
+  pub name: String,
   pub end_time: ObservableTimestamp,
   sender: mpsc::Sender<TimerCommand>,
   is_finished: Arc<AtomicBool>,
@@ -144,18 +144,33 @@ type TimerLifetimesMap = HashMap<UUID, (TimerLifetime, mpsc::Sender<ResetTimerEv
 
 impl TimerManager {
   pub fn new() -> Self {
+    let (timer_manager, event_loop) = Self::create(Clock::System);
+    spawn(event_loop);
+    timer_manager
+  }
+
+  /// Runs the Timers on the current tokio runtime, so that a single-threaded runtime can drive
+  /// them together with a VirtualClock
+  pub fn with_clock(clock: Clock) -> Self {
+    let (timer_manager, event_loop) = Self::create(clock);
+    tokio::spawn(event_loop);
+    timer_manager
+  }
+
+  fn create(clock: Clock) -> (Self, impl Future<Output = ()>) {
     let (tx_commands, rx_commands) = mpsc::channel::<TimerCommand>(TIMER_COMMAND_CHANNEL_SIZE);
 
     let (tx_state_updates, _rx_state_updates) =
       broadcast::channel::<TimerStateUpdate>(TIMER_STATE_UPDATE_CHANNEL_SIZE);
 
-    spawn(event_loop(
+    let event_loop = event_loop(
       rx_commands,
       tx_commands.clone(),
       tx_state_updates.clone(),
-    ));
+      clock.clone(),
+    );
 
-    Self { tx_commands }
+    (Self { tx_commands, clock }, event_loop)
   }
 
   pub async fn send(
@@ -171,8 +186,8 @@ impl TimerManager {
     context: Arc<EventContext>,
   ) -> Result<UUID, mpsc::error::SendError<TimerCommand>> {
     let id = UUID::new();
-    let name = timer.name_tmpl.render(&context.match_context);
-    let start_time = Timestamp::now();
+    let name = context.render(&timer.name_tmpl);
+    let start_time = self.clock.now();
     let end_time = ObservableTimestamp::new(&start_time + &timer.duration);
     let context = context.to_owned();
     let is_finished = Arc::new(AtomicBool::new(false));
@@ -194,7 +209,7 @@ impl TimerManager {
 
     self
       .tx_commands
-      .send(TimerCommand::Begin(timer_lifetime))
+      .send(TimerCommand::Begin(Box::new(timer_lifetime)))
       .await
       .map(|_| id)
   }
@@ -206,10 +221,7 @@ impl TimerManager {
   /// before it begins consuming events, in which case it must call this function
   /// again to get a fresh snapshot and a new up-to-date `broadcast::Receiver`).
   pub async fn subscribe(&self) -> (Vec<TimerLifetime>, broadcast::Receiver<TimerStateUpdate>) {
-    let (setter, getter) = oneshot::channel::<(
-      Vec<TimerLifetime>,
-      Arc<broadcast::Receiver<TimerStateUpdate>>,
-    )>();
+    let (setter, getter) = oneshot::channel::<TimerSubscription>();
 
     if let Err(_send_error) = self
       .tx_commands
@@ -232,6 +244,7 @@ async fn event_loop(
   mut rx_command: mpsc::Receiver<TimerCommand>,
   tx_command: mpsc::Sender<TimerCommand>,
   tx_state_update: broadcast::Sender<TimerStateUpdate>,
+  clock: Clock,
 ) {
   debug!("Starting TimerManager event loop");
   let mut timer_lifetimes: TimerLifetimesMap = HashMap::new();
@@ -251,6 +264,7 @@ async fn event_loop(
           _ = setter.send((snapshot, Arc::new(subscription)));
         }
         Some(TimerCommand::Begin(timer_lifetime)) => {
+          let timer_lifetime = *timer_lifetime;
           let TimerLifetime {
             id,
             timer,
@@ -265,27 +279,28 @@ async fn event_loop(
           match &timer.start_policy {
             TimerStartPolicy::AlwaysStartNewTimer => { /* nothing to do here */ }
             TimerStartPolicy::DoNothingIfTimerRunning => {
-              if is_timer_running_with_name(&name, &timer_lifetimes) {
+              if is_timer_running_with_name(name, &timer_lifetimes) {
                 debug!("Timer[{id}] DoNothingIfTimerRunning policy [ name = `{name}` ]");
-                return;
+                continue;
               }
             }
             TimerStartPolicy::StartAndReplacesAllTimersOfTrigger => {
               kill_timers_of_trigger(&timer.trigger_id, &mut timer_lifetimes, &tx_state_update);
             }
             TimerStartPolicy::StartAndReplacesAnyTimerOfTriggerWithNameTemplateMatching(replaced_name_template) => {
-              let replaced_name = replaced_name_template.render(&context.match_context);
+              let replaced_name = context.render(replaced_name_template);
               kill_timers_of_trigger_with_name(&timer.trigger_id, &replaced_name, &mut timer_lifetimes, &tx_state_update);
             }
           }
 
-          let tx_reaper = spawn_timer_reaper(id.clone(), timer.duration.clone(), tx_command.clone());
+          let tx_reaper = spawn_timer_reaper(id.clone(), timer.duration.clone(), tx_command.clone(), clock.clone());
           timer_lifetimes.insert(id.clone(), (timer_lifetime.clone(), tx_reaper));
 
-          _ = tx_state_update.send(TimerStateUpdate::TimerAdded(timer_lifetime.clone()));
+          _ = tx_state_update.send(TimerStateUpdate::TimerAdded(Box::new(timer_lifetime.clone())));
 
           let context = context.with_timer_context(TimerContext {
             timer_id: id.clone(),
+            name: name.clone(),
             sender: tx_command.clone(),
             end_time: end_time.clone(),
             is_finished: is_finished.clone(),
@@ -304,7 +319,7 @@ async fn event_loop(
         }
         Some(TimerCommand::Restart(timer_id)) => {
           if let Some((timer_lifetime, reaper_sender)) = timer_lifetimes.get(&timer_id) {
-            let new_start_timestamp = Timestamp::now();
+            let new_start_timestamp = clock.now();
             let new_end_timestamp = &new_start_timestamp + &timer_lifetime.timer.duration;
             timer_lifetime.end_time.set(new_end_timestamp.clone());
 
@@ -335,14 +350,16 @@ fn spawn_timer_reaper(
   timer_id: UUID,
   duration: Duration,
   tx_timer_event: mpsc::Sender<TimerCommand>,
+  clock: Clock,
 ) -> mpsc::Sender<ResetTimerEvent> {
   let (tx_reaper_event, mut rx_reaper_event) =
     mpsc::channel::<ResetTimerEvent>(RESET_TIMER_CHANNEL_SIZE);
-  spawn(async move {
+  // Spawned next to the Timer event loop
+  tokio::spawn(async move {
     debug!("Timer[{timer_id}] Reaper task spawned");
 
-    let duration: tokio::time::Duration = duration.into();
-    let mut end_instant: Instant = Instant::now() + duration;
+    let duration: std::time::Duration = duration.into();
+    let mut deadline = clock.deadline_after(duration);
 
     let mut quit = quitter();
     loop {
@@ -352,7 +369,7 @@ fn spawn_timer_reaper(
           debug!("Timer reaper QUITTING");
           break;
         }
-        () = tokio::time::sleep_until(end_instant) => { // Instant implements Copy
+        () = clock.sleep_until_deadline(&deadline) => {
           _ = tx_timer_event.send(TimerCommand::Terminate(timer_id)).await;
           break;
         }
@@ -365,7 +382,7 @@ fn spawn_timer_reaper(
               break;
             }
             Some(_reset_timer_event) => {
-              end_instant = Instant::now() + duration;
+              deadline = clock.deadline_after(duration);
             },
           }
         },
@@ -0,0 +1,470 @@
+//! The TriggerHistory keeps the most recent Trigger firings in memory and, if the journal is
+//! enabled in the config, appends every firing (and every Effect error) to a JSON Lines file
+//! in the data directory so that the history outlives the process. The journal is written from
+//! its own thread, and once it grows past TRIGGER_HISTORY_JOURNAL_MAX_BYTES it is moved aside
+//! to a `.1` file (replacing the previous one) and started over.
+use crate::{
+  common::{fatal_error, timestamp::Timestamp, UUID},
+  matchers::MatchContext,
+  triggers::trigger_index::TriggerIndex,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+  collections::{HashMap, VecDeque},
+  fs,
+  io::{self, BufRead as _, Write as _},
+  path::{Path, PathBuf},
+  sync::mpsc,
+  thread,
+};
+use tracing::error;
+
+/// How many firings are kept in memory
+const TRIGGER_HISTORY_CAPACITY: usize = 1000;
+
+/// How large the journal file grows before it is rotated
+const TRIGGER_HISTORY_JOURNAL_MAX_BYTES: u64 = 20 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
+pub struct TriggerFiring {
+  pub id: UUID,
+  pub trigger_id: UUID,
+  pub trigger_name: String,
+  pub character_name: String,
+  pub fired_at: Timestamp,
+  pub line: String,
+  pub captures: MatchContext,
+  pub effect_errors: Vec<TriggerEffectError>,
+  /// Microseconds from when the reactor received the line until the Trigger matched it
+  pub latency_micros: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
+pub struct TriggerEffectError {
+  pub effect_id: UUID,
+  pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, ts_rs::TS)]
+pub struct TriggerStats {
+  pub trigger_id: UUID,
+  pub trigger_name: String,
+  pub fire_count: u32,
+  pub effect_error_count: u32,
+  pub last_fired_at: Option<Timestamp>,
+}
+
+/// Each line of the journal file is one of these
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "variant", content = "value")]
+enum JournalEntry {
+  Fired(Box<TriggerFiring>),
+  EffectFailed {
+    firing_id: UUID,
+    error: TriggerEffectError,
+  },
+}
+
+#[derive(Debug)]
+pub struct TriggerHistory {
+  firings: VecDeque<TriggerFiring>,
+  journal: Option<Journal>,
+}
+
+/// Sends entries to the thread that writes them to the journal file. Dropping it waits for the
+/// entries sent so far to be written.
+#[derive(Debug)]
+struct Journal {
+  tx: Option<mpsc::Sender<JournalEntry>>,
+  writer: Option<thread::JoinHandle<()>>,
+}
+
+struct JournalWriter {
+  path: PathBuf,
+  file: io::BufWriter<fs::File>,
+  len: u64,
+}
+
+impl TriggerHistory {
+  pub fn new(journal_path: Option<&Path>) -> Self {
+    let mut history = Self {
+      firings: VecDeque::with_capacity(TRIGGER_HISTORY_CAPACITY),
+      journal: None,
+    };
+    history.set_journal(journal_path);
+    history
+  }
+
+  /// Starts appending to the journal at the given path, or stops journaling if `None`
+  pub fn set_journal(&mut self, journal_path: Option<&Path>) {
+    self.journal = journal_path.and_then(|path| match Journal::open(path) {
+      Ok(journal) => Some(journal),
+      Err(e) => {
+        error!(
+          "Could not open trigger history journal {}! Error: {e:?}",
+          path.display()
+        );
+        None
+      }
+    });
+  }
+
+  pub fn record(&mut self, firing: TriggerFiring) {
+    if let Some(journal) = &self.journal {
+      journal.append(JournalEntry::Fired(Box::new(firing.clone())));
+    }
+    if self.firings.len() == TRIGGER_HISTORY_CAPACITY {
+      self.firings.pop_front();
+    }
+    self.firings.push_back(firing);
+  }
+
+  pub fn record_effect_error(&mut self, firing_id: &UUID, error: TriggerEffectError) {
+    if let Some(journal) = &self.journal {
+      journal.append(JournalEntry::EffectFailed {
+        firing_id: firing_id.clone(),
+        error: error.clone(),
+      });
+    }
+    if let Some(firing) = self.firings.iter_mut().rev().find(|f| &f.id == firing_id) {
+      firing.effect_errors.push(error);
+    }
+  }
+
+  /// Returns the most recent firings first, optionally only those of a single Trigger
+  pub fn recent(&self, trigger_id: Option<&UUID>, limit: usize) -> Vec<TriggerFiring> {
+    recent(self.firings.iter().rev(), trigger_id, limit)
+  }
+
+  pub fn stats(&self, index: &TriggerIndex) -> Vec<TriggerStats> {
+    stats(self.firings.iter(), index)
+  }
+}
+
+impl Journal {
+  fn open(path: &Path) -> io::Result<Self> {
+    let writer = JournalWriter::open(path)?;
+    let (tx, rx) = mpsc::channel::<JournalEntry>();
+    let writer = thread::Builder::new()
+      .name("LogQuest TriggerHistory journal".into())
+      .spawn(move || writer.run(rx))?;
+    Ok(Self {
+      tx: Some(tx),
+      writer: Some(writer),
+    })
+  }
+
+  fn append(&self, entry: JournalEntry) {
+    if let Some(tx) = &self.tx {
+      _ = tx.send(entry);
+    }
+  }
+}
+
+impl Drop for Journal {
+  fn drop(&mut self) {
+    drop(self.tx.take());
+    if let Some(writer) = self.writer.take() {
+      _ = writer.join();
+    }
+  }
+}
+
+impl JournalWriter {
+  fn open(path: &Path) -> io::Result<Self> {
+    let file = fs::OpenOptions::new()
+      .create(true)
+      .append(true)
+      .open(path)?;
+    let len = file.metadata()?.len();
+    Ok(Self {
+      path: path.to_owned(),
+      file: io::BufWriter::new(file),
+      len,
+    })
+  }
+
+  /// Writes entries as they arrive, and flushes whenever there are none waiting
+  fn run(mut self, rx: mpsc::Receiver<JournalEntry>) {
+    while let Ok(entry) = rx.recv() {
+      self.write(&entry);
+      while let Ok(entry) = rx.try_recv() {
+        self.write(&entry);
+      }
+      if let Err(e) = self.file.flush() {
+        self.log_error(e);
+      }
+      if self.len >= TRIGGER_HISTORY_JOURNAL_MAX_BYTES {
+        if let Err(e) = self.rotate() {
+          self.log_error(e);
+        }
+      }
+    }
+  }
+
+  fn write(&mut self, entry: &JournalEntry) {
+    let result = serde_json::to_string(entry)
+      .map_err(io::Error::from)
+      .and_then(|json| {
+        writeln!(self.file, "{json}")?;
+        Ok(json.len() as u64 + 1)
+      });
+    match result {
+      Ok(written) => self.len += written,
+      Err(e) => self.log_error(e),
+    }
+  }
+
+  fn rotate(&mut self) -> io::Result<()> {
+    let rotated_path = rotated_journal_path(&self.path);
+    // Renaming onto an existing file fails on Windows
+    match fs::remove_file(&rotated_path) {
+      Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+      _ => {}
+    }
+    fs::rename(&self.path, &rotated_path)?;
+    *self = Self::open(&self.path)?;
+    Ok(())
+  }
+
+  fn log_error(&self, e: io::Error) {
+    error!(
+      "Could not write to trigger history journal {}! Error: {e:?}",
+      self.path.display()
+    );
+  }
+}
+
+/// Where the journal is moved to when it is rotated
+fn rotated_journal_path(path: &Path) -> PathBuf {
+  let mut file_name = path.file_name().unwrap_or_default().to_owned();
+  file_name.push(".1");
+  path.with_file_name(file_name)
+}
+
+/// Reads every firing recorded in a journal file (and in the file it was last rotated to),
+/// oldest first. Lines that cannot be parsed (e.g. one that was only partially written) are
+/// skipped.
+pub fn read_journal(path: &Path) -> io::Result<Vec<TriggerFiring>> {
+  let file = fs::File::open(path)?;
+  let rotated = match fs::File::open(rotated_journal_path(path)) {
+    Ok(rotated) => Some(rotated),
+    Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+    Err(e) => return Err(e),
+  };
+  let mut firings: Vec<TriggerFiring> = Vec::new();
+  let mut positions: HashMap<UUID, usize> = HashMap::new();
+  let lines = rotated
+    .into_iter()
+    .chain(std::iter::once(file))
+    .flat_map(|file| io::BufReader::new(file).lines());
+  for line in lines {
+    match serde_json::from_str::<JournalEntry>(&line?) {
+      Ok(JournalEntry::Fired(firing)) => {
+        positions.insert(firing.id.clone(), firings.len());
+        firings.push(*firing);
+      }
+      Ok(JournalEntry::EffectFailed { firing_id, error }) => {
+        if let Some(position) = positions.get(&firing_id) {
+          firings[*position].effect_errors.push(error);
+        }
+      }
+      Err(_) => {}
+    }
+  }
+  Ok(firings)
+}
+
+fn recent<'a, I>(newest_first: I, trigger_id: Option<&UUID>, limit: usize) -> Vec<TriggerFiring>
+where
+  I: Iterator<Item = &'a TriggerFiring>,
+{
+  newest_first
+    .filter(|firing| trigger_id.is_none_or(|id| &firing.trigger_id == id))
+    .take(limit)
+    .cloned()
+    .collect()
+}
+
+/// Every Trigger in the index is included, so Triggers that never fired show up with a
+/// `fire_count` of zero. Firings of Triggers that have since been deleted are included too.
+/// The noisiest Triggers come first.
+pub fn stats<'a, I>(firings: I, index: &TriggerIndex) -> Vec<TriggerStats>
+where
+  I: Iterator<Item = &'a TriggerFiring>,
+{
+  let mut by_trigger: HashMap<UUID, TriggerStats> = index
+    .triggers
+    .values()
+    .map(|trigger| {
+      (
+        trigger.id.clone(),
+        TriggerStats {
+          trigger_id: trigger.id.clone(),
+          trigger_name: trigger.name.clone(),
+          fire_count: 0,
+          effect_error_count: 0,
+          last_fired_at: None,
+        },
+      )
+    })
+    .collect();
+
+  for firing in firings {
+    let entry = by_trigger
+      .entry(firing.trigger_id.clone())
+      .or_insert_with(|| TriggerStats {
+        trigger_id: firing.trigger_id.clone(),
+        trigger_name: firing.trigger_name.clone(),
+        fire_count: 0,
+        effect_error_count: 0,
+        last_fired_at: None,
+      });
+    entry.fire_count += 1;
+    entry.effect_error_count += firing.effect_errors.len() as u32;
+    if entry.last_fired_at.as_ref() < Some(&firing.fired_at) {
+      entry.last_fired_at = Some(firing.fired_at.clone());
+    }
+  }
+
+  let mut stats: Vec<TriggerStats> = by_trigger.into_values().collect();
+  stats.sort_by(|a, b| {
+    b.fire_count
+      .cmp(&a.fire_count)
+      .then_with(|| a.trigger_name.cmp(&b.trigger_name))
+  });
+  stats
+}
+
+/// This function is designed to be used from the CLI. It prints the journal to STDOUT.
+pub fn print_journal(journal_path: &Path, trigger_id: Option<&UUID>, limit: usize) {
+  let firings = match read_journal(journal_path) {
+    Ok(firings) => firings,
+    Err(e) if e.kind() == io::ErrorKind::NotFound => {
+      println!(
+        "No trigger history journal found at {}",
+        journal_path.display()
+      );
+      return;
+    }
+    Err(e) => fatal_error(format!(
+      "Could not read trigger history journal {}: {e}",
+      journal_path.display()
+    )),
+  };
+  for firing in recent(firings.iter().rev(), trigger_id, limit).iter().rev() {
+    println!(
+      "[{}] {} ({}) fired for {} in {}µs",
+      firing.fired_at,
+      firing.trigger_name,
+      firing.trigger_id,
+      firing.character_name,
+      firing.latency_micros
+    );
+    println!("  {}", firing.line);
+    for error in firing.effect_errors.iter() {
+      println!("  ERROR in effect {}: {}", error.effect_id, error.message);
+    }
+  }
+}
+
+/// This function is designed to be used from the CLI. It prints the stats to STDOUT.
+pub fn print_journal_stats(journal_path: &Path, index: &TriggerIndex) {
+  let firings = read_journal(journal_path).unwrap_or_default();
+  for stat in stats(firings.iter(), index).iter() {
+    let last_fired_at = match &stat.last_fired_at {
+      Some(timestamp) => timestamp.to_string(),
+      None => "never".to_owned(),
+    };
+    println!(
+      "{:>6} fired {:>4} errors  last: {last_fired_at}  {} ({})",
+      stat.fire_count, stat.effect_error_count, stat.trigger_name, stat.trigger_id
+    );
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{
+    read_journal, rotated_journal_path, JournalEntry, JournalWriter, TriggerEffectError,
+    TriggerFiring, TriggerHistory,
+  };
+  use crate::{
+    common::{random_id, timestamp::Timestamp, UUID},
+    matchers::MatchContext,
+  };
+
+  #[test]
+  fn test_trigger_history_journal() {
+    let journal_path = std::env::temp_dir().join(format!(
+      "trigger_history_{}.jsonl",
+      random_id(8).to_lowercase()
+    ));
+    let mut history = TriggerHistory::new(Some(&journal_path));
+
+    let noisy_id = UUID::new();
+    let quiet_id = UUID::new();
+    for trigger_id in [&noisy_id, &quiet_id, &noisy_id] {
+      history.record(sample_firing(trigger_id));
+    }
+    let failed_firing_id = history.recent(Some(&quiet_id), 1)[0].id.clone();
+    history.record_effect_error(
+      &failed_firing_id,
+      TriggerEffectError {
+        effect_id: UUID::new(),
+        message: "oops".into(),
+      },
+    );
+
+    assert_eq!(history.recent(Some(&noisy_id), 10).len(), 2);
+    assert_eq!(history.recent(None, 2).len(), 2);
+
+    drop(history);
+    let from_journal = read_journal(&journal_path).unwrap();
+    _ = std::fs::remove_file(&journal_path);
+    assert_eq!(from_journal.len(), 3);
+    assert_eq!(from_journal[1].id, failed_firing_id);
+    assert_eq!(from_journal[1].effect_errors[0].message, "oops");
+    assert!(from_journal[0].effect_errors.is_empty());
+  }
+
+  #[test]
+  fn test_trigger_history_journal_rotation() {
+    let journal_path = std::env::temp_dir().join(format!(
+      "trigger_history_{}.jsonl",
+      random_id(8).to_lowercase()
+    ));
+    let trigger_id = UUID::new();
+    let firings: Vec<TriggerFiring> = (0..3).map(|_| sample_firing(&trigger_id)).collect();
+
+    let mut writer = JournalWriter::open(&journal_path).unwrap();
+    writer.write(&JournalEntry::Fired(Box::new(firings[0].clone())));
+    writer.write(&JournalEntry::Fired(Box::new(firings[1].clone())));
+    writer.rotate().unwrap();
+    writer.write(&JournalEntry::Fired(Box::new(firings[2].clone())));
+    drop(writer);
+
+    let from_journal = read_journal(&journal_path).unwrap();
+    _ = std::fs::remove_file(rotated_journal_path(&journal_path));
+    _ = std::fs::remove_file(&journal_path);
+    let ids: Vec<&UUID> = from_journal.iter().map(|firing| &firing.id).collect();
+    assert_eq!(
+      ids,
+      firings.iter().map(|firing| &firing.id).collect::<Vec<_>>()
+    );
+  }
+
+  fn sample_firing(trigger_id: &UUID) -> TriggerFiring {
+    TriggerFiring {
+      id: UUID::new(),
+      trigger_id: trigger_id.clone(),
+      trigger_name: "Sample".into(),
+      character_name: "Xenk".into(),
+      fired_at: Timestamp::now(),
+      line: "You gain experience!!".into(),
+      captures: MatchContext::empty("Xenk"),
+      effect_errors: vec![],
+      latency_micros: 10,
+    }
+  }
+}
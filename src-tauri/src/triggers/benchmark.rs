@@ -0,0 +1,294 @@
+//! The benchmark runs every line of a log file through a set of Triggers to measure how fast
+//! they can be matched, and which Triggers and Matchers are the most expensive.
+use super::Trigger;
+use crate::{
+  common::UUID,
+  gina::regex::CheckTimings,
+  logs,
+  matchers::{LineToMatch, Matcher},
+  state::{active_triggers::ActiveTriggers, config::TriggerLoadError},
+};
+use std::{
+  cmp::Reverse,
+  fs, io,
+  path::Path,
+  time::{Duration, Instant},
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum BenchmarkError {
+  #[error("Could not load triggers")]
+  LoadTriggers(#[from] TriggerLoadError),
+  #[error("Could not read log file")]
+  ReadLogFile(#[from] io::Error),
+}
+
+#[derive(Debug)]
+pub struct BenchmarkReport {
+  pub line_count: usize,
+  pub trigger_count: usize,
+  /// Time spent matching every line against all Triggers the way the reactor does,
+  /// including the prefilter
+  pub matching_time: Duration,
+  pub triggers: Vec<TriggerCost>,
+  /// Every Matcher of every Trigger, most expensive first
+  pub matchers: Vec<MatcherCost>,
+}
+
+#[derive(Debug)]
+pub struct TriggerCost {
+  pub trigger_id: UUID,
+  pub trigger_name: String,
+  /// Lines that the prefilter did not rule out for this Trigger
+  pub candidate_count: usize,
+  pub match_count: usize,
+  /// Time spent checking this Trigger's Matchers against every line, without the prefilter
+  pub total: Duration,
+}
+
+#[derive(Debug)]
+pub struct MatcherCost {
+  pub trigger_name: String,
+  pub matcher_id: UUID,
+  pub pattern: String,
+  pub match_count: usize,
+  pub total: Duration,
+  pub slowest_line: Duration,
+  /// The number of numeric constraints (e.g. {N>=100}) in a GINA Matcher
+  pub condition_count: usize,
+  /// For GINA Matchers, the time spent in the regex and in the numeric constraints
+  pub gina_timings: Option<CheckTimings>,
+}
+
+impl BenchmarkReport {
+  pub fn lines_per_second(&self) -> f64 {
+    let seconds = self.matching_time.as_secs_f64();
+    if seconds == 0.0 {
+      0.0
+    } else {
+      self.line_count as f64 / seconds
+    }
+  }
+}
+
+impl MatcherCost {
+  fn new(trigger: &Trigger, matcher: &Matcher) -> Self {
+    Self {
+      trigger_name: trigger.name.clone(),
+      matcher_id: matcher.id().clone(),
+      pattern: matcher.raw_pattern().to_owned(),
+      match_count: 0,
+      total: Duration::ZERO,
+      slowest_line: Duration::ZERO,
+      condition_count: match matcher {
+        Matcher::GINA { pattern, .. } => pattern.condition_count(),
+        _ => 0,
+      },
+      gina_timings: match matcher {
+        Matcher::GINA { .. } => Some(CheckTimings::default()),
+        _ => None,
+      },
+    }
+  }
+
+  fn check(&mut self, matcher: &Matcher, line: &str, character_name: &str) -> bool {
+    let started_at = Instant::now();
+    let matched = matcher
+      .check_with_timings(
+        &LineToMatch::new(line, character_name, &[]),
+        self.gina_timings.as_mut(),
+      )
+      .is_some();
+    let elapsed = started_at.elapsed();
+    self.total += elapsed;
+    self.slowest_line = self.slowest_line.max(elapsed);
+    if matched {
+      self.match_count += 1;
+    }
+    matched
+  }
+}
+
+/// Reads the lines of a log file, skipping any that are not in the log file format
+pub fn read_log_lines(log_file_path: &Path) -> io::Result<Vec<String>> {
+  let file = fs::File::open(log_file_path)?;
+  logs::read_log_lines(io::BufReader::new(file))
+    .map(|line| line.map(|line| line.content))
+    .collect()
+}
+
+pub fn run(triggers: Vec<Trigger>, lines: &[String], character_name: &str) -> BenchmarkReport {
+  let trigger_count = triggers.len();
+  let active_triggers = ActiveTriggers::from_triggers(triggers);
+
+  // First, match the way the reactor does to measure throughput
+  let mut candidate_counts = vec![0; trigger_count];
+  let started_at = Instant::now();
+  for line in lines.iter() {
+    std::hint::black_box(active_triggers.check(line, character_name));
+  }
+  let matching_time = started_at.elapsed();
+  for line in lines.iter() {
+    for index in active_triggers.candidate_indices(line) {
+      candidate_counts[index] += 1;
+    }
+  }
+
+  // Then check every Matcher against every line on its own to find the expensive ones
+  let mut triggers: Vec<TriggerCost> = Vec::with_capacity(trigger_count);
+  let mut matchers: Vec<MatcherCost> = Vec::new();
+  for (index, candidate_count) in candidate_counts.into_iter().enumerate() {
+    let trigger = active_triggers.get(index);
+    let mut trigger_cost = TriggerCost {
+      trigger_id: trigger.id.clone(),
+      trigger_name: trigger.name.clone(),
+      candidate_count,
+      match_count: 0,
+      total: Duration::ZERO,
+    };
+    let mut matcher_costs: Vec<MatcherCost> = trigger
+      .filter
+      .matchers()
+      .iter()
+      .map(|matcher| MatcherCost::new(trigger, matcher))
+      .collect();
+    for line in lines.iter() {
+      // Like Filter::check, stop at the first Matcher that matches
+      let matched = trigger
+        .filter
+        .matchers()
+        .iter()
+        .zip(matcher_costs.iter_mut())
+        .any(|(matcher, cost)| cost.check(matcher, line, character_name));
+      if matched {
+        trigger_cost.match_count += 1;
+      }
+    }
+    trigger_cost.total = matcher_costs.iter().map(|cost| cost.total).sum();
+    triggers.push(trigger_cost);
+    matchers.extend(matcher_costs);
+  }
+
+  triggers.sort_by_key(|cost| Reverse(cost.total));
+  matchers.sort_by_key(|cost| Reverse(cost.total));
+
+  BenchmarkReport {
+    line_count: lines.len(),
+    trigger_count,
+    matching_time,
+    triggers,
+    matchers,
+  }
+}
+
+/// This function is designed to be used from the CLI. It prints the report to STDOUT.
+pub fn print_report(report: &BenchmarkReport, top: usize) {
+  println!(
+    "Matched {} lines against {} triggers in {:.3}s ({:.0} lines/sec)",
+    report.line_count,
+    report.trigger_count,
+    report.matching_time.as_secs_f64(),
+    report.lines_per_second()
+  );
+
+  println!("\nMost expensive triggers (without the prefilter):");
+  for cost in report.triggers.iter().take(top) {
+    println!(
+      "{:>10.3}ms  {:>7} candidate lines  {:>6} matches  {} ({})",
+      cost.total.as_secs_f64() * 1000.0,
+      cost.candidate_count,
+      cost.match_count,
+      cost.trigger_name,
+      cost.trigger_id,
+    );
+  }
+
+  println!("\nSlowest patterns:");
+  for cost in report.matchers.iter().take(top) {
+    let per_line_micros = if report.line_count == 0 {
+      0.0
+    } else {
+      cost.total.as_secs_f64() * 1_000_000.0 / report.line_count as f64
+    };
+    println!(
+      "{:>10.3}ms  {per_line_micros:>8.2}µs/line  slowest {:>8.2}µs  {} ({})",
+      cost.total.as_secs_f64() * 1000.0,
+      cost.slowest_line.as_secs_f64() * 1_000_000.0,
+      cost.trigger_name,
+      cost.matcher_id,
+    );
+    println!("    {}", cost.pattern);
+    if let Some(timings) = &cost.gina_timings {
+      println!(
+        "    regex {:.3}ms, {} numeric conditions {:.3}ms",
+        timings.regex.as_secs_f64() * 1000.0,
+        cost.condition_count,
+        timings.conditions.as_secs_f64() * 1000.0,
+      );
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::run;
+  use crate::{
+    common::{timestamp::Timestamp, UUID},
+    matchers::Matcher,
+    triggers::Trigger,
+  };
+
+  #[test]
+  fn test_benchmark_report() {
+    let damage = sample_trigger("Damage", r"^You have taken {N>=100} points of damage\.$");
+    let tell = sample_trigger("Tell", r"^{S} tells you, '(.+)'$");
+    let lines: Vec<String> = [
+      "You have taken 150 points of damage.",
+      "You have taken 50 points of damage.",
+      "Soandso tells you, 'hi'",
+      "You gain experience!!",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect();
+
+    let report = run(vec![damage.clone(), tell.clone()], &lines, "Xenk");
+    assert_eq!(report.line_count, 4);
+    assert_eq!(report.trigger_count, 2);
+    assert_eq!(report.matchers.len(), 2);
+
+    let damage_cost = report
+      .triggers
+      .iter()
+      .find(|cost| cost.trigger_id == damage.id)
+      .unwrap();
+    assert_eq!(damage_cost.candidate_count, 2);
+    assert_eq!(damage_cost.match_count, 1);
+
+    let tell_cost = report
+      .triggers
+      .iter()
+      .find(|cost| cost.trigger_id == tell.id)
+      .unwrap();
+    assert_eq!(tell_cost.candidate_count, 1);
+    assert_eq!(tell_cost.match_count, 1);
+
+    assert!(report
+      .matchers
+      .iter()
+      .all(|cost| cost.gina_timings.is_some()));
+  }
+
+  fn sample_trigger(name: &str, pattern: &str) -> Trigger {
+    Trigger {
+      id: UUID::new(),
+      parent_id: None,
+      name: name.into(),
+      comment: None,
+      filter: vec![Matcher::gina(pattern).unwrap()].into(),
+      effects: vec![],
+      created_at: Timestamp::now(),
+      updated_at: Timestamp::now(),
+    }
+  }
+}
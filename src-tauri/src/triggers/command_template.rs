@@ -51,7 +51,7 @@ impl CommandTemplate {
     let formatted_input = self
       .write_to_stdin
       .as_ref()
-      .map_or_else(|| String::new(), |tmpl| format!("\n\n{}", tmpl.tmpl()));
+      .map_or_else(String::new, |tmpl| format!("\n\n{}", tmpl.tmpl()));
 
     format!("{}\n\n{formatted_params}{formatted_input}", self.command)
   }
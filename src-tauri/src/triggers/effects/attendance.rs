@@ -0,0 +1,27 @@
+use super::{EffectError, EffectResult, ReadyEffect};
+use crate::{
+  dry_run::RecordedOutput,
+  reactor::{EventContext, ReactorEvent},
+  triggers::template_string::TemplateString,
+};
+use async_trait::async_trait;
+use std::sync::Arc;
+
+/// The template is rendered into the label of the snapshot
+pub struct TakeAttendanceSnapshotEffect(pub(super) TemplateString);
+
+#[async_trait]
+impl ReadyEffect for TakeAttendanceSnapshotEffect {
+  async fn fire(self: Box<Self>, context: Arc<EventContext>) -> EffectResult {
+    let label = context.render(&self.0);
+    if let Some(recorder) = &context.dry_run {
+      recorder.record(RecordedOutput::TakeAttendanceSnapshot(label));
+      return Ok(());
+    }
+    context
+      .reactor_tx
+      .send(ReactorEvent::TakeAttendanceSnapshot(label))
+      .await
+      .map_err(|_| EffectError::ReactorChannelClosed)
+  }
+}
@@ -8,7 +8,7 @@ pub struct CopyToClipboardEffect(pub(super) TemplateString);
 #[async_trait]
 impl ReadyEffect for CopyToClipboardEffect {
   async fn fire(self: Box<Self>, context: Arc<EventContext>) -> EffectResult {
-    let text = self.0.render(&context.match_context);
+    let text = context.render(&self.0);
     context.clipboard.write_text(&text).await;
     Ok(())
   }
@@ -1,3 +1,4 @@
+mod attendance;
 mod clipboard;
 mod nothing;
 mod overlay_message;
@@ -11,7 +12,7 @@ mod sys_cmd;
 mod timer_effects;
 
 use super::command_template::{CommandTemplate, CommandTemplateSecurityCheck};
-use super::timers::{Stopwatch, Timer, TimerEffect};
+use super::timers::{Stopwatch, Timer, TimerEffect, TimerStartPolicy};
 use super::TemplateString;
 use crate::audio::PlayAudioFileError;
 use crate::common::UUID;
@@ -23,6 +24,7 @@ use std::sync::Arc;
 use tokio::sync::mpsc;
 use tracing::error;
 
+use attendance::TakeAttendanceSnapshotEffect;
 use clipboard::CopyToClipboardEffect;
 use nothing::DoNothingEffect;
 use overlay_message::OverlayMessageEffect;
@@ -58,6 +60,56 @@ impl EffectWithID {
       effect: self.effect.security_check(),
     }
   }
+
+  /// Calls `f` with every TemplateString in this Effect and the Effects nested in it, along
+  /// with the ID of the Effect that contains the TemplateString
+  pub fn each_template<F>(&self, f: &mut F)
+  where
+    F: FnMut(&UUID, &TemplateString),
+  {
+    match &self.effect {
+      Effect::Parallel(effects) | Effect::Sequence(effects) => {
+        effects.iter().for_each(|effect| effect.each_template(f))
+      }
+      Effect::PlayAudioFile(Some(tmpl))
+      | Effect::CopyToClipboard(tmpl)
+      | Effect::TakeAttendanceSnapshot(tmpl)
+      | Effect::OverlayMessage(tmpl)
+      | Effect::Speak { tmpl, .. } => f(&self.id, tmpl),
+      Effect::StartTimer(timer) => {
+        f(&self.id, &timer.name_tmpl);
+        if let TimerStartPolicy::StartAndReplacesAnyTimerOfTriggerWithNameTemplateMatching(tmpl) =
+          &timer.start_policy
+        {
+          f(&self.id, tmpl);
+        }
+        timer
+          .effects
+          .iter()
+          .for_each(|effect| effect.each_template(f));
+      }
+      Effect::StartStopwatch(stopwatch) => {
+        f(&self.id, &stopwatch.name);
+        stopwatch
+          .effects
+          .iter()
+          .for_each(|effect| effect.each_template(f));
+      }
+      Effect::RunSystemCommand(
+        CommandTemplateSecurityCheck::Approved(_, cmd_tmpl)
+        | CommandTemplateSecurityCheck::Unapproved(cmd_tmpl),
+      ) => {
+        for tmpl in cmd_tmpl.params.iter().chain(cmd_tmpl.write_to_stdin.iter()) {
+          f(&self.id, tmpl);
+        }
+      }
+      Effect::PlayAudioFile(None)
+      | Effect::ScopedTimerEffect(_)
+      | Effect::Pause(_)
+      | Effect::SpeakStop
+      | Effect::DoNothing => {}
+    }
+  }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ts_rs::TS)]
@@ -71,6 +123,8 @@ pub enum Effect {
   /// importing to allow the user to select a file during/after import.
   PlayAudioFile(Option<TemplateString>),
   CopyToClipboard(TemplateString),
+  /// Saves the most recent `/who` listing in the log, labeled with the rendered template
+  TakeAttendanceSnapshot(TemplateString),
   OverlayMessage(TemplateString),
   StartTimer(Timer),
   StartStopwatch(Stopwatch),
@@ -120,6 +174,9 @@ pub enum EffectError {
   #[error("Encountered a TimerEffect created incorrectly! No TimerContext")]
   TimerEffectWithoutTimerContext,
 
+  #[error("The reactor is not running")]
+  ReactorChannelClosed,
+
   #[error("Tried to send a message to a dead Timer")]
   TimerCommandChannelClosedError(#[from] mpsc::error::SendError<TimerCommand>),
 }
@@ -149,6 +206,7 @@ impl Effect {
       Self::StartTimer(timer) => Box::new(StartTimerEffect(timer)),
       Self::OverlayMessage(tmpl) => Box::new(OverlayMessageEffect(tmpl)),
       Self::CopyToClipboard(tmpl) => Box::new(CopyToClipboardEffect(tmpl)),
+      Self::TakeAttendanceSnapshot(tmpl) => Box::new(TakeAttendanceSnapshotEffect(tmpl)),
       Self::PlayAudioFile(None) => Box::new(DoNothingEffect),
       Self::PlayAudioFile(Some(tmpl)) => Box::new(PlayAudioFileEffect(tmpl)),
       Self::SpeakStop => Box::new(SpeakStopEffect),
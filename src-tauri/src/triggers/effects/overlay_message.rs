@@ -9,7 +9,7 @@ pub(super) struct OverlayMessageEffect(pub(super) TemplateString);
 #[async_trait]
 impl ReadyEffect for OverlayMessageEffect {
   async fn fire(self: Box<Self>, context: Arc<EventContext>) -> EffectResult {
-    let message = self.0.render(&context.match_context);
+    let message = context.render(&self.0);
     info!(r#"TriggerEffect::OverlayMessage("{message}")"#);
     context.overlay_manager.message(message);
     Ok(())
@@ -3,7 +3,6 @@ use crate::reactor::EventContext;
 use async_trait::async_trait;
 use futures::future::join_all;
 use std::sync::Arc;
-use tauri::async_runtime::{spawn, JoinHandle};
 
 pub(super) struct EffectParallel(pub(super) Vec<Box<dyn ReadyEffect>>);
 
@@ -12,22 +11,19 @@ impl ReadyEffect for EffectParallel {
   async fn fire(self: Box<Self>, context: Arc<EventContext>) -> EffectResult {
     let contexts = (0..self.0.len()).map(|_| context.clone());
 
-    let join_handles: Vec<JoinHandle<EffectResult>> = self
-      .0
-      .into_iter()
-      .zip(contexts)
-      .map(|(effect, ctx)| spawn(async move { effect.fire(ctx).await }))
-      .collect();
-
-    let errors = join_all(join_handles)
-      .await
-      .into_iter()
-      .filter_map(|tauri_error| match tauri_error {
-        Ok(Ok(())) => None,
-        Ok(Err(effect_error)) => Some(effect_error),
-        Err(tauri_error) => Some(tauri_error.into()),
-      })
-      .collect::<Vec<EffectError>>();
+    // The Effects run concurrently in this task rather than being spawned, so that they stay on
+    // the runtime that a dry run drives
+    let errors = join_all(
+      self
+        .0
+        .into_iter()
+        .zip(contexts)
+        .map(|(effect, ctx)| effect.fire(ctx)),
+    )
+    .await
+    .into_iter()
+    .filter_map(Result::err)
+    .collect::<Vec<EffectError>>();
 
     match errors.len() {
       0 => Ok(()),
@@ -8,8 +8,8 @@ pub(super) struct PauseEffect(pub(super) common::duration::Duration);
 
 #[async_trait]
 impl ReadyEffect for PauseEffect {
-  async fn fire(self: Box<Self>, _context: Arc<EventContext>) -> EffectResult {
-    tokio::time::sleep(self.0.into()).await;
+  async fn fire(self: Box<Self>, context: Arc<EventContext>) -> EffectResult {
+    context.clock.sleep(self.0.into()).await;
     Ok(())
   }
 }
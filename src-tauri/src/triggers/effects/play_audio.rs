@@ -8,7 +8,7 @@ pub(super) struct PlayAudioFileEffect(pub(super) TemplateString);
 #[async_trait]
 impl ReadyEffect for PlayAudioFileEffect {
   async fn fire(self: Box<Self>, context: Arc<EventContext>) -> EffectResult {
-    let file_path = self.0.render(&context.match_context);
+    let file_path = context.render(&self.0);
     context
       .mixer
       .play_file(&file_path)
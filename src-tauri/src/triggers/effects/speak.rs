@@ -16,11 +16,11 @@ pub(super) struct SpeakStopEffect;
 #[async_trait]
 impl ReadyEffect for SpeakEffect {
   async fn fire(self: Box<Self>, context: Arc<EventContext>) -> EffectResult {
-    let message = self.tmpl.render(&context.match_context);
+    let message = context.render(&self.tmpl);
 
     let (tx_done, rx_done) = oneshot::channel::<()>();
 
-    if let Err(_) = context
+    if context
       .t2s_tx
       .send(TTS::Speak {
         text: message.clone(),
@@ -28,6 +28,7 @@ impl ReadyEffect for SpeakEffect {
         tx_done: Arc::new(tx_done),
       })
       .await
+      .is_err()
     {
       error!(r#"Text-to-Speech channel closed! Ignoring TTS message: "{message}""#);
     }
@@ -1,6 +1,6 @@
 use super::{EffectError, EffectResult, ReadyEffect};
 use crate::{
-  common::bytes_to_utf8_with_escaped_special_chars, reactor::EventContext,
+  common::bytes_to_utf8_with_escaped_special_chars, dry_run::RecordedOutput, reactor::EventContext,
   triggers::command_template::CommandTemplateSecurityCheck,
 };
 use async_trait::async_trait;
@@ -35,7 +35,7 @@ impl ReadyEffect for SystemCommandEffect {
     let args: Vec<String> = command_template
       .params
       .into_iter()
-      .map(|p| p.render(&context.match_context))
+      .map(|p| context.render(&p))
       .collect();
 
     let formatted_command: String = std::iter::once(command_name.clone())
@@ -45,7 +45,16 @@ impl ReadyEffect for SystemCommandEffect {
 
     let write_to_stdin = command_template
       .write_to_stdin
-      .map(|tmpl| tmpl.render(&context.match_context));
+      .map(|tmpl| context.render(&tmpl));
+
+    if let Some(recorder) = &context.dry_run {
+      recorder.record(RecordedOutput::RunSystemCommand {
+        command: command_name,
+        params: args,
+        write_to_stdin,
+      });
+      return Ok(());
+    }
 
     let mut command = Command::new(command_name);
 
@@ -55,14 +64,12 @@ impl ReadyEffect for SystemCommandEffect {
     command.stdin(
       write_to_stdin
         .as_ref()
-        .map_or_else(|| Stdio::null(), |_| Stdio::piped()),
+        .map_or_else(Stdio::null, |_| Stdio::piped()),
     );
 
     info!("SystemCommandEffect executing: `{formatted_command}`");
 
-    let mut subprocess = command
-      .spawn()
-      .map_err(|e| EffectError::CommandIOError(e))?;
+    let mut subprocess = command.spawn().map_err(EffectError::CommandIOError)?;
 
     let finish_subprocess = async move {
       if let Some(input) = write_to_stdin {
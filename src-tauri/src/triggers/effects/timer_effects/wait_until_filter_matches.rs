@@ -3,15 +3,15 @@ use super::{
   try_get_timer_context,
 };
 use crate::{
-  logs::log_line_stream::LogLineStream, matchers::FilterWithContext, reactor::EventContext,
+  common::clock::Deadline,
+  logs::log_line_stream::LogLineStream,
+  matchers::{FilterWithContext, LineToMatch},
+  reactor::EventContext,
 };
 use async_trait::async_trait;
 use futures::StreamExt;
 use std::sync::Arc;
-use tokio::{
-  select,
-  time::{sleep_until, Instant},
-};
+use tokio::select;
 
 pub struct WaitUntilFilterMatchesTimerEffect(
   pub FilterWithContext,
@@ -23,26 +23,35 @@ impl ReadyEffect for WaitUntilFilterMatchesTimerEffect {
   async fn fire(self: Box<Self>, context: Arc<EventContext>) -> EffectResult {
     let timer_context = try_get_timer_context(&context)?;
 
-    let subscription = context.tx_log_file_events.subscribe();
-    let mut stream = LogLineStream::create(&context.cursor_after, subscription).await?;
+    let mut stream = match &context.dry_run {
+      Some(recorder) => recorder.lines().boxed(),
+      None => {
+        let subscription = context.tx_log_file_events.subscribe();
+        LogLineStream::create(&context.cursor_after, subscription)
+          .await?
+          .map(|(line, _cursor_after)| line)
+          .boxed()
+      }
+    };
 
     let filter = self.0.compile_with_context(&context.match_context);
 
-    let timeout_instant_maybe: Option<Instant> = self.1.map(|d| Instant::now() + d.into());
+    let timeout_maybe: Option<Deadline> = self.1.map(|d| context.clock.deadline_after(d.into()));
 
     loop {
       select! {
         () = async {
-          sleep_until(timeout_instant_maybe.unwrap()).await // unwrap is infallible here due to is_some() check
-        }, if timeout_instant_maybe.is_some() => break,
+          context.clock.sleep_until_deadline(timeout_maybe.as_ref().unwrap()).await // unwrap is infallible here due to is_some() check
+        }, if timeout_maybe.is_some() => break,
 
         () = timer_context.finished() => break,
 
         next = stream.next() => match next {
           None => break,
-          Some((line, _cursor_after)) => {
+          Some(line) => {
             let character_name = &context.match_context.character_name;
-            if filter.check(&line.content, character_name).is_some() {
+            let line = LineToMatch::new(&line.content, character_name, &context.boxed_set);
+            if filter.check_line(&line).is_some() {
               break;
             }
           }
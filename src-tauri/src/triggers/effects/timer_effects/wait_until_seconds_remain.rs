@@ -2,10 +2,10 @@ use super::{
   super::{EffectResult, ReadyEffect},
   try_get_timer_context,
 };
-use crate::{common::timestamp::Timestamp, reactor::EventContext};
+use crate::reactor::EventContext;
 use async_trait::async_trait;
 use std::sync::Arc;
-use tokio::{select, time::Instant};
+use tokio::select;
 
 pub struct WaitUntilSecondsRemainEffect(pub u32);
 
@@ -18,17 +18,17 @@ impl ReadyEffect for WaitUntilSecondsRemainEffect {
 
     let mut end_time_observer = timer_context.end_time.clone();
 
-    let mut wait_until = instant_from_future_timestamp(&end_time_observer.get()) - duration_offset;
+    let mut wait_until = &*end_time_observer.get() - duration_offset;
 
     loop {
       select! {
-        () = tokio::time::sleep_until(wait_until) => break,
+        () = context.clock.sleep_until(&wait_until) => break,
 
         () = timer_context.finished() => break,
 
         change = end_time_observer.changed() => match change {
           Ok(()) => {
-            wait_until = instant_from_future_timestamp(&end_time_observer.get()) - duration_offset;
+            wait_until = &*end_time_observer.get() - duration_offset;
           }
           Err(_recv_error) => break
         }
@@ -38,7 +38,3 @@ impl ReadyEffect for WaitUntilSecondsRemainEffect {
     Ok(())
   }
 }
-
-fn instant_from_future_timestamp(future_timestamp: &Timestamp) -> Instant {
-  Instant::now() + Timestamp::now().duration_until(future_timestamp)
-}
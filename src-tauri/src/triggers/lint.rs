@@ -0,0 +1,452 @@
+//! Static checks over a `TriggerIndex` that find problems which would otherwise only
+//! surface at runtime (or would silently do nothing at all).
+use super::command_template::{CommandTemplate, CommandTemplateSecurityCheck};
+use super::effects::{Effect, EffectWithID};
+use super::template_string::TemplateString;
+use super::timers::{TimerEffect, TimerStartPolicy};
+use super::trigger_index::TriggerIndex;
+use super::Trigger;
+use crate::common::UUID;
+use crate::gina::regex::RegexGINA;
+use crate::matchers::{Matcher, MatcherWithContext, BUILT_IN_VARIABLE_NAMES};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::mem::{discriminant, Discriminant};
+use std::path::Path;
+
+#[derive(Debug, Clone, Serialize, ts_rs::TS)]
+pub struct TriggerLintIssue {
+  pub trigger_id: UUID,
+  pub trigger_name: String,
+  /// The Effect the problem was found in, if the problem is not with the Trigger itself
+  pub effect_id: Option<UUID>,
+  pub problem: TriggerLintProblem,
+}
+
+#[derive(Debug, Clone, Serialize, ts_rs::TS)]
+#[serde(tag = "variant", content = "value")]
+#[ts(tag = "variant", content = "value")]
+pub enum TriggerLintProblem {
+  /// GINA exports do not include sound file paths, so imported PlayAudioFile effects start out empty
+  AudioFileNotSelected,
+  AudioFileNotFound(String),
+  /// This would fail at runtime with `EffectError::TimerEffectWithoutTimerContext`
+  ScopedTimerEffectOutsideTimer(TimerEffect),
+  UndefinedTemplateVariable {
+    template: String,
+    variable: String,
+  },
+  /// Only possible in Triggers that were not saved through the editor, e.g. edited by hand
+  InvalidTemplate {
+    template: String,
+    message: String,
+  },
+  /// An unquoted default like `${1|uper}` is most likely a misspelled pipe
+  DefaultResemblesPipe {
+    template: String,
+    default: String,
+    pipe: String,
+  },
+  UnapprovedCommand(CommandTemplate),
+  NotInAnyTriggerTag,
+  NotInActiveTriggerTag,
+  DuplicateFilter {
+    other_trigger_id: UUID,
+    other_trigger_name: String,
+  },
+}
+
+type FilterKey<'a> = Vec<(Discriminant<Matcher>, &'a str)>;
+
+/// Checks every Trigger in the index. If `active_trigger_tags` is `None`, Triggers are only
+/// checked for belonging to any TriggerTag at all, since it's unknown which tags are active.
+pub fn lint(
+  index: &TriggerIndex,
+  active_trigger_tags: Option<&HashSet<UUID>>,
+) -> Vec<TriggerLintIssue> {
+  let mut triggers: Vec<&Trigger> = index.triggers.values().collect();
+  triggers.sort_by(|a, b| a.name.cmp(&b.name));
+
+  let mut issues: Vec<TriggerLintIssue> = Vec::new();
+  let mut triggers_by_filter: HashMap<FilterKey<'_>, Vec<&Trigger>> = HashMap::new();
+
+  for trigger in triggers.iter() {
+    let mut linter = TriggerLinter::new(trigger);
+    linter.lint_effects(&trigger.effects, false);
+    linter.lint_trigger_tags(index, active_trigger_tags);
+    issues.append(&mut linter.issues);
+
+    if !trigger.filter.matchers().is_empty() {
+      triggers_by_filter
+        .entry(filter_key(trigger))
+        .or_default()
+        .push(trigger);
+    }
+  }
+
+  for duplicates in triggers_by_filter.values().filter(|ts| ts.len() > 1) {
+    for (index, trigger) in duplicates.iter().enumerate() {
+      let other = duplicates[if index == 0 { 1 } else { 0 }];
+      issues.push(TriggerLintIssue::new(
+        trigger,
+        None,
+        TriggerLintProblem::DuplicateFilter {
+          other_trigger_id: other.id.clone(),
+          other_trigger_name: other.name.clone(),
+        },
+      ));
+    }
+  }
+
+  issues
+}
+
+/// This function is designed to be used from the CLI. It prints the report to STDOUT and
+/// exits with status code 1 if any problems were found.
+pub fn print_report(issues: &[TriggerLintIssue]) -> ! {
+  if issues.is_empty() {
+    println!("No problems found");
+    std::process::exit(0);
+  }
+  for issue in issues.iter() {
+    println!(
+      "[{}] ({}) {}",
+      issue.trigger_name, issue.trigger_id, issue.problem
+    );
+  }
+  println!("\nFound {} problem(s)", issues.len());
+  std::process::exit(1)
+}
+
+struct TriggerLinter<'a> {
+  trigger: &'a Trigger,
+  issues: Vec<TriggerLintIssue>,
+}
+
+impl<'a> TriggerLinter<'a> {
+  fn new(trigger: &'a Trigger) -> Self {
+    Self {
+      trigger,
+      issues: Vec::new(),
+    }
+  }
+
+  fn report(&mut self, effect_id: Option<&UUID>, problem: TriggerLintProblem) {
+    self.issues.push(TriggerLintIssue::new(
+      self.trigger,
+      effect_id.cloned(),
+      problem,
+    ));
+  }
+
+  fn lint_effects(&mut self, effects: &[EffectWithID], within_timer: bool) {
+    for effect in effects.iter() {
+      self.lint_effect(effect, within_timer);
+    }
+  }
+
+  fn lint_effect(&mut self, effect_with_id: &EffectWithID, within_timer: bool) {
+    let id = &effect_with_id.id;
+    match &effect_with_id.effect {
+      Effect::Parallel(effects) | Effect::Sequence(effects) => {
+        self.lint_effects(effects, within_timer);
+      }
+      Effect::PlayAudioFile(None) => {
+        self.report(Some(id), TriggerLintProblem::AudioFileNotSelected);
+      }
+      Effect::PlayAudioFile(Some(tmpl)) => {
+        self.lint_template(id, tmpl);
+        if tmpl.variable_names().is_empty() && !Path::new(tmpl.tmpl()).is_file() {
+          self.report(
+            Some(id),
+            TriggerLintProblem::AudioFileNotFound(tmpl.tmpl().to_owned()),
+          );
+        }
+      }
+      Effect::CopyToClipboard(tmpl)
+      | Effect::TakeAttendanceSnapshot(tmpl)
+      | Effect::OverlayMessage(tmpl)
+      | Effect::Speak { tmpl, .. } => {
+        self.lint_template(id, tmpl);
+      }
+      Effect::StartTimer(timer) => {
+        self.lint_template(id, &timer.name_tmpl);
+        if let TimerStartPolicy::StartAndReplacesAnyTimerOfTriggerWithNameTemplateMatching(tmpl) =
+          &timer.start_policy
+        {
+          self.lint_template(id, tmpl);
+        }
+        self.lint_effects(&timer.effects, true);
+      }
+      Effect::StartStopwatch(stopwatch) => {
+        self.lint_template(id, &stopwatch.name);
+        self.lint_effects(&stopwatch.effects, true);
+      }
+      Effect::RunSystemCommand(cmd_tmpl_sec_check) => {
+        let cmd_tmpl = match cmd_tmpl_sec_check {
+          CommandTemplateSecurityCheck::Approved(_, cmd_tmpl) => cmd_tmpl,
+          CommandTemplateSecurityCheck::Unapproved(cmd_tmpl) => {
+            self.report(
+              Some(id),
+              TriggerLintProblem::UnapprovedCommand(cmd_tmpl.clone()),
+            );
+            cmd_tmpl
+          }
+        };
+        for tmpl in cmd_tmpl.params.iter().chain(cmd_tmpl.write_to_stdin.iter()) {
+          self.lint_template(id, tmpl);
+        }
+      }
+      Effect::ScopedTimerEffect(timer_effect) => {
+        if !within_timer {
+          self.report(
+            Some(id),
+            TriggerLintProblem::ScopedTimerEffectOutsideTimer(timer_effect.clone()),
+          );
+        }
+        if let TimerEffect::WaitUntilFilterMatches(filter, _) = timer_effect {
+          for matcher in filter.matchers().iter() {
+            if let MatcherWithContext::GINA { pattern, .. } = matcher {
+              for variable in RegexGINA::context_references(pattern) {
+                self.lint_variable(id, pattern, &variable);
+              }
+            }
+          }
+        }
+      }
+      Effect::Pause(_) | Effect::SpeakStop | Effect::DoNothing => {}
+    }
+  }
+
+  fn lint_template(&mut self, effect_id: &UUID, tmpl: &TemplateString) {
+    if let Err(e) = tmpl.validate() {
+      self.report(
+        Some(effect_id),
+        TriggerLintProblem::InvalidTemplate {
+          template: tmpl.tmpl().to_owned(),
+          message: e.to_string(),
+        },
+      );
+    }
+    for (default, pipe) in tmpl.defaults_resembling_pipes() {
+      self.report(
+        Some(effect_id),
+        TriggerLintProblem::DefaultResemblesPipe {
+          template: tmpl.tmpl().to_owned(),
+          default,
+          pipe: pipe.to_owned(),
+        },
+      );
+    }
+    // Expressions with a default are allowed to reference variables that might not be defined
+    for variable in tmpl.required_variable_names() {
+      self.lint_variable(effect_id, tmpl.tmpl(), &variable);
+    }
+  }
+
+  /// A variable is only considered defined if every Matcher of the Trigger's Filter defines it,
+  /// since any one of them could be the Matcher that produces the MatchContext. Built-in
+  /// variables are not checked because they do not come from the Filter.
+  fn lint_variable(&mut self, effect_id: &UUID, template: &str, variable: &str) {
+    if is_built_in_variable(variable) {
+      return;
+    }
+    let defined = self
+      .trigger
+      .filter
+      .matchers()
+      .iter()
+      .all(|matcher| matcher.defines_variable(variable));
+    if !defined {
+      self.report(
+        Some(effect_id),
+        TriggerLintProblem::UndefinedTemplateVariable {
+          template: template.to_owned(),
+          variable: variable.to_owned(),
+        },
+      );
+    }
+  }
+
+  fn lint_trigger_tags(
+    &mut self,
+    index: &TriggerIndex,
+    active_trigger_tags: Option<&HashSet<UUID>>,
+  ) {
+    let trigger_tags = index.trigger_tags_with_trigger(&self.trigger.id);
+    if trigger_tags.is_empty() {
+      self.report(None, TriggerLintProblem::NotInAnyTriggerTag);
+    } else if let Some(active_trigger_tags) = active_trigger_tags {
+      if !trigger_tags
+        .iter()
+        .any(|tag| active_trigger_tags.contains(&tag.id))
+      {
+        self.report(None, TriggerLintProblem::NotInActiveTriggerTag);
+      }
+    }
+  }
+}
+
+impl TriggerLintIssue {
+  fn new(trigger: &Trigger, effect_id: Option<UUID>, problem: TriggerLintProblem) -> Self {
+    Self {
+      trigger_id: trigger.id.clone(),
+      trigger_name: trigger.name.clone(),
+      effect_id,
+      problem,
+    }
+  }
+}
+
+impl std::fmt::Display for TriggerLintProblem {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::AudioFileNotSelected => write!(f, "Play Audio File effect has no file selected"),
+      Self::AudioFileNotFound(path) => write!(f, "Audio file does not exist: {path}"),
+      Self::ScopedTimerEffectOutsideTimer(timer_effect) => write!(
+        f,
+        "Timer effect {timer_effect:?} is used outside of a Timer's effects"
+      ),
+      Self::UndefinedTemplateVariable { template, variable } => write!(
+        f,
+        "Template `{template}` references ${{{variable}}}, which the filter does not capture"
+      ),
+      Self::InvalidTemplate { template, message } => {
+        write!(f, "Template `{template}` is invalid: {message}")
+      }
+      Self::DefaultResemblesPipe {
+        template,
+        default,
+        pipe,
+      } => write!(
+        f,
+        "Template `{template}` uses `{default}` as a default, but it looks like the `{pipe}` pipe (quote it if it is meant to be a default)"
+      ),
+      Self::UnapprovedCommand(cmd_tmpl) => write!(
+        f,
+        "System command `{}` has not been approved",
+        cmd_tmpl.command
+      ),
+      Self::NotInAnyTriggerTag => write!(f, "Trigger is not in any Trigger Tag"),
+      Self::NotInActiveTriggerTag => write!(f, "Trigger is not in any active Trigger Tag"),
+      Self::DuplicateFilter {
+        other_trigger_id,
+        other_trigger_name,
+      } => write!(
+        f,
+        "Filter is identical to the filter of Trigger `{other_trigger_name}` ({other_trigger_id})"
+      ),
+    }
+  }
+}
+
+fn is_built_in_variable(variable: &str) -> bool {
+  BUILT_IN_VARIABLE_NAMES
+    .iter()
+    .any(|name| name.eq_ignore_ascii_case(variable))
+}
+
+fn filter_key(trigger: &Trigger) -> FilterKey<'_> {
+  trigger
+    .filter
+    .matchers()
+    .iter()
+    .map(|matcher| (discriminant(matcher), matcher.raw_pattern()))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{lint, TriggerLintProblem};
+  use crate::{
+    common::{timestamp::Timestamp, UUID},
+    matchers::Matcher,
+    triggers::{
+      effects::{Effect, EffectWithID},
+      timers::TimerEffect,
+      trigger_index::{Mutation, TriggerIndex},
+      Trigger,
+    },
+  };
+
+  #[test]
+  fn test_lint_effects() {
+    let mut index = TriggerIndex::new();
+    let trigger = sample_trigger(
+      r"^{S1} hits YOU for (\d+)",
+      vec![
+        Effect::PlayAudioFile(None),
+        Effect::ScopedTimerEffect(TimerEffect::ClearTimer),
+        Effect::Speak {
+          tmpl: "${S1} hit ${C} for ${1|uper} and ${2}".into(),
+          interrupt: false,
+        },
+      ],
+    );
+    let trigger_id = trigger.id.clone();
+    index.import_trigger(trigger);
+    let tag_id = index.create_trigger_tag("Tag").id;
+    index
+      .mutate(Mutation::TagTrigger {
+        trigger_id,
+        trigger_tag_id: tag_id,
+      })
+      .unwrap();
+
+    let problems: Vec<TriggerLintProblem> = lint(&index, None)
+      .into_iter()
+      .map(|issue| issue.problem)
+      .collect();
+
+    assert_eq!(problems.len(), 4);
+    assert!(matches!(
+      problems[0],
+      TriggerLintProblem::AudioFileNotSelected
+    ));
+    assert!(matches!(
+      problems[1],
+      TriggerLintProblem::ScopedTimerEffectOutsideTimer(TimerEffect::ClearTimer)
+    ));
+    assert!(matches!(
+      &problems[2],
+      TriggerLintProblem::DefaultResemblesPipe { default, pipe, .. }
+        if default == "uper" && pipe == "upper"
+    ));
+    assert!(matches!(
+      &problems[3],
+      TriggerLintProblem::UndefinedTemplateVariable { variable, .. } if variable == "2"
+    ));
+  }
+
+  #[test]
+  fn test_lint_tags_and_duplicates() {
+    let mut index = TriggerIndex::new();
+    index.import_trigger(sample_trigger("^You have been slain", vec![]));
+    index.import_trigger(sample_trigger("^You have been slain", vec![]));
+
+    let issues = lint(&index, None);
+    let not_tagged = issues
+      .iter()
+      .filter(|issue| matches!(issue.problem, TriggerLintProblem::NotInAnyTriggerTag))
+      .count();
+    let duplicates = issues
+      .iter()
+      .filter(|issue| matches!(issue.problem, TriggerLintProblem::DuplicateFilter { .. }))
+      .count();
+    assert_eq!(not_tagged, 2);
+    assert_eq!(duplicates, 2);
+  }
+
+  fn sample_trigger(pattern: &str, effects: Vec<Effect>) -> Trigger {
+    Trigger {
+      id: UUID::new(),
+      parent_id: None,
+      name: "Lint Sample".into(),
+      comment: None,
+      filter: vec![Matcher::gina(pattern).unwrap()].into(),
+      effects: effects.into_iter().map(EffectWithID::new).collect(),
+      created_at: Timestamp::now(),
+      updated_at: Timestamp::now(),
+    }
+  }
+}
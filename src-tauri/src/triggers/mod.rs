@@ -1,8 +1,13 @@
+pub mod benchmark;
 pub mod command_template;
 pub mod effects;
+pub mod lint;
+pub mod prefilter;
 pub mod template_string;
+pub mod test_bench;
 pub mod timers;
 pub mod trigger_index;
+pub mod worker_pool;
 
 use crate::{
   common::{timestamp::Timestamp, UUID},
@@ -12,7 +17,7 @@ use crate::{
 use effects::EffectWithID;
 use serde::{Deserialize, Serialize};
 use template_string::TemplateString;
-use trigger_index::{TriggerGroupDescendant, TriggerIndex};
+use trigger_index::{DataMutationError, TriggerGroupDescendant, TriggerIndex};
 
 #[derive(thiserror::Error, Debug)]
 pub enum TriggerLoadOrCreateError {
@@ -59,6 +64,23 @@ impl Trigger {
     Self { effects, ..self }
   }
 
+  /// Returns an error for the first template in the Trigger's Effects that cannot be parsed
+  fn validate_templates(&self) -> Result<(), DataMutationError> {
+    let mut result = Ok(());
+    for effect in self.effects.iter() {
+      effect.each_template(&mut |effect_id, tmpl| {
+        if let (Ok(()), Err(e)) = (&result, tmpl.validate()) {
+          result = Err(DataMutationError::InvalidTemplate {
+            effect_id: effect_id.to_owned(),
+            template: tmpl.tmpl().to_owned(),
+            message: e.to_string(),
+          });
+        }
+      });
+    }
+    result
+  }
+
   fn updated_now(&mut self) {
     self.updated_at = Timestamp::now();
   }
@@ -166,7 +188,8 @@ mod test {
   }
 
   fn simple_sample() -> (Trigger, TriggerGroup) {
-    let now = Timestamp::now();
+    // Timestamps are saved with millisecond precision, so anything finer would not survive
+    let now = Timestamp::from(chrono::SubsecRound::trunc_subsecs(chrono::Utc::now(), 3));
     let trigger_id = UUID::new();
     let group_id = UUID::new();
     let trigger = Trigger {
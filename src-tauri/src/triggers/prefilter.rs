@@ -0,0 +1,468 @@
+//! The prefilter avoids running every active Trigger's Filter on every line. Each Matcher is
+//! reduced to a literal substring that any line it matches must contain, and all of those
+//! literals are searched for in a single pass over the line. Only the Triggers whose literals
+//! were found (or that have a Matcher with no required literal) need their Filters checked.
+use super::Trigger;
+use crate::{common::UUID, matchers::Matcher};
+use aho_corasick::{AhoCorasick, AhoCorasickBuilder};
+use fancy_regex::Regex;
+use std::collections::HashMap;
+use tracing::error;
+
+lazy_static::lazy_static! {
+  /// GINA variable placeholders (e.g. {S}, {N>100}, {C}) at the start of the remaining pattern
+  static ref GINA_TOKEN: Regex = Regex::new(r"^\{\s*(?:[Cc]|[Ss]\d*|[Nn]\d*\s*(?:(?:>=|<=|=|>|<)\s*-?\d+)?)\s*\}").unwrap();
+}
+
+/// A substring that every line matched by a Matcher must contain
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RequiredLiteral {
+  pub text: String,
+  /// If true, the line only has to contain the text when ignoring (ASCII) case
+  pub case_insensitive: bool,
+}
+
+pub struct TriggerPrefilter {
+  trigger_ids: Vec<UUID>,
+  /// Case-sensitive and case-insensitive literals are searched for separately
+  searchers: Vec<LiteralSearcher>,
+  /// Indices of Triggers that have at least one Matcher without a required literal
+  always_candidates: Vec<usize>,
+}
+
+struct LiteralSearcher {
+  searcher: AhoCorasick,
+  /// For each literal in the searcher, the indices of the Triggers that require it
+  literal_triggers: Vec<Vec<usize>>,
+}
+
+impl TriggerPrefilter {
+  pub fn new<'a, I>(triggers: I) -> Self
+  where
+    I: IntoIterator<Item = &'a Trigger>,
+  {
+    let mut trigger_ids: Vec<UUID> = Vec::new();
+    let mut literal_positions: HashMap<RequiredLiteral, usize> = HashMap::new();
+    let mut literals: Vec<RequiredLiteral> = Vec::new();
+    let mut literal_triggers: Vec<Vec<usize>> = Vec::new();
+    let mut always_candidates: Vec<usize> = Vec::new();
+
+    for (trigger_index, trigger) in triggers.into_iter().enumerate() {
+      trigger_ids.push(trigger.id.clone());
+      let required: Option<Vec<RequiredLiteral>> = trigger
+        .filter
+        .matchers()
+        .iter()
+        .map(required_literal)
+        .collect();
+      let Some(required) = required else {
+        always_candidates.push(trigger_index);
+        continue;
+      };
+      for literal in required {
+        let position = *literal_positions.entry(literal.clone()).or_insert_with(|| {
+          literals.push(literal);
+          literal_triggers.push(Vec::new());
+          literals.len() - 1
+        });
+        literal_triggers[position].push(trigger_index);
+      }
+    }
+
+    let mut searchers: Vec<LiteralSearcher> = Vec::new();
+    for case_insensitive in [false, true] {
+      let (texts, triggers): (Vec<&str>, Vec<Vec<usize>>) = literals
+        .iter()
+        .zip(literal_triggers.iter())
+        .filter(|(literal, _)| literal.case_insensitive == case_insensitive)
+        .map(|(literal, triggers)| (literal.text.as_str(), triggers.clone()))
+        .unzip();
+      if texts.is_empty() {
+        continue;
+      }
+      let searcher = AhoCorasickBuilder::new()
+        .ascii_case_insensitive(case_insensitive)
+        .build(&texts);
+      match searcher {
+        Ok(searcher) => searchers.push(LiteralSearcher {
+          searcher,
+          literal_triggers: triggers,
+        }),
+        Err(e) => {
+          // Without a searcher, every Trigger is a candidate
+          error!("Could not build trigger prefilter! Error: {e:?}");
+          always_candidates = (0..trigger_ids.len()).collect();
+          searchers.clear();
+          break;
+        }
+      }
+    }
+
+    Self {
+      trigger_ids,
+      searchers,
+      always_candidates,
+    }
+  }
+
+  /// Returns the IDs of the Triggers whose Filters could match the line, in the same order as
+  /// the Triggers were given to `new`.
+  #[cfg(test)]
+  pub fn candidates(&self, line: &str) -> Vec<&UUID> {
+    self
+      .candidate_indices(line)
+      .into_iter()
+      .map(|index| &self.trigger_ids[index])
+      .collect()
+  }
+
+  /// Returns the positions of the candidate Triggers in the order they were given to `new`
+  pub fn candidate_indices(&self, line: &str) -> Vec<usize> {
+    let mut is_candidate = vec![false; self.trigger_ids.len()];
+    for trigger_index in self.always_candidates.iter() {
+      is_candidate[*trigger_index] = true;
+    }
+    for LiteralSearcher {
+      searcher,
+      literal_triggers,
+    } in self.searchers.iter()
+    {
+      for found in searcher.find_overlapping_iter(line) {
+        for trigger_index in literal_triggers[found.pattern().as_usize()].iter() {
+          is_candidate[*trigger_index] = true;
+        }
+      }
+    }
+    is_candidate
+      .into_iter()
+      .enumerate()
+      .filter_map(|(index, is_candidate)| is_candidate.then_some(index))
+      .collect()
+  }
+
+  pub fn literal_count(&self) -> usize {
+    self
+      .searchers
+      .iter()
+      .map(|searcher| searcher.literal_triggers.len())
+      .sum()
+  }
+}
+
+/// Returns a substring that every line matched by the Matcher must contain, if there is one.
+/// The literal is searched for in the line before the Matcher's options normalize it, so with
+/// `collapse_whitespace` only a piece without whitespace can be required, and with
+/// `case_insensitive` only an ASCII one.
+pub fn required_literal(matcher: &Matcher) -> Option<RequiredLiteral> {
+  let options = matcher.options();
+  let literal = match matcher {
+    Matcher::WholeLine { pattern, .. } | Matcher::PartialLine { pattern, .. } => {
+      Some(options.normalize(&pattern.raw).into_owned())
+    }
+    Matcher::Pattern { pattern, .. } => longest_required_literal(&pattern.pattern, false),
+    Matcher::GINA { pattern, .. } => longest_required_literal(&pattern.raw, true),
+  };
+  let literal = if options.collapse_whitespace {
+    literal?
+      .split_whitespace()
+      .max_by_key(|piece| piece.len())
+      .map(str::to_owned)
+  } else {
+    literal
+  };
+  literal
+    .filter(|literal| !literal.is_empty())
+    .filter(|literal| !options.case_insensitive || literal.is_ascii())
+    .map(|text| RequiredLiteral {
+      text,
+      case_insensitive: options.case_insensitive,
+    })
+}
+
+/// This is a conservative scan of a regex pattern: only runs of literal characters in the
+/// top-level sequence are considered, and anything it does not understand ends the current run.
+/// Groups and character classes are skipped entirely. If the pattern has a top-level
+/// alternation or a flag that changes how literals match, there is no required literal.
+fn longest_required_literal(pattern: &str, is_gina: bool) -> Option<String> {
+  let chars: Vec<char> = pattern.chars().collect();
+  let mut runs: Vec<String> = Vec::new();
+  let mut run = String::new();
+  let mut i = 0;
+
+  while i < chars.len() {
+    match chars[i] {
+      '\\' => {
+        let escaped = *chars.get(i + 1)?;
+        if escaped.is_ascii_alphanumeric() {
+          // a character class (\d), an assertion (\b), a back-reference (\1), etc.
+          runs.push(std::mem::take(&mut run));
+          i = skip_escape(&chars, i)?;
+        } else {
+          run.push(escaped);
+          i += 2;
+        }
+      }
+      '|' => return None,
+      '(' => {
+        if chars.get(i + 1) == Some(&'?') {
+          let flags: String = chars[i + 2..]
+            .iter()
+            .take_while(|c| c.is_ascii_alphabetic() || **c == '-')
+            .collect();
+          if flags.contains(['i', 'x']) {
+            return None;
+          }
+        }
+        runs.push(std::mem::take(&mut run));
+        i = skip_group(&chars, i)?;
+      }
+      '[' => {
+        runs.push(std::mem::take(&mut run));
+        i = skip_character_class(&chars, i)?;
+      }
+      '{' => {
+        let rest: String = chars[i..].iter().collect();
+        let gina_token_len = if is_gina {
+          GINA_TOKEN
+            .find(&rest)
+            .ok()
+            .flatten()
+            .map(|m| m.as_str().chars().count())
+        } else {
+          None
+        };
+        if let Some(token_len) = gina_token_len {
+          runs.push(std::mem::take(&mut run));
+          i += token_len;
+        } else {
+          // a counted repetition like {2,3}, which might allow zero of the previous character
+          run.pop();
+          runs.push(std::mem::take(&mut run));
+          i += chars[i..].iter().position(|c| *c == '}')? + 1;
+        }
+      }
+      '*' | '?' => {
+        run.pop();
+        runs.push(std::mem::take(&mut run));
+        i += 1;
+      }
+      '+' | '.' | '^' | '$' => {
+        runs.push(std::mem::take(&mut run));
+        i += 1;
+      }
+      c => {
+        run.push(c);
+        i += 1;
+      }
+    }
+  }
+  runs.push(run);
+
+  runs.into_iter().max_by_key(|run| run.len())
+}
+
+/// Returns the index after an escape sequence that starts with a letter or digit, including its
+/// argument, e.g. the `41` of `\x41` or the `{L}` of `\p{L}`, so that the argument is not taken
+/// for a literal. Returns None for an escape whose end cannot be found.
+fn skip_escape(chars: &[char], start: usize) -> Option<usize> {
+  let after_letter = start + 2;
+  let bracketed = |open: char, close: char| -> Option<Option<usize>> {
+    if chars.get(after_letter) != Some(&open) {
+      return None;
+    }
+    Some(
+      chars[after_letter + 1..]
+        .iter()
+        .position(|c| *c == close)
+        .map(|end| after_letter + 1 + end + 1),
+    )
+  };
+  let fixed = |len: usize| Some(after_letter + len).filter(|end| *end <= chars.len());
+
+  match chars[start + 1] {
+    'x' => bracketed('{', '}').unwrap_or_else(|| fixed(2)),
+    'u' => bracketed('{', '}').unwrap_or_else(|| fixed(4)),
+    'U' => bracketed('{', '}').unwrap_or_else(|| fixed(8)),
+    'p' | 'P' => bracketed('{', '}').unwrap_or_else(|| fixed(1)),
+    'c' => fixed(1),
+    'k' | 'g' => bracketed('<', '>')
+      .or_else(|| bracketed('{', '}'))
+      .or_else(|| bracketed('\'', '\''))
+      .flatten(),
+    digit if digit.is_ascii_digit() => Some(
+      after_letter
+        + chars[after_letter..]
+          .iter()
+          .take_while(|c| c.is_ascii_digit())
+          .count(),
+    ),
+    _ => Some(after_letter),
+  }
+}
+
+/// Returns the index after the group's closing parenthesis
+fn skip_group(chars: &[char], start: usize) -> Option<usize> {
+  let mut depth = 0;
+  let mut i = start;
+  while i < chars.len() {
+    match chars[i] {
+      '\\' => i += 1,
+      '[' => {
+        i = skip_character_class(chars, i)?;
+        continue;
+      }
+      '(' => depth += 1,
+      ')' => {
+        depth -= 1;
+        if depth == 0 {
+          return Some(i + 1);
+        }
+      }
+      _ => {}
+    }
+    i += 1;
+  }
+  None
+}
+
+/// Returns the index after the character class's closing bracket
+fn skip_character_class(chars: &[char], start: usize) -> Option<usize> {
+  let mut depth = 0;
+  let mut i = start;
+  while i < chars.len() {
+    match chars[i] {
+      '\\' => i += 1,
+      '[' => {
+        depth += 1;
+        // a `]` right after the opening bracket (or `[^`) is a literal
+        if chars.get(i + 1) == Some(&'^') {
+          i += 1;
+        }
+        if chars.get(i + 1) == Some(&']') {
+          i += 1;
+        }
+      }
+      ']' => {
+        depth -= 1;
+        if depth == 0 {
+          return Some(i + 1);
+        }
+      }
+      _ => {}
+    }
+    i += 1;
+  }
+  None
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{longest_required_literal, TriggerPrefilter};
+  use crate::{
+    common::{timestamp::Timestamp, UUID},
+    matchers::{MatchOptions, Matcher},
+    triggers::Trigger,
+  };
+
+  #[test]
+  fn test_longest_required_literal() {
+    let gina = |pattern: &str| longest_required_literal(pattern, true);
+    assert_eq!(
+      gina(r"^{S} begins to cast a spell\.$").as_deref(),
+      Some(" begins to cast a spell.")
+    );
+    assert_eq!(
+      gina(r"^{S} tells you, '(.+)'$").as_deref(),
+      Some(" tells you, '")
+    );
+    assert_eq!(
+      gina(r"^You have taken {N>=100} points of damage").as_deref(),
+      Some(" points of damage")
+    );
+    assert_eq!(
+      gina(r"Your (\w+) spell has worn off\.?").as_deref(),
+      Some(" spell has worn off")
+    );
+    assert_eq!(gina(r"colou?r").as_deref(), Some("colo"));
+    assert_eq!(gina(r"aaa[abc]+bb").as_deref(), Some("aaa"));
+    assert_eq!(gina(r"x{2,3}yz").as_deref(), Some("yz"));
+    assert_eq!(gina(r"(?i)slain"), None);
+    assert_eq!(gina(r"slain|died"), None);
+    assert_eq!(gina(r"abc(de|fg)hi").as_deref(), Some("abc"));
+  }
+
+  #[test]
+  fn test_longest_required_literal_skips_escape_arguments() {
+    let pattern = |pattern: &str| longest_required_literal(pattern, false);
+    assert_eq!(pattern(r"ab\x41cdefg").as_deref(), Some("cdefg"));
+    assert_eq!(pattern(r"ab\x{41}cdefg").as_deref(), Some("cdefg"));
+    assert_eq!(pattern(r"ab\u0041cdefg").as_deref(), Some("cdefg"));
+    assert_eq!(pattern(r"ab\U00000041cdefg").as_deref(), Some("cdefg"));
+    assert_eq!(pattern(r"\p{Lu}ab cd").as_deref(), Some("ab cd"));
+    assert_eq!(pattern(r"\pLab cd").as_deref(), Some("ab cd"));
+    assert_eq!(pattern(r"(?<n>x)\k<n>ab cd").as_deref(), Some("ab cd"));
+    assert_eq!(pattern(r"(x)\12ab cd").as_deref(), Some("ab cd"));
+    assert_eq!(pattern(r"\d+ points").as_deref(), Some(" points"));
+    assert_eq!(pattern(r"abc\x4"), None);
+    assert_eq!(pattern(r"abc\k<n"), None);
+  }
+
+  #[test]
+  fn test_prefilter_candidates() {
+    let cast = sample_trigger(vec![
+      Matcher::gina(r"^{S} begins to cast a spell\.$").unwrap()
+    ]);
+    let tell = sample_trigger(vec![
+      Matcher::gina(r"^{S} tells you, '(.+)'$").unwrap(),
+      Matcher::PartialLine {
+        id: UUID::new(),
+        pattern: "told you".into(),
+        options: MatchOptions::default(),
+      },
+    ]);
+    let anything = sample_trigger(vec![Matcher::gina(r"^(.+)$").unwrap()]);
+    let loose = sample_trigger(vec![Matcher::WholeLine {
+      id: UUID::new(),
+      pattern: "You have been   MESMERIZED.".into(),
+      options: MatchOptions {
+        case_insensitive: true,
+        collapse_whitespace: true,
+        ignore_trailing_punctuation: true,
+        ..MatchOptions::default()
+      },
+    }]);
+
+    let prefilter = TriggerPrefilter::new([&cast, &tell, &anything, &loose]);
+    assert_eq!(prefilter.literal_count(), 4);
+
+    assert_eq!(
+      prefilter.candidates("Soandso begins to cast a spell."),
+      vec![&cast.id, &anything.id]
+    );
+    assert_eq!(
+      prefilter.candidates("Soandso told you, 'hi'"),
+      vec![&tell.id, &anything.id]
+    );
+    assert_eq!(
+      prefilter.candidates("You gain experience!!"),
+      vec![&anything.id]
+    );
+    assert_eq!(
+      prefilter.candidates("you have  been mesmerized!"),
+      vec![&anything.id, &loose.id]
+    );
+  }
+
+  fn sample_trigger(matchers: Vec<Matcher>) -> Trigger {
+    Trigger {
+      id: UUID::new(),
+      parent_id: None,
+      name: "Prefilter Sample".into(),
+      comment: None,
+      filter: matchers.into(),
+      effects: vec![],
+      created_at: Timestamp::now(),
+      updated_at: Timestamp::now(),
+    }
+  }
+}
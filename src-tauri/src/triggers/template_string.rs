@@ -1,20 +1,138 @@
+//! A TemplateString is text with `${...}` expressions that are filled in from a MatchContext.
+//!
+//! An expression is a variable (`${1}`, `${name}`, `${C}`) or arithmetic on variables and
+//! numbers (`${N1 / 1000}`), followed by any number of `|`-separated pipes that are applied
+//! from left to right:
+//!
+//! - `upper`, `lower`, `title` change the case of the value
+//! - `round` rounds a number to a whole number, `round(2)` to two decimal places
+//! - `commas` adds thousands separators to a number
+//! - `duration` formats a number of seconds (or `H:MM:SS`) like `1h 2m 3s`, and
+//!   `duration_long` like `1 hour 2 minutes 3 seconds`
+//! - anything else is a default for when the value is missing, e.g. `${1|someone}`; a default
+//!   can be quoted to use the name of a pipe, a `|` or a `(` as the default text: `${1|"upper"}`.
+//!   An unquoted default with a `(` is rejected as an unknown pipe, e.g. `${1|rund(2)}`
+//!
+//! Besides the captures, a variable can be one of the built-in variables listed in
+//! `BUILT_IN_VARIABLE_NAMES` (e.g. `${ZONE}` or `${REMAINING|duration}`).
+//!
+//! Inside arithmetic, integers are numbers rather than group references, so groups are written
+//! with a `$` prefix (`${$1 * 2}`). The result of arithmetic is rounded to a whole number unless
+//! it is piped to `round(N)`. A variable that is missing or is not a number makes the whole
+//! expression missing.
 use crate::matchers::MatchContext;
 use fancy_regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::sync::{Arc, OnceLock};
 
 lazy_static::lazy_static! {
   static ref TEMPLATE_VARS: Regex = Regex::new(r"\$\{\s*(\w+)\s*\}").unwrap();
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, ts_rs::TS)]
-pub struct TemplateString(String);
+/// The names of the pipes, for recognizing defaults that were meant to be a pipe
+const PIPE_NAMES: [&str; 7] = [
+  "upper",
+  "lower",
+  "title",
+  "round",
+  "commas",
+  "duration",
+  "duration_long",
+];
+
+#[derive(Clone, ts_rs::TS)]
+#[ts(type = "string")]
+pub struct TemplateString {
+  tmpl: String,
+  /// Only parsed the first time the template is used, rather than on every render
+  #[ts(skip)]
+  parsed: Arc<OnceLock<Result<Vec<Segment>, TemplateParseError>>>,
+}
+
+/// Positions are byte offsets into the template
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum TemplateParseError {
+  #[error("The expression starting at position {0} is missing a closing }}")]
+  Unclosed(usize),
+
+  #[error("The expression at position {0} is empty")]
+  EmptyExpression(usize),
+
+  #[error("Unexpected {found:?} at position {position}")]
+  Unexpected { position: usize, found: String },
+
+  #[error("The {pipe} pipe at position {position} has an invalid argument")]
+  InvalidArgument { position: usize, pipe: String },
+
+  #[error("Unknown pipe {pipe:?} at position {position} (quote it to use it as a default)")]
+  UnknownPipe { position: usize, pipe: String },
+}
+
+enum Segment {
+  Literal(String),
+  Expression(Expression),
+}
+
+struct Expression {
+  value: Value,
+  pipes: Vec<Pipe>,
+}
+
+enum Value {
+  Variable(String),
+  Arithmetic(Arithmetic),
+}
+
+enum Arithmetic {
+  Number(f64),
+  Variable(String),
+  Negate(Box<Arithmetic>),
+  Binary(Box<Arithmetic>, Operator, Box<Arithmetic>),
+}
+
+#[derive(Clone, Copy)]
+enum Operator {
+  Add,
+  Subtract,
+  Multiply,
+  Divide,
+  Remainder,
+}
+
+enum Pipe {
+  Default { text: String, quoted: bool },
+  Upper,
+  Lower,
+  Title,
+  Round(usize),
+  Commas,
+  Duration,
+  DurationLong,
+}
+
+/// The value of an Expression as its Pipes are applied to it
+enum Evaluated {
+  Missing,
+  Text(String),
+  Number(f64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+  Number(f64),
+  Variable(String),
+  Operator(char),
+  Open,
+  Close,
+}
 
 impl Serialize for TemplateString {
   fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
   where
     S: serde::Serializer,
   {
-    serializer.serialize_str(&self.0)
+    serializer.serialize_str(&self.tmpl)
   }
 }
 
@@ -24,51 +142,794 @@ impl<'de> Deserialize<'de> for TemplateString {
     D: serde::Deserializer<'de>,
   {
     let tmpl: String = Deserialize::deserialize(deserializer)?;
-    Ok(Self(tmpl))
+    Ok(tmpl.into())
   }
 }
 
 impl TemplateString {
   pub fn tmpl(&self) -> &str {
-    &self.0
+    &self.tmpl
+  }
+
+  fn parsed(&self) -> Result<&[Segment], &TemplateParseError> {
+    self.parsed.get_or_init(|| parse(&self.tmpl)).as_deref()
+  }
+
+  /// Templates are not validated when they are deserialized, so that a Trigger with an invalid
+  /// template can still be loaded. This is checked when a Trigger is saved instead.
+  pub fn validate(&self) -> Result<(), TemplateParseError> {
+    self.parsed().map(|_| ()).map_err(Clone::clone)
+  }
+
+  /// Returns the unquoted defaults that look like a misspelled pipe, along with the name of
+  /// that pipe, e.g. `("uper", "upper")` for `${1|uper}`
+  pub fn defaults_resembling_pipes(&self) -> Vec<(String, &'static str)> {
+    let Ok(segments) = self.parsed() else {
+      return Vec::new();
+    };
+    let mut resembling = Vec::new();
+    for segment in segments.iter() {
+      let Segment::Expression(expression) = segment else {
+        continue;
+      };
+      for pipe in expression.pipes.iter() {
+        if let Pipe::Default {
+          text,
+          quoted: false,
+        } = pipe
+        {
+          if let Some(pipe_name) = resembled_pipe_name(text) {
+            resembling.push((text.clone(), pipe_name));
+          }
+        }
+      }
+    }
+    resembling
+  }
+
+  /// Returns the upper-cased names of all variables referenced by this template
+  pub fn variable_names(&self) -> Vec<String> {
+    self.collect_variable_names(true)
+  }
+
+  /// Like `variable_names`, but without the variables of expressions that have a default
+  pub fn required_variable_names(&self) -> Vec<String> {
+    self.collect_variable_names(false)
+  }
+
+  fn collect_variable_names(&self, include_defaulted: bool) -> Vec<String> {
+    let Ok(segments) = self.parsed() else {
+      return TEMPLATE_VARS
+        .captures_iter(&self.tmpl)
+        .filter_map(|caps| caps.ok())
+        .filter_map(|caps| caps.get(1).map(|m| m.as_str().to_uppercase()))
+        .collect();
+    };
+    let mut names = Vec::new();
+    for segment in segments.iter() {
+      let Segment::Expression(expression) = segment else {
+        continue;
+      };
+      let has_default = expression
+        .pipes
+        .iter()
+        .any(|pipe| matches!(pipe, Pipe::Default { .. }));
+      if include_defaulted || !has_default {
+        expression.value.collect_variable_names(&mut names);
+      }
+    }
+    names
   }
 
   pub fn render(&self, context: &MatchContext) -> String {
+    let Ok(segments) = self.parsed() else {
+      // An invalid template renders the way templates did before expressions were supported
+      return self.render_variables_only(context);
+    };
+    let mut rendered = String::with_capacity(self.tmpl.len());
+    for segment in segments.iter() {
+      match segment {
+        Segment::Literal(text) => rendered.push_str(text),
+        Segment::Expression(expression) => rendered.push_str(&expression.render(context)),
+      }
+    }
+    rendered
+  }
+
+  fn render_variables_only(&self, context: &MatchContext) -> String {
     TEMPLATE_VARS
-      .replace_all(&self.0, |caps: &fancy_regex::Captures| {
+      .replace_all(&self.tmpl, |caps: &fancy_regex::Captures| {
         let var_name = caps
           .get(1)
           .expect("TEMPLATE_VARS should always capture a group 1 in replace_all")
-          .as_str()
-          .to_uppercase();
-        if var_name == "C" {
-          // This case not be necessary if it's ALWAYS added to named_values
-          return context.character_name.clone();
+          .as_str();
+        // Replace the var with an empty string if it's missing from the context
+        lookup(context, var_name).unwrap_or_default()
+      })
+      .into_owned()
+  }
+}
+
+fn lookup(context: &MatchContext, var_name: &str) -> Option<String> {
+  context.variable(var_name).map(Cow::into_owned)
+}
+
+fn parse(tmpl: &str) -> Result<Vec<Segment>, TemplateParseError> {
+  let mut segments = Vec::new();
+  let mut rest_start = 0;
+  while let Some(offset) = tmpl[rest_start..].find("${") {
+    let start = rest_start + offset;
+    if start > rest_start {
+      segments.push(Segment::Literal(tmpl[rest_start..start].to_owned()));
+    }
+    let body_start = start + 2;
+    let body_len =
+      find_unquoted(&tmpl[body_start..], '}').ok_or(TemplateParseError::Unclosed(start))?;
+    let body = &tmpl[body_start..body_start + body_len];
+    segments.push(Segment::Expression(Expression::parse(body, body_start)?));
+    rest_start = body_start + body_len + 1;
+  }
+  if rest_start < tmpl.len() {
+    segments.push(Segment::Literal(tmpl[rest_start..].to_owned()));
+  }
+  Ok(segments)
+}
+
+/// Finds the first occurrence of the character that is not inside double quotes
+fn find_unquoted(text: &str, needle: char) -> Option<usize> {
+  let mut in_quotes = false;
+  for (index, c) in text.char_indices() {
+    if c == '"' {
+      in_quotes = !in_quotes;
+    } else if c == needle && !in_quotes {
+      return Some(index);
+    }
+  }
+  None
+}
+
+impl Expression {
+  /// `position` is where the body starts in the template, for error reporting
+  fn parse(body: &str, position: usize) -> Result<Self, TemplateParseError> {
+    let mut parts: Vec<(usize, &str)> = Vec::new();
+    let mut part_start = 0;
+    while let Some(offset) = find_unquoted(&body[part_start..], '|') {
+      parts.push((part_start, &body[part_start..part_start + offset]));
+      part_start += offset + 1;
+    }
+    parts.push((part_start, &body[part_start..]));
+
+    let (value_offset, value) = parts[0];
+    let value = Value::parse(value, position + value_offset)?;
+    let pipes = parts[1..]
+      .iter()
+      .map(|(offset, pipe)| Pipe::parse(pipe, position + offset))
+      .collect::<Result<Vec<Pipe>, TemplateParseError>>()?;
+    Ok(Self { value, pipes })
+  }
+
+  fn render(&self, context: &MatchContext) -> String {
+    let mut evaluated = self.value.evaluate(context);
+    for pipe in self.pipes.iter() {
+      evaluated = pipe.apply(evaluated);
+    }
+    match evaluated {
+      Evaluated::Missing => String::new(),
+      Evaluated::Text(text) => text,
+      Evaluated::Number(number) => format_number(number, 0),
+    }
+  }
+}
+
+impl Value {
+  fn parse(text: &str, position: usize) -> Result<Self, TemplateParseError> {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+      return Err(TemplateParseError::EmptyExpression(position));
+    }
+    if trimmed.chars().all(|c| c.is_alphanumeric() || c == '_') {
+      // This includes group numbers, e.g. ${1}
+      return Ok(Self::Variable(trimmed.to_owned()));
+    }
+    let tokens = tokenize(text, position)?;
+    let mut parser = ArithmeticParser {
+      tokens: &tokens,
+      next: 0,
+    };
+    let arithmetic = parser.parse_sum()?;
+    if let Some((position, token)) = tokens.get(parser.next) {
+      return Err(TemplateParseError::Unexpected {
+        position: *position,
+        found: token.to_string(),
+      });
+    }
+    Ok(Self::Arithmetic(arithmetic))
+  }
+
+  fn evaluate(&self, context: &MatchContext) -> Evaluated {
+    match self {
+      Self::Variable(name) => match lookup(context, name) {
+        Some(value) => Evaluated::Text(value),
+        None => Evaluated::Missing,
+      },
+      Self::Arithmetic(arithmetic) => match arithmetic.evaluate(context) {
+        Some(number) => Evaluated::Number(number),
+        None => Evaluated::Missing,
+      },
+    }
+  }
+
+  fn collect_variable_names(&self, names: &mut Vec<String>) {
+    match self {
+      Self::Variable(name) => names.push(name.to_uppercase()),
+      Self::Arithmetic(arithmetic) => arithmetic.collect_variable_names(names),
+    }
+  }
+}
+
+fn tokenize(text: &str, position: usize) -> Result<Vec<(usize, Token)>, TemplateParseError> {
+  let chars: Vec<(usize, char)> = text.char_indices().collect();
+  let mut tokens = Vec::new();
+  let mut i = 0;
+  while i < chars.len() {
+    let (offset, c) = chars[i];
+    let start = i;
+    let token = match c {
+      c if c.is_whitespace() => {
+        i += 1;
+        continue;
+      }
+      '0'..='9' | '.' => {
+        while i < chars.len() && (chars[i].1.is_ascii_digit() || chars[i].1 == '.') {
+          i += 1;
+        }
+        let number: String = chars[start..i].iter().map(|(_, c)| c).collect();
+        let number = number
+          .parse::<f64>()
+          .map_err(|_| TemplateParseError::Unexpected {
+            position: position + offset,
+            found: number,
+          })?;
+        tokens.push((position + offset, Token::Number(number)));
+        continue;
+      }
+      '$' | 'A'..='Z' | 'a'..='z' | '_' => {
+        i += 1;
+        while i < chars.len() && (chars[i].1.is_alphanumeric() || chars[i].1 == '_') {
+          i += 1;
         }
-        if let Ok(group_number) = var_name.parse::<usize>() {
-          if let Some(value) = context.group(group_number) {
-            return value.to_owned();
+        let name: String = chars[start..i].iter().map(|(_, c)| c).collect();
+        let name = name.trim_start_matches('$');
+        if name.is_empty() {
+          return Err(TemplateParseError::Unexpected {
+            position: position + offset,
+            found: "$".to_owned(),
+          });
+        }
+        tokens.push((position + offset, Token::Variable(name.to_owned())));
+        continue;
+      }
+      '+' | '-' | '*' | '/' | '%' => Token::Operator(c),
+      '(' => Token::Open,
+      ')' => Token::Close,
+      c => {
+        return Err(TemplateParseError::Unexpected {
+          position: position + offset,
+          found: c.to_string(),
+        })
+      }
+    };
+    tokens.push((position + offset, token));
+    i += 1;
+  }
+  Ok(tokens)
+}
+
+struct ArithmeticParser<'a> {
+  tokens: &'a [(usize, Token)],
+  next: usize,
+}
+
+impl ArithmeticParser<'_> {
+  fn parse_sum(&mut self) -> Result<Arithmetic, TemplateParseError> {
+    let mut left = self.parse_product()?;
+    while let Some(operator) = self.next_operator(&['+', '-']) {
+      let right = self.parse_product()?;
+      left = Arithmetic::Binary(Box::new(left), operator, Box::new(right));
+    }
+    Ok(left)
+  }
+
+  fn parse_product(&mut self) -> Result<Arithmetic, TemplateParseError> {
+    let mut left = self.parse_factor()?;
+    while let Some(operator) = self.next_operator(&['*', '/', '%']) {
+      let right = self.parse_factor()?;
+      left = Arithmetic::Binary(Box::new(left), operator, Box::new(right));
+    }
+    Ok(left)
+  }
+
+  fn parse_factor(&mut self) -> Result<Arithmetic, TemplateParseError> {
+    let Some((position, token)) = self.tokens.get(self.next) else {
+      let position = self.tokens.last().map(|(p, _)| *p).unwrap_or_default();
+      return Err(TemplateParseError::Unexpected {
+        position,
+        found: "end of expression".to_owned(),
+      });
+    };
+    self.next += 1;
+    match token {
+      Token::Number(number) => Ok(Arithmetic::Number(*number)),
+      Token::Variable(name) => Ok(Arithmetic::Variable(name.clone())),
+      Token::Operator('-') => Ok(Arithmetic::Negate(Box::new(self.parse_factor()?))),
+      Token::Open => {
+        let inner = self.parse_sum()?;
+        match self.tokens.get(self.next) {
+          Some((_, Token::Close)) => {
+            self.next += 1;
+            Ok(inner)
           }
+          _ => Err(TemplateParseError::Unexpected {
+            position: *position,
+            found: "unclosed (".to_owned(),
+          }),
+        }
+      }
+      token => Err(TemplateParseError::Unexpected {
+        position: *position,
+        found: token.to_string(),
+      }),
+    }
+  }
+
+  fn next_operator(&mut self, operators: &[char]) -> Option<Operator> {
+    let Some((_, Token::Operator(c))) = self.tokens.get(self.next) else {
+      return None;
+    };
+    if !operators.contains(c) {
+      return None;
+    }
+    self.next += 1;
+    Some(match c {
+      '+' => Operator::Add,
+      '-' => Operator::Subtract,
+      '*' => Operator::Multiply,
+      '/' => Operator::Divide,
+      _ => Operator::Remainder,
+    })
+  }
+}
+
+impl std::fmt::Display for Token {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Number(number) => write!(f, "{number}"),
+      Self::Variable(name) => write!(f, "{name}"),
+      Self::Operator(c) => write!(f, "{c}"),
+      Self::Open => write!(f, "("),
+      Self::Close => write!(f, ")"),
+    }
+  }
+}
+
+impl Arithmetic {
+  fn evaluate(&self, context: &MatchContext) -> Option<f64> {
+    let result = match self {
+      Self::Number(number) => *number,
+      Self::Variable(name) => parse_number(&lookup(context, name)?)?,
+      Self::Negate(inner) => -inner.evaluate(context)?,
+      Self::Binary(left, operator, right) => {
+        let (left, right) = (left.evaluate(context)?, right.evaluate(context)?);
+        match operator {
+          Operator::Add => left + right,
+          Operator::Subtract => left - right,
+          Operator::Multiply => left * right,
+          Operator::Divide if right == 0.0 => return None,
+          Operator::Divide => left / right,
+          Operator::Remainder if right == 0.0 => return None,
+          Operator::Remainder => left % right,
+        }
+      }
+    };
+    result.is_finite().then_some(result)
+  }
+
+  fn collect_variable_names(&self, names: &mut Vec<String>) {
+    match self {
+      Self::Number(_) => {}
+      Self::Variable(name) => names.push(name.to_uppercase()),
+      Self::Negate(inner) => inner.collect_variable_names(names),
+      Self::Binary(left, _, right) => {
+        left.collect_variable_names(names);
+        right.collect_variable_names(names);
+      }
+    }
+  }
+}
+
+impl Pipe {
+  fn parse(text: &str, position: usize) -> Result<Self, TemplateParseError> {
+    let trimmed = text.trim();
+    if let Some(quoted) = trimmed
+      .strip_prefix('"')
+      .and_then(|rest| rest.strip_suffix('"'))
+    {
+      return Ok(Self::Default {
+        text: quoted.to_owned(),
+        quoted: true,
+      });
+    }
+    let name = trimmed.split('(').next().unwrap_or_default().trim();
+    let pipe = match name.to_lowercase().as_str() {
+      "upper" => Self::Upper,
+      "lower" => Self::Lower,
+      "title" => Self::Title,
+      "commas" => Self::Commas,
+      "duration" => Self::Duration,
+      "duration_long" => Self::DurationLong,
+      "round" => {
+        let argument = trimmed[name.len()..].trim();
+        let decimals = if argument.is_empty() {
+          Some(0)
         } else {
-          if let Some(value) = context.named_value(&var_name) {
-            return value.to_owned();
+          argument
+            .strip_prefix('(')
+            .and_then(|rest| rest.strip_suffix(')'))
+            .and_then(|digits| digits.trim().parse::<usize>().ok())
+        };
+        match decimals {
+          Some(decimals) => Self::Round(decimals),
+          None => {
+            return Err(TemplateParseError::InvalidArgument {
+              position,
+              pipe: name.to_owned(),
+            })
           }
         }
-        // Replace the var with an empty string if it's missing from the context
-        String::new()
-      })
-      .into_owned()
+      }
+      // A `(` means this was meant to be a pipe with an argument, not a default
+      _ if trimmed.contains('(') => {
+        return Err(TemplateParseError::UnknownPipe {
+          position,
+          pipe: name.to_owned(),
+        })
+      }
+      _ => Self::Default {
+        text: trimmed.to_owned(),
+        quoted: false,
+      },
+    };
+    Ok(pipe)
+  }
+
+  fn apply(&self, value: Evaluated) -> Evaluated {
+    use Evaluated::{Missing, Number, Text};
+    match (self, value) {
+      (Self::Default { text, .. }, Missing) => Text(text.clone()),
+      (_, Missing) => Missing,
+      (Self::Default { .. }, value) => value,
+      (Self::Upper, value) => Text(value.into_text().to_uppercase()),
+      (Self::Lower, value) => Text(value.into_text().to_lowercase()),
+      (Self::Title, value) => Text(title_case(&value.into_text())),
+      (Self::Round(decimals), value) => match value.as_number() {
+        Some(number) => Text(format_number(number, *decimals)),
+        None => value,
+      },
+      (Self::Commas, Number(number)) => Text(add_commas(&format_number(number, 0))),
+      (Self::Commas, Text(text)) => match parse_number(&text) {
+        Some(_) => Text(add_commas(&text.trim().replace(',', ""))),
+        None => Text(text),
+      },
+      (Self::Duration, value) => match value.as_seconds() {
+        Some(seconds) => Text(format_duration(seconds, false)),
+        None => value,
+      },
+      (Self::DurationLong, value) => match value.as_seconds() {
+        Some(seconds) => Text(format_duration(seconds, true)),
+        None => value,
+      },
+    }
+  }
+}
+
+impl Evaluated {
+  fn into_text(self) -> String {
+    match self {
+      Self::Missing => String::new(),
+      Self::Text(text) => text,
+      Self::Number(number) => format_number(number, 0),
+    }
+  }
+
+  fn as_number(&self) -> Option<f64> {
+    match self {
+      Self::Missing => None,
+      Self::Text(text) => parse_number(text),
+      Self::Number(number) => Some(*number),
+    }
+  }
+
+  /// A number of seconds, or a clock time like `1:30` or `1:02:03`
+  fn as_seconds(&self) -> Option<u64> {
+    if let Self::Text(text) = self {
+      let parts: Vec<&str> = text.trim().split(':').collect();
+      if (2..=3).contains(&parts.len()) {
+        return parts.iter().try_fold(0u64, |seconds, part| {
+          Some(seconds * 60 + part.trim().parse::<u64>().ok()?)
+        });
+      }
+    }
+    let seconds = self.as_number()?.round();
+    (seconds >= 0.0).then_some(seconds as u64)
+  }
+}
+
+/// Numbers in log lines can have thousands separators, e.g. "1,234 points of damage"
+fn parse_number(text: &str) -> Option<f64> {
+  let number = text.trim().replace(',', "").parse::<f64>().ok()?;
+  number.is_finite().then_some(number)
+}
+
+fn format_number(number: f64, decimals: usize) -> String {
+  let scale = 10f64.powi(decimals as i32);
+  let rounded = (number * scale).round() / scale;
+  // Avoids rendering "-0"
+  let rounded = if rounded == 0.0 { 0.0 } else { rounded };
+  format!("{rounded:.decimals$}")
+}
+
+fn add_commas(number: &str) -> String {
+  let (sign, unsigned) = match number.strip_prefix('-') {
+    Some(unsigned) => ("-", unsigned),
+    None => ("", number),
+  };
+  let (integer, fraction) = match unsigned.split_once('.') {
+    Some((integer, fraction)) => (integer, Some(fraction)),
+    None => (unsigned, None),
+  };
+  let mut with_commas = String::with_capacity(number.len() + integer.len() / 3);
+  with_commas.push_str(sign);
+  for (index, digit) in integer.chars().enumerate() {
+    if index > 0 && (integer.len() - index) % 3 == 0 {
+      with_commas.push(',');
+    }
+    with_commas.push(digit);
+  }
+  if let Some(fraction) = fraction {
+    with_commas.push('.');
+    with_commas.push_str(fraction);
+  }
+  with_commas
+}
+
+fn title_case(text: &str) -> String {
+  text
+    .split(' ')
+    .map(|word| {
+      let mut chars = word.chars();
+      match chars.next() {
+        Some(first) => first
+          .to_uppercase()
+          .chain(chars.flat_map(char::to_lowercase))
+          .collect(),
+        None => String::new(),
+      }
+    })
+    .collect::<Vec<String>>()
+    .join(" ")
+}
+
+/// A default resembles a pipe if it is a small number of edits away from the pipe's name,
+/// scaled by the length of the name so that short words are not all flagged
+fn resembled_pipe_name(default: &str) -> Option<&'static str> {
+  let default = default.to_lowercase();
+  PIPE_NAMES.into_iter().find(|name| {
+    let max_distance = (name.len() / 4).max(1);
+    edit_distance(&default, name) <= max_distance
+  })
+}
+
+/// The Levenshtein distance between two strings, in chars
+fn edit_distance(a: &str, b: &str) -> usize {
+  let b: Vec<char> = b.chars().collect();
+  let mut previous: Vec<usize> = (0..=b.len()).collect();
+  for (i, a_char) in a.chars().enumerate() {
+    let mut current = vec![i + 1; b.len() + 1];
+    for (j, b_char) in b.iter().enumerate() {
+      let substitution = previous[j] + usize::from(a_char != *b_char);
+      current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+    }
+    previous = current;
+  }
+  previous[b.len()]
+}
+
+fn format_duration(seconds: u64, long: bool) -> String {
+  let units = [
+    (seconds / 3600, "h", "hour"),
+    (seconds / 60 % 60, "m", "minute"),
+    (seconds % 60, "s", "second"),
+  ];
+  let parts: Vec<String> = units
+    .iter()
+    .filter(|(amount, _, _)| *amount > 0)
+    .map(|(amount, short, long_name)| {
+      if !long {
+        format!("{amount}{short}")
+      } else if *amount == 1 {
+        format!("{amount} {long_name}")
+      } else {
+        format!("{amount} {long_name}s")
+      }
+    })
+    .collect();
+  if parts.is_empty() {
+    if long { "0 seconds" } else { "0s" }.to_owned()
+  } else {
+    parts.join(" ")
+  }
+}
+
+impl TemplateParseError {
+  pub fn position(&self) -> usize {
+    match self {
+      Self::Unclosed(position) | Self::EmptyExpression(position) => *position,
+      Self::Unexpected { position, .. }
+      | Self::InvalidArgument { position, .. }
+      | Self::UnknownPipe { position, .. } => *position,
+    }
   }
 }
 
 impl From<&str> for TemplateString {
   fn from(tmpl: &str) -> Self {
-    TemplateString(tmpl.to_owned())
+    tmpl.to_owned().into()
   }
 }
 
 impl From<String> for TemplateString {
   fn from(tmpl: String) -> Self {
-    tmpl.as_str().into()
+    Self {
+      tmpl,
+      parsed: Arc::new(OnceLock::new()),
+    }
+  }
+}
+
+impl std::fmt::Debug for TemplateString {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_tuple("TemplateString").field(&self.tmpl).finish()
+  }
+}
+
+impl Eq for TemplateString {}
+impl PartialEq for TemplateString {
+  fn eq(&self, other: &Self) -> bool {
+    self.tmpl == other.tmpl
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{TemplateParseError, TemplateString};
+  use crate::matchers::{BuiltInVariables, Matcher};
+
+  #[test]
+  fn test_template_expressions() {
+    let matcher =
+      Matcher::gina(r"^(\w+) hit (?<target>\w+) for {N} points of damage\.(?: \((\w+)\))?$")
+        .unwrap();
+    let context = matcher
+      .check("Soandso hit a_rat for 42123 points of damage.", "Xenk")
+      .unwrap();
+    let render = |tmpl: &str| TemplateString::from(tmpl).render(&context);
+
+    assert_eq!(
+      render("${1} hit ${target} (${C})"),
+      "Soandso hit a_rat (Xenk)"
+    );
+    assert_eq!(render("${3|nobody} ${3}!"), "nobody !");
+    assert_eq!(render(r#"${3|"upper"|upper}"#), "UPPER");
+    assert_eq!(
+      render("${1|upper} ${1|lower} ${target|title}"),
+      "SOANDSO soandso A_rat"
+    );
+    assert_eq!(render("${N / 1000}k"), "42k");
+    assert_eq!(render("${N / 1000|round(1)}k"), "42.1k");
+    assert_eq!(render("${N|commas} ${N * 10|commas}"), "42,123 421,230");
+    assert_eq!(render("${(N - 123) % 1000 + -1}"), "-1");
+    assert_eq!(render("${N / 0|infinity}"), "infinity");
+    assert_eq!(render("${$3 * 2|none}"), "none");
+    assert_eq!(render("${N / 100|duration}"), "7m 1s");
+    assert_eq!(
+      render("${N / 10|duration_long}"),
+      "1 hour 10 minutes 12 seconds"
+    );
+
+    assert_eq!(
+      TemplateString::from("${N / 1000} ${1|x} ${target}").required_variable_names(),
+      vec!["N", "TARGET"]
+    );
+  }
+
+  #[test]
+  fn test_built_in_variables() {
+    let matcher = Matcher::gina(r"^(?<line>\w+) begins to cast a spell\.$").unwrap();
+    let mut context = matcher
+      .check("Soandso begins to cast a spell.", "Xenk")
+      .unwrap();
+    context.built_ins = BuiltInVariables {
+      server: Some("P1999Green".into()),
+      line: Some("Soandso begins to cast a spell.".into()),
+      zone: Some("East Commonlands".into()),
+      channel: Some("Say".into()),
+      speaker: Some("Soandso".into()),
+      timer_remaining_seconds: Some(90),
+      ..Default::default()
+    };
+    let render = |tmpl: &str| TemplateString::from(tmpl).render(&context);
+
+    assert_eq!(
+      render("${C} on ${SERVER} in ${zone}"),
+      "Xenk on P1999Green in East Commonlands"
+    );
+    // A capture with the same name as a built-in variable takes precedence
+    assert_eq!(render("${LINE}"), "Soandso");
+    assert_eq!(render("${SPEAKER} in ${CHANNEL|lower}"), "Soandso in say");
+    assert_eq!(render("${REMAINING|duration}"), "1m 30s");
+    assert_eq!(render("${TIMER|no timer}"), "no timer");
+  }
+
+  #[test]
+  fn test_defaults_resembling_pipes() {
+    let resembling = |tmpl: &str| TemplateString::from(tmpl).defaults_resembling_pipes();
+    assert_eq!(
+      resembling("${1|uper} ${2|Duration Long} ${3|someone}"),
+      vec![
+        ("uper".to_owned(), "upper"),
+        ("Duration Long".to_owned(), "duration_long")
+      ]
+    );
+    assert!(resembling(r#"${1|"uper"} ${1|upper} ${1|nobody}"#).is_empty());
+  }
+
+  #[test]
+  fn test_template_parse_errors() {
+    let validate = |tmpl: &str| TemplateString::from(tmpl).validate();
+    assert_eq!(validate("no expressions"), Ok(()));
+    assert_eq!(validate("${1} and ${ name }"), Ok(()));
+    assert_eq!(validate("oops ${1"), Err(TemplateParseError::Unclosed(5)));
+    assert_eq!(
+      validate("${ }"),
+      Err(TemplateParseError::EmptyExpression(2))
+    );
+    assert_eq!(
+      validate("${N1 / }"),
+      Err(TemplateParseError::Unexpected {
+        position: 5,
+        found: "end of expression".into()
+      })
+    );
+    assert_eq!(
+      validate("${1|round(x)}"),
+      Err(TemplateParseError::InvalidArgument {
+        position: 4,
+        pipe: "round".into()
+      })
+    );
+    assert_eq!(
+      validate("${1|rund(2)}"),
+      Err(TemplateParseError::UnknownPipe {
+        position: 4,
+        pipe: "rund".into()
+      })
+    );
+    assert_eq!(validate(r#"${1|"rund(2)"}"#), Ok(()));
+    // Invalid templates still render their plain variables
+    let tmpl = TemplateString::from("${C} ${N1 / }");
+    assert_eq!(
+      tmpl.render(&crate::matchers::MatchContext::empty("Xenk")),
+      "Xenk ${N1 / }"
+    );
   }
 }
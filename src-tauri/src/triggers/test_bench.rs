@@ -0,0 +1,249 @@
+//! The test bench runs a Trigger's Filter against sample log lines and renders every template
+//! in its effect tree, without firing any of the effects.
+use super::command_template::CommandTemplateSecurityCheck;
+use super::effects::{Effect, EffectWithID};
+use super::template_string::TemplateString;
+use super::timers::{TimerEffect, TimerStartPolicy};
+use super::trigger_index::TriggerIndex;
+use super::Trigger;
+use crate::common::UUID;
+use crate::logs::Line;
+use crate::matchers::{BuiltInVariables, LineToMatch, MatchContext};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize, ts_rs::TS)]
+#[serde(tag = "variant", content = "value")]
+#[ts(tag = "variant", content = "value")]
+pub enum TriggerTestSubject {
+  Saved(UUID),
+  Unsaved(Trigger),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum TriggerTestError {
+  #[error("Could not find a Trigger with ID {0}")]
+  TriggerNotFound(UUID),
+}
+
+#[derive(Debug, Serialize, ts_rs::TS)]
+pub struct TriggerTestResult {
+  pub line: String,
+  /// This is `None` if none of the Trigger's Matchers matched the line
+  pub matched: Option<TriggerTestMatch>,
+}
+
+#[derive(Debug, Serialize, ts_rs::TS)]
+pub struct TriggerTestMatch {
+  pub matcher_id: UUID,
+  pub context: MatchContext,
+  pub rendered: Vec<RenderedTemplate>,
+}
+
+#[derive(Debug, Serialize, ts_rs::TS)]
+pub struct RenderedTemplate {
+  pub effect_id: UUID,
+  pub template: String,
+  pub rendered: String,
+}
+
+impl TriggerTestSubject {
+  pub fn resolve(self, index: &TriggerIndex) -> Result<Trigger, TriggerTestError> {
+    match self {
+      Self::Saved(trigger_id) => index
+        .triggers
+        .get(&trigger_id)
+        .cloned()
+        .ok_or(TriggerTestError::TriggerNotFound(trigger_id)),
+      Self::Unsaved(trigger) => Ok(trigger),
+    }
+  }
+}
+
+/// Lines may be given with or without the log file's `[datetime]` prefix.
+pub fn test_trigger<S: AsRef<str>>(
+  trigger: &Trigger,
+  lines: &[S],
+  character_name: &str,
+  boxed_set: &[String],
+) -> Vec<TriggerTestResult> {
+  lines
+    .iter()
+    .map(|line| {
+      let line = line.as_ref();
+      let (content, log_timestamp) = match Line::from(line) {
+        Ok(parsed_line) => (parsed_line.content, Some(parsed_line.raw_datetime)),
+        Err(_) => (line.to_owned(), None),
+      };
+      let line_to_match = LineToMatch::new(&content, character_name, boxed_set);
+      let matched = trigger.filter.matchers().iter().find_map(|matcher| {
+        let mut context = matcher.check_line(&line_to_match)?;
+        context.built_ins = BuiltInVariables::for_line(
+          &content,
+          log_timestamp.as_deref(),
+          &trigger.name,
+          Some(line_to_match.chat()),
+          None,
+          None,
+        );
+        let mut rendered = Vec::new();
+        render_effects(&trigger.effects, &context, &mut rendered);
+        Some(TriggerTestMatch {
+          matcher_id: matcher.id().clone(),
+          context,
+          rendered,
+        })
+      });
+      TriggerTestResult {
+        line: content,
+        matched,
+      }
+    })
+    .collect()
+}
+
+/// This function is designed to be used from the CLI. It prints the results to STDOUT.
+pub fn print_results(results: &[TriggerTestResult]) {
+  for result in results.iter() {
+    println!("{}", result.line);
+    let Some(matched) = &result.matched else {
+      println!("  (no match)\n");
+      continue;
+    };
+    println!("  matched by {}", matched.matcher_id);
+    for (index, value) in matched.context.group_values.iter().enumerate() {
+      println!("  ${{{index}}} = {}", value.as_deref().unwrap_or("(none)"));
+    }
+    let mut named_values: Vec<_> = matched.context.named_values.iter().collect();
+    named_values.sort();
+    for (name, value) in named_values {
+      println!("  ${{{name}}} = {value}");
+    }
+    for rendered in matched.rendered.iter() {
+      println!("  `{}` => `{}`", rendered.template, rendered.rendered);
+    }
+    println!();
+  }
+}
+
+fn render_effects(
+  effects: &[EffectWithID],
+  context: &MatchContext,
+  out: &mut Vec<RenderedTemplate>,
+) {
+  for effect_with_id in effects.iter() {
+    let mut render = |tmpl: &TemplateString| {
+      out.push(RenderedTemplate {
+        effect_id: effect_with_id.id.clone(),
+        template: tmpl.tmpl().to_owned(),
+        rendered: tmpl.render(context),
+      })
+    };
+    match &effect_with_id.effect {
+      Effect::Parallel(effects) | Effect::Sequence(effects) => {
+        render_effects(effects, context, out)
+      }
+      Effect::PlayAudioFile(Some(tmpl))
+      | Effect::CopyToClipboard(tmpl)
+      | Effect::TakeAttendanceSnapshot(tmpl)
+      | Effect::OverlayMessage(tmpl)
+      | Effect::Speak { tmpl, .. } => render(tmpl),
+      Effect::StartTimer(timer) => {
+        render(&timer.name_tmpl);
+        if let TimerStartPolicy::StartAndReplacesAnyTimerOfTriggerWithNameTemplateMatching(tmpl) =
+          &timer.start_policy
+        {
+          render(tmpl);
+        }
+        render_effects(&timer.effects, context, out);
+      }
+      Effect::StartStopwatch(stopwatch) => {
+        render(&stopwatch.name);
+        render_effects(&stopwatch.effects, context, out);
+      }
+      Effect::RunSystemCommand(
+        CommandTemplateSecurityCheck::Approved(_, cmd_tmpl)
+        | CommandTemplateSecurityCheck::Unapproved(cmd_tmpl),
+      ) => {
+        cmd_tmpl
+          .params
+          .iter()
+          .chain(cmd_tmpl.write_to_stdin.iter())
+          .for_each(render);
+      }
+      Effect::ScopedTimerEffect(TimerEffect::WaitUntilFilterMatches(filter, _)) => {
+        // The "rendered" form of a Filter with context is the pattern that would be compiled
+        let compiled = filter.compile_with_context(context);
+        for (with_context, matcher) in filter.matchers().iter().zip(compiled.matchers().iter()) {
+          out.push(RenderedTemplate {
+            effect_id: effect_with_id.id.clone(),
+            template: with_context.raw_pattern().to_owned(),
+            rendered: matcher.raw_pattern().to_owned(),
+          });
+        }
+      }
+      Effect::PlayAudioFile(None)
+      | Effect::ScopedTimerEffect(_)
+      | Effect::Pause(_)
+      | Effect::SpeakStop
+      | Effect::DoNothing => {}
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::test_trigger;
+  use crate::{
+    common::{timestamp::Timestamp, UUID},
+    matchers::Matcher,
+    triggers::{
+      effects::{Effect, EffectWithID},
+      Trigger,
+    },
+  };
+
+  #[test]
+  fn test_trigger_test_bench() {
+    let trigger = Trigger {
+      id: UUID::new(),
+      parent_id: None,
+      name: "Tells".into(),
+      comment: None,
+      filter: vec![
+        Matcher::gina(r"^{S} tells you, '(.+)'$").unwrap(),
+        Matcher::gina(r"^{S} told you, '(.+)'$").unwrap(),
+      ]
+      .into(),
+      effects: vec![EffectWithID::new(Effect::Sequence(vec![
+        EffectWithID::new(Effect::Speak {
+          tmpl: "${S} says ${1} to ${C}".into(),
+          interrupt: false,
+        }),
+      ]))],
+      created_at: Timestamp::now(),
+      updated_at: Timestamp::now(),
+    };
+    let second_matcher_id = trigger.filter.matchers()[1].id().clone();
+
+    let results = test_trigger(
+      &trigger,
+      &[
+        "[Thu Jul 18 17:35:14 2024] Tunare told you, 'hello'",
+        "You gain experience!!",
+      ],
+      "Xenk",
+      &[],
+    );
+
+    let matched = results[0]
+      .matched
+      .as_ref()
+      .expect("first line should match");
+    assert_eq!(results[0].line, "Tunare told you, 'hello'");
+    assert_eq!(matched.matcher_id, second_matcher_id);
+    assert_eq!(matched.context.named_value("S"), Some("Tunare"));
+    assert_eq!(matched.rendered.len(), 1);
+    assert_eq!(matched.rendered[0].rendered, "Tunare says hello to Xenk");
+    assert!(results[1].matched.is_none());
+  }
+}
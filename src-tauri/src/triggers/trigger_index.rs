@@ -17,6 +17,10 @@ pub struct TriggerTag {
   pub id: UUID,
   name: String,
   triggers: HashSet<UUID>,
+  /// If any zones are listed, the TriggerTag is activated when the current character enters
+  /// one of them and deactivated when they enter any other zone
+  #[serde(default)]
+  zones: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, ts_rs::TS)]
@@ -53,6 +57,10 @@ pub enum Mutation {
   DeleteTriggerGroup(UUID),
   CreateTriggerTag(String),
   RenameTriggerTag(UUID, String),
+  SetTriggerTagZones {
+    trigger_tag_id: UUID,
+    zones: Vec<String>,
+  },
   DeleteTriggerTag(UUID),
   TagTrigger {
     trigger_id: UUID,
@@ -91,6 +99,10 @@ pub enum DataDelta {
 
   TriggerTagCreated(TriggerTag),
   TriggerTagRenamed(UUID, String),
+  TriggerTagZonesChanged {
+    trigger_tag_id: UUID,
+    zones: Vec<String>,
+  },
   TriggerTagTriggersChanged {
     trigger_tag_id: UUID,
     triggers: Vec<UUID>,
@@ -114,6 +126,13 @@ pub enum DataMutationError {
 
   #[error("Tried performing an incorrect Effect mutation!")]
   IncorrectEffectType,
+
+  #[error("Invalid template {template:?} in Effect {effect_id}: {message}")]
+  InvalidTemplate {
+    effect_id: UUID,
+    template: String,
+    message: String,
+  },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
@@ -168,7 +187,8 @@ impl TriggerIndex {
   }
 
   /// Takes an iterator of Tag IDs and returns borrows of all distinct Triggers tagged by any
-  /// of the tags. The returned triggers have an arbitrary order (due to underlying HashSet).
+  /// of the tags. The returned triggers are sorted by name (then ID), so that they are always
+  /// matched, and their Effects fired, in the same order.
   pub fn get_distinct_triggers_tagged_by_any_of<'a, I>(&'a self, tag_ids: I) -> Vec<&'a Trigger>
   where
     I: Iterator<Item = &'a UUID>,
@@ -178,10 +198,46 @@ impl TriggerIndex {
       .into_iter()
       .flat_map(|tag| tag.triggers.iter())
       .collect();
-    distinct_trigger_ids
+    let mut triggers: Vec<&Trigger> = distinct_trigger_ids
       .into_iter()
       .filter_map(|trigger_id| self.triggers.get(trigger_id))
-      .collect()
+      .collect();
+    triggers.sort_by(|a, b| {
+      a.name
+        .cmp(&b.name)
+        .then_with(|| a.id.as_ref().cmp(b.id.as_ref()))
+    });
+    triggers
+  }
+
+  /// Returns the TriggerTags that list any zones, and the ones that list the given zone
+  pub fn zone_rule_trigger_tags(&self, zone: &str) -> (HashSet<UUID>, HashSet<UUID>) {
+    let zone_scoped: HashSet<UUID> = self
+      .trigger_tags
+      .values()
+      .filter(|tag| tag.is_zone_scoped())
+      .map(|tag| tag.id.clone())
+      .collect();
+    let in_zone: HashSet<UUID> = self
+      .trigger_tags
+      .values()
+      .filter(|tag| tag.lists_zone(zone))
+      .map(|tag| tag.id.clone())
+      .collect();
+    (zone_scoped, in_zone)
+  }
+
+  /// Finds a TriggerTag by its ID, or by its name ignoring case
+  pub fn find_trigger_tag(&self, id_or_name: &str) -> Option<&TriggerTag> {
+    self
+      .trigger_tags
+      .get(&UUID::from_str_unchecked(id_or_name))
+      .or_else(|| {
+        self
+          .trigger_tags
+          .values()
+          .find(|tag| tag.name.eq_ignore_ascii_case(id_or_name))
+      })
   }
 
   fn get_trigger_tags<'a, I>(&'a self, tag_ids: I) -> Vec<&'a TriggerTag>
@@ -209,6 +265,7 @@ impl TriggerIndex {
         trigger_tag_ids,
         parent_position,
       } => {
+        trigger.validate_templates()?;
         trigger.updated_now();
 
         let trigger_id = trigger.id.clone();
@@ -259,6 +316,7 @@ impl TriggerIndex {
         trigger_tag_ids,
       } => {
         _ = self.try_get_mutable_trigger(&trigger.id)?;
+        trigger.validate_templates()?;
         trigger.updated_now();
 
         let trigger_id = trigger.id.clone();
@@ -274,9 +332,7 @@ impl TriggerIndex {
               _ => true,
             };
             if let Some(previous_parent_id) = replaced_trigger.parent_id {
-              let Some(parent_group) = self.groups.get_mut(&previous_parent_id) else {
-                return None;
-              };
+              let parent_group = self.groups.get_mut(&previous_parent_id)?;
               parent_group.children.retain(different_tgd);
               Some(DataDelta::TriggerGroupChildrenChanged {
                 trigger_group_id: parent_group.id.clone(),
@@ -294,7 +350,7 @@ impl TriggerIndex {
           deltas.push(parent_delta);
         }
 
-        let new_trigger_tag_ids: HashSet<UUID> = HashSet::from_iter(trigger_tag_ids.into_iter());
+        let new_trigger_tag_ids: HashSet<UUID> = HashSet::from_iter(trigger_tag_ids);
 
         for each_trigger_tag in self.trigger_tags.values_mut() {
           if each_trigger_tag.triggers.contains(&trigger_id) {
@@ -349,8 +405,8 @@ impl TriggerIndex {
           .collect();
 
         let deltas = once(DataDelta::TriggerDeleted(trigger_id))
-          .chain(parent_delta.into_iter())
-          .chain(trigger_tag_deltas.into_iter())
+          .chain(parent_delta)
+          .chain(trigger_tag_deltas)
           .collect();
 
         Ok(deltas)
@@ -397,7 +453,7 @@ impl TriggerIndex {
             TriggerGroupDescendant::G(descendant_group_id) => {
               if let Some(mut descendant_group) = self.groups.remove(&descendant_group_id) {
                 descendants.append(&mut VecDeque::from_iter(
-                  descendant_group.children.drain(..).into_iter(),
+                  descendant_group.children.drain(..),
                 ));
                 nested_groups.push_back(descendant_group);
               }
@@ -446,10 +502,10 @@ impl TriggerIndex {
           .collect();
 
         let deltas: Vec<DataDelta> = once(DataDelta::TriggerGroupDeleted(group_id))
-          .chain(parent_delta.into_iter())
-          .chain(trigger_deltas.into_iter())
-          .chain(trigger_group_deltas.into_iter())
-          .chain(trigger_tag_deltas.into_iter())
+          .chain(parent_delta)
+          .chain(trigger_deltas)
+          .chain(trigger_group_deltas)
+          .chain(trigger_tag_deltas)
           .collect();
 
         Ok(deltas)
@@ -478,7 +534,7 @@ impl TriggerIndex {
         if let Some(tag) = self.trigger_tags.get_mut(&trigger_tag_id) {
           tag.triggers.remove(&trigger_id);
           Ok(vec![DataDelta::TriggerUntagged {
-            trigger_id: trigger_id,
+            trigger_id,
             trigger_tag_id,
           }])
         } else {
@@ -500,6 +556,23 @@ impl TriggerIndex {
         trigger_tag.name = name.clone();
         Ok(vec![DataDelta::TriggerTagRenamed(trigger_tag_id, name)])
       }
+      Mutation::SetTriggerTagZones {
+        trigger_tag_id,
+        zones,
+      } => {
+        let Some(trigger_tag) = self.trigger_tags.get_mut(&trigger_tag_id) else {
+          return Err(DataMutationError::TriggerTagNotFound(trigger_tag_id));
+        };
+        trigger_tag.zones = zones
+          .iter()
+          .map(|zone| zone.trim().to_owned())
+          .filter(|zone| !zone.is_empty())
+          .collect();
+        Ok(vec![DataDelta::TriggerTagZonesChanged {
+          trigger_tag_id,
+          zones: trigger_tag.zones.clone(),
+        }])
+      }
       Mutation::DeleteTriggerTag(trigger_tag_id) => self.delete_trigger_tag(&trigger_tag_id),
     }
   }
@@ -547,17 +620,11 @@ impl TriggerIndex {
     Ok(vec![group_delta, parent_delta])
   }
 
-  fn trigger_tags_with_trigger(&self, trigger_id: &UUID) -> Vec<&TriggerTag> {
+  pub fn trigger_tags_with_trigger(&self, trigger_id: &UUID) -> Vec<&TriggerTag> {
     self
       .trigger_tags
-      .iter()
-      .filter_map(|(_, tag)| {
-        if tag.triggers.contains(trigger_id) {
-          Some(tag)
-        } else {
-          None
-        }
-      })
+      .values()
+      .filter(|tag| tag.triggers.contains(trigger_id))
       .collect()
   }
 
@@ -582,8 +649,20 @@ impl TriggerTag {
       id: UUID::new(),
       name: name.to_owned(),
       triggers: HashSet::new(),
+      zones: Vec::new(),
     }
   }
+
+  pub fn is_zone_scoped(&self) -> bool {
+    !self.zones.is_empty()
+  }
+
+  pub fn lists_zone(&self, zone: &str) -> bool {
+    self
+      .zones
+      .iter()
+      .any(|listed| listed.eq_ignore_ascii_case(zone))
+  }
 }
 
 impl std::hash::Hash for TriggerTag {
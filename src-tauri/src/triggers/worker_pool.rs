@@ -0,0 +1,185 @@
+//! The TriggerWorkerPool checks a line against the active Triggers on several threads at once.
+//! The candidate Triggers for a line are split into one shard per worker, and the matches from
+//! every shard are merged back into the order that checking them one at a time would produce,
+//! so the reactor can fire their Effects in the same order either way.
+use crate::{matchers::MatchContext, state::active_triggers::ActiveTriggers};
+use std::{
+  sync::{mpsc, Arc},
+  thread,
+};
+use tokio::sync::oneshot;
+use tracing::{debug, error};
+
+/// Lines with fewer candidate Triggers than this are checked on the calling thread, since
+/// handing them off to the workers would cost more than it saves
+const MIN_CANDIDATES_TO_PARALLELIZE: usize = 32;
+
+pub struct TriggerWorkerPool {
+  workers: Vec<mpsc::Sender<Job>>,
+}
+
+struct Job {
+  active_triggers: Arc<ActiveTriggers>,
+  line: Arc<str>,
+  character_name: Arc<str>,
+  candidates: Arc<Vec<usize>>,
+  shard: usize,
+  shard_count: usize,
+  tx_matches: oneshot::Sender<Vec<(usize, MatchContext)>>,
+}
+
+impl TriggerWorkerPool {
+  /// Starts one worker thread per available CPU core. The threads exit when the pool is dropped.
+  pub fn new() -> Self {
+    let worker_count = thread::available_parallelism()
+      .map(|n| n.get())
+      .unwrap_or(1);
+    Self::with_worker_count(worker_count)
+  }
+
+  pub fn with_worker_count(worker_count: usize) -> Self {
+    debug!("Starting {worker_count} trigger worker threads");
+    let workers = (0..worker_count.max(1))
+      .filter_map(|n| {
+        let (tx, rx) = mpsc::channel::<Job>();
+        let spawned = thread::Builder::new()
+          .name(format!("trigger-worker-{n}"))
+          .spawn(move || run_worker(rx));
+        match spawned {
+          Ok(_) => Some(tx),
+          Err(e) => {
+            error!("Could not start trigger worker thread! Error: {e:?}");
+            None
+          }
+        }
+      })
+      .collect();
+    Self { workers }
+  }
+
+  /// Returns the same matches as `ActiveTriggers::check`, in the same order
+  pub async fn check(
+    &self,
+    active_triggers: &Arc<ActiveTriggers>,
+    line: &str,
+    character_name: &str,
+  ) -> Vec<(usize, MatchContext)> {
+    let candidates = active_triggers.candidate_indices(line);
+    if self.workers.len() < 2 || candidates.len() < MIN_CANDIDATES_TO_PARALLELIZE {
+      return active_triggers.check_each(candidates, line, character_name);
+    }
+
+    let candidates = Arc::new(candidates);
+    let line: Arc<str> = line.into();
+    let character_name: Arc<str> = character_name.into();
+    let shard_count = self.workers.len();
+
+    let mut rx_shards = Vec::with_capacity(shard_count);
+    for (shard, worker) in self.workers.iter().enumerate() {
+      let (tx_matches, rx_matches) = oneshot::channel();
+      let job = Job {
+        active_triggers: active_triggers.clone(),
+        line: line.clone(),
+        character_name: character_name.clone(),
+        candidates: candidates.clone(),
+        shard,
+        shard_count,
+        tx_matches,
+      };
+      rx_shards.push(worker.send(job).map(|_| rx_matches));
+    }
+
+    let mut matches: Vec<(usize, MatchContext)> = Vec::new();
+    for (shard, rx_shard) in rx_shards.into_iter().enumerate() {
+      let shard_matches = match rx_shard {
+        Ok(rx_matches) => rx_matches.await.ok(),
+        Err(_) => None,
+      };
+      // If the worker is gone (which should only happen if it panicked), its shard is
+      // checked here instead so that no Trigger is skipped
+      let shard_matches = shard_matches.unwrap_or_else(|| {
+        error!("Trigger worker {shard} is not running! Checking its triggers on the reactor");
+        let indices = shard_indices(&candidates, shard, shard_count);
+        active_triggers.check_each(indices, &line, &character_name)
+      });
+      matches.extend(shard_matches);
+    }
+    matches.sort_unstable_by_key(|(index, _)| *index);
+    matches
+  }
+}
+
+fn run_worker(rx: mpsc::Receiver<Job>) {
+  while let Ok(job) = rx.recv() {
+    let indices = shard_indices(&job.candidates, job.shard, job.shard_count);
+    let matches = job
+      .active_triggers
+      .check_each(indices, &job.line, &job.character_name);
+    _ = job.tx_matches.send(matches);
+  }
+}
+
+/// The shards are interleaved so that expensive Triggers that were created together are less
+/// likely to all end up on the same worker
+fn shard_indices(
+  candidates: &[usize],
+  shard: usize,
+  shard_count: usize,
+) -> impl Iterator<Item = usize> + '_ {
+  candidates.iter().skip(shard).step_by(shard_count).copied()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::TriggerWorkerPool;
+  use crate::{
+    common::{timestamp::Timestamp, UUID},
+    matchers::Matcher,
+    state::active_triggers::ActiveTriggers,
+    triggers::Trigger,
+  };
+  use std::sync::Arc;
+
+  #[tokio::test]
+  async fn test_worker_pool_matches_in_order() {
+    let triggers: Vec<Trigger> = (0..100)
+      .map(|n| {
+        let pattern = if n % 3 == 0 {
+          r"^{S} tells you, '(.+)'$".to_owned()
+        } else {
+          format!(r"^{{S}} tells you, 'code {n}'$")
+        };
+        Trigger {
+          id: UUID::new(),
+          parent_id: None,
+          name: format!("Trigger {n}"),
+          comment: None,
+          filter: vec![Matcher::gina(&pattern).unwrap()].into(),
+          effects: vec![],
+          created_at: Timestamp::now(),
+          updated_at: Timestamp::now(),
+        }
+      })
+      .collect();
+    let active_triggers = Arc::new(ActiveTriggers::from_triggers(triggers));
+    let pool = TriggerWorkerPool::with_worker_count(4);
+
+    for line in [
+      "Soandso tells you, 'code 7'",
+      "Soandso tells you, 'hello'",
+      "You gain experience!!",
+    ] {
+      let sequential = active_triggers.check(line, "Xenk");
+      let parallel = pool.check(&active_triggers, line, "Xenk").await;
+      let positions =
+        |matches: &Vec<_>| -> Vec<usize> { matches.iter().map(|(index, _)| *index).collect() };
+      assert_eq!(positions(&parallel), positions(&sequential));
+    }
+
+    let matches = pool
+      .check(&active_triggers, "Soandso tells you, 'code 7'", "Xenk")
+      .await;
+    assert_eq!(matches.len(), 35);
+    assert!(matches.iter().any(|(index, _)| *index == 7));
+  }
+}
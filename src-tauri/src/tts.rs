@@ -16,6 +16,7 @@ pub enum TTS {
     tx_done: Arc<oneshot::Sender<()>>,
   },
   StopSpeaking,
+  #[allow(unused)]
   SetVoice(String),
   Quit,
 }
@@ -142,7 +143,7 @@ pub fn print_voices() {
     return;
   }
   let mut wtr = csv::Writer::from_writer(std::io::stdout());
-  if let Err(e) = wtr.write_record(&["ID", "Language", "Gender"]) {
+  if let Err(e) = wtr.write_record(["ID", "Language", "Gender"]) {
     fatal_error(format!("Could not write CSV output: {e:?}"));
   }
   for voice in voices.iter() {
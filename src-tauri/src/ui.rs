@@ -1,7 +1,8 @@
 use crate::{
   commands,
-  common::shutdown::shutdown,
+  common::shutdown::{quitter, shutdown},
   common::{fatal_error, ternary},
+  logs::combat::CombatUpdate,
   reactor,
   state::{
     overlay::{OverlayManager, OverlayMode, OVERLAY_EDITABLE_CHANGED_EVENT_NAME},
@@ -13,6 +14,7 @@ use std::sync::Arc;
 use tauri::async_runtime::spawn;
 use tauri::App;
 use tauri::{AppHandle, GlobalShortcutManager, Manager, Window, WindowEvent};
+use tokio::{select, sync::broadcast};
 use tracing::{debug, error, info};
 
 pub type OverlayManagerState = Arc<OverlayManager>;
@@ -23,6 +25,10 @@ pub const OVERLAY_WINDOW_LABEL: &str = "overlay";
 
 pub const PROGRESS_UPDATE_EVENT_NAME: &str = "progress-update";
 pub const PROGRESS_UPDATE_FINISHED_EVENT_NAME: &str = "progress-update-finished";
+pub const ZONE_CHANGED_EVENT_NAME: &str = "zone-changed";
+pub const COMBAT_FIGHT_UPDATED_EVENT_NAME: &str = "combat-fight-updated";
+pub const COMBAT_FIGHT_FINISHED_EVENT_NAME: &str = "combat-fight-finished";
+pub const LOG_SEARCH_HITS_EVENT_NAME: &str = "log-search-hits";
 
 const TOGGLE_OVERLAY_ACCELERATOR: &str = "CommandOrControl+Alt+Shift+L";
 
@@ -35,6 +41,21 @@ pub fn launch(state: StateHandle) {
       let overlay_manager = create_overlay_manager(&app_handle, &timer_manager);
       app.manage(overlay_manager.clone() as OverlayManagerState);
       reactor(&app_handle, &state, timer_manager, overlay_manager);
+      forward_broadcast(
+        &app_handle,
+        state.zone_changed.subscribe(),
+        |app, zone_change| app.emit_all(ZONE_CHANGED_EVENT_NAME, zone_change),
+      );
+      forward_broadcast(
+        &app_handle,
+        state.combat_updates.subscribe(),
+        |app, update| match update {
+          CombatUpdate::FightUpdated(fight) => app.emit_all(COMBAT_FIGHT_UPDATED_EVENT_NAME, fight),
+          CombatUpdate::FightFinished(fight) => {
+            app.emit_all(COMBAT_FIGHT_FINISHED_EVENT_NAME, fight)
+          }
+        },
+      );
       setup(&app_handle);
       Ok(())
     })
@@ -90,6 +111,28 @@ fn reactor(
   });
 }
 
+/// Emits everything sent on a StateHandle broadcast channel to the UI until shutdown
+fn forward_broadcast<T, F>(app: &AppHandle, mut receiver: broadcast::Receiver<T>, emit: F)
+where
+  T: Clone + Send + 'static,
+  F: Fn(&AppHandle, T) -> tauri::Result<()> + Send + 'static,
+{
+  let app = app.clone();
+  spawn(async move {
+    let mut quit = quitter();
+    loop {
+      select! {
+        _ = &mut quit => break,
+        received = receiver.recv() => match received {
+          Ok(value) => _ = emit(&app, value),
+          Err(broadcast::error::RecvError::Lagged(_)) => {}
+          Err(broadcast::error::RecvError::Closed) => break,
+        }
+      }
+    }
+  });
+}
+
 fn setup(app: &AppHandle) {
   register_global_shortcut_manager(app);
   setup_overlay(app);
@@ -218,6 +261,6 @@ fn set_overlay_editable(app: &AppHandle, new_value: bool) {
   )
 }
 
-fn state(app: &AppHandle) -> tauri::State<StateHandle> {
+fn state(app: &AppHandle) -> tauri::State<'_, StateHandle> {
   app.state::<StateHandle>()
 }
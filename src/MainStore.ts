@@ -10,7 +10,10 @@ import triggerEditorReducer, {
 import triggersReducer, {
   TRIGGERS_SLICE,
 } from './features/triggers/triggersSlice';
-import { initOverlayStateListeners } from './tauriEventListeners';
+import {
+  initOverlayStateListeners,
+  initReactorStateListeners,
+} from './tauriEventListeners';
 
 const store = configureStore({
   reducer: {
@@ -32,6 +35,7 @@ const store = configureStore({
 });
 
 initOverlayStateListeners(store.dispatch);
+initReactorStateListeners(store.dispatch);
 
 export type MainRootState = ReturnType<typeof store.getState>;
 export type MainDispatch = typeof store.dispatch;
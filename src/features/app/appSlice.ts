@@ -5,6 +5,7 @@ import { Character } from '../../generated/Character';
 import { ProgressUpdate } from '../../generated/ProgressUpdate';
 import { ReactorState } from '../../generated/ReactorState';
 import { UUID } from '../../generated/UUID';
+import { ZoneChange } from '../../generated/ZoneChange';
 import { seqFromProgressUpdate } from '../../util';
 
 export const APP_SLICE = 'app';
@@ -69,6 +70,15 @@ const appSlice = createSlice({
       }
     },
 
+    setCharacterZone(
+      slice: AppState,
+      { payload: { character_name, zone } }: PayloadAction<ZoneChange>
+    ) {
+      if (slice.reactor) {
+        slice.reactor.character_zones[character_name] = zone;
+      }
+    },
+
     navigateTo(slice: AppState, { payload: mode }: PayloadAction<MODE>) {
       slice.currentMode = mode;
     },
@@ -107,6 +117,7 @@ export const {
   exitLoadingState,
   initReactor,
   navigateTo,
+  setCharacterZone,
   setCurrentCharacter,
   updateActivedTriggerTagIDs,
   updateProgress,
@@ -134,6 +145,11 @@ export const $currentCharacter = appSelector(
   ({ reactor }) => reactor?.current_character
 );
 
+export const $currentZone = appSelector(({ reactor }) => {
+  const characterName = reactor?.current_character?.name;
+  return characterName ? reactor.character_zones[characterName] : undefined;
+});
+
 export const $activeTriggerTags = appSelector(
   ({ reactor }) => reactor?.active_trigger_tags
 );
@@ -78,6 +78,16 @@ export function TriggerTagRenamed(
   index.trigger_tags[trigger_tag_id].name = name;
 }
 
+export function TriggerTagZonesChanged(
+  index: TriggerIndex,
+  { trigger_tag_id, zones }: { trigger_tag_id: UUID; zones: string[] }
+) {
+  const tag = index.trigger_tags[trigger_tag_id];
+  if (tag) {
+    tag.zones = zones;
+  }
+}
+
 export function TriggerTagTriggersChanged(
   index: TriggerIndex,
   { trigger_tag_id, triggers }: { trigger_tag_id: UUID; triggers: UUID[] }
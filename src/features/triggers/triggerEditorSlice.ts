@@ -73,6 +73,11 @@ export type EffectVariantCopyToClipboard = Extract<
   { variant: 'CopyToClipboard' }
 >;
 
+export type EffectVariantTakeAttendanceSnapshot = Extract<
+  Effect,
+  { variant: 'TakeAttendanceSnapshot' }
+>;
+
 export type EffectVariantSpeak = Extract<Effect, { variant: 'Speak' }>;
 
 export type EffectVariantOverlayMessage = Extract<
@@ -210,7 +215,16 @@ const triggerEditorSlice = createSlice({
       const filter = selector(slice);
       filter.push({
         variant,
-        value: { id: uuid(), pattern: '' },
+        value: {
+          id: uuid(),
+          pattern: '',
+          options: {
+            case_insensitive: false,
+            collapse_whitespace: false,
+            ignore_trailing_punctuation: false,
+            constraints: null,
+          },
+        },
       } as M);
     },
 
@@ -301,6 +315,19 @@ const triggerEditorSlice = createSlice({
       copyToClipboard.value = tmpl;
     },
 
+    setAttendanceSnapshotLabel(
+      slice: TriggerEditorState,
+      {
+        payload: { tmpl, selector },
+      }: PayloadAction<{
+        tmpl: string;
+        selector: TriggerEditorSelector<EffectVariantTakeAttendanceSnapshot>;
+      }>
+    ) {
+      const takeAttendanceSnapshot = selector(slice);
+      takeAttendanceSnapshot.value = tmpl;
+    },
+
     setSpeakTemplate(
       slice: TriggerEditorState,
       {
@@ -402,6 +429,7 @@ export const {
   forgetError,
   insertNewEffect,
   insertNewEffectOrTimerEffect,
+  setAttendanceSnapshotLabel,
   setAudioFile,
   setCommandTemplateSecurityCheck,
   setCopyToClipboardTemplate,
@@ -463,6 +491,8 @@ function newEffect(variant: EffectVariant, triggerID: UUID): Effect {
     case 'OverlayMessage':
     case 'CopyToClipboard':
       return { variant, value: '' };
+    case 'TakeAttendanceSnapshot':
+      return { variant, value: '${TRIGGER}' };
     case 'Parallel':
     case 'Sequence':
       return { variant, value: [] };
@@ -128,6 +128,8 @@ const triggersSlice = createSlice({
           deltas[variant](state.index, value);
         } else if (variant === 'TriggerTagRenamed') {
           deltas[variant](state.index, value);
+        } else if (variant === 'TriggerTagZonesChanged') {
+          deltas[variant](state.index, value);
         } else if (variant === 'TriggerGroupDeleted') {
           deltas[variant](state.index, value);
         } else {
@@ -0,0 +1,20 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Timestamp } from './Timestamp';
+import type { UUID } from './UUID';
+import type { WhoEntry } from './WhoEntry';
+
+export type AttendanceSnapshot = {
+  id: UUID;
+  label: string;
+  /**
+   * The character whose log the listing was read from
+   */
+  character_name: string;
+  listed_at: Timestamp;
+  location: string;
+  /**
+   * The number of players EverQuest said there were
+   */
+  reported_count: number;
+  players: Array<WhoEntry>;
+};
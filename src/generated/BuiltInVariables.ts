@@ -0,0 +1,46 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Values about the environment of a match rather than the matched text. They are filled in
+ * by whatever reacts to the line, so any of them can be missing (e.g. the timer values
+ * outside of a Timer's Effects).
+ */
+export type BuiltInVariables = {
+  /**
+   * `${SERVER}`
+   */
+  server: string | null;
+  /**
+   * `${LINE}` is the content of the line without the timestamp
+   */
+  line: string | null;
+  /**
+   * `${TIMESTAMP}` is the timestamp of the line as written in the log file
+   */
+  log_timestamp: string | null;
+  /**
+   * `${TRIGGER}`
+   */
+  trigger_name: string | null;
+  /**
+   * `${ZONE}` is taken from the latest "You have entered <Zone>." line
+   */
+  zone: string | null;
+  /**
+   * `${CHANNEL}` is the chat channel of the line (e.g. "Tell", or the name of a named channel),
+   * or "Combat" or "System" if it is not chat
+   */
+  channel: string | null;
+  /**
+   * `${SPEAKER}` is who said the line, if it is chat
+   */
+  speaker: string | null;
+  /**
+   * `${TIMER}`
+   */
+  timer_name: string | null;
+  /**
+   * `${REMAINING}` is in whole seconds, so it can be piped to `duration`
+   */
+  timer_remaining_seconds: number | null;
+};
@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type ChatChannel =
+  | 'Tell'
+  | 'Group'
+  | 'Guild'
+  | 'Raid'
+  | 'Say'
+  | 'Shout'
+  | 'Auction'
+  | 'OOC'
+  | 'Channel'
+  | 'System'
+  | 'Combat';
@@ -0,0 +1,23 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ChatChannel } from './ChatChannel';
+
+/**
+ * Restricts a Matcher to lines of certain chat channels or speakers. A speaker can be given as
+ * `${C}` (or `{C}`) for the character whose log it is, and speakers are compared
+ * case-insensitively.
+ */
+export type ChatConstraints = {
+  /**
+   * Any channel if empty
+   */
+  channels: Array<ChatChannel>;
+  /**
+   * Any speaker if empty. Lines that are not chat have no speaker, so they never match.
+   */
+  allowed_speakers: Array<string>;
+  blocked_speakers: Array<string>;
+  /**
+   * Only chat said by the character or by another character of the boxed set matches
+   */
+  from_boxed_set: boolean;
+};
@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ChatChannel } from './ChatChannel';
+import type { Timestamp } from './Timestamp';
+
+/**
+ * A classified line from a log file, as found by `search_log_file`
+ */
+export type ChatMessage = {
+  logged_at: Timestamp;
+  channel: ChatChannel;
+  channel_name: string | null;
+  speaker: string | null;
+  message: string;
+};
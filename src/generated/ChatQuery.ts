@@ -0,0 +1,13 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ChatChannel } from './ChatChannel';
+
+export type ChatQuery = {
+  /**
+   * Any channel if empty
+   */
+  channels: Array<ChatChannel>;
+  /**
+   * Case-insensitive, and `None` is any speaker
+   */
+  speaker: string | null;
+};
@@ -28,6 +28,10 @@ export type DataDelta =
   | { variant: 'TriggerGroupDeleted'; value: UUID }
   | { variant: 'TriggerTagCreated'; value: TriggerTag }
   | { variant: 'TriggerTagRenamed'; value: [UUID, string] }
+  | {
+      variant: 'TriggerTagZonesChanged';
+      value: { trigger_tag_id: UUID; zones: Array<string> };
+    }
   | {
       variant: 'TriggerTagTriggersChanged';
       value: { trigger_tag_id: UUID; triggers: Array<UUID> };
@@ -6,4 +6,7 @@ export type DataMutationError =
   | { TriggerGroupNotFound: UUID }
   | { TriggerTagNotFound: UUID }
   | { EffectNotFound: UUID }
-  | 'IncorrectEffectType';
+  | 'IncorrectEffectType'
+  | {
+      InvalidTemplate: { effect_id: UUID; template: string; message: string };
+    };
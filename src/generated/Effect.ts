@@ -12,6 +12,7 @@ export type Effect =
   | { variant: 'Sequence'; value: Array<EffectWithID> }
   | { variant: 'PlayAudioFile'; value: TemplateString | null }
   | { variant: 'CopyToClipboard'; value: TemplateString }
+  | { variant: 'TakeAttendanceSnapshot'; value: TemplateString }
   | { variant: 'OverlayMessage'; value: TemplateString }
   | { variant: 'StartTimer'; value: Timer }
   | { variant: 'StartStopwatch'; value: Stopwatch }
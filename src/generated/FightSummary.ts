@@ -0,0 +1,22 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SourceSummary } from './SourceSummary';
+import type { Timestamp } from './Timestamp';
+
+export type FightSummary = {
+  fight_id: number;
+  character_name: string;
+  started_at: Timestamp;
+  ended_at: Timestamp;
+  /**
+   * Log timestamps only have a resolution of one second, so this is at least 1
+   */
+  duration_seconds: number;
+  total_damage: number;
+  total_healing: number;
+  dps: number;
+  hps: number;
+  /**
+   * Sorted by the most damage done
+   */
+  sources: Array<SourceSummary>;
+};
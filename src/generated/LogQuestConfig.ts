@@ -1,3 +1,59 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { LogWatcherMode } from './LogWatcherMode';
+import type { PinnedCharacter } from './PinnedCharacter';
 
-export type LogQuestConfig = { everquest_directory: string | null };
+export type LogQuestConfig = {
+  everquest_directory: string | null;
+  /**
+   * When enabled, every Trigger firing is also appended to a journal file in the data directory
+   */
+  trigger_history_journal: boolean;
+  /**
+   * When enabled, each line is checked against the active Triggers on a pool of worker
+   * threads instead of one at a time
+   */
+  parallel_trigger_evaluation: boolean;
+  /**
+   * When enabled, the damage and healing in the followed character's log are parsed into
+   * fights
+   */
+  combat_parsing: boolean;
+  /**
+   * When enabled, the loot and `/random` rolls in the followed character's log are saved to
+   * the character's loot history
+   */
+  loot_tracking: boolean;
+  /**
+   * The names of the other characters that are played alongside whichever one is active, for
+   * Matchers that only accept chat from the boxed set
+   */
+  boxed_set: Array<string>;
+  /**
+   * When enabled, the old lines of the log files of characters that are not being played are
+   * moved into compressed archives in the Logs/Archive directory every few hours
+   */
+  log_archiving: boolean;
+  /**
+   * How many days of lines are left in a log file when it is archived
+   */
+  log_archive_after_days: number | null;
+  /**
+   * How the logs directory is watched for changes. The Poll and Hybrid modes are for
+   * filesystems that do not reliably send change notifications.
+   */
+  log_watcher_mode: LogWatcherMode;
+  /**
+   * How often the log files are checked in the Poll and Hybrid modes. Anything shorter than
+   * MIN_POLL_INTERVAL is raised to it.
+   */
+  log_watcher_poll_interval_ms: number | null;
+  /**
+   * How long the log file of the active character must go without being written to before the
+   * character of another log file that keeps being written to becomes the active one
+   */
+  character_switch_delay_secs: number | null;
+  /**
+   * When set, this character is always the active one, whichever log files are written to
+   */
+  pinned_character: PinnedCharacter | null;
+};
@@ -0,0 +1,17 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Timestamp } from './Timestamp';
+
+export type LogSearchHit = {
+  log_file_path: string;
+  character_name: string;
+  server: string;
+  /**
+   * Where the line starts in the log file, in bytes
+   */
+  offset: number;
+  logged_at: Timestamp;
+  /**
+   * The content of the line without the timestamp
+   */
+  line: string;
+};
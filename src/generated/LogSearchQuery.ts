@@ -0,0 +1,23 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Matcher } from './Matcher';
+import type { Timestamp } from './Timestamp';
+
+export type LogSearchQuery = {
+  matcher: Matcher;
+  /**
+   * Only lines logged at or after this
+   */
+  from: Timestamp | null;
+  /**
+   * Only lines logged before this
+   */
+  until: Timestamp | null;
+  /**
+   * Only the log files of this character (case-insensitive), or of every character if None
+   */
+  character_name: string | null;
+  /**
+   * The search stops after this many hits
+   */
+  limit: number | null;
+};
@@ -0,0 +1,13 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Timestamp } from './Timestamp';
+
+export type LootDrop = {
+  looted_at: Timestamp;
+  looter: string;
+  item: string;
+  quantity: number;
+  /**
+   * Only newer servers say which corpse the item was looted from
+   */
+  corpse: string | null;
+};
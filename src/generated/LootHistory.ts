@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { LootDrop } from './LootDrop';
+import type { RollSession } from './RollSession';
+
+export type LootHistory = {
+  /**
+   * Oldest first
+   */
+  drops: Array<LootDrop>;
+  /**
+   * Oldest first
+   */
+  roll_sessions: Array<RollSession>;
+};
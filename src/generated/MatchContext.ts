@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BuiltInVariables } from './BuiltInVariables';
+
+export type MatchContext = {
+  group_values: Array<string | null>;
+  named_values: { [key: string]: string };
+  character_name: string;
+  built_ins: BuiltInVariables;
+};
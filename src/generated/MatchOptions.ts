@@ -0,0 +1,25 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ChatConstraints } from './ChatConstraints';
+
+/**
+ * Options that change how a Matcher compares the line. The whitespace and punctuation options
+ * are applied to the line before it is matched (and to the pattern of WholeLine and
+ * PartialLine Matchers), so regex patterns should be written to match the normalized line.
+ * Captured values keep the case they have in the line.
+ */
+export type MatchOptions = {
+  case_insensitive: boolean;
+  /**
+   * Runs of whitespace are treated as a single space, and leading/trailing whitespace is ignored
+   */
+  collapse_whitespace: boolean;
+  /**
+   * Any of `.!?,;:` (and whitespace) at the end of the line is ignored
+   */
+  ignore_trailing_punctuation: boolean;
+  /**
+   * Checked before the pattern, so that e.g. the text of a Trigger that someone pasted into
+   * OOC does not fire it
+   */
+  constraints: ChatConstraints | null;
+};
@@ -1,10 +1,23 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { MatchOptions } from './MatchOptions';
 import type { RegexGINA } from './RegexGINA';
 import type { SerializableRegex } from './SerializableRegex';
 import type { UUID } from './UUID';
 
 export type Matcher =
-  | { variant: 'WholeLine'; value: { id: UUID; pattern: string } }
-  | { variant: 'PartialLine'; value: { id: UUID; pattern: string } }
-  | { variant: 'Pattern'; value: { id: UUID; pattern: SerializableRegex } }
-  | { variant: 'GINA'; value: { id: UUID; pattern: RegexGINA } };
+  | {
+      variant: 'WholeLine';
+      value: { id: UUID; pattern: string; options: MatchOptions };
+    }
+  | {
+      variant: 'PartialLine';
+      value: { id: UUID; pattern: string; options: MatchOptions };
+    }
+  | {
+      variant: 'Pattern';
+      value: { id: UUID; pattern: SerializableRegex; options: MatchOptions };
+    }
+  | {
+      variant: 'GINA';
+      value: { id: UUID; pattern: RegexGINA; options: MatchOptions };
+    };
@@ -1,4 +1,5 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { MatchOptions } from './MatchOptions';
 import type { UUID } from './UUID';
 
 /**
@@ -12,7 +13,19 @@ import type { UUID } from './UUID';
  * that the values stored in the Strings are guaranteed to be error-free.
  */
 export type MatcherWithContext =
-  | { variant: 'WholeLine'; value: { id: UUID; pattern: string } }
-  | { variant: 'PartialLine'; value: { id: UUID; pattern: string } }
-  | { variant: 'Pattern'; value: { id: UUID; pattern: string } }
-  | { variant: 'GINA'; value: { id: UUID; pattern: string } };
+  | {
+      variant: 'WholeLine';
+      value: { id: UUID; pattern: string; options: MatchOptions };
+    }
+  | {
+      variant: 'PartialLine';
+      value: { id: UUID; pattern: string; options: MatchOptions };
+    }
+  | {
+      variant: 'Pattern';
+      value: { id: UUID; pattern: string; options: MatchOptions };
+    }
+  | {
+      variant: 'GINA';
+      value: { id: UUID; pattern: string; options: MatchOptions };
+    };
@@ -28,6 +28,10 @@ export type Mutation =
   | { variant: 'DeleteTriggerGroup'; value: UUID }
   | { variant: 'CreateTriggerTag'; value: string }
   | { variant: 'RenameTriggerTag'; value: [UUID, string] }
+  | {
+      variant: 'SetTriggerTagZones';
+      value: { trigger_tag_id: UUID; zones: Array<string> };
+    }
   | { variant: 'DeleteTriggerTag'; value: UUID }
   | { variant: 'TagTrigger'; value: { trigger_id: UUID; trigger_tag_id: UUID } }
   | {
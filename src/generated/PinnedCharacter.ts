@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A character that is always the active one, regardless of which log files are written to
+ */
+export type PinnedCharacter = { name: string; server: string };
@@ -1,8 +1,26 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { Character } from './Character';
+import type { CharacterDetectionReason } from './CharacterDetectionReason';
 import type { UUID } from './UUID';
 
 export type ReactorState = {
   current_character: Character | null;
+  /**
+   * Why the current character was detected as the active one
+   */
+  current_character_reason: CharacterDetectionReason | null;
   active_trigger_tags: Array<UUID>;
+  /**
+   * The last known zone of each character whose log file has been followed, by name
+   */
+  character_zones: { [key: string]: string };
+  /**
+   * TriggerTags that were turned on or off by hand this session. Zone rules do not change
+   * these until LogQuest is restarted.
+   */
+  trigger_tag_overrides: { [key: UUID]: boolean };
+  /**
+   * TriggerTags that list the zone the current character is in
+   */
+  zone_activated_trigger_tags: Array<UUID>;
 };
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { UUID } from './UUID';
+
+export type RenderedTemplate = {
+  effect_id: UUID;
+  template: string;
+  rendered: string;
+};
@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Timestamp } from './Timestamp';
+
+export type Roll = {
+  rolled_at: Timestamp;
+  roller: string;
+  low: number;
+  high: number;
+  result: number;
+};
@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Roll } from './Roll';
+import type { Timestamp } from './Timestamp';
+
+export type RollSession = {
+  started_at: Timestamp;
+  low: number;
+  high: number;
+  rolls: Array<Roll>;
+  /**
+   * Whoever rolled the highest; more than one if there was a tie. Only the first roll of each
+   * roller counts.
+   */
+  winners: Array<string>;
+};
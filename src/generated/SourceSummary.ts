@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * The damage and healing done by a single character, pet or NPC during a fight
+ */
+export type SourceSummary = {
+  name: string;
+  damage: number;
+  healing: number;
+  dps: number;
+  hps: number;
+  hits: number;
+  heals: number;
+};
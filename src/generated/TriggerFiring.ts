@@ -0,0 +1,20 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { MatchContext } from './MatchContext';
+import type { Timestamp } from './Timestamp';
+import type { TriggerEffectError } from './TriggerEffectError';
+import type { UUID } from './UUID';
+
+export type TriggerFiring = {
+  id: UUID;
+  trigger_id: UUID;
+  trigger_name: string;
+  character_name: string;
+  fired_at: Timestamp;
+  line: string;
+  captures: MatchContext;
+  effect_errors: Array<TriggerEffectError>;
+  /**
+   * Microseconds from when the reactor received the line until the Trigger matched it
+   */
+  latency_micros: number;
+};
@@ -0,0 +1,13 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { TriggerLintProblem } from './TriggerLintProblem';
+import type { UUID } from './UUID';
+
+export type TriggerLintIssue = {
+  trigger_id: UUID;
+  trigger_name: string;
+  /**
+   * The Effect the problem was found in, if the problem is not with the Trigger itself
+   */
+  effect_id: UUID | null;
+  problem: TriggerLintProblem;
+};
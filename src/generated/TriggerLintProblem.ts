@@ -0,0 +1,25 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CommandTemplate } from './CommandTemplate';
+import type { TimerEffect } from './TimerEffect';
+import type { UUID } from './UUID';
+
+export type TriggerLintProblem =
+  | { variant: 'AudioFileNotSelected' }
+  | { variant: 'AudioFileNotFound'; value: string }
+  | { variant: 'ScopedTimerEffectOutsideTimer'; value: TimerEffect }
+  | {
+      variant: 'UndefinedTemplateVariable';
+      value: { template: string; variable: string };
+    }
+  | { variant: 'InvalidTemplate'; value: { template: string; message: string } }
+  | {
+      variant: 'DefaultResemblesPipe';
+      value: { template: string; default: string; pipe: string };
+    }
+  | { variant: 'UnapprovedCommand'; value: CommandTemplate }
+  | { variant: 'NotInAnyTriggerTag' }
+  | { variant: 'NotInActiveTriggerTag' }
+  | {
+      variant: 'DuplicateFilter';
+      value: { other_trigger_id: UUID; other_trigger_name: string };
+    };
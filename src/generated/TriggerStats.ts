@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Timestamp } from './Timestamp';
+import type { UUID } from './UUID';
+
+export type TriggerStats = {
+  trigger_id: UUID;
+  trigger_name: string;
+  fire_count: number;
+  effect_error_count: number;
+  last_fired_at: Timestamp | null;
+};
@@ -1,4 +1,13 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { UUID } from './UUID';
 
-export type TriggerTag = { id: UUID; name: string; triggers: Array<UUID> };
+export type TriggerTag = {
+  id: UUID;
+  name: string;
+  triggers: Array<UUID>;
+  /**
+   * If any zones are listed, the TriggerTag is activated when the current character enters
+   * one of them and deactivated when they enter any other zone
+   */
+  zones: Array<string>;
+};
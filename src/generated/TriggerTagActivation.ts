@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { TriggerTagActivationReason } from './TriggerTagActivationReason';
+import type { UUID } from './UUID';
+
+export type TriggerTagActivation = {
+  active_trigger_tags: Array<UUID>;
+  /**
+   * Why each of the active TriggerTags is active
+   */
+  reasons: { [key: UUID]: TriggerTagActivationReason };
+};
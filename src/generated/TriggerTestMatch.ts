@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { MatchContext } from './MatchContext';
+import type { RenderedTemplate } from './RenderedTemplate';
+import type { UUID } from './UUID';
+
+export type TriggerTestMatch = {
+  matcher_id: UUID;
+  context: MatchContext;
+  rendered: Array<RenderedTemplate>;
+};
@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { TriggerTestMatch } from './TriggerTestMatch';
+
+export type TriggerTestResult = {
+  line: string;
+  /**
+   * This is `None` if none of the Trigger's Matchers matched the line
+   */
+  matched: TriggerTestMatch | null;
+};
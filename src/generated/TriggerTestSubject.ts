@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Trigger } from './Trigger';
+import type { UUID } from './UUID';
+
+export type TriggerTestSubject =
+  | { variant: 'Saved'; value: UUID }
+  | { variant: 'Unsaved'; value: Trigger };
@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type WhoEntry = {
+  name: string;
+  /**
+   * Anonymous players do not show their level, class or race
+   */
+  level: number | null;
+  class: string | null;
+  race: string | null;
+  guild: string | null;
+};
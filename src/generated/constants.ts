@@ -29,3 +29,15 @@ export const PROGRESS_UPDATE_EVENT_NAME = 'progress-update';
 
 /// From `crate::ui::PROGRESS_UPDATE_FINISHED_EVENT_NAME`
 export const PROGRESS_UPDATE_FINISHED_EVENT_NAME = 'progress-update-finished';
+
+/// From `crate::ui::ZONE_CHANGED_EVENT_NAME`
+export const ZONE_CHANGED_EVENT_NAME = 'zone-changed';
+
+/// From `crate::ui::COMBAT_FIGHT_UPDATED_EVENT_NAME`
+export const COMBAT_FIGHT_UPDATED_EVENT_NAME = 'combat-fight-updated';
+
+/// From `crate::ui::COMBAT_FIGHT_FINISHED_EVENT_NAME`
+export const COMBAT_FIGHT_FINISHED_EVENT_NAME = 'combat-fight-finished';
+
+/// From `crate::ui::LOG_SEARCH_HITS_EVENT_NAME`
+export const LOG_SEARCH_HITS_EVENT_NAME = 'log-search-hits';
@@ -3,19 +3,33 @@ import { invoke } from '@tauri-apps/api/tauri';
 import { clamp } from 'lodash';
 import { v4 as uuid } from 'uuid';
 
+import { AttendanceSnapshot } from './generated/AttendanceSnapshot';
 import { Bootstrap } from './generated/Bootstrap';
+import { ChatMessage } from './generated/ChatMessage';
+import { ChatQuery } from './generated/ChatQuery';
 import { Character } from './generated/Character';
 import { CommandTemplate } from './generated/CommandTemplate';
 import { CommandTemplateSecurityCheck } from './generated/CommandTemplateSecurityCheck';
 import { DataDelta } from './generated/DataDelta';
 import { LogQuestConfig } from './generated/LogQuestConfig';
+import { LogSearchQuery } from './generated/LogSearchQuery';
+import { LogWatcherMode } from './generated/LogWatcherMode';
+import { LootExport } from './generated/LootExport';
+import { LootHistory } from './generated/LootHistory';
 import { Mutation } from './generated/Mutation';
 import { OverlayState } from './generated/OverlayState';
+import { PinnedCharacter } from './generated/PinnedCharacter';
 import { SystemCommandInfo } from './generated/SystemCommandInfo';
 import { TimerLifetime } from './generated/TimerLifetime';
 import { Trigger } from './generated/Trigger';
 import { TriggerGroup } from './generated/TriggerGroup';
+import { TriggerFiring } from './generated/TriggerFiring';
 import { TriggerIndex } from './generated/TriggerIndex';
+import { TriggerLintIssue } from './generated/TriggerLintIssue';
+import { TriggerStats } from './generated/TriggerStats';
+import { TriggerTagActivation } from './generated/TriggerTagActivation';
+import { TriggerTestResult } from './generated/TriggerTestResult';
+import { TriggerTestSubject } from './generated/TriggerTestSubject';
 import { UUID } from './generated/UUID';
 import { nowTimestamp } from './util';
 
@@ -118,10 +132,10 @@ export async function renameTriggerTag(
 export async function deleteTriggerTag(
   triggerTagID: UUID
 ): Promise<{ activeTriggerTags: UUID[]; deltas: DataDelta[] }> {
-  const activeTriggerTags = await setTriggerTagActivated(triggerTagID, false);
   const deltas = await mutate([
     { variant: 'DeleteTriggerTag', value: triggerTagID },
   ]);
+  const activeTriggerTags = await getActiveTriggerTags();
   return { activeTriggerTags, deltas };
 }
 
@@ -132,8 +146,29 @@ export async function getActiveTriggerTags(): Promise<UUID[]> {
 export async function setTriggerTagActivated(
   id: UUID,
   activated: boolean
-): Promise<UUID[]> {
-  return invoke<UUID[]>('set_trigger_tag_activated', { id, activated });
+): Promise<TriggerTagActivation> {
+  return invoke<TriggerTagActivation>('set_trigger_tag_activated', {
+    id,
+    activated,
+  });
+}
+
+export async function activateCreatedTriggerTag(
+  id: UUID
+): Promise<TriggerTagActivation> {
+  return invoke<TriggerTagActivation>('activate_created_trigger_tag', { id });
+}
+
+export async function setTriggerTagZones(
+  triggerTagId: UUID,
+  zones: string[]
+): Promise<DataDelta[]> {
+  return mutate([
+    {
+      variant: 'SetTriggerTagZones',
+      value: { trigger_tag_id: triggerTagId, zones },
+    },
+  ]);
 }
 
 export async function addTriggerToTag(
@@ -180,6 +215,22 @@ export async function importGinaTriggersFile(
   });
 }
 
+export async function lintTriggers(): Promise<TriggerLintIssue[]> {
+  return await invoke<TriggerLintIssue[]>('lint_triggers');
+}
+
+export async function testTrigger(
+  subject: TriggerTestSubject,
+  lines: string[],
+  characterName: string
+): Promise<TriggerTestResult[]> {
+  return await invoke<TriggerTestResult[]>('test_trigger', {
+    subject,
+    lines,
+    characterName,
+  });
+}
+
 export async function getSystemCommandInfo(
   command: string
 ): Promise<SystemCommandInfo> {
@@ -215,6 +266,14 @@ export async function validateGINARegexWithContext(
   );
 }
 
+export type ValidateTemplateResponse = [number | null, string] | null;
+
+export async function validateTemplate(
+  tmpl: string
+): Promise<ValidateTemplateResponse> {
+  return await invoke<ValidateTemplateResponse>('validate_template', { tmpl });
+}
+
 export async function playAudioFile(path: string) {
   await invoke('play_audio_file', { path });
 }
@@ -222,3 +281,138 @@ export async function playAudioFile(path: string) {
 export async function getCurrentCharacter() {
   return await invoke<Character | null>('get_current_character');
 }
+
+export async function listCharacters(): Promise<Character[]> {
+  return await invoke<Character[]>('list_characters');
+}
+
+export async function setPinnedCharacter(
+  character: PinnedCharacter | null
+): Promise<LogQuestConfig> {
+  return await invoke<LogQuestConfig>('set_pinned_character', { character });
+}
+
+export async function setCharacterSwitchDelay(
+  secs: number | null
+): Promise<LogQuestConfig> {
+  return await invoke<LogQuestConfig>('set_character_switch_delay', { secs });
+}
+
+export async function getTriggerHistory(
+  triggerId: UUID | null,
+  limit: number
+): Promise<TriggerFiring[]> {
+  return await invoke<TriggerFiring[]>('get_trigger_history', {
+    triggerId,
+    limit,
+  });
+}
+
+export async function getTriggerStats(): Promise<TriggerStats[]> {
+  return await invoke<TriggerStats[]>('get_trigger_stats');
+}
+
+export async function setTriggerHistoryJournal(
+  enabled: boolean
+): Promise<LogQuestConfig> {
+  return await invoke<LogQuestConfig>('set_trigger_history_journal', {
+    enabled,
+  });
+}
+
+export async function setCombatParsing(
+  enabled: boolean
+): Promise<LogQuestConfig> {
+  return await invoke<LogQuestConfig>('set_combat_parsing', { enabled });
+}
+
+export async function setLogArchiving(
+  enabled: boolean,
+  afterDays: number | null
+): Promise<LogQuestConfig> {
+  return await invoke<LogQuestConfig>('set_log_archiving', {
+    enabled,
+    afterDays,
+  });
+}
+
+export async function setLogWatcherMode(
+  mode: LogWatcherMode,
+  pollIntervalMs: number | null
+): Promise<LogQuestConfig> {
+  return await invoke<LogQuestConfig>('set_log_watcher_mode', {
+    mode,
+    pollIntervalMs,
+  });
+}
+
+export async function setLootTracking(
+  enabled: boolean
+): Promise<LogQuestConfig> {
+  return await invoke<LogQuestConfig>('set_loot_tracking', { enabled });
+}
+
+export async function getLootHistory(
+  characterName: string
+): Promise<LootHistory> {
+  return await invoke<LootHistory>('get_loot_history', { characterName });
+}
+
+export async function exportLootHistoryCsv(
+  characterName: string,
+  exportKind: LootExport,
+  csvPath: string
+) {
+  await invoke('export_loot_history_csv', {
+    characterName,
+    export: exportKind,
+    csvPath,
+  });
+}
+
+export async function searchChat(
+  logFilePath: string,
+  query: ChatQuery
+): Promise<ChatMessage[]> {
+  return await invoke<ChatMessage[]>('search_chat', { logFilePath, query });
+}
+
+export async function searchLogs(
+  query: LogSearchQuery,
+  useIndex: boolean
+): Promise<number> {
+  return await invoke<number>('search_logs', { query, useIndex });
+}
+
+export async function takeAttendanceSnapshot(
+  label: string
+): Promise<AttendanceSnapshot> {
+  return await invoke<AttendanceSnapshot>('take_attendance_snapshot', {
+    label,
+  });
+}
+
+export async function getAttendanceSnapshots(): Promise<AttendanceSnapshot[]> {
+  return await invoke<AttendanceSnapshot[]>('get_attendance_snapshots');
+}
+
+export async function exportAttendanceCsv(
+  snapshotIds: UUID[],
+  csvPath: string
+) {
+  await invoke('export_attendance_csv', { snapshotIds, csvPath });
+}
+
+export async function setBoxedSet(
+  characterNames: string[]
+): Promise<LogQuestConfig> {
+  return await invoke<LogQuestConfig>('set_boxed_set', { characterNames });
+}
+
+export async function setParallelTriggerEvaluation(
+  enabled: boolean
+): Promise<LogQuestConfig> {
+  return await invoke<LogQuestConfig>('set_parallel_trigger_evaluation', {
+    enabled,
+  });
+}
@@ -105,11 +105,9 @@ const OverviewMode: React.FC<{}> = () => {
                     label={tag.name}
                     control={<Switch />}
                     onChange={async (_, checked) => {
-                      const activatedIDs = await setTriggerTagActivated(
-                        tag.id,
-                        checked
-                      );
-                      dispatch(updateActivedTriggerTagIDs(activatedIDs));
+                      const { active_trigger_tags } =
+                        await setTriggerTagActivated(tag.id, checked);
+                      dispatch(updateActivedTriggerTagIDs(active_trigger_tags));
                     }}
                   />
                 );
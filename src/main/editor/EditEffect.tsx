@@ -7,6 +7,7 @@ import {
   EffectVariantPlayAudioFile,
   EffectVariantRunSystemCommand,
   EffectVariantSpeak,
+  EffectVariantTakeAttendanceSnapshot,
   triggerEditorSelector,
   TriggerEditorSelector,
   TriggerEditorState,
@@ -25,6 +26,7 @@ import { EditSequenceEffect, EditParallelEffect } from './EditCompositeEffects';
 import EditSpeakEffect from './EditSpeakEffect';
 import EditSpeakStopEffect from './EditSpeakStopEffect';
 import EditStartTimerEffect from './EditStartTimerEffect';
+import EditTakeAttendanceSnapshotEffect from './EditTakeAttendanceSnapshotEffect';
 import EffectWithOptions from './EffectWithOptions';
 
 type EffectVariantScopedTimer = Extract<
@@ -64,6 +66,15 @@ const EditEffect: React.FC<{
           onDelete={onDelete}
         />
       );
+    case 'TakeAttendanceSnapshot':
+      return (
+        <EditTakeAttendanceSnapshotEffect
+          selector={$$innerAs<EffectVariantTakeAttendanceSnapshot>(
+            effectSelector
+          )}
+          onDelete={onDelete}
+        />
+      );
     case 'Speak':
       return (
         <EditSpeakEffect
@@ -0,0 +1,51 @@
+import { useDispatch, useSelector } from 'react-redux';
+
+import Card from '@mui/material/Card';
+import CardContent from '@mui/material/CardContent';
+import CardHeader from '@mui/material/CardHeader';
+import TextField from '@mui/material/TextField';
+
+import {
+  EffectVariantTakeAttendanceSnapshot,
+  setAttendanceSnapshotLabel,
+  triggerEditorSelector,
+  TriggerEditorSelector,
+} from '../../features/triggers/triggerEditorSlice';
+import { EffectHeader, EffectTitle } from './widgets/EffectHeader';
+
+const EditTakeAttendanceSnapshotEffect: React.FC<{
+  selector: TriggerEditorSelector<EffectVariantTakeAttendanceSnapshot>;
+  onDelete: () => void;
+}> = ({ selector, onDelete }) => {
+  const dispatch = useDispatch();
+  const { value: tmpl } = useSelector(triggerEditorSelector(selector));
+  return (
+    <Card elevation={10}>
+      <CardHeader
+        title={
+          <EffectHeader onDelete={onDelete}>
+            <EffectTitle
+              variant="TakeAttendanceSnapshot"
+              help="Saves the most recent /who output in the log as an attendance snapshot"
+            />
+          </EffectHeader>
+        }
+      />
+      <CardContent>
+        <TextField
+          label="Snapshot Label (Template)"
+          defaultValue={tmpl}
+          fullWidth
+          className="template-input"
+          onBlur={(e) =>
+            dispatch(
+              setAttendanceSnapshotLabel({ tmpl: e.target.value, selector })
+            )
+          }
+        />
+      </CardContent>
+    </Card>
+  );
+};
+
+export default EditTakeAttendanceSnapshotEffect;
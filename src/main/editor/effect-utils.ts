@@ -5,6 +5,7 @@ import FormatAlignLeft from '@mui/icons-material/FormatAlignLeft';
 import HideSourceOutlined from '@mui/icons-material/HideSourceOutlined';
 import HourglassBottomOutlined from '@mui/icons-material/HourglassBottomOutlined';
 import HourglassTopOutlined from '@mui/icons-material/HourglassTopOutlined';
+import HowToRegOutlined from '@mui/icons-material/HowToRegOutlined';
 import InsertCommentOutlined from '@mui/icons-material/InsertCommentOutlined';
 import KeyboardDoubleArrowDownOutlined from '@mui/icons-material/KeyboardDoubleArrowDownOutlined';
 import LabelOffSharp from '@mui/icons-material/LabelOffSharp';
@@ -39,6 +40,7 @@ export const EFFECT_VARIANTS: EffectVariant[] = [
   'Speak',
   'SpeakStop',
   'RunSystemCommand',
+  'TakeAttendanceSnapshot',
   'Sequence',
   'Parallel',
   'Pause',
@@ -75,6 +77,7 @@ export const EffectIcon: { [key in EffectVariant]: React.ComponentType } = {
   SpeakStop: VoiceOverOffOutlined,
   StartStopwatch: WatchLater,
   StartTimer: AvTimer,
+  TakeAttendanceSnapshot: HowToRegOutlined,
 
   // ScopedTimerEffect isn't shown like normal Effects, so this icon isn't used
   // but it's included here because of the TypeScript completeness check on EffectIcon
@@ -133,6 +136,7 @@ const HUMANIZED_EFFECT_NAMES: { [key in EffectVariant]: string } = {
   ScopedTimerEffect: 'Timer Effect',
   SpeakStop: 'Stop Speaking',
   StartStopwatch: 'Start Stopwatch',
+  TakeAttendanceSnapshot: 'Take Attendance Snapshot',
 };
 
 export function humanizeEffectName(
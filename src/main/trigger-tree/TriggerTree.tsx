@@ -35,9 +35,9 @@ import {
 } from '../../features/triggers/triggersSlice';
 import { TriggerGroupDescendant } from '../../generated/TriggerGroupDescendant';
 import {
+  activateCreatedTriggerTag,
   createTriggerGroup,
   createTriggerTag,
-  setTriggerTagActivated,
 } from '../../ipc';
 import StandardTooltip from '../../widgets/StandardTooltip';
 import TriggerGroupListItem from './TriggerGroupListItem';
@@ -155,11 +155,10 @@ const TriggerTree: React.FC<{}> = () => {
               );
               if (creation) {
                 dispatch(activateTriggerTagID(creation.value.id));
-                const activeTriggerTags = await setTriggerTagActivated(
-                  creation.value.id,
-                  true
+                const { active_trigger_tags } = await activateCreatedTriggerTag(
+                  creation.value.id
                 );
-                dispatch(updateActivedTriggerTagIDs(activeTriggerTags));
+                dispatch(updateActivedTriggerTagIDs(active_trigger_tags));
               }
             }}
           />
@@ -8,15 +8,25 @@ import {
   removeMessage,
   setEditable,
 } from './features/overlay/overlaySlice';
+import {
+  setCharacterZone,
+  updateActivedTriggerTagIDs,
+} from './features/app/appSlice';
 import { initTimers, timerStateUpdate } from './features/timers/timersSlice';
 import {
   CROSS_DISPATCH_EVENT_NAME,
   OVERLAY_EDITABLE_CHANGED_EVENT_NAME,
   OVERLAY_MESSAGE_EVENT_NAME,
   OVERLAY_STATE_UPDATE_EVENT_NAME,
+  ZONE_CHANGED_EVENT_NAME,
 } from './generated/constants';
 import { TimerStateUpdate } from './generated/TimerStateUpdate';
-import { getOverlayBootstrap, startTimersSync } from './ipc';
+import { ZoneChange } from './generated/ZoneChange';
+import {
+  getActiveTriggerTags,
+  getOverlayBootstrap,
+  startTimersSync,
+} from './ipc';
 import { OverlayDispatch } from './OverlayStore';
 
 // TODO: This should come from an overlay config setting
@@ -41,6 +51,16 @@ export const initOverlayStateListeners = (dispatch: Dispatch) => {
   initOverlayEditableListener(dispatch);
 };
 
+export const initReactorStateListeners = (dispatch: Dispatch) => {
+  return listen<ZoneChange>(ZONE_CHANGED_EVENT_NAME, ({ payload: change }) => {
+    dispatch(setCharacterZone(change));
+    // Trigger Tags with zones may have been activated or deactivated
+    getActiveTriggerTags().then((triggerTags) =>
+      dispatch(updateActivedTriggerTagIDs(triggerTags))
+    );
+  });
+};
+
 const initTimersSync = (dispatch: Dispatch) => {
   startTimersSync().then((timerLifetimes) => {
     dispatch(initTimers(timerLifetimes));